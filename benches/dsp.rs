@@ -0,0 +1,160 @@
+//! Throughput of the DSP building blocks, in samples per second, so a regression in any of
+//! them shows up on its own rather than only in the whole-synth numbers.
+//!
+//!     cargo bench --bench dsp
+//!     cargo bench --bench dsp -- --save-baseline main    # then compare with --baseline main
+
+use std::hint::black_box;
+use std::sync::Arc;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+use rust_vst_synth::dynamics::OutputNormalization;
+use rust_vst_synth::filter::{Filter, FilterParameters, FilterSlope, FilterType, SaturationCurve};
+use rust_vst_synth::oscillator::{AdditiveTables, BasicOscillator, Footage, NoiseTables, OscillatorConfig, WaveformGenerator};
+use rust_vst_synth::synthesizer::{midi_note_to_freq, Synthesizer, SynthesizerConfig};
+use rust_vst_synth::voice::{Voice, VoiceConfig};
+use rust_vst_synth::voice_configuration::Waveform;
+
+const SAMPLE_RATE: f32 = 48000.0;
+const BLOCK_SIZE: usize = 512;
+const POLYPHONY: [usize; 4] = [1, 8, 32, 64];
+
+fn filter(slope: FilterSlope) -> Filter {
+    Filter::new(FilterParameters {
+        filter_type: FilterType::LowPass,
+        slope,
+        cutoff_frequency: 1500.0,
+        resonance_amount: 0.8,
+        modulation_amount: 0.0,
+        drive: 0.0,
+        saturation: SaturationCurve::Tanh,
+    }, SAMPLE_RATE)
+}
+
+// Two oscillators through the steepest filter, as in the bench binary's worst case
+fn patch() -> SynthesizerConfig {
+    SynthesizerConfig {
+        oscillator_configs: vec![
+            OscillatorConfig::with_waveform(Waveform::SAW),
+            OscillatorConfig { octave: Footage::Feet16, detune_semitones: 0.07, volume: 0.7, ..OscillatorConfig::with_waveform(Waveform::SQUARE) },
+        ],
+        filter: filter(FilterSlope::Slope24dB),
+        normalization: OutputNormalization::AutoGain,
+        sample_rate: SAMPLE_RATE,
+        ..SynthesizerConfig::default()
+    }
+}
+
+fn voice_config(config: &SynthesizerConfig) -> VoiceConfig {
+    VoiceConfig {
+        oscillator_configs: config.oscillator_configs.clone(),
+        noise_tables: Arc::new(NoiseTables::generate(config.noise_seed)),
+        additive_tables: Arc::new(AdditiveTables::generate(&config.harmonics)),
+        filter: config.filter.clone(),
+        filter2: config.filter2.clone(),
+        filter_routing: config.filter_routing,
+        filter_envelope_config: config.filter_envelope_config.clone(),
+        mod_envelope_configs: config.mod_envelope_configs.clone(),
+        modulation_routes: config.modulation_routes.clone(),
+        stereo_filter_spread: config.stereo_filter_spread,
+        dc_blocking: config.voice_dc_blocking,
+        insert: config.voice_insert,
+        waveshaper: config.voice_waveshaper,
+        gain_compensation: config.oscillator_gain_compensation,
+        detune_spread: config.detune_spread,
+        key_tracking: config.filter_key_tracking,
+        master_tuning: config.master_tuning_semitones(),
+        freeze_modulation_on_release: config.freeze_modulation_on_release,
+        release_velocity: config.release_velocity,
+        glide: config.glide,
+        vibrato: config.vibrato,
+        drift: config.drift,
+        sends: config.sends,
+    }
+}
+
+fn oscillators(c: &mut Criterion) {
+    let mut group = c.benchmark_group("oscillator");
+    group.throughput(Throughput::Elements(BLOCK_SIZE as u64));
+    let mut out = vec![0.0; BLOCK_SIZE];
+    for waveform in [Waveform::SINE, Waveform::SAW, Waveform::SQUARE, Waveform::WHITE_NOISE] {
+        let mut osc = BasicOscillator::new(SAMPLE_RATE, 220.0, oscillator_config(waveform));
+        group.bench_function(BenchmarkId::new("next_sample", waveform.label()), |b| {
+            b.iter(|| {
+                for sample in out.iter_mut() {
+                    *sample = osc.next_sample();
+                }
+                black_box(&out);
+            })
+        });
+        group.bench_function(BenchmarkId::new("fill_block", waveform.label()), |b| {
+            b.iter(|| {
+                osc.fill_block(&mut out);
+                black_box(&out);
+            })
+        });
+    }
+    group.finish();
+}
+
+fn filters(c: &mut Criterion) {
+    let mut group = c.benchmark_group("filter");
+    group.throughput(Throughput::Elements(BLOCK_SIZE as u64));
+    let mut noise = BasicOscillator::new(SAMPLE_RATE, 220.0, OscillatorConfig::with_waveform(Waveform::WHITE_NOISE));
+    let input: Vec<f32> = (0..BLOCK_SIZE).map(|_| noise.next_sample()).collect();
+    for slope in FilterSlope::ALL {
+        let mut filter = filter(slope);
+        group.bench_function(BenchmarkId::new("process_sample", slope.label()), |b| {
+            b.iter(|| {
+                for &sample in &input {
+                    black_box(filter.process_sample(sample));
+                }
+            })
+        });
+    }
+    group.finish();
+}
+
+fn voices(c: &mut Criterion) {
+    let mut group = c.benchmark_group("voice");
+    group.throughput(Throughput::Elements(BLOCK_SIZE as u64));
+    let config = patch();
+    for factor in [1, 2, 4] {
+        let mut voice = Voice::new(&voice_config(&config), &config.envelope_config, SAMPLE_RATE);
+        voice.set_oversampling(factor);
+        voice.trigger(midi_note_to_freq(45), 0, 1.0, None, None);
+        group.bench_function(BenchmarkId::new("next_frame", format!("{}x", factor)), |b| {
+            b.iter(|| {
+                for _ in 0..BLOCK_SIZE {
+                    black_box(voice.next_frame());
+                }
+            })
+        });
+    }
+    group.finish();
+}
+
+fn synthesizer(c: &mut Criterion) {
+    let mut group = c.benchmark_group("synthesizer");
+    group.throughput(Throughput::Elements(BLOCK_SIZE as u64));
+    let mut left = vec![0.0; BLOCK_SIZE];
+    let mut right = vec![0.0; BLOCK_SIZE];
+    for voices in POLYPHONY {
+        let mut synth = Synthesizer::new(SynthesizerConfig { max_voices: voices, ..patch() });
+        synth.set_sample_rate(SAMPLE_RATE);
+        for i in 0..voices {
+            synth.note_on(midi_note_to_freq(36 + i as u8), 1.0);
+        }
+        group.bench_with_input(BenchmarkId::new("render_stereo", voices), &voices, |b, _| {
+            b.iter(|| {
+                synth.render_stereo(&mut left, &mut right);
+                black_box((&left, &right));
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, oscillators, filters, voices, synthesizer);
+criterion_main!(benches);
@@ -0,0 +1,76 @@
+//! Holds a four-note pad chord with slow envelopes and reverb, renders it offline and
+//! reports the level of the attack, the held chord and the release tail.
+//!
+//!     cargo run --example chord_pad
+
+use rust_vst_synth::effects::{ReverbConfig, SendConfig};
+use rust_vst_synth::envelope::EnvelopeConfig;
+use rust_vst_synth::oscillator::{Footage, OscillatorConfig};
+use rust_vst_synth::synthesizer::{midi_note_to_freq, Synthesizer, SynthesizerConfig};
+use rust_vst_synth::voice_configuration::Waveform;
+
+const SAMPLE_RATE: f32 = 48000.0;
+const CHORD: [u8; 4] = [48, 55, 60, 64];
+const HOLD_SECS: f32 = 3.0;
+const TAIL_SECS: f32 = 2.0;
+
+fn pad_config() -> SynthesizerConfig {
+    let oscillator = |waveform, octave, detune_semitones, volume| OscillatorConfig {
+        waveform,
+        octave,
+        detune_semitones,
+        volume,
+        ..OscillatorConfig::default()
+    };
+
+    SynthesizerConfig {
+        oscillator_configs: vec![
+            oscillator(Waveform::SAW, Footage::Feet8, -0.08, 0.6),
+            oscillator(Waveform::SAW, Footage::Feet8, 0.08, 0.6),
+        ],
+        envelope_config: EnvelopeConfig::new(0.8, 0.5, 0.8, 1.5, false).expect("the pad envelope times are valid"),
+        sends: SendConfig { reverb: 0.5, ..SendConfig::default() },
+        reverb: ReverbConfig { size: 0.85, damping: 0.5, ..ReverbConfig::default() },
+        max_voices: CHORD.len(),
+        sample_rate: SAMPLE_RATE,
+        ..SynthesizerConfig::default()
+    }
+}
+
+fn rms(left: &[f32], right: &[f32]) -> f32 {
+    let sum: f32 = left.iter().zip(right).map(|(l, r)| l * l + r * r).sum();
+    (sum / (2 * left.len()).max(1) as f32).sqrt()
+}
+
+fn render_secs(synth: &mut Synthesizer, secs: f32) -> (Vec<f32>, Vec<f32>) {
+    let frames = (secs * SAMPLE_RATE) as usize;
+    let mut left = vec![0.0; frames];
+    let mut right = vec![0.0; frames];
+    synth.render_stereo(&mut left, &mut right);
+    (left, right)
+}
+
+fn main() {
+    let mut synth = Synthesizer::new(pad_config());
+    synth.set_sample_rate(SAMPLE_RATE);
+
+    for &note in &CHORD {
+        synth.note_on(midi_note_to_freq(note), 0.7);
+    }
+    let (attack_l, attack_r) = render_secs(&mut synth, 0.5);
+    let (held_l, held_r) = render_secs(&mut synth, HOLD_SECS - 0.5);
+    let stats = synth.voice_stats();
+
+    for &note in &CHORD {
+        synth.note_off(midi_note_to_freq(note));
+    }
+    let (tail_l, tail_r) = render_secs(&mut synth, TAIL_SECS);
+
+    println!("attack  rms {:.4}", rms(&attack_l, &attack_r));
+    println!("held    rms {:.4}  ({} of {} voices)", rms(&held_l, &held_r), stats.active_voices, stats.max_voices);
+    println!("tail    rms {:.4}", rms(&tail_l, &tail_r));
+
+    assert_eq!(stats.active_voices, CHORD.len(), "every chord note should hold a voice");
+    assert!(rms(&held_l, &held_r) > rms(&attack_l, &attack_r), "the slow attack should still be rising");
+    assert!(held_l.iter().chain(&held_r).all(|s| s.is_finite()));
+}
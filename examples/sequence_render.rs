@@ -0,0 +1,82 @@
+//! Lets the step sequencer play an arpeggio through the voice engine, renders two bars
+//! offline and writes them to a 16-bit stereo WAV file.
+//!
+//!     cargo run --example sequence_render -- arpeggio.wav
+
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+
+use rust_vst_synth::sequencer::{StepSequencerConfig, STEP_COUNT};
+use rust_vst_synth::synthesizer::{Synthesizer, SynthesizerConfig};
+use rust_vst_synth::tempo::SyncDivision;
+
+const SAMPLE_RATE: f32 = 44100.0;
+const TEMPO_BPM: f32 = 128.0;
+const BARS: f32 = 2.0;
+const BLOCK_SIZE: usize = 256;
+const ARPEGGIO: [u8; 8] = [45, 52, 57, 60, 64, 60, 57, 52];
+
+fn arpeggio() -> StepSequencerConfig {
+    StepSequencerConfig {
+        enabled: true,
+        drive_notes: true,
+        notes: std::array::from_fn(|i| Some(ARPEGGIO[i % ARPEGGIO.len()])),
+        length: STEP_COUNT,
+        division: SyncDivision::Sixteenth,
+        gate: 0.6,
+        ..StepSequencerConfig::default()
+    }
+}
+
+fn write_wav(path: &str, left: &[f32], right: &[f32], sample_rate: u32) -> Result<(), Box<dyn Error>> {
+    let data_len = (left.len() * 4) as u32;
+    let mut out = BufWriter::new(File::create(path)?);
+    out.write_all(b"RIFF")?;
+    out.write_all(&(36 + data_len).to_le_bytes())?;
+    out.write_all(b"WAVEfmt ")?;
+    out.write_all(&16u32.to_le_bytes())?;
+    out.write_all(&1u16.to_le_bytes())?;               // PCM
+    out.write_all(&2u16.to_le_bytes())?;               // channels
+    out.write_all(&sample_rate.to_le_bytes())?;
+    out.write_all(&(sample_rate * 4).to_le_bytes())?;  // bytes per second
+    out.write_all(&4u16.to_le_bytes())?;               // bytes per frame
+    out.write_all(&16u16.to_le_bytes())?;              // bits per sample
+    out.write_all(b"data")?;
+    out.write_all(&data_len.to_le_bytes())?;
+    for (l, r) in left.iter().zip(right) {
+        for sample in [l, r] {
+            out.write_all(&((sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16).to_le_bytes())?;
+        }
+    }
+    out.flush()?;
+    Ok(())
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let path = std::env::args().nth(1).unwrap_or_else(|| "sequence_render.wav".to_string());
+
+    let mut synth = Synthesizer::new(SynthesizerConfig {
+        tempo_bpm: TEMPO_BPM,
+        sample_rate: SAMPLE_RATE,
+        ..SynthesizerConfig::default()
+    });
+    synth.set_sample_rate(SAMPLE_RATE);
+    synth.set_sequencer(arpeggio());
+
+    // Render in host-sized blocks, as a plugin or audio callback would
+    let frames = (BARS * 4.0 * 60.0 / TEMPO_BPM * SAMPLE_RATE) as usize;
+    let mut left = vec![0.0; frames];
+    let mut right = vec![0.0; frames];
+    for (l, r) in left.chunks_mut(BLOCK_SIZE).zip(right.chunks_mut(BLOCK_SIZE)) {
+        synth.render_stereo(l, r);
+    }
+
+    let peak = left.iter().chain(&right).fold(0.0_f32, |peak, s| peak.max(s.abs()));
+    assert!(peak > 0.0, "the sequencer should have played notes");
+    assert!(synth.is_sounding());
+
+    write_wav(&path, &left, &right, SAMPLE_RATE as u32)?;
+    println!("wrote {} ({} frames, peak {:.3})", path, frames, peak);
+    Ok(())
+}
@@ -0,0 +1,159 @@
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use crate::midi_mapping::{self, CcInbox, CcMapping};
+use crate::oscillator::{AdditiveTables, Harmonics, NoiseTables};
+use crate::params::MyParams;
+use crate::preset::{self, Preset, PresetEntry, ProgramChangeInbox};
+use crate::sample::SampleData;
+
+/// Slow, non-realtime work that runs on nih-plug's background thread instead of the audio
+/// or GUI thread. Results the editor needs come back through `TaskResults`.
+pub enum SynthTask {
+    /// Reads the factory and user presets.
+    ScanPresets,
+    /// Writes `preset` into `dir`, then scans again so the list shows it.
+    SavePreset { dir: PathBuf, preset: Preset },
+    /// Renames the user preset at `path`, then scans again.
+    RenamePreset { path: PathBuf, new_name: String },
+    /// Builds the random oscillator tables for a noise seed and hands them to the audio
+    /// thread through `TaskResults::noise_tables`, so switching to it doesn't build them there.
+    BuildNoiseTables(u64),
+    /// Reads and decodes the sample layer's file and hands it to the audio thread through
+    /// `TaskResults::samples`. Takes the path from the parameters when it runs, so a file
+    /// picked in the meantime is the one loaded.
+    LoadSample,
+    /// Drops a file the audio thread switched away from, which may be its last copy.
+    FreeSample(Arc<SampleData>),
+    /// Builds the additive oscillator tables for a set of harmonic levels and hands them to
+    /// the audio thread through `TaskResults::additive_tables`.
+    BuildAdditiveTables(Harmonics),
+    /// Merges a patch's controller mappings with the saved controller setup and hands them
+    /// to the audio thread, so mapped controllers work without the editor.
+    UpdateCcMappings(Vec<CcMapping>),
+    /// Loads the preset a MIDI program change picked, from the full preset list, so it
+    /// plays without the editor; see `Preset::load_into_overrides`.
+    ProgramChange(u8),
+}
+
+pub enum TaskResult {
+    PresetsScanned(Vec<PresetEntry>),
+    PresetSaved { preset: Preset, result: Result<PathBuf, String> },
+    PresetRenamed { new_name: String, result: Result<PathBuf, String> },
+}
+
+/// How loading the sample layer's file last went, for the editor to show.
+pub struct SampleLoad {
+    pub path: String,
+    pub result: Result<(f32, f32), String>,     // length in seconds and sample rate, or why it failed
+}
+
+/// Finished tasks waiting for the editor, which picks them up on its timer, and tables
+/// and files waiting for the audio thread.
+#[derive(Default)]
+pub struct TaskResults {
+    results: Mutex<Vec<TaskResult>>,
+    pub noise_tables: Handoff<u64, NoiseTables>,
+    pub additive_tables: Handoff<Harmonics, AdditiveTables>,
+    pub samples: Handoff<String, SampleData>,
+    pub sample_load: Mutex<Option<SampleLoad>>,
+}
+
+impl TaskResults {
+    pub fn push(&self, result: TaskResult) {
+        self.results.lock().unwrap_or_else(|e| e.into_inner()).push(result);
+    }
+
+    /// Everything finished since the last call, oldest first.
+    pub fn take(&self) -> Vec<TaskResult> {
+        std::mem::take(&mut *self.results.lock().unwrap_or_else(|e| e.into_inner()))
+    }
+}
+
+/// Passes one value built on the background thread, keyed by what it was built from, to
+/// the audio thread of the same plugin instance. The audio thread gives back what the value
+/// replaced, which is freed here with the next hand-off rather than on the audio thread.
+pub struct Handoff<K, T> {
+    slot: Mutex<HandoffSlot<K, T>>,
+}
+
+struct HandoffSlot<K, T> {
+    ready: Option<(K, Arc<T>)>,
+    retired: Option<Arc<T>>,
+}
+
+impl<K, T> Default for Handoff<K, T> {
+    fn default() -> Self {
+        Self { slot: Mutex::new(HandoffSlot { ready: None, retired: None }) }
+    }
+}
+
+impl<K: PartialEq, T> Handoff<K, T> {
+    /// Offers `value`, replacing one the audio thread hasn't taken yet; never called from
+    /// the audio thread.
+    pub fn put(&self, key: K, value: Arc<T>) {
+        let mut slot = self.slot.lock().unwrap_or_else(|e| e.into_inner());
+        let previous = (slot.ready.replace((key, value)), slot.retired.take());
+        drop(slot);
+        drop(previous);     // after the lock is released
+    }
+
+    /// Hands the value built for `key`, if it is ready, to `swap`, which returns the value
+    /// it replaced. Never blocks: a value that is being stored right now counts as not
+    /// ready yet. Returns whether it was swapped in.
+    pub fn take(&self, key: &K, swap: impl FnOnce(Arc<T>) -> Arc<T>) -> bool {
+        let Ok(mut slot) = self.slot.try_lock() else {
+            return false;
+        };
+        let Some((_, value)) = slot.ready.take_if(|(ready, _)| ready == key) else {
+            return false;
+        };
+        // `put` emptied `retired` when it stored the value, so nothing is freed here
+        slot.retired = Some(swap(value));
+        true
+    }
+}
+
+pub fn run(task: SynthTask, results: &TaskResults, params: &MyParams, cc_inbox: &CcInbox, program_inbox: &ProgramChangeInbox) {
+    match task {
+        SynthTask::ScanPresets => results.push(TaskResult::PresetsScanned(preset::all_presets())),
+        SynthTask::SavePreset { dir, preset } => {
+            let result = preset::save_user_preset(&dir, &preset).map_err(|e| e.to_string());
+            results.push(TaskResult::PresetSaved { preset, result });
+            results.push(TaskResult::PresetsScanned(preset::all_presets()));
+        }
+        SynthTask::RenamePreset { path, new_name } => {
+            let result = preset::rename_user_preset(&path, &new_name).map_err(|e| e.to_string());
+            results.push(TaskResult::PresetRenamed { new_name, result });
+            results.push(TaskResult::PresetsScanned(preset::all_presets()));
+        }
+        SynthTask::BuildNoiseTables(seed) => results.noise_tables.put(seed, Arc::new(NoiseTables::generate(seed))),
+        SynthTask::LoadSample => load_sample(results, params),
+        SynthTask::FreeSample(data) => drop(data),
+        SynthTask::BuildAdditiveTables(harmonics) => {
+            results.additive_tables.put(harmonics, Arc::new(AdditiveTables::generate(&harmonics)));
+        }
+        SynthTask::UpdateCcMappings(preset) => cc_inbox.set_mappings(midi_mapping::active_mappings(&preset)),
+        SynthTask::ProgramChange(program) => {
+            let presets = preset::all_presets();
+            if let Some(entry) = preset::program_preset(&presets, program) {
+                entry.preset.load_into_overrides(params);
+                cc_inbox.set_mappings(midi_mapping::active_mappings(&entry.preset.cc_mappings));
+            }
+            program_inbox.record(program);
+        }
+    }
+}
+
+fn load_sample(results: &TaskResults, params: &MyParams) {
+    let Some(path) = params.sample_path.read().unwrap_or_else(|e| e.into_inner()).clone() else {
+        return;
+    };
+    let loaded = SampleData::load(Path::new(&path));
+    let result = loaded.as_ref().map(|data| (data.duration_secs(), data.sample_rate)).map_err(|e| e.to_string());
+    *results.sample_load.lock().unwrap_or_else(|e| e.into_inner()) = Some(SampleLoad { path: path.clone(), result });
+    // A file that fails to load is never offered, so the voices keep the one they have
+    if let Ok(data) = loaded {
+        results.samples.put(path, Arc::new(data));
+    }
+}
@@ -0,0 +1,92 @@
+use std::time::{Duration, Instant};
+
+use rust_vst_synth::dynamics::OutputNormalization;
+use rust_vst_synth::filter::{Filter, FilterParameters, FilterSlope, FilterType, SaturationCurve};
+use rust_vst_synth::oscillator::{Footage, OscillatorConfig};
+use rust_vst_synth::synthesizer::{midi_note_to_freq, Synthesizer, SynthesizerConfig};
+use rust_vst_synth::voice_configuration::Waveform;
+
+const SAMPLE_RATE: f32 = 48000.0;
+const BUFFER_SIZES: [usize; 6] = [32, 64, 128, 256, 512, 1024];
+const SECONDS_PER_SIZE: f32 = 5.0;
+
+// Every voice busy, both oscillators on, the steepest filter in stereo and the auto-gain limiter
+fn worst_case_config() -> SynthesizerConfig {
+    let filter = Filter::new(FilterParameters {
+        filter_type: FilterType::LowPass,
+        slope: FilterSlope::Slope24dB,
+        cutoff_frequency: 1500.0,
+        resonance_amount: 2.0,
+        modulation_amount: 0.5,
+        drive: 0.0,
+        saturation: SaturationCurve::Tanh,
+    }, SAMPLE_RATE);
+
+    SynthesizerConfig {
+        oscillator_configs: vec![
+            OscillatorConfig::with_waveform(Waveform::SAW),
+            OscillatorConfig { octave: Footage::Feet16, detune_semitones: 0.07, volume: 0.7, ..OscillatorConfig::with_waveform(Waveform::SQUARE) },
+        ],
+        filter,
+        stereo_filter_spread: 0.5,
+        normalization: OutputNormalization::AutoGain,
+        max_voices: 32,
+        sample_rate: SAMPLE_RATE,
+        ..SynthesizerConfig::default()
+    }
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let seconds = args.iter()
+        .position(|a| a == "--seconds")
+        .and_then(|i| args.get(i + 1))
+        .and_then(|v| v.parse::<f32>().ok())
+        .unwrap_or(SECONDS_PER_SIZE);
+    // --threads N spreads the voices over N render threads
+    let threads = args.iter()
+        .position(|a| a == "--threads")
+        .and_then(|i| args.get(i + 1))
+        .and_then(|v| v.parse::<usize>().ok())
+        .unwrap_or(1);
+
+    // Build with --no-default-features to compare against the scalar oscillator path
+    println!("oscillators: {}", if cfg!(feature = "simd") { "simd" } else { "scalar" });
+    println!("render threads: {}", threads);
+    println!("{:>8} {:>12} {:>12} {:>12} {:>8}", "frames", "budget us", "mean us", "max us", "load %");
+
+    for &frames in BUFFER_SIZES.iter() {
+        let config = SynthesizerConfig { render_threads: threads, ..worst_case_config() };
+        let voices = config.max_voices;
+        let mut synth = Synthesizer::new(config);
+        synth.set_sample_rate(SAMPLE_RATE);
+        for i in 0..voices {
+            synth.note_on(midi_note_to_freq(36 + i as u8), 1.0);
+        }
+
+        let mut left = vec![0.0; frames];
+        let mut right = vec![0.0; frames];
+        let blocks = ((seconds * SAMPLE_RATE) as usize / frames).max(1);
+
+        let mut total = Duration::ZERO;
+        let mut worst = Duration::ZERO;
+        for _ in 0..blocks {
+            let start = Instant::now();
+            synth.render_stereo(&mut left, &mut right);
+            let elapsed = start.elapsed();
+            total += elapsed;
+            worst = worst.max(elapsed);
+        }
+
+        let budget = frames as f64 / SAMPLE_RATE as f64 * 1e6;
+        let mean = total.as_secs_f64() / blocks as f64 * 1e6;
+        println!(
+            "{:>8} {:>12.1} {:>12.1} {:>12.1} {:>8.1}",
+            frames,
+            budget,
+            mean,
+            worst.as_secs_f64() * 1e6,
+            mean / budget * 100.0
+        );
+    }
+}
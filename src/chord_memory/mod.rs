@@ -0,0 +1,170 @@
+use crate::keyzone::frequency_to_key;
+
+/// Most notes a remembered chord holds, the played note included.
+pub const MAX_CHORD_NOTES: usize = 8;
+const KEYS: usize = 128;
+const CHORD_KEYS: usize = 2 * KEYS;    // a chord on the top key reaches up to 127 semitones higher
+
+/// A chord as semitones above its lowest note, which is always the first interval, 0.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct ChordShape {
+    intervals: [u8; MAX_CHORD_NOTES],
+    len: usize,
+}
+
+impl Default for ChordShape {
+    fn default() -> Self {
+        Self { intervals: [0; MAX_CHORD_NOTES], len: 1 }
+    }
+}
+
+impl ChordShape {
+    /// The shape of the keys set in `keys`, one bit per MIDI note, or `None` when none are.
+    /// Keys past the first `MAX_CHORD_NOTES` from the bottom are dropped.
+    pub fn from_keys(keys: u128) -> Option<Self> {
+        if keys == 0 {
+            return None;
+        }
+        let root = keys.trailing_zeros() as u8;
+        let mut shape = Self { intervals: [0; MAX_CHORD_NOTES], len: 0 };
+        let mut rest = keys;
+        while rest != 0 && shape.len < MAX_CHORD_NOTES {
+            shape.intervals[shape.len] = rest.trailing_zeros() as u8 - root;
+            shape.len += 1;
+            rest &= rest - 1;
+        }
+        Some(shape)
+    }
+
+    /// Rebuilds a shape from stored intervals; the root is added if they leave it out.
+    pub fn from_intervals(intervals: &[u8]) -> Self {
+        let keys = intervals.iter().filter(|&&i| i < 128).fold(1u128, |keys, &i| keys | 1 << i);
+        Self::from_keys(keys).unwrap_or_default()
+    }
+
+    pub fn intervals(&self) -> &[u8] {
+        &self.intervals[..self.len]
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct ChordMemoryConfig {
+    pub enabled: bool,
+    pub shape: ChordShape,
+}
+
+/// The frequencies one played note turned into.
+#[derive(Clone, Copy)]
+pub struct ChordNotes {
+    frequencies: [f32; MAX_CHORD_NOTES],
+    len: usize,
+}
+
+impl ChordNotes {
+    fn single(frequency: f32) -> Self {
+        let mut frequencies = [0.0; MAX_CHORD_NOTES];
+        frequencies[0] = frequency;
+        Self { frequencies, len: 1 }
+    }
+
+    fn push(&mut self, frequency: f32) {
+        if self.len < MAX_CHORD_NOTES {
+            self.frequencies[self.len] = frequency;
+            self.len += 1;
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = f32> + '_ {
+        self.frequencies[..self.len].iter().copied()
+    }
+}
+
+/// Turns each played note into the remembered chord on top of it, and remembers which
+/// chord every note started so its note-off releases exactly those notes. Notes shared by
+/// overlapping chords keep sounding until the last chord holding them is released.
+/// Notes are tracked by MIDI key in fixed arrays, so playing never allocates.
+pub struct ChordMemory {
+    config: ChordMemoryConfig,
+    held_keys: u128,                            // played keys, before expansion
+    playing: [Option<ChordShape>; KEYS],        // by played key, the chord it started
+    holders: [u8; CHORD_KEYS],                  // by expanded key, the chords holding it
+}
+
+impl Default for ChordMemory {
+    fn default() -> Self {
+        Self {
+            config: ChordMemoryConfig::default(),
+            held_keys: 0,
+            playing: [None; KEYS],
+            holders: [0; CHORD_KEYS],
+        }
+    }
+}
+
+impl ChordMemory {
+    pub fn with_config(config: ChordMemoryConfig) -> Self {
+        Self { config, ..Self::default() }
+    }
+
+    pub fn set_config(&mut self, config: ChordMemoryConfig) {
+        self.config = config;
+    }
+
+    pub fn config(&self) -> &ChordMemoryConfig {
+        &self.config
+    }
+
+    /// Remembers the keys held right now as the chord shape.
+    pub fn capture(&mut self) -> Option<ChordShape> {
+        let shape = ChordShape::from_keys(self.held_keys)?;
+        self.config.shape = shape;
+        Some(shape)
+    }
+
+    /// The notes to start for a played note.
+    pub fn note_on(&mut self, frequency: f32) -> ChordNotes {
+        let key = frequency_to_key(frequency);
+        self.held_keys |= 1 << key;
+        if !self.config.enabled {
+            return ChordNotes::single(frequency);
+        }
+
+        let shape = self.config.shape;
+        let retriggered = self.playing[key as usize].replace(shape).is_some();
+        let mut notes = ChordNotes { frequencies: [0.0; MAX_CHORD_NOTES], len: 0 };
+        for &interval in shape.intervals() {
+            if !retriggered {
+                let holders = &mut self.holders[key as usize + interval as usize];
+                *holders = holders.saturating_add(1);
+            }
+            notes.push(frequency * 2.0f32.powf(interval as f32 / 12.0));
+        }
+        notes
+    }
+
+    /// The notes to release for a released note: its chord's notes no other chord still holds.
+    pub fn note_off(&mut self, frequency: f32) -> ChordNotes {
+        let key = frequency_to_key(frequency);
+        self.held_keys &= !(1 << key);
+        let Some(shape) = self.playing[key as usize].take() else {
+            return ChordNotes::single(frequency);
+        };
+
+        let mut notes = ChordNotes { frequencies: [0.0; MAX_CHORD_NOTES], len: 0 };
+        for &interval in shape.intervals() {
+            let holders = &mut self.holders[key as usize + interval as usize];
+            *holders = holders.saturating_sub(1);
+            if *holders == 0 {
+                notes.push(frequency * 2.0f32.powf(interval as f32 / 12.0));
+            }
+        }
+        notes
+    }
+
+    /// Forgets every held key and chord, after the voices have been silenced some other way.
+    pub fn clear(&mut self) {
+        self.held_keys = 0;
+        self.playing = [None; KEYS];
+        self.holders = [0; CHORD_KEYS];
+    }
+}
@@ -0,0 +1,77 @@
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::Duration;
+
+/// Share of the previous reading kept when the load falls, so short dips don't flicker the
+/// display; rises show at once.
+const LOAD_DECAY: f32 = 0.9;
+/// Load at which the meter warns that the callback is getting close to its deadline.
+pub const WARNING_LOAD: f32 = 0.7;
+/// Load past which buffers are likely to be late and the output drops out.
+pub const OVERLOAD_LOAD: f32 = 0.95;
+
+/// How close the audio callback runs to its deadline.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum CpuLoadState {
+    Normal,
+    Warning,
+    Overload,
+}
+
+/// Time spent rendering each buffer relative to how long the buffer plays, published by the
+/// audio thread for the GUI without locking it.
+pub struct CpuMeter {
+    load: AtomicU32,        // f32 bits, 1.0 when rendering takes as long as playing
+    peak: AtomicU32,        // f32 bits, highest single-buffer load since the last reset
+}
+
+impl Default for CpuMeter {
+    fn default() -> Self {
+        Self {
+            load: AtomicU32::new(0),
+            peak: AtomicU32::new(0),
+        }
+    }
+}
+
+impl CpuMeter {
+    /// Records one callback that rendered `frames` at `sample_rate` in `elapsed`.
+    pub fn record(&self, elapsed: Duration, frames: usize, sample_rate: f32) {
+        if frames == 0 || sample_rate <= 0.0 {
+            return;
+        }
+        let buffer_secs = frames as f32 / sample_rate;
+        let load = elapsed.as_secs_f32() / buffer_secs;
+
+        let shown = f32::from_bits(self.load.load(Ordering::Relaxed));
+        let shown = if load >= shown { load } else { shown * LOAD_DECAY + load * (1.0 - LOAD_DECAY) };
+        self.load.store(shown.to_bits(), Ordering::Relaxed);
+        if load > self.peak() {
+            self.peak.store(load.to_bits(), Ordering::Relaxed);
+        }
+    }
+
+    /// Smoothed load, 0.0 upwards; past 1.0 the callback misses its deadline.
+    pub fn load(&self) -> f32 {
+        f32::from_bits(self.load.load(Ordering::Relaxed))
+    }
+
+    pub fn peak(&self) -> f32 {
+        f32::from_bits(self.peak.load(Ordering::Relaxed))
+    }
+
+    pub fn reset_peak(&self) {
+        self.peak.store(0, Ordering::Relaxed);
+    }
+
+    pub fn state(&self) -> CpuLoadState {
+        match self.load() {
+            load if load >= OVERLOAD_LOAD => CpuLoadState::Overload,
+            load if load >= WARNING_LOAD => CpuLoadState::Warning,
+            _ => CpuLoadState::Normal,
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        format!("CPU {:.0}% (peak {:.0}%)", self.load() * 100.0, self.peak() * 100.0)
+    }
+}
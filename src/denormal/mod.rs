@@ -0,0 +1,81 @@
+/// Tiny DC offset fed into recursive filter stages, so decaying state settles on it
+/// instead of sinking into denormals on targets without flush-to-zero.
+pub const ANTI_DENORMAL: f32 = 1e-18;
+
+/// Replaces NaN and infinity with silence.
+#[inline]
+pub fn scrub(sample: f32) -> f32 {
+    if sample.is_finite() { sample } else { 0.0 }
+}
+
+/// Flushes denormals to zero (FTZ/DAZ on x86_64, FZ on aarch64) on the current thread
+/// until dropped, then restores the previous floating point mode.
+pub struct DenormalGuard {
+    previous: u64,
+}
+
+impl DenormalGuard {
+    pub fn enable() -> Self {
+        let previous = arch::read_mode();
+        arch::write_mode(previous | arch::FLUSH_TO_ZERO);
+        Self { previous }
+    }
+}
+
+impl Drop for DenormalGuard {
+    fn drop(&mut self) {
+        arch::write_mode(self.previous);
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+mod arch {
+    // Deprecated in favour of inline assembly, but they are exactly the MXCSR access we need
+    #[allow(deprecated)]
+    use std::arch::x86_64::{_mm_getcsr, _mm_setcsr};
+
+    // MXCSR flush-to-zero and denormals-are-zero bits
+    pub const FLUSH_TO_ZERO: u64 = (1 << 15) | (1 << 6);
+
+    #[allow(deprecated)]
+    pub fn read_mode() -> u64 {
+        // SSE is part of the x86_64 baseline
+        unsafe { _mm_getcsr() as u64 }
+    }
+
+    #[allow(deprecated)]
+    pub fn write_mode(mode: u64) {
+        unsafe { _mm_setcsr(mode as u32) };
+    }
+}
+
+#[cfg(target_arch = "aarch64")]
+mod arch {
+    // There is no intrinsic for FPCR, so this one stays assembly
+    use std::arch::asm;
+
+    // FPCR flush-to-zero bit
+    pub const FLUSH_TO_ZERO: u64 = 1 << 24;
+
+    pub fn read_mode() -> u64 {
+        let fpcr: u64;
+        unsafe { asm!("mrs {}, fpcr", out(reg) fpcr, options(nomem, nostack, preserves_flags)) };
+        fpcr
+    }
+
+    pub fn write_mode(mode: u64) {
+        unsafe { asm!("msr fpcr, {}", in(reg) mode, options(nomem, nostack, preserves_flags)) };
+    }
+}
+
+// Elsewhere only the filter offsets and output scrubbing protect the signal path
+#[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+mod arch {
+    pub const FLUSH_TO_ZERO: u64 = 0;
+
+    pub fn read_mode() -> u64 {
+        0
+    }
+
+    pub fn write_mode(_mode: u64) {}
+}
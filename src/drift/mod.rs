@@ -0,0 +1,116 @@
+const GENERATORS: usize = 4;            // oscillators beyond this share a drift generator
+const PITCH_DRIFT_PERIOD_SECS: f32 = 1.5;
+const CUTOFF_WOBBLE_PERIOD_SECS: f32 = 0.4;
+
+/// Depths of the analog imperfections; all zero turns drift off.
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct DriftConfig {
+    pub pitch_drift_cents: f32,         // slow wandering of each oscillator
+    pub note_detune_cents: f32,         // random offset picked per voice on note-on
+    pub cutoff_wobble_octaves: f32,     // slow wandering of the filter cutoff
+}
+
+impl DriftConfig {
+    pub fn is_enabled(&self) -> bool {
+        self.pitch_drift_cents > 0.0 || self.note_detune_cents > 0.0 || self.cutoff_wobble_octaves > 0.0
+    }
+}
+
+// Smoothed random walk in -1.0..1.0: picks a new target every period and eases towards it
+#[derive(Clone, Copy, Default)]
+struct SlowNoise {
+    value: f32,
+    target: f32,
+    samples_left: usize,
+}
+
+/// Per-voice analog drift. Each voice needs its own seed or every voice would drift alike.
+#[derive(Clone)]
+pub struct Drift {
+    config: DriftConfig,
+    sample_rate: f32,
+    rng: u64,
+    oscillators: [SlowNoise; GENERATORS],
+    cutoff: SlowNoise,
+    note_detune: f32,                   // -1.0 to 1.0, scaled by note_detune_cents
+    detune_spread: f32,                 // macro scaling of note_detune_cents
+}
+
+impl Drift {
+    pub fn new(config: DriftConfig, sample_rate: f32) -> Self {
+        Self {
+            config,
+            sample_rate,
+            rng: 12345,
+            oscillators: [SlowNoise::default(); GENERATORS],
+            cutoff: SlowNoise::default(),
+            note_detune: 0.0,
+            detune_spread: 1.0,
+        }
+    }
+
+    pub fn seed(&mut self, seed: u64) {
+        self.rng = seed.wrapping_mul(0x9E3779B97F4A7C15) | 1;
+    }
+
+    pub fn set_config(&mut self, config: DriftConfig) {
+        self.config = config;
+    }
+
+    pub fn set_detune_spread(&mut self, spread: f32) {
+        self.detune_spread = spread;
+    }
+
+    pub fn update_sample_rate(&mut self, new_sample_rate: f32) {
+        self.sample_rate = new_sample_rate;
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.config.is_enabled()
+    }
+
+    /// Picks a fresh detune for the next note.
+    pub fn retrigger(&mut self) {
+        self.note_detune = self.next_random();
+    }
+
+    pub fn advance(&mut self) {
+        let pitch_period = (PITCH_DRIFT_PERIOD_SECS * self.sample_rate) as usize;
+        let cutoff_period = (CUTOFF_WOBBLE_PERIOD_SECS * self.sample_rate) as usize;
+        for i in 0..GENERATORS {
+            let mut noise = self.oscillators[i];
+            self.step(&mut noise, pitch_period);
+            self.oscillators[i] = noise;
+        }
+        let mut cutoff = self.cutoff;
+        self.step(&mut cutoff, cutoff_period);
+        self.cutoff = cutoff;
+    }
+
+    /// Frequency multiplier for oscillator `index`.
+    pub fn pitch_ratio(&self, index: usize) -> f32 {
+        let cents = self.note_detune * self.config.note_detune_cents * self.detune_spread
+            + self.oscillators[index % GENERATORS].value * self.config.pitch_drift_cents;
+        2.0f32.powf(cents / 1200.0)
+    }
+
+    pub fn cutoff_offset(&self) -> f32 {
+        self.cutoff.value * self.config.cutoff_wobble_octaves
+    }
+
+    fn step(&mut self, noise: &mut SlowNoise, period: usize) {
+        if noise.samples_left == 0 {
+            noise.target = self.next_random();
+            noise.samples_left = period.max(1);
+        }
+        noise.samples_left -= 1;
+        noise.value += (noise.target - noise.value) / period.max(1) as f32;
+    }
+
+    fn next_random(&mut self) -> f32 {
+        self.rng = self.rng
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        ((self.rng >> 32) as f32) / ((u32::MAX as f32) + 1.0) * 2.0 - 1.0
+    }
+}
@@ -0,0 +1,126 @@
+use std::collections::VecDeque;
+
+const LOOKAHEAD_SECS: f32 = 0.005;
+const LIMITER_RELEASE_SECS: f32 = 0.15;
+const RMS_WINDOW_SECS: f32 = 1.5;
+const TARGET_RMS: f32 = 0.125;          // about -18 dBFS
+const CEILING: f32 = 0.98;
+const MIN_GAIN: f32 = 0.1;              // -20 dB
+const MAX_GAIN: f32 = 4.0;              // +12 dB
+const SILENCE_RMS: f32 = 0.001;         // below -60 dBFS the auto-gain holds its level
+
+/// How the summed voices are brought to a usable output level.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum OutputNormalization {
+    /// Scale every voice by 1/sqrt(size of the voice pool), so notes keep their level no
+    /// matter how many others are sounding.
+    FixedHeadroom,
+    /// Slow RMS-based auto-gain followed by a lookahead peak limiter.
+    AutoGain,
+}
+
+impl OutputNormalization {
+    pub const ALL: [OutputNormalization; 2] = [OutputNormalization::FixedHeadroom, OutputNormalization::AutoGain];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            OutputNormalization::FixedHeadroom => "Fixed Headroom",
+            OutputNormalization::AutoGain => "Auto Gain",
+        }
+    }
+}
+
+/// Stereo auto-gain: a slow RMS follower pulls the level towards a fixed target and a
+/// lookahead limiter catches the peaks the follower is too slow for. The output is
+/// delayed by [`AutoGain::latency_samples`].
+#[derive(Clone)]
+pub struct AutoGain {
+    sample_rate: f32,
+    mean_square: f32,
+    rms_coeff: f32,
+    auto_gain: f32,
+    delay: VecDeque<(f32, f32)>,
+    lookahead: usize,
+    limiter_gain: f32,
+    limiter_target: f32,
+    attack_step: f32,
+    hold_samples_left: usize,
+    release_coeff: f32,
+}
+
+impl AutoGain {
+    pub fn new(sample_rate: f32) -> Self {
+        let mut auto_gain = Self {
+            sample_rate: 0.0,
+            mean_square: TARGET_RMS * TARGET_RMS,
+            rms_coeff: 0.0,
+            auto_gain: 1.0,
+            delay: VecDeque::new(),
+            lookahead: 0,
+            limiter_gain: 1.0,
+            limiter_target: 1.0,
+            attack_step: 0.0,
+            hold_samples_left: 0,
+            release_coeff: 0.0,
+        };
+        auto_gain.update_sample_rate(sample_rate);
+        auto_gain
+    }
+
+    pub fn update_sample_rate(&mut self, sample_rate: f32) {
+        if sample_rate == self.sample_rate {
+            return;
+        }
+        self.sample_rate = sample_rate;
+        self.rms_coeff = 1.0 - (-1.0 / (RMS_WINDOW_SECS * sample_rate)).exp();
+        self.release_coeff = 1.0 - (-1.0 / (LIMITER_RELEASE_SECS * sample_rate)).exp();
+        self.lookahead = ((LOOKAHEAD_SECS * sample_rate) as usize).max(1);
+        self.reset();
+    }
+
+    pub fn reset(&mut self) {
+        self.delay.clear();
+        self.delay.extend(std::iter::repeat((0.0, 0.0)).take(self.lookahead));
+        self.limiter_gain = 1.0;
+        self.limiter_target = 1.0;
+        self.attack_step = 0.0;
+        self.hold_samples_left = 0;
+    }
+
+    pub fn latency_samples(&self) -> usize {
+        self.lookahead
+    }
+
+    pub fn process(&mut self, left: f32, right: f32) -> (f32, f32) {
+        // Slow level follower
+        let square = 0.5 * (left * left + right * right);
+        self.mean_square += (square - self.mean_square) * self.rms_coeff;
+        let rms = self.mean_square.sqrt();
+        if rms > SILENCE_RMS {
+            let wanted = (TARGET_RMS / rms).clamp(MIN_GAIN, MAX_GAIN);
+            self.auto_gain += (wanted - self.auto_gain) * self.rms_coeff;
+        }
+        let (left, right) = (left * self.auto_gain, right * self.auto_gain);
+
+        // Ramp the limiter down over the lookahead so it reaches the required gain as the peak leaves the delay
+        let peak = left.abs().max(right.abs());
+        let required = if peak > CEILING { CEILING / peak } else { 1.0 };
+        if required < self.limiter_target {
+            self.limiter_target = required;
+            self.attack_step = (self.limiter_gain - required).max(0.0) / self.lookahead as f32;
+            self.hold_samples_left = self.lookahead;
+        }
+        if self.limiter_gain > self.limiter_target {
+            self.limiter_gain = (self.limiter_gain - self.attack_step).max(self.limiter_target);
+        } else if self.hold_samples_left > 0 {
+            self.hold_samples_left -= 1;
+        } else {
+            self.limiter_target += (1.0 - self.limiter_target) * self.release_coeff;
+            self.limiter_gain = self.limiter_target;
+        }
+
+        self.delay.push_back((left, right));
+        let (delayed_left, delayed_right) = self.delay.pop_front().unwrap_or((0.0, 0.0));
+        (delayed_left * self.limiter_gain, delayed_right * self.limiter_gain)
+    }
+}
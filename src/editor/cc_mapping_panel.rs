@@ -0,0 +1,288 @@
+use nih_plug::prelude::{ParamPtr, Params};
+use nih_plug_vizia::vizia::prelude::*;
+use nih_plug_vizia::widgets::RawParamEvent;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use super::locale::{localized_label, translate, LocaleModel};
+use crate::midi_mapping::{self, CcInbox, CcMapping, ControllerSetup, MappingScope};
+use crate::params::MyParams;
+
+/// How often incoming controllers are applied to their parameters.
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+#[derive(Clone, PartialEq, Data)]
+struct MappingRow {
+    description: String,
+    global: bool,
+}
+
+enum CcMappingEvent {
+    Poll,
+    SetTarget(String),
+    SetRange(String),
+    Learn(MappingScope),
+    CycleCurve(usize),
+    Delete(usize),
+    CyclePolicy,
+}
+
+/// Merges the global controller setup with the patch's own mappings and moves the mapped
+/// parameters whenever a controller arrives.
+#[derive(Lens)]
+struct CcMappingModel {
+    params: Arc<MyParams>,
+    inbox: Arc<CcInbox>,
+    setup_path: Option<PathBuf>,
+    setup: ControllerSetup,
+    preset_mappings: Vec<CcMapping>,
+    param_ptrs: HashMap<String, ParamPtr>,
+    param_names: HashMap<String, String>,
+    merged: Vec<(MappingScope, CcMapping)>,
+    rows: Vec<MappingRow>,
+    policy: String,
+    target: String,
+    range: String,              // "min-max" in percent, for the next learned mapping
+    learning: Option<MappingScope>,
+    last_cc: String,
+    status: String,
+}
+
+impl CcMappingModel {
+    fn rebuild(&mut self) {
+        self.merged = midi_mapping::merge(&self.setup.mappings, &self.preset_mappings, self.setup.merge_policy);
+        self.rows = self
+            .merged
+            .iter()
+            .map(|(scope, mapping)| {
+                let name = self.param_names.get(&mapping.param_id).map_or(mapping.param_id.as_str(), String::as_str);
+                MappingRow {
+                    description: format!("{} → {} ({})", mapping.describe_source(), name, mapping.describe_range()),
+                    global: *scope == MappingScope::Global,
+                }
+            })
+            .collect();
+        self.policy = self.setup.merge_policy.name().to_string();
+        self.inbox.set_mappings(self.merged.iter().map(|(_, mapping)| mapping.clone()).collect());
+    }
+
+    fn save_setup(&mut self) {
+        let Some(path) = self.setup_path.clone() else {
+            self.status = "No user data directory".to_string();
+            return;
+        };
+        if let Err(err) = midi_mapping::save_setup(&path, &self.setup) {
+            self.status = format!("Save failed: {}", err);
+        }
+    }
+
+    fn write_preset_mappings(&self) {
+        *self.params.preset_cc_mappings.write().unwrap_or_else(|e| e.into_inner()) = self.preset_mappings.clone();
+    }
+
+    fn poll(&mut self, cx: &mut EventContext) {
+        // Loading a preset swaps the patch's mappings behind the panel's back
+        let current = self.params.preset_cc_mappings.read().unwrap_or_else(|e| e.into_inner()).clone();
+        if current != self.preset_mappings {
+            self.preset_mappings = current;
+            self.rebuild();
+        }
+
+        if let Some((channel, cc)) = self.inbox.last_received() {
+            self.last_cc = format!("CC {} ch {}", cc, channel + 1);
+        }
+        if let Some(scope) = self.learning {
+            if let Some((channel, cc)) = self.inbox.take_learned() {
+                self.learning = None;
+                self.finish_learn(scope, channel, cc);
+            }
+        }
+
+        let merged = &self.merged;
+        let param_ptrs = &self.param_ptrs;
+        self.inbox.drain(|channel, cc, value| {
+            for (_, mapping) in merged.iter().filter(|(_, m)| m.matches(channel, cc)) {
+                if let Some(&ptr) = param_ptrs.get(&mapping.param_id) {
+                    cx.emit(RawParamEvent::BeginSetParameter(ptr));
+                    cx.emit(RawParamEvent::SetParameterNormalized(ptr, mapping.scale(value)));
+                    cx.emit(RawParamEvent::EndSetParameter(ptr));
+                }
+            }
+        });
+    }
+
+    /// Arms learn mode; the next controller to arrive is mapped to the target parameter.
+    /// Learning again cancels.
+    fn learn(&mut self, scope: MappingScope) {
+        if self.learning.take().is_some() {
+            self.inbox.cancel_learn();
+            self.status = "Learn cancelled".to_string();
+            return;
+        }
+        let param_id = self.target.trim();
+        if !self.param_ptrs.contains_key(param_id) {
+            self.status = format!("Unknown parameter ID: {}", param_id);
+            return;
+        }
+        if parse_range(&self.range).is_none() {
+            self.status = format!("Range must look like 0-100, not {}", self.range);
+            return;
+        }
+        self.inbox.arm_learn();
+        self.learning = Some(scope);
+        self.status = "Move a controller...".to_string();
+    }
+
+    fn finish_learn(&mut self, scope: MappingScope, channel: u8, cc: u8) {
+        let (min, max) = parse_range(&self.range).unwrap_or((0.0, 1.0));
+        let mapping = CcMapping::new(cc, Some(channel), self.target.trim()).with_range(min, max);
+        self.status = format!("Mapped {}", mapping.describe_source());
+        self.store(scope, |mappings| midi_mapping::assign(mappings, mapping));
+    }
+
+    /// Edits the mappings of one scope and saves them where that scope keeps them.
+    fn store(&mut self, scope: MappingScope, edit: impl FnOnce(&mut Vec<CcMapping>)) {
+        match scope {
+            MappingScope::Global => {
+                edit(&mut self.setup.mappings);
+                self.save_setup();
+            }
+            MappingScope::Preset => {
+                edit(&mut self.preset_mappings);
+                self.write_preset_mappings();
+            }
+        }
+        self.rebuild();
+    }
+
+    fn cycle_curve(&mut self, index: usize) {
+        let Some((scope, mapping)) = self.merged.get(index).cloned() else {
+            return;
+        };
+        let curve = mapping.curve.next();
+        self.store(scope, |mappings| {
+            if let Some(m) = mappings.iter_mut().find(|m| **m == mapping) {
+                m.curve = curve;
+            }
+        });
+    }
+
+    fn delete(&mut self, index: usize) {
+        let Some((scope, mapping)) = self.merged.get(index).cloned() else {
+            return;
+        };
+        self.store(scope, |mappings| mappings.retain(|m| *m != mapping));
+    }
+}
+
+// "20-80" in percent to normalized (0.2, 0.8); "80-20" turns the controller around
+fn parse_range(text: &str) -> Option<(f32, f32)> {
+    let (min, max) = text.split_once('-')?;
+    let min: f32 = min.trim().trim_end_matches('%').parse().ok()?;
+    let max: f32 = max.trim().trim_end_matches('%').parse().ok()?;
+    let valid = |v: f32| (0.0..=100.0).contains(&v);
+    (valid(min) && valid(max)).then_some((min / 100.0, max / 100.0))
+}
+
+impl Model for CcMappingModel {
+    fn event(&mut self, cx: &mut EventContext, event: &mut Event) {
+        event.map(|mapping_event, _| match mapping_event {
+            CcMappingEvent::Poll => self.poll(cx),
+            CcMappingEvent::SetTarget(target) => self.target = target.clone(),
+            CcMappingEvent::SetRange(range) => self.range = range.clone(),
+            CcMappingEvent::Learn(scope) => self.learn(*scope),
+            CcMappingEvent::CycleCurve(index) => self.cycle_curve(*index),
+            CcMappingEvent::Delete(index) => self.delete(*index),
+            CcMappingEvent::CyclePolicy => {
+                self.setup.merge_policy = self.setup.merge_policy.next();
+                self.save_setup();
+                self.rebuild();
+            }
+        });
+    }
+}
+
+/// MIDI learn for controller mappings: type a parameter ID and a range, press Map Global
+/// (stored with the controller setup) or Map Preset (stored with the patch), then move a knob.
+pub fn build(cx: &mut Context, params: Arc<MyParams>, inbox: Arc<CcInbox>) {
+    let setup_path = midi_mapping::setup_path();
+    let setup = setup_path.as_deref().map(midi_mapping::load_setup).unwrap_or_default();
+    let param_map = params.param_map();
+    let preset_mappings = params.preset_cc_mappings.read().unwrap_or_else(|e| e.into_inner()).clone();
+
+    let mut model = CcMappingModel {
+        params: params.clone(),
+        inbox,
+        setup_path,
+        setup,
+        preset_mappings,
+        param_ptrs: param_map.iter().map(|(id, ptr, _)| (id.clone(), *ptr)).collect(),
+        param_names: param_map.iter().map(|(id, ptr, _)| (id.clone(), unsafe { ptr.name() }.to_string())).collect(),
+        merged: Vec::new(),
+        rows: Vec::new(),
+        policy: String::new(),
+        target: String::new(),
+        range: "0-100".to_string(),
+        learning: None,
+        last_cc: "-".to_string(),
+        status: String::new(),
+    };
+    model.rebuild();
+    model.build(cx);
+
+    let timer = cx.add_timer(POLL_INTERVAL, None, |cx, action| {
+        if let TimerAction::Tick(_) = action {
+            cx.emit(CcMappingEvent::Poll);
+        }
+    });
+    cx.start_timer(timer);
+
+    HStack::new(cx, |cx| {
+        Label::new(cx, CcMappingModel::last_cc).width(Pixels(110.0)).hoverable(false);
+        Textbox::new(cx, CcMappingModel::target)
+            .on_submit(|cx, text, _| cx.emit(CcMappingEvent::SetTarget(text)))
+            .width(Stretch(1.0));
+        Textbox::new(cx, CcMappingModel::range)
+            .on_submit(|cx, text, _| cx.emit(CcMappingEvent::SetRange(text)))
+            .width(Pixels(70.0));
+        Button::new(cx, |cx| cx.emit(CcMappingEvent::Learn(MappingScope::Global)), |cx| localized_label(cx, "Map Global"))
+            .width(Pixels(96.0));
+        Button::new(cx, |cx| cx.emit(CcMappingEvent::Learn(MappingScope::Preset)), |cx| localized_label(cx, "Map Preset"))
+            .width(Pixels(96.0));
+        Button::new(cx, |cx| cx.emit(CcMappingEvent::CyclePolicy), |cx| Label::new(cx, CcMappingModel::policy))
+            .width(Pixels(110.0));
+    })
+    .height(Pixels(26.0))
+    .col_between(Pixels(6.0));
+
+    List::new(cx, CcMappingModel::rows, |cx, index, row| {
+        HStack::new(cx, |cx| {
+            Label::new(cx, row.map(|r| r.description.clone())).width(Stretch(1.0));
+            Binding::new(cx, LocaleModel::language, move |cx, language| {
+                let language = language.get(cx);
+                Label::new(cx, row.map(move |r| translate(language, if r.global { "Global" } else { "Preset" }).to_string()))
+                    .width(Pixels(64.0))
+                    .opacity(0.6);
+            });
+            Button::new(
+                cx,
+                move |cx| cx.emit(CcMappingEvent::CycleCurve(index)),
+                |cx| localized_label(cx, "Curve"),
+            )
+            .width(Pixels(64.0));
+            Button::new(
+                cx,
+                move |cx| cx.emit(CcMappingEvent::Delete(index)),
+                |cx| localized_label(cx, "Delete"),
+            )
+            .width(Pixels(64.0));
+        })
+        .height(Pixels(26.0))
+        .col_between(Pixels(6.0));
+    })
+    .row_between(Pixels(2.0));
+
+    Label::new(cx, CcMappingModel::status).opacity(0.7).hoverable(false);
+}
@@ -0,0 +1,34 @@
+use nih_plug_vizia::vizia::prelude::*;
+use std::sync::Arc;
+
+use crate::cpu_meter::{CpuLoadState, CpuMeter};
+
+#[derive(Lens)]
+struct CpuIndicatorModel {
+    meter: Arc<CpuMeter>,
+}
+
+impl Model for CpuIndicatorModel {}
+
+/// Audio callback load as a percentage, turning amber when it nears the buffer deadline and
+/// red once it is about to miss it. Clicking it clears the held peak.
+pub fn build(cx: &mut Context, meter: Arc<CpuMeter>) {
+    CpuIndicatorModel { meter: meter.clone() }.build(cx);
+
+    Button::new(
+        cx,
+        move |_| meter.reset_peak(),
+        |cx| {
+            Label::new(cx, CpuIndicatorModel::meter.map(|m| m.describe()))
+                .color(CpuIndicatorModel::meter.map(|m| match m.state() {
+                    CpuLoadState::Normal => Color::rgb(200, 200, 200),
+                    CpuLoadState::Warning => Color::rgb(230, 180, 60),
+                    CpuLoadState::Overload => Color::rgb(230, 80, 70),
+                }))
+                .hoverable(false)
+        },
+    )
+    .width(Pixels(150.0))
+    .top(Stretch(1.0))
+    .bottom(Stretch(1.0));
+}
@@ -0,0 +1,248 @@
+use nih_plug::prelude::ParamPtr;
+use nih_plug_vizia::vizia::prelude::*;
+use nih_plug_vizia::widgets::RawParamEvent;
+use std::sync::Arc;
+
+use super::mod_matrix_panel::ModMatrixEvent;
+use crate::modulation::ModulationRoute;
+use crate::params::MyParams;
+use crate::preset::CompareSlot;
+
+/// Undo steps kept; the oldest is dropped past this.
+const MAX_STEPS: usize = 100;
+
+pub(super) enum EditHistoryEvent {
+    /// Parameter edits up to the matching `EndGroup` undo as one step, e.g. a preset load.
+    /// Carries the routes and the active compare slot from before the step, as changes to
+    /// those don't wait for the event queue.
+    BeginGroup(String, Vec<ModulationRoute>, CompareSlot),
+    EndGroup,
+    Undo,
+    Redo,
+    /// Sent after an undo or redo's own parameter events, so they aren't recorded.
+    EndReplay,
+}
+
+struct ParamChange {
+    param: ParamPtr,
+    before: f32,        // normalized
+    after: f32,
+}
+
+struct EditStep {
+    label: String,
+    params: Vec<ParamChange>,
+    routes: Option<(Vec<ModulationRoute>, Vec<ModulationRoute>)>,  // before and after
+    compare_slot: Option<(CompareSlot, CompareSlot)>,               // before and after an A/B switch
+}
+
+/// Undo and redo of the GUI's parameter edits and preset loads. Edits are picked up from the
+/// widgets' begin/end gestures, so a slider drag is one step and host automation, which
+/// comes without a GUI gesture, is never recorded. Undo and redo only set the parameters a
+/// step changed, inside gestures of their own, so the host records them like any other edit.
+#[derive(Lens)]
+pub(super) struct EditHistoryModel {
+    #[lens(ignore)]
+    params: Arc<MyParams>,
+    #[lens(ignore)]
+    undo: Vec<EditStep>,
+    #[lens(ignore)]
+    redo: Vec<EditStep>,
+    #[lens(ignore)]
+    open: Vec<(ParamPtr, f32)>,     // parameters inside a gesture or group, with their value before it
+    #[lens(ignore)]
+    group: Option<(String, Vec<ModulationRoute>, CompareSlot)>,
+    #[lens(ignore)]
+    replaying: bool,
+    undo_label: String,
+    redo_label: String,
+}
+
+impl EditHistoryEvent {
+    pub fn begin_group(label: impl Into<String>, params: &MyParams) -> Self {
+        let routes = params.modulation_routes.read().unwrap_or_else(|e| e.into_inner()).clone();
+        Self::BeginGroup(label.into(), routes, compare_slot(params))
+    }
+}
+
+fn compare_slot(params: &MyParams) -> CompareSlot {
+    params.compare_slots.read().unwrap_or_else(|e| e.into_inner()).active()
+}
+
+impl EditHistoryModel {
+    pub fn build_for(cx: &mut Context, params: Arc<MyParams>) {
+        Self {
+            params,
+            undo: Vec::new(),
+            redo: Vec::new(),
+            open: Vec::new(),
+            group: None,
+            replaying: false,
+            undo_label: String::new(),
+            redo_label: String::new(),
+        }
+        .build(cx);
+    }
+
+    fn routes(&self) -> Vec<ModulationRoute> {
+        self.params.modulation_routes.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    fn begin_param(&mut self, param: ParamPtr) {
+        if !self.open.iter().any(|(p, _)| *p == param) {
+            self.open.push((param, unsafe { param.unmodulated_normalized_value() }));
+        }
+    }
+
+    fn end_param(&mut self, param: ParamPtr) {
+        if self.group.is_some() {
+            return;
+        }
+        let Some(index) = self.open.iter().position(|(p, _)| *p == param) else {
+            return;
+        };
+        let (_, before) = self.open.remove(index);
+        let change = ParamChange { param, before, after: unsafe { param.unmodulated_normalized_value() } };
+        if change.before != change.after {
+            let label = unsafe { param.name() }.to_string();
+            self.push(EditStep { label, params: vec![change], routes: None, compare_slot: None });
+        }
+    }
+
+    fn end_group(&mut self) {
+        let Some((label, routes_before, slot_before)) = self.group.take() else {
+            return;
+        };
+        let params: Vec<ParamChange> = self
+            .open
+            .drain(..)
+            .map(|(param, before)| ParamChange { param, before, after: unsafe { param.unmodulated_normalized_value() } })
+            .filter(|change| change.before != change.after)
+            .collect();
+        let routes_after = self.routes();
+        let routes = (routes_after != routes_before).then_some((routes_before, routes_after));
+        let slot_after = compare_slot(&self.params);
+        let compare_slot = (slot_after != slot_before).then_some((slot_before, slot_after));
+        if !params.is_empty() || routes.is_some() || compare_slot.is_some() {
+            self.push(EditStep { label, params, routes, compare_slot });
+        }
+    }
+
+    fn push(&mut self, step: EditStep) {
+        self.undo.push(step);
+        if self.undo.len() > MAX_STEPS {
+            self.undo.remove(0);
+        }
+        self.redo.clear();
+        self.update_labels();
+    }
+
+    // Undoing plays a step's `before` values, redoing its `after` values
+    fn replay(&mut self, cx: &mut EventContext, step: &EditStep, undo: bool) {
+        self.replaying = true;
+        for change in &step.params {
+            let value = if undo { change.before } else { change.after };
+            cx.emit(RawParamEvent::BeginSetParameter(change.param));
+            cx.emit(RawParamEvent::SetParameterNormalized(change.param, value));
+            cx.emit(RawParamEvent::EndSetParameter(change.param));
+        }
+        if let Some((before, after)) = &step.routes {
+            let routes = if undo { before } else { after };
+            *self.params.modulation_routes.write().unwrap_or_else(|e| e.into_inner()) = routes.clone();
+            cx.emit_custom(
+                Event::new(ModMatrixEvent::Refresh)
+                    .target(Entity::root())
+                    .propagate(Propagation::Subtree),
+            );
+        }
+        if let Some((before, after)) = step.compare_slot {
+            let slot = if undo { before } else { after };
+            self.params.compare_slots.write().unwrap_or_else(|e| e.into_inner()).set_active(slot);
+        }
+        cx.emit(EditHistoryEvent::EndReplay);
+    }
+
+    fn undo(&mut self, cx: &mut EventContext) {
+        if let Some(step) = self.undo.pop() {
+            self.replay(cx, &step, true);
+            self.redo.push(step);
+            self.update_labels();
+        }
+    }
+
+    fn redo(&mut self, cx: &mut EventContext) {
+        if let Some(step) = self.redo.pop() {
+            self.replay(cx, &step, false);
+            self.undo.push(step);
+            self.update_labels();
+        }
+    }
+
+    fn update_labels(&mut self) {
+        self.undo_label = self.undo.last().map_or(String::new(), |s| s.label.clone());
+        self.redo_label = self.redo.last().map_or(String::new(), |s| s.label.clone());
+    }
+}
+
+impl Model for EditHistoryModel {
+    fn event(&mut self, cx: &mut EventContext, event: &mut Event) {
+        event.map(|param_event, _| {
+            if self.replaying {
+                return;
+            }
+            match *param_event {
+                RawParamEvent::BeginSetParameter(param) => self.begin_param(param),
+                RawParamEvent::EndSetParameter(param) => self.end_param(param),
+                _ => {}
+            }
+        });
+
+        event.map(|history_event, _| match history_event {
+            EditHistoryEvent::BeginGroup(label, routes, compare_slot) => {
+                if self.group.is_none() {
+                    self.group = Some((label.clone(), routes.clone(), *compare_slot));
+                }
+            }
+            EditHistoryEvent::EndGroup => self.end_group(),
+            EditHistoryEvent::Undo => self.undo(cx),
+            EditHistoryEvent::Redo => self.redo(cx),
+            EditHistoryEvent::EndReplay => self.replaying = false,
+        });
+
+        // Ctrl+Z undoes, Ctrl+Shift+Z and Ctrl+Y redo; Cmd stands in for Ctrl on macOS
+        event.map(|window_event, meta| {
+            let WindowEvent::KeyDown(code, _) = window_event else {
+                return;
+            };
+            let modifiers = *cx.modifiers();
+            if !modifiers.intersects(Modifiers::CTRL | Modifiers::LOGO) {
+                return;
+            }
+            match code {
+                Code::KeyZ if modifiers.contains(Modifiers::SHIFT) => self.redo(cx),
+                Code::KeyZ => self.undo(cx),
+                Code::KeyY => self.redo(cx),
+                _ => return,
+            }
+            meta.consume();
+        });
+    }
+}
+
+/// Undo and redo buttons, each naming the step it would take back or do again.
+pub(super) fn build(cx: &mut Context) {
+    Button::new(cx, |cx| cx.emit(EditHistoryEvent::Undo), |cx| {
+        Label::new(cx, EditHistoryModel::undo_label.map(|label| format!("Undo {}", label)))
+    })
+    .width(Pixels(120.0))
+    .disabled(EditHistoryModel::undo_label.map(String::is_empty))
+    .top(Stretch(1.0))
+    .bottom(Stretch(1.0));
+    Button::new(cx, |cx| cx.emit(EditHistoryEvent::Redo), |cx| {
+        Label::new(cx, EditHistoryModel::redo_label.map(|label| format!("Redo {}", label)))
+    })
+    .width(Pixels(120.0))
+    .disabled(EditHistoryModel::redo_label.map(String::is_empty))
+    .top(Stretch(1.0))
+    .bottom(Stretch(1.0));
+}
@@ -0,0 +1,185 @@
+use nih_plug::prelude::Param;
+use nih_plug_vizia::vizia::prelude::*;
+use nih_plug_vizia::vizia::vg;
+use nih_plug_vizia::widgets::ParamEvent;
+use std::sync::Arc;
+
+use crate::params::{EnvelopeKind, EnvelopeParams, MyParams};
+
+const HANDLE_RADIUS: f32 = 5.0;
+const GRAB_DISTANCE: f32 = 10.0;
+
+#[derive(Clone, Copy, PartialEq)]
+enum DragHandle {
+    Attack,
+    DecaySustain,
+    Release,
+}
+
+/// Draws an ADSR curve from the envelope params and lets the user drag its corners.
+/// Attack, decay and release each get a quarter of the width scaled by their normalized
+/// value; the remaining quarter is the sustain plateau.
+pub struct EnvelopeEditor {
+    params: Arc<MyParams>,
+    kind: EnvelopeKind,
+    color: vg::Color,
+    dragging: Option<DragHandle>,
+    mouse: (f32, f32),
+}
+
+impl EnvelopeEditor {
+    pub fn new(cx: &mut Context, params: Arc<MyParams>, kind: EnvelopeKind, color: vg::Color) -> Handle<Self> {
+        Self {
+            params,
+            kind,
+            color,
+            dragging: None,
+            mouse: (0.0, 0.0),
+        }
+        .build(cx, |_| {})
+    }
+
+    fn envelope(&self) -> &EnvelopeParams {
+        self.params.envelope(self.kind)
+    }
+
+    // Corner points of the curve: start, attack peak, decay end, sustain end, release end
+    fn points(&self, bounds: BoundingBox) -> [(f32, f32); 5] {
+        let envelope = self.envelope();
+        let segment = bounds.w / 4.0;
+        let top = bounds.y + HANDLE_RADIUS;
+        let bottom = bounds.y + bounds.h - HANDLE_RADIUS;
+        let sustain_y = bottom - (bottom - top) * envelope.sustain.unmodulated_normalized_value();
+
+        let attack_x = bounds.x + segment * envelope.attack.unmodulated_normalized_value();
+        let decay_x = attack_x + segment * envelope.decay.unmodulated_normalized_value();
+        let sustain_x = decay_x + segment;
+        let release_x = sustain_x + segment * envelope.release.unmodulated_normalized_value();
+
+        [
+            (bounds.x, bottom),
+            (attack_x, top),
+            (decay_x, sustain_y),
+            (sustain_x, sustain_y),
+            (release_x, bottom),
+        ]
+    }
+
+    fn handle_at(&self, bounds: BoundingBox, x: f32, y: f32) -> Option<DragHandle> {
+        let points = self.points(bounds);
+        [
+            (DragHandle::Attack, points[1]),
+            (DragHandle::DecaySustain, points[2]),
+            (DragHandle::Release, points[4]),
+        ]
+        .into_iter()
+        .find(|(_, (px, py))| (px - x).hypot(py - y) <= GRAB_DISTANCE)
+        .map(|(handle, _)| handle)
+    }
+
+    fn set_normalized<P: Param>(cx: &mut EventContext, param: &P, value: f32) {
+        cx.emit(ParamEvent::SetParameterNormalized(param, value.clamp(0.0, 1.0)).upcast());
+    }
+
+    fn drag(&self, cx: &mut EventContext, handle: DragHandle, x: f32, y: f32) {
+        let bounds = cx.bounds();
+        let points = self.points(bounds);
+        let segment = bounds.w / 4.0;
+        let envelope = self.envelope();
+
+        match handle {
+            DragHandle::Attack => {
+                Self::set_normalized(cx, &envelope.attack, (x - points[0].0) / segment);
+            }
+            DragHandle::DecaySustain => {
+                Self::set_normalized(cx, &envelope.decay, (x - points[1].0) / segment);
+                let top = bounds.y + HANDLE_RADIUS;
+                let bottom = bounds.y + bounds.h - HANDLE_RADIUS;
+                Self::set_normalized(cx, &envelope.sustain, (bottom - y) / (bottom - top));
+            }
+            DragHandle::Release => {
+                Self::set_normalized(cx, &envelope.release, (x - points[3].0) / segment);
+            }
+        }
+    }
+
+    fn begin_or_end<P: Param>(cx: &mut EventContext, param: &P, begin: bool) {
+        if begin {
+            cx.emit(ParamEvent::BeginSetParameter(param).upcast());
+        } else {
+            cx.emit(ParamEvent::EndSetParameter(param).upcast());
+        }
+    }
+
+    fn gesture(&self, cx: &mut EventContext, handle: DragHandle, begin: bool) {
+        let envelope = self.envelope();
+        match handle {
+            DragHandle::Attack => Self::begin_or_end(cx, &envelope.attack, begin),
+            DragHandle::DecaySustain => {
+                Self::begin_or_end(cx, &envelope.decay, begin);
+                Self::begin_or_end(cx, &envelope.sustain, begin);
+            }
+            DragHandle::Release => Self::begin_or_end(cx, &envelope.release, begin),
+        }
+    }
+}
+
+impl View for EnvelopeEditor {
+    fn element(&self) -> Option<&'static str> {
+        Some("envelope-editor")
+    }
+
+    fn event(&mut self, cx: &mut EventContext, event: &mut Event) {
+        event.map(|window_event, meta| match *window_event {
+            WindowEvent::MouseDown(MouseButton::Left) => {
+                let (x, y) = self.mouse;
+                if let Some(handle) = self.handle_at(cx.bounds(), x, y) {
+                    self.dragging = Some(handle);
+                    self.gesture(cx, handle, true);
+                    cx.capture();
+                    meta.consume();
+                }
+            }
+            WindowEvent::MouseUp(MouseButton::Left) => {
+                if let Some(handle) = self.dragging.take() {
+                    self.gesture(cx, handle, false);
+                    cx.release();
+                    meta.consume();
+                }
+            }
+            WindowEvent::MouseMove(x, y) => {
+                self.mouse = (x, y);
+                if let Some(handle) = self.dragging {
+                    self.drag(cx, handle, x, y);
+                    cx.needs_redraw();
+                }
+            }
+            _ => {}
+        });
+    }
+
+    fn draw(&self, cx: &mut DrawContext, canvas: &mut Canvas) {
+        let bounds = cx.bounds();
+        if bounds.w == 0.0 || bounds.h == 0.0 {
+            return;
+        }
+
+        let mut background = vg::Path::new();
+        background.rect(bounds.x, bounds.y, bounds.w, bounds.h);
+        canvas.fill_path(&background, &vg::Paint::color(vg::Color::rgb(24, 25, 30)));
+
+        let points = self.points(bounds);
+        let mut curve = vg::Path::new();
+        curve.move_to(points[0].0, points[0].1);
+        for &(x, y) in &points[1..] {
+            curve.line_to(x, y);
+        }
+        canvas.stroke_path(&curve, &vg::Paint::color(self.color).with_line_width(2.0));
+
+        let mut handles = vg::Path::new();
+        for &(x, y) in [points[1], points[2], points[4]].iter() {
+            handles.circle(x, y, HANDLE_RADIUS);
+        }
+        canvas.fill_path(&handles, &vg::Paint::color(self.color));
+    }
+}
@@ -0,0 +1,127 @@
+use nih_plug::prelude::Param;
+use nih_plug_vizia::vizia::prelude::*;
+use nih_plug_vizia::vizia::vg;
+use nih_plug_vizia::widgets::ParamEvent;
+use std::sync::Arc;
+
+use super::edit_history::EditHistoryEvent;
+use super::locale::localized_label;
+use crate::oscillator::{DrawbarPreset, HARMONIC_COUNT};
+use crate::params::MyParams;
+
+const BAR_GAP: f32 = 2.0;
+
+/// The additive waveform's harmonic levels as a bar per harmonic, the fundamental on the
+/// left. Clicking or dragging over the bars draws the levels; each bar touched is its own
+/// parameter gesture.
+pub struct HarmonicEditor {
+    params: Arc<MyParams>,
+    dragging: Option<usize>,   // the bar inside a gesture
+    mouse: (f32, f32),
+}
+
+impl HarmonicEditor {
+    pub fn new(cx: &mut Context, params: Arc<MyParams>) -> Handle<Self> {
+        Self {
+            params,
+            dragging: None,
+            mouse: (0.0, 0.0),
+        }
+        .build(cx, |_| {})
+    }
+
+    fn bar_at(bounds: BoundingBox, x: f32) -> usize {
+        let index = ((x - bounds.x) / bounds.w * HARMONIC_COUNT as f32).floor();
+        index.clamp(0.0, (HARMONIC_COUNT - 1) as f32) as usize
+    }
+
+    fn draw_level(&mut self, cx: &mut EventContext, x: f32, y: f32) {
+        let bounds = cx.bounds();
+        let bar = Self::bar_at(bounds, x);
+        if self.dragging != Some(bar) {
+            if let Some(previous) = self.dragging.replace(bar) {
+                cx.emit(ParamEvent::EndSetParameter(&self.params.harmonics[previous].level).upcast());
+            }
+            cx.emit(ParamEvent::BeginSetParameter(&self.params.harmonics[bar].level).upcast());
+        }
+        let level = ((bounds.y + bounds.h - y) / bounds.h).clamp(0.0, 1.0);
+        cx.emit(ParamEvent::SetParameterNormalized(&self.params.harmonics[bar].level, level).upcast());
+    }
+}
+
+impl View for HarmonicEditor {
+    fn element(&self) -> Option<&'static str> {
+        Some("harmonic-editor")
+    }
+
+    fn event(&mut self, cx: &mut EventContext, event: &mut Event) {
+        event.map(|window_event, meta| match *window_event {
+            WindowEvent::MouseDown(MouseButton::Left) => {
+                let (x, y) = self.mouse;
+                self.draw_level(cx, x, y);
+                cx.capture();
+                meta.consume();
+            }
+            WindowEvent::MouseUp(MouseButton::Left) => {
+                if let Some(bar) = self.dragging.take() {
+                    cx.emit(ParamEvent::EndSetParameter(&self.params.harmonics[bar].level).upcast());
+                    cx.release();
+                    meta.consume();
+                }
+            }
+            WindowEvent::MouseMove(x, y) => {
+                self.mouse = (x, y);
+                if self.dragging.is_some() {
+                    self.draw_level(cx, x, y);
+                    cx.needs_redraw();
+                }
+            }
+            _ => {}
+        });
+    }
+
+    fn draw(&self, cx: &mut DrawContext, canvas: &mut Canvas) {
+        let bounds = cx.bounds();
+        if bounds.w == 0.0 || bounds.h == 0.0 {
+            return;
+        }
+
+        let mut background = vg::Path::new();
+        background.rect(bounds.x, bounds.y, bounds.w, bounds.h);
+        canvas.fill_path(&background, &vg::Paint::color(vg::Color::rgb(24, 25, 30)));
+
+        let width = bounds.w / HARMONIC_COUNT as f32;
+        let mut bars = vg::Path::new();
+        for (index, harmonic) in self.params.harmonics.iter().enumerate() {
+            let height = bounds.h * harmonic.level.unmodulated_normalized_value();
+            let x = bounds.x + index as f32 * width + BAR_GAP * 0.5;
+            bars.rect(x, bounds.y + bounds.h - height, (width - BAR_GAP).max(1.0), height);
+        }
+        canvas.fill_path(&bars, &vg::Paint::color(vg::Color::rgb(230, 190, 90)));
+    }
+}
+
+/// A button per drawbar preset, each setting every harmonic level as one undo step.
+pub fn build_presets(cx: &mut Context, params: Arc<MyParams>) {
+    HStack::new(cx, |cx| {
+        for preset in DrawbarPreset::ALL {
+            let params = params.clone();
+            Button::new(
+                cx,
+                move |cx| {
+                    cx.emit(EditHistoryEvent::begin_group(preset.label(), &params));
+                    for (harmonic, level) in params.harmonics.iter().zip(preset.harmonics().0) {
+                        cx.emit(ParamEvent::BeginSetParameter(&harmonic.level).upcast());
+                        cx.emit(ParamEvent::SetParameter(&harmonic.level, level).upcast());
+                        cx.emit(ParamEvent::EndSetParameter(&harmonic.level).upcast());
+                    }
+                    cx.emit(EditHistoryEvent::EndGroup);
+                },
+                move |cx| localized_label(cx, preset.label()),
+            )
+            .width(Stretch(1.0));
+        }
+    })
+    .height(Pixels(26.0))
+    .col_between(Pixels(4.0));
+}
@@ -0,0 +1,160 @@
+use nih_plug::prelude::Params;
+use nih_plug_vizia::vizia::prelude::*;
+use nih_plug_vizia::widgets::RawParamEvent;
+use std::sync::Arc;
+use std::time::Duration;
+
+use super::edit_history::EditHistoryEvent;
+use super::locale::localized_label;
+use super::mod_matrix_panel::ModMatrixEvent;
+use crate::params::MyParams;
+use crate::preset::{self, ParamDiff, MAIN_SECTION};
+
+/// How often the list is compared against the patch again.
+const REFRESH_INTERVAL: Duration = Duration::from_millis(500);
+const ROUTES_SECTION: &str = "Mod Matrix";
+
+#[derive(Clone, PartialEq, Data)]
+struct DiffRow {
+    section: String,
+    text: String,
+    header: bool,   // first row of a section, which carries the revert button
+}
+
+enum InitDiffEvent {
+    Refresh,
+    RevertSection(String),
+}
+
+#[derive(Lens)]
+struct InitDiffModel {
+    #[lens(ignore)]
+    params: Arc<MyParams>,
+    #[lens(ignore)]
+    diffs: Vec<ParamDiff>,
+    #[lens(ignore)]
+    routes_changed: bool,
+    rows: Vec<DiffRow>,
+    summary: String,
+}
+
+impl InitDiffModel {
+    fn refresh(&mut self) {
+        let diffs = preset::diff_from_init(&self.params);
+        let routes_changed = preset::routes_differ_from_init(&self.params);
+        if diffs == self.diffs && routes_changed == self.routes_changed {
+            return;
+        }
+        self.diffs = diffs;
+        self.routes_changed = routes_changed;
+        self.rebuild_rows();
+    }
+
+    fn rebuild_rows(&mut self) {
+        let mut rows: Vec<DiffRow> = Vec::new();
+        for diff in &self.diffs {
+            rows.push(DiffRow {
+                section: diff.section.clone(),
+                text: format!("{}: {} (init {})", diff.name, diff.value, diff.init_value),
+                header: rows.last().is_none_or(|r| r.section != diff.section),
+            });
+        }
+        if self.routes_changed {
+            rows.push(DiffRow {
+                section: ROUTES_SECTION.to_string(),
+                text: "Routes differ from init".to_string(),
+                header: true,
+            });
+        }
+        self.rows = rows;
+        self.summary = match (self.diffs.len(), self.routes_changed) {
+            (0, false) => "Same as init".to_string(),
+            (0, true) => "Mod matrix changed from init".to_string(),
+            (count, false) => format!("{} parameters changed from init", count),
+            (count, true) => format!("{} parameters and the mod matrix changed from init", count),
+        };
+    }
+
+    fn revert_section(&mut self, cx: &mut EventContext, section: &str) {
+        let init_preset = preset::init_preset();
+        cx.emit(EditHistoryEvent::begin_group(format!("Revert {}", section), &self.params));
+        if section == ROUTES_SECTION {
+            *self.params.modulation_routes.write().unwrap_or_else(|e| e.into_inner()) = init_preset.modulation_routes.clone();
+            cx.emit_custom(
+                Event::new(ModMatrixEvent::Refresh)
+                    .target(Entity::root())
+                    .propagate(Propagation::Subtree),
+            );
+        } else {
+            for (id, ptr, group) in self.params.param_map() {
+                let group = if group.is_empty() { MAIN_SECTION } else { group.as_str() };
+                if group != section || !self.diffs.iter().any(|d| d.id == id) {
+                    continue;
+                }
+                let init = init_preset.normalized_value(&id, ptr);
+                cx.emit(RawParamEvent::BeginSetParameter(ptr));
+                cx.emit(RawParamEvent::SetParameterNormalized(ptr, init));
+                cx.emit(RawParamEvent::EndSetParameter(ptr));
+            }
+        }
+        cx.emit(EditHistoryEvent::EndGroup);
+    }
+}
+
+impl Model for InitDiffModel {
+    fn event(&mut self, cx: &mut EventContext, event: &mut Event) {
+        event.map(|diff_event, _| match diff_event {
+            InitDiffEvent::Refresh => self.refresh(),
+            InitDiffEvent::RevertSection(section) => self.revert_section(cx, section),
+        });
+    }
+}
+
+/// Every parameter that differs from the init patch, grouped by section, each section
+/// with a button that puts just that section back to init.
+pub fn build(cx: &mut Context, params: Arc<MyParams>) {
+    let mut model = InitDiffModel {
+        params,
+        diffs: Vec::new(),
+        routes_changed: false,
+        rows: Vec::new(),
+        summary: String::new(),
+    };
+    model.refresh();
+    model.rebuild_rows();
+    model.build(cx);
+
+    let timer = cx.add_timer(REFRESH_INTERVAL, None, |cx, action| {
+        if let TimerAction::Tick(_) = action {
+            cx.emit(InitDiffEvent::Refresh);
+        }
+    });
+    cx.start_timer(timer);
+
+    Label::new(cx, InitDiffModel::summary).opacity(0.7).hoverable(false);
+
+    List::new(cx, InitDiffModel::rows, |cx, _, row| {
+        HStack::new(cx, |cx| {
+            Label::new(cx, row.map(|r| if r.header { r.section.clone() } else { String::new() }))
+                .width(Pixels(110.0))
+                .hoverable(false);
+            Label::new(cx, row.map(|r| r.text.clone())).width(Stretch(1.0)).hoverable(false);
+            Binding::new(cx, row.map(|r| r.header), move |cx, header| {
+                if header.get(cx) {
+                    let section = row.get(cx).section;
+                    Button::new(
+                        cx,
+                        move |cx| cx.emit(InitDiffEvent::RevertSection(section.clone())),
+                        |cx| localized_label(cx, "Revert"),
+                    )
+                    .width(Pixels(80.0));
+                } else {
+                    Element::new(cx).width(Pixels(80.0));
+                }
+            });
+        })
+        .height(Pixels(24.0))
+        .col_between(Pixels(6.0));
+    })
+    .row_between(Pixels(2.0));
+}
@@ -0,0 +1,87 @@
+use nih_plug_vizia::vizia::prelude::*;
+use nih_plug_vizia::vizia::vg;
+use std::sync::Arc;
+
+use crate::level_meter::LevelMeter;
+
+const METER_HEIGHT: f32 = 24.0;
+const LEVEL_FLOOR_DB: f32 = -60.0;
+/// Where the bars turn from green to yellow and from yellow to red.
+const WARN_DB: f32 = -12.0;
+const CLIP_DB: f32 = -1.0;
+
+#[derive(Lens)]
+struct LevelMeterModel {
+    meter: Arc<LevelMeter>,
+}
+
+impl Model for LevelMeterModel {}
+
+/// Stereo output meter: the peak and RMS levels in dB, turning red once a peak reaches full
+/// scale, over a bar per channel filled to the RMS level with a tick at the held peak.
+pub fn build(cx: &mut Context, meter: Arc<LevelMeter>) {
+    LevelMeterModel { meter }.build(cx);
+
+    Label::new(cx, LevelMeterModel::meter.map(|m| m.describe()))
+        .height(Pixels(super::ROW_HEIGHT))
+        .width(Stretch(1.0))
+        .background_color(LevelMeterModel::meter.map(|m| {
+            if m.clipping() { Color::rgb(120, 40, 36) } else { Color::rgba(0, 0, 0, 0) }
+        }))
+        .hoverable(false);
+
+    // Rebuilt on every published block, which keeps the bars moving
+    Binding::new(cx, LevelMeterModel::meter.map(|m| m.generation()), move |cx, _| {
+        let meter = LevelMeterModel::meter.get(cx);
+        StereoBars { meter }.build(cx, |_| {}).height(Pixels(METER_HEIGHT));
+    });
+}
+
+struct StereoBars {
+    meter: Arc<LevelMeter>,
+}
+
+impl View for StereoBars {
+    fn element(&self) -> Option<&'static str> {
+        Some("level-meter")
+    }
+
+    fn draw(&self, cx: &mut DrawContext, canvas: &mut Canvas) {
+        let bounds = cx.bounds();
+        if bounds.w == 0.0 || bounds.h == 0.0 {
+            return;
+        }
+
+        let mut background = vg::Path::new();
+        background.rect(bounds.x, bounds.y, bounds.w, bounds.h);
+        canvas.fill_path(&background, &vg::Paint::color(vg::Color::rgb(40, 44, 52)));
+
+        let to_db = |level: f32| 20.0 * level.max(1e-6).log10();
+        let fill = |db: f32| ((db - LEVEL_FLOOR_DB) / -LEVEL_FLOOR_DB).clamp(0.0, 1.0);
+        let color = |db: f32| {
+            if db >= CLIP_DB {
+                vg::Color::rgb(220, 80, 70)
+            } else if db >= WARN_DB {
+                vg::Color::rgb(230, 200, 80)
+            } else {
+                vg::Color::rgb(90, 200, 120)
+            }
+        };
+
+        let row = bounds.h / 2.0;
+        for channel in 0..2 {
+            let y = bounds.y + channel as f32 * row + 1.0;
+            let height = (row - 2.0).max(1.0);
+
+            let rms_db = to_db(self.meter.rms(channel));
+            let mut bar = vg::Path::new();
+            bar.rect(bounds.x, y, bounds.w * fill(rms_db), height);
+            canvas.fill_path(&bar, &vg::Paint::color(color(rms_db)));
+
+            let peak_db = to_db(self.meter.peak(channel));
+            let mut tick = vg::Path::new();
+            tick.rect(bounds.x + (bounds.w * fill(peak_db) - 2.0).max(0.0), y, 2.0, height);
+            canvas.fill_path(&tick, &vg::Paint::color(color(peak_db)));
+        }
+    }
+}
@@ -0,0 +1,172 @@
+use nih_plug_vizia::vizia::prelude::*;
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, OnceLock};
+
+use crate::params::MyParams;
+use crate::preset;
+
+/// Translations shipped inside the plugin, as `(code, table)`. A plugin bundle has nowhere
+/// else to keep them; more languages can be added as files without rebuilding.
+const BUNDLED: [(&str, &str); 1] = [("de", include_str!("locales/de.txt"))];
+
+/// Subdirectory of the user data directory holding `<code>.txt` translation files. A file
+/// for a bundled language adds to it and overrides its entries.
+const LOCALE_DIR: &str = "locales";
+
+/// One GUI language. The English text is the lookup key, so English has an empty table;
+/// every other language is a table of `English text = translation` lines, plus an optional
+/// `@name = ...` line naming the language in itself.
+struct Locale {
+    code: String,
+    name: String,
+    table: HashMap<String, String>,
+}
+
+impl Locale {
+    fn english() -> Self {
+        Self { code: "en".into(), name: "English".into(), table: HashMap::new() }
+    }
+
+    fn parse(code: &str, source: &str) -> Self {
+        let mut locale = Self { code: code.to_string(), name: code.to_string(), table: HashMap::new() };
+        locale.merge(source);
+        locale
+    }
+
+    fn merge(&mut self, source: &str) {
+        let entries = source
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .filter_map(|line| line.split_once(" = "))
+            .map(|(key, value)| (key.trim(), value.trim()));
+        for (key, value) in entries {
+            if key == "@name" {
+                self.name = value.to_string();
+            } else {
+                self.table.insert(key.to_string(), value.to_string());
+            }
+        }
+    }
+}
+
+static LOCALES: OnceLock<Vec<Locale>> = OnceLock::new();
+
+// English first, then the bundled languages, then any others found on disk. Loaded on the
+// first lookup, on the GUI thread
+fn locales() -> &'static [Locale] {
+    LOCALES.get_or_init(|| {
+        let mut locales = vec![Locale::english()];
+        locales.extend(BUNDLED.iter().map(|(code, source)| Locale::parse(code, source)));
+
+        let dir = preset::user_data_dir().map(|dir| dir.join(LOCALE_DIR));
+        let mut files: Vec<PathBuf> = dir
+            .and_then(|dir| fs::read_dir(dir).ok())
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "txt"))
+            .collect();
+        files.sort();
+        for path in files {
+            let (Some(code), Ok(source)) = (path.file_stem().and_then(|s| s.to_str()), fs::read_to_string(&path)) else {
+                continue;
+            };
+            match locales.iter_mut().find(|l| l.code == code) {
+                Some(locale) => locale.merge(&source),
+                None => locales.push(Locale::parse(code, &source)),
+            }
+        }
+        locales
+    })
+}
+
+/// A GUI language, by its position among the loaded locales.
+#[derive(Clone, Copy, PartialEq, Data)]
+pub(super) struct Language(usize);
+
+impl Language {
+    pub const ENGLISH: Language = Language(0);
+
+    fn locale(self) -> &'static Locale {
+        &locales()[self.0]
+    }
+
+    pub fn code(self) -> &'static str {
+        &self.locale().code
+    }
+
+    /// Shown in the language selector, in the language itself.
+    pub fn name(self) -> &'static str {
+        &self.locale().name
+    }
+
+    pub fn from_code(code: &str) -> Self {
+        locales().iter().position(|l| l.code == code).map_or(Self::ENGLISH, Language)
+    }
+
+    fn next(self) -> Self {
+        Language((self.0 + 1) % locales().len())
+    }
+}
+
+/// Text for `key` in `language`; keys missing from a table fall back to the English text.
+pub(super) fn translate(language: Language, key: &str) -> &str {
+    language.locale().table.get(key).map_or(key, String::as_str)
+}
+
+pub(super) enum LocaleEvent {
+    CycleLanguage,
+}
+
+/// The selected GUI language, persisted per plugin instance.
+#[derive(Lens)]
+pub(super) struct LocaleModel {
+    params: Arc<MyParams>,
+    pub language: Language,
+}
+
+impl LocaleModel {
+    pub fn build_for(cx: &mut Context, params: Arc<MyParams>) {
+        let language = Language::from_code(&params.language.read().unwrap_or_else(|e| e.into_inner()));
+        Self { params, language }.build(cx);
+    }
+}
+
+impl Model for LocaleModel {
+    fn event(&mut self, _cx: &mut EventContext, event: &mut Event) {
+        event.map(|locale_event, _| match locale_event {
+            LocaleEvent::CycleLanguage => {
+                self.language = self.language.next();
+                *self.params.language.write().unwrap_or_else(|e| e.into_inner()) = self.language.code().to_string();
+            }
+        });
+    }
+}
+
+/// A label that follows the selected language.
+pub(super) fn localized_label<'a>(cx: &'a mut Context, key: &'static str) -> Handle<'a, Label> {
+    Label::new(cx, LocaleModel::language.map(move |l| translate(*l, key).to_string()))
+}
+
+/// Hover text that follows the selected language, one line per key.
+pub(super) fn localized_tooltip<'a>(cx: &'a mut Context, lines: Vec<String>) -> Handle<'a, Tooltip> {
+    Tooltip::new(cx, move |cx| {
+        for line in lines {
+            Label::new(cx, LocaleModel::language.map(move |l| translate(*l, &line).to_string()));
+        }
+    })
+}
+
+/// Button cycling through the available languages.
+pub(super) fn language_selector(cx: &mut Context) {
+    Button::new(
+        cx,
+        |cx| cx.emit(LocaleEvent::CycleLanguage),
+        |cx| Label::new(cx, LocaleModel::language.map(|l| l.name().to_string())),
+    )
+    .width(Pixels(90.0))
+    .tooltip(|cx| localized_tooltip(cx, vec!["GUI language; more can be added as text files".to_string()]));
+}
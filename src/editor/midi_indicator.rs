@@ -0,0 +1,33 @@
+use nih_plug_vizia::vizia::prelude::*;
+use std::sync::Arc;
+
+use crate::midi_monitor::MidiMonitor;
+
+#[derive(Lens)]
+struct MidiIndicatorModel {
+    monitor: Arc<MidiMonitor>,
+}
+
+impl Model for MidiIndicatorModel {}
+
+/// Activity light that flashes on incoming MIDI, next to a readout of the last event.
+pub fn build(cx: &mut Context, monitor: Arc<MidiMonitor>) {
+    MidiIndicatorModel { monitor }.build(cx);
+
+    HStack::new(cx, |cx| {
+        Element::new(cx)
+            .size(Pixels(10.0))
+            .border_radius(Pixels(5.0))
+            .top(Stretch(1.0))
+            .bottom(Stretch(1.0))
+            .background_color(MidiIndicatorModel::monitor.map(|m| {
+                if m.is_active() { Color::rgb(90, 220, 120) } else { Color::rgb(60, 64, 72) }
+            }));
+        Label::new(cx, MidiIndicatorModel::monitor.map(|m| m.describe_last()))
+            .width(Pixels(200.0))
+            .opacity(0.8)
+            .hoverable(false);
+    })
+    .width(Auto)
+    .col_between(Pixels(6.0));
+}
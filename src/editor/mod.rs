@@ -0,0 +1,504 @@
+use nih_plug::prelude::{AsyncExecutor, Editor, Param};
+use nih_plug_vizia::vizia::prelude::*;
+use nih_plug_vizia::vizia::vg;
+use nih_plug_vizia::widgets::*;
+use nih_plug_vizia::{assets, create_vizia_editor, ViziaState, ViziaTheming};
+use std::sync::Arc;
+
+use crate::background::TaskResults;
+use crate::cpu_meter::CpuMeter;
+use crate::keyboard::KeyboardState;
+use crate::level_meter::LevelMeter;
+use crate::midi_mapping::CcInbox;
+use crate::midi_monitor::MidiMonitor;
+use crate::params::{EnvelopeKind, MyParams};
+use crate::preset::{self, ProgramChangeInbox};
+use crate::scope::ScopeBuffer;
+use crate::sysex::ParamInbox;
+use crate::voice_meter::VoiceMeter;
+use crate::MySynth;
+
+mod cc_mapping_panel;
+mod cpu_indicator;
+mod edit_history;
+mod envelope_editor;
+mod harmonic_editor;
+mod init_diff_view;
+mod level_meter_view;
+mod locale;
+mod midi_indicator;
+mod mod_matrix_panel;
+mod param_keyboard_control;
+mod preset_browser;
+mod sample_panel;
+mod scope_view;
+mod sysex_receiver;
+mod virtual_keyboard;
+mod voice_meter_view;
+
+use envelope_editor::EnvelopeEditor;
+use harmonic_editor::HarmonicEditor;
+use locale::{localized_label, localized_tooltip, translate, LocaleModel};
+use param_keyboard_control::ParamKeyboardControl;
+use virtual_keyboard::VirtualKeyboard;
+
+const LABEL_WIDTH: f32 = 90.0;
+const ROW_HEIGHT: f32 = 28.0;
+const ENVELOPE_EDITOR_HEIGHT: f32 = 80.0;
+const SCOPE_HEIGHT: f32 = 170.0;
+const KEYBOARD_HEIGHT: f32 = 90.0;
+
+/// Samples kept for the oscilloscope and spectrum views.
+pub(crate) const SCOPE_CAPACITY: usize = 8192;
+
+#[derive(Lens)]
+struct ParamsModel {
+    params: Arc<MyParams>,
+}
+
+impl Model for ParamsModel {}
+
+enum SectionEvent {
+    Toggle(&'static str),
+}
+
+/// Which sections are collapsed; every toggle is written back to the persisted params.
+#[derive(Lens)]
+struct SectionsModel {
+    params: Arc<MyParams>,
+    collapsed: Vec<String>,
+}
+
+impl Model for SectionsModel {
+    fn event(&mut self, _cx: &mut EventContext, event: &mut Event) {
+        event.map(|section_event, _| match *section_event {
+            SectionEvent::Toggle(title) => {
+                let mut collapsed = self.params.collapsed_sections.write().unwrap_or_else(|e| e.into_inner());
+                if let Some(index) = collapsed.iter().position(|t| t == title) {
+                    collapsed.remove(index);
+                } else {
+                    collapsed.push(title.to_string());
+                }
+                self.collapsed = collapsed.clone();
+            }
+        });
+    }
+}
+
+pub(crate) fn default_state() -> Arc<ViziaState> {
+    ViziaState::new(|| (900, 3900))
+}
+
+pub(crate) fn create(
+    params: Arc<MyParams>,
+    async_executor: AsyncExecutor<MySynth>,
+    task_results: Arc<TaskResults>,
+    editor_state: Arc<ViziaState>,
+    scope: Arc<ScopeBuffer>,
+    keyboard: Arc<KeyboardState>,
+    midi_monitor: Arc<MidiMonitor>,
+    cc_inbox: Arc<CcInbox>,
+    program_inbox: Arc<ProgramChangeInbox>,
+    sysex_inbox: Arc<ParamInbox>,
+    voice_meter: Arc<VoiceMeter>,
+    level_meter: Arc<LevelMeter>,
+    cpu_meter: Arc<CpuMeter>,
+) -> Option<Box<dyn Editor>> {
+    create_vizia_editor(editor_state, ViziaTheming::Custom, move |cx, _| {
+        assets::register_noto_sans_light(cx);
+
+        ParamsModel { params: params.clone() }.build(cx);
+        edit_history::EditHistoryModel::build_for(cx, params.clone());
+        SectionsModel {
+            params: params.clone(),
+            collapsed: params.collapsed_sections.read().unwrap_or_else(|e| e.into_inner()).clone(),
+        }
+        .build(cx);
+        LocaleModel::build_for(cx, params.clone());
+
+        VStack::new(cx, |cx| {
+            HStack::new(cx, |cx| {
+                Label::new(cx, "My Rust Synth")
+                    .font_size(24.0)
+                    .width(Auto)
+                    .hoverable(false);
+                Label::new(cx, ParamsModel::params.map(|p| preset::patch_title(p)))
+                    .font_size(16.0)
+                    .left(Pixels(12.0))
+                    .width(Stretch(1.0))
+                    .top(Stretch(1.0))
+                    .bottom(Stretch(1.0))
+                    .opacity(0.8)
+                    .hoverable(false);
+                edit_history::build(cx);
+                let panic_keyboard = keyboard.clone();
+                Button::new(cx, move |_| panic_keyboard.request_panic(), |cx| localized_label(cx, "Panic"))
+                    .tooltip(|cx| localized_tooltip(cx, vec!["Silences every voice at once".to_string()]))
+                    .width(Pixels(72.0))
+                    .top(Stretch(1.0))
+                    .bottom(Stretch(1.0));
+                cpu_indicator::build(cx, cpu_meter.clone());
+                midi_indicator::build(cx, midi_monitor.clone());
+                locale::language_selector(cx);
+            })
+            .height(Pixels(36.0));
+
+            section(cx, "PRESETS", |cx| {
+                preset_browser::build(
+                    cx,
+                    params.clone(),
+                    program_inbox.clone(),
+                    async_executor.clone(),
+                    task_results.clone(),
+                );
+            });
+
+            section(cx, "CHANGES", |cx| {
+                init_diff_view::build(cx, params.clone());
+            });
+
+            section(cx, "MIDI MAP", |cx| {
+                cc_mapping_panel::build(cx, params.clone(), cc_inbox.clone());
+                sysex_receiver::build(cx, params.clone(), sysex_inbox.clone());
+            });
+
+            HStack::new(cx, |cx| {
+                section(cx, "OSC", |cx| {
+                    param_row(cx, "Osc 1", |p| &p.osc1.waveform);
+                    param_row(cx, "Octave", |p| &p.osc1.octave);
+                    param_row(cx, "Detune", |p| &p.osc1.detune);
+                    param_row(cx, "Volume", |p| &p.osc1.volume);
+                    param_row(cx, "Phase", |p| &p.osc1.phase);
+                    param_row(cx, "Phase Mode", |p| &p.osc1.phase_mode);
+                    param_row(cx, "Damping", |p| &p.osc1.damping);
+                    param_row(cx, "Saw Detune", |p| &p.osc1.supersaw_detune);
+                    param_row(cx, "Saw Mix", |p| &p.osc1.supersaw_mix);
+                    toggle_row(cx, |p| &p.osc1.mute);
+                    toggle_row(cx, |p| &p.osc1.solo);
+                    param_row(cx, "Osc 2", |p| &p.osc2.waveform);
+                    param_row(cx, "Octave", |p| &p.osc2.octave);
+                    param_row(cx, "Detune", |p| &p.osc2.detune);
+                    param_row(cx, "Volume", |p| &p.osc2.volume);
+                    param_row(cx, "Phase", |p| &p.osc2.phase);
+                    param_row(cx, "Phase Mode", |p| &p.osc2.phase_mode);
+                    param_row(cx, "Damping", |p| &p.osc2.damping);
+                    param_row(cx, "Saw Detune", |p| &p.osc2.supersaw_detune);
+                    param_row(cx, "Saw Mix", |p| &p.osc2.supersaw_mix);
+                    toggle_row(cx, |p| &p.osc2.mute);
+                    toggle_row(cx, |p| &p.osc2.solo);
+                    toggle_row(cx, |p| &p.oscillator_gain_compensation);
+                    param_row(cx, "Sub", |p| &p.sub_level);
+                    param_row(cx, "Spread", |p| &p.detune_spread);
+                    param_row(cx, "Voice Mode", |p| &p.voice_mode);
+                    param_row(cx, "Glide", |p| &p.glide_time);
+                    param_row(cx, "Glide Mode", |p| &p.glide_mode);
+                    param_row(cx, "Glide Rate", |p| &p.glide_rate);
+                    toggle_row(cx, |p| &p.glide_legato);
+                    param_row(cx, "Vibrato", |p| &p.vibrato_depth);
+                    param_row(cx, "Vib Rate", |p| &p.vibrato_rate);
+                    param_row(cx, "Vib Delay", |p| &p.vibrato_delay);
+                    param_row(cx, "Vib Fade", |p| &p.vibrato_fade_in);
+                    param_row(cx, "Drift", |p| &p.drift_pitch);
+                    param_row(cx, "Detune Rnd", |p| &p.drift_detune);
+                });
+
+                section(cx, "SAMPLE", |cx| {
+                    sample_panel::build(cx, params.clone(), task_results.clone());
+                    param_row(cx, "Sample", |p| &p.sample.level);
+                    param_row(cx, "Root Key", |p| &p.sample.root_note);
+                    param_row(cx, "Start", |p| &p.sample.start);
+                    param_row(cx, "Loop", |p| &p.sample.loop_mode);
+                    param_row(cx, "Loop Start", |p| &p.sample.loop_start);
+                    param_row(cx, "Loop End", |p| &p.sample.loop_end);
+                });
+
+                section(cx, "HARMONICS", |cx| {
+                    HarmonicEditor::new(cx, params.clone())
+                        .height(Pixels(ENVELOPE_EDITOR_HEIGHT));
+                    harmonic_editor::build_presets(cx, params.clone());
+                });
+
+                section(cx, "ZONES", |cx| {
+                    param_row(cx, "Zone Mode", |p| &p.zone_mode);
+                    param_row(cx, "Split Key", |p| &p.split_key);
+                    param_row(cx, "A Oscs", |p| &p.zone_a.oscillators);
+                    param_row(cx, "A Transp", |p| &p.zone_a.transpose);
+                    param_row(cx, "A Level", |p| &p.zone_a.level);
+                    param_row(cx, "B Oscs", |p| &p.zone_b.oscillators);
+                    param_row(cx, "B Transp", |p| &p.zone_b.transpose);
+                    param_row(cx, "B Level", |p| &p.zone_b.level);
+                    param_row(cx, "Key Low", |p| &p.key_low);
+                    param_row(cx, "Key High", |p| &p.key_high);
+                });
+
+                section(cx, "CHORD", |cx| {
+                    toggle_row(cx, |p| &p.chord_memory);
+                    let capture_keyboard = keyboard.clone();
+                    Button::new(cx, move |_| capture_keyboard.request_chord_capture(), |cx| localized_label(cx, "Capture Chord"))
+                        .height(Pixels(ROW_HEIGHT));
+                });
+
+                section(cx, "ENV", |cx| {
+                    EnvelopeEditor::new(cx, params.clone(), EnvelopeKind::Amp, vg::Color::rgb(120, 200, 255))
+                        .height(Pixels(ENVELOPE_EDITOR_HEIGHT));
+                    param_row(cx, "Attack", |p| &p.amp_envelope.attack);
+                    param_row(cx, "Decay", |p| &p.amp_envelope.decay);
+                    param_row(cx, "Sustain", |p| &p.amp_envelope.sustain);
+                    param_row(cx, "Release", |p| &p.amp_envelope.release);
+                    param_row(cx, "Rel Vel", |p| &p.release_velocity_source);
+                    param_row(cx, "Rel Vel Amt", |p| &p.release_velocity_amount);
+                    param_row(cx, "Rel Vel Fix", |p| &p.release_velocity_fixed);
+                });
+
+                section(cx, "FILTER", |cx| {
+                    param_row(cx, "Type", |p| &p.filter_type);
+                    param_row(cx, "Slope", |p| &p.filter_slope);
+                    param_row(cx, "Cutoff", |p| &p.cutoff);
+                    param_row(cx, "Resonance", |p| &p.resonance);
+                    param_row(cx, "Drive", |p| &p.filter_drive);
+                    param_row(cx, "Saturation", |p| &p.filter_saturation);
+                    toggle_row(cx, |p| &p.voice_waveshaper.enabled);
+                    param_row(cx, "Shaper", |p| &p.voice_waveshaper.drive);
+                    param_row(cx, "Shape", |p| &p.voice_waveshaper.shape);
+                    param_row(cx, "Tone", |p| &p.voice_waveshaper.tone);
+                    param_row(cx, "Oversample", |p| &p.voice_waveshaper.oversampling);
+                    param_row(cx, "Env Amount", |p| &p.filter_env_amount);
+                    param_row(cx, "Key Track", |p| &p.filter_key_tracking);
+                    param_row(cx, "Stereo", |p| &p.filter_stereo_spread);
+                    param_row(cx, "Wobble", |p| &p.drift_cutoff);
+                    param_row(cx, "Routing", |p| &p.filter_routing);
+                    param_row(cx, "Mix", |p| &p.filter_mix);
+                    param_row(cx, "F2 Type", |p| &p.filter2_type);
+                    param_row(cx, "F2 Slope", |p| &p.filter2_slope);
+                    param_row(cx, "F2 Cutoff", |p| &p.cutoff2);
+                    param_row(cx, "F2 Res", |p| &p.resonance2);
+                    param_row(cx, "F2 Env", |p| &p.filter2_env_amount);
+                    EnvelopeEditor::new(cx, params.clone(), EnvelopeKind::Filter, vg::Color::rgb(255, 170, 90))
+                        .height(Pixels(ENVELOPE_EDITOR_HEIGHT));
+                    param_row(cx, "Attack", |p| &p.filter_envelope.attack);
+                    param_row(cx, "Decay", |p| &p.filter_envelope.decay);
+                    param_row(cx, "Sustain", |p| &p.filter_envelope.sustain);
+                    param_row(cx, "Release", |p| &p.filter_envelope.release);
+                });
+
+                section(cx, "FX", |cx| {
+                    param_row(cx, "Gain", |p| &p.gain);
+                    param_row(cx, "Level", |p| &p.normalization);
+                    param_row(cx, "Quality", |p| &p.quality);
+                    param_row(cx, "Oversampling", |p| &p.oversampling);
+                    param_row(cx, "Tune", |p| &p.master_tune);
+                    param_row(cx, "Transpose", |p| &p.transpose);
+                    param_row(cx, "Delay Send", |p| &p.delay_send);
+                    param_row(cx, "Reverb Send", |p| &p.reverb_send);
+                    param_row(cx, "Send Vel", |p| &p.send_velocity_scaling);
+                    param_row(cx, "Send Key", |p| &p.send_key_scaling);
+                    toggle_row(cx, |p| &p.delay_enabled);
+                    param_row(cx, "Delay Time", |p| &p.delay_time);
+                    param_row(cx, "Feedback", |p| &p.delay_feedback);
+                    toggle_row(cx, |p| &p.delay_tempo_sync);
+                    param_row(cx, "Division", |p| &p.delay_division);
+                    toggle_row(cx, |p| &p.delay_ping_pong);
+                    param_row(cx, "Low Cut", |p| &p.delay_low_cut);
+                    param_row(cx, "High Cut", |p| &p.delay_high_cut);
+                    toggle_row(cx, |p| &p.reverb_enabled);
+                    param_row(cx, "Rev Size", |p| &p.reverb_size);
+                    param_row(cx, "Damping", |p| &p.reverb_damping);
+                    param_row(cx, "Pre-Delay", |p| &p.reverb_pre_delay);
+                    param_row(cx, "Width", |p| &p.reverb_width);
+                    param_row(cx, "Rev Mix", |p| &p.reverb_mix);
+                    toggle_row(cx, |p| &p.waveshaper.enabled);
+                    param_row(cx, "Shaper", |p| &p.waveshaper.drive);
+                    param_row(cx, "Shape", |p| &p.waveshaper.shape);
+                    param_row(cx, "Tone", |p| &p.waveshaper.tone);
+                    param_row(cx, "Oversample", |p| &p.waveshaper.oversampling);
+                    toggle_row(cx, |p| &p.eq_enabled);
+                    param_row(cx, "Low Freq", |p| &p.eq_low_freq);
+                    param_row(cx, "Low Gain", |p| &p.eq_low_gain);
+                    param_row(cx, "Mid Freq", |p| &p.eq_mid_freq);
+                    param_row(cx, "Mid Gain", |p| &p.eq_mid_gain);
+                    param_row(cx, "Mid Q", |p| &p.eq_mid_q);
+                    param_row(cx, "High Freq", |p| &p.eq_high_freq);
+                    param_row(cx, "High Gain", |p| &p.eq_high_gain);
+                    toggle_row(cx, |p| &p.compressor_enabled);
+                    param_row(cx, "Threshold", |p| &p.compressor_threshold);
+                    param_row(cx, "Ratio", |p| &p.compressor_ratio);
+                    param_row(cx, "Attack", |p| &p.compressor_attack);
+                    param_row(cx, "Release", |p| &p.compressor_release);
+                    param_row(cx, "Makeup", |p| &p.compressor_makeup);
+                    toggle_row(cx, |p| &p.phaser.enabled);
+                    param_row(cx, "Stages", |p| &p.phaser.stages);
+                    param_row(cx, "Rate", |p| &p.phaser.rate);
+                    toggle_row(cx, |p| &p.phaser.tempo_sync);
+                    param_row(cx, "Division", |p| &p.phaser.division);
+                    param_row(cx, "Depth", |p| &p.phaser.depth);
+                    param_row(cx, "Feedback", |p| &p.phaser.feedback);
+                    param_row(cx, "Mix", |p| &p.phaser.mix);
+                    toggle_row(cx, |p| &p.flanger.enabled);
+                    param_row(cx, "Rate", |p| &p.flanger.rate);
+                    toggle_row(cx, |p| &p.flanger.tempo_sync);
+                    param_row(cx, "Division", |p| &p.flanger.division);
+                    param_row(cx, "Delay", |p| &p.flanger.delay);
+                    param_row(cx, "Depth", |p| &p.flanger.depth);
+                    param_row(cx, "Feedback", |p| &p.flanger.feedback);
+                    param_row(cx, "Mix", |p| &p.flanger.mix);
+                    param_row(cx, "Voice FX", |p| &p.voice_insert);
+                    param_row(cx, "FX Amount", |p| &p.voice_insert_amount);
+                    toggle_row(cx, |p| &p.voice_dc_blocking);
+                    toggle_row(cx, |p| &p.audition);
+                });
+            })
+            .col_between(Pixels(8.0))
+            .height(Auto);
+
+            HStack::new(cx, |cx| {
+                section(cx, "SEQUENCER", |cx| {
+                    HStack::new(cx, |cx| {
+                        toggle_row(cx, |p| &p.sequencer_enabled);
+                        toggle_row(cx, |p| &p.sequencer_notes);
+                    })
+                    .col_between(Pixels(8.0))
+                    .height(Auto);
+                    param_row(cx, "Rate", |p| &p.sequencer_division);
+                    param_row(cx, "Gate", |p| &p.sequencer_gate);
+                    param_row(cx, "Glide", |p| &p.sequencer_glide);
+                    param_row(cx, "Swing", |p| &p.sequencer_swing);
+                    param_row(cx, "Humanize", |p| &p.sequencer_humanize);
+                });
+
+                section(cx, "FX CHAIN", |cx| {
+                    param_row(cx, "Slot 1", |p| &p.fx_slot1);
+                    param_row(cx, "Slot 2", |p| &p.fx_slot2);
+                    param_row(cx, "Slot 3", |p| &p.fx_slot3);
+                    param_row(cx, "Slot 4", |p| &p.fx_slot4);
+                    param_row(cx, "Slot 5", |p| &p.fx_slot5);
+                    Label::new(
+                        cx,
+                        ParamsModel::params.map(|p| p.fx_chain_config().err().map(|e| e.to_string()).unwrap_or_default()),
+                    )
+                    .color(Color::rgb(240, 140, 90))
+                    .display(ParamsModel::params.map(|p| if p.fx_chain_config().is_err() { Display::Flex } else { Display::None }))
+                    .hoverable(false);
+                    param_row(cx, "Sends", |p| &p.send_order);
+                });
+
+                section(cx, "MOD ENV", |cx| {
+                    EnvelopeEditor::new(cx, params.clone(), EnvelopeKind::ModEnv3, vg::Color::rgb(170, 230, 140))
+                        .height(Pixels(ENVELOPE_EDITOR_HEIGHT));
+                    param_row(cx, "Attack", |p| &p.mod_envelope3.attack);
+                    param_row(cx, "Decay", |p| &p.mod_envelope3.decay);
+                    param_row(cx, "Sustain", |p| &p.mod_envelope3.sustain);
+                    param_row(cx, "Release", |p| &p.mod_envelope3.release);
+                    toggle_row(cx, |p| &p.mod_envelope3_loop);
+                    EnvelopeEditor::new(cx, params.clone(), EnvelopeKind::ModEnv4, vg::Color::rgb(210, 150, 240))
+                        .height(Pixels(ENVELOPE_EDITOR_HEIGHT));
+                    param_row(cx, "Attack", |p| &p.mod_envelope4.attack);
+                    param_row(cx, "Decay", |p| &p.mod_envelope4.decay);
+                    param_row(cx, "Sustain", |p| &p.mod_envelope4.sustain);
+                    param_row(cx, "Release", |p| &p.mod_envelope4.release);
+                    toggle_row(cx, |p| &p.mod_envelope4_loop);
+                });
+
+                section(cx, "MODULATION", |cx| {
+                    param_row(cx, "LFO 1", |p| &p.lfo1.shape);
+                    param_row(cx, "Rate", |p| &p.lfo1.division);
+                    param_row(cx, "Phase", |p| &p.lfo1.phase_offset);
+                    param_row(cx, "Seed", |p| &p.lfo1.seed);
+                    param_row(cx, "LFO 2", |p| &p.lfo2.shape);
+                    param_row(cx, "Rate", |p| &p.lfo2.division);
+                    param_row(cx, "Phase", |p| &p.lfo2.phase_offset);
+                    param_row(cx, "Seed", |p| &p.lfo2.seed);
+                    toggle_row(cx, |p| &p.freeze_modulation);
+                    mod_matrix_panel::build(cx, params.clone());
+                });
+            })
+            .col_between(Pixels(8.0))
+            .height(Auto);
+
+            section(cx, "SCOPE", |cx| {
+                VStack::new(cx, |cx| scope_view::build(cx, scope.clone()))
+                    .height(Pixels(SCOPE_HEIGHT));
+            });
+
+            section(cx, "OUTPUT", |cx| {
+                level_meter_view::build(cx, level_meter.clone());
+            });
+
+            section(cx, "VOICES", |cx| {
+                voice_meter_view::build(cx, voice_meter.clone());
+            });
+
+            section(cx, "KEYBOARD", |cx| {
+                VirtualKeyboard::new(cx, keyboard.clone())
+                    .height(Pixels(KEYBOARD_HEIGHT));
+            });
+        })
+        .row_between(Pixels(8.0))
+        .child_space(Pixels(10.0));
+
+        ResizeHandle::new(cx);
+    })
+}
+
+/// Titled panel; clicking the title collapses or expands the content.
+fn section(cx: &mut Context, title: &'static str, content: impl FnOnce(&mut Context)) {
+    let is_collapsed = move |collapsed: &Vec<String>| collapsed.iter().any(|t| t == title);
+
+    VStack::new(cx, |cx| {
+        Button::new(
+            cx,
+            move |cx| cx.emit(SectionEvent::Toggle(title)),
+            move |cx| {
+                HStack::new(cx, |cx| {
+                    Binding::new(cx, LocaleModel::language, move |cx, language| {
+                        let text = translate(language.get(cx), title);
+                        Label::new(
+                            cx,
+                            SectionsModel::collapsed.map(move |c| format!("{} {}", if is_collapsed(c) { "+" } else { "-" }, text)),
+                        )
+                        .font_size(16.0);
+                    });
+                })
+            },
+        )
+        .height(Pixels(22.0));
+        VStack::new(cx, content)
+            .row_between(Pixels(4.0))
+            .height(Auto)
+            .display(SectionsModel::collapsed.map(move |c| if is_collapsed(c) { Display::None } else { Display::Flex }));
+    })
+    .background_color(Color::rgb(38, 40, 46))
+    .border_radius(Pixels(4.0))
+    .child_space(Pixels(8.0))
+    .row_between(Pixels(4.0))
+    .width(Stretch(1.0))
+    .height(Auto);
+}
+
+fn param_row<P, F>(cx: &mut Context, label: &'static str, params_to_param: F)
+where
+    P: Param + 'static,
+    F: Fn(&Arc<MyParams>) -> &P + Copy + 'static,
+{
+    ParamKeyboardControl::new(cx, params_to_param, |cx| {
+        HStack::new(cx, |cx| {
+            localized_label(cx, label)
+                .width(Pixels(LABEL_WIDTH))
+                .hoverable(false);
+            ParamSlider::new(cx, ParamsModel::params, params_to_param)
+                .width(Stretch(1.0));
+        })
+        .col_between(Pixels(6.0));
+    })
+    .height(Pixels(ROW_HEIGHT));
+}
+
+fn toggle_row<P, F>(cx: &mut Context, params_to_param: F)
+where
+    P: Param + 'static,
+    F: Fn(&Arc<MyParams>) -> &P + Copy + 'static,
+{
+    ParamKeyboardControl::new(cx, params_to_param, |cx| {
+        ParamButton::new(cx, ParamsModel::params, params_to_param);
+    })
+    .height(Pixels(ROW_HEIGHT));
+}
@@ -0,0 +1,95 @@
+use nih_plug_vizia::vizia::prelude::*;
+use std::sync::Arc;
+
+use crate::modulation::ModulationRoute;
+use crate::params::MyParams;
+
+use super::locale::{localized_label, translate, LocaleModel};
+
+#[derive(Clone, PartialEq, Data)]
+struct RouteRow {
+    description: String,
+    muted: bool,
+}
+
+pub(super) enum ModMatrixEvent {
+    ToggleMute(usize),
+    Delete(usize),
+    /// The routes were replaced from outside the panel, e.g. by loading a preset.
+    Refresh,
+}
+
+/// GUI copy of the patch's routes; edits are written straight back to the persisted params.
+#[derive(Lens)]
+struct ModMatrixModel {
+    params: Arc<MyParams>,
+    rows: Vec<RouteRow>,
+}
+
+fn rows_from(routes: &[ModulationRoute]) -> Vec<RouteRow> {
+    routes
+        .iter()
+        .map(|r| RouteRow {
+            description: r.description(),
+            muted: r.muted,
+        })
+        .collect()
+}
+
+impl Model for ModMatrixModel {
+    fn event(&mut self, _cx: &mut EventContext, event: &mut Event) {
+        event.map(|matrix_event, _| {
+            let mut routes = self.params.modulation_routes.write().unwrap_or_else(|e| e.into_inner());
+            match *matrix_event {
+                ModMatrixEvent::ToggleMute(index) => {
+                    if let Some(route) = routes.get_mut(index) {
+                        route.muted = !route.muted;
+                    }
+                }
+                ModMatrixEvent::Delete(index) => {
+                    if index < routes.len() {
+                        routes.remove(index);
+                    }
+                }
+                ModMatrixEvent::Refresh => {}
+            }
+            self.rows = rows_from(&routes);
+        });
+    }
+}
+
+/// Lists every modulation route as "source → destination @ amount" with mute and delete buttons.
+pub fn build(cx: &mut Context, params: Arc<MyParams>) {
+    let rows = rows_from(&params.modulation_routes.read().unwrap_or_else(|e| e.into_inner()));
+    ModMatrixModel { params, rows }.build(cx);
+
+    List::new(cx, ModMatrixModel::rows, |cx, index, row| {
+        HStack::new(cx, |cx| {
+            Label::new(cx, row.map(|r| r.description.clone()))
+                .width(Stretch(1.0))
+                .opacity(row.map(|r| if r.muted { 0.4 } else { 1.0 }));
+            Button::new(
+                cx,
+                move |cx| cx.emit(ModMatrixEvent::ToggleMute(index)),
+                move |cx| {
+                    HStack::new(cx, move |cx| {
+                        Binding::new(cx, LocaleModel::language, move |cx, language| {
+                            let language = language.get(cx);
+                            Label::new(cx, row.map(move |r| translate(language, if r.muted { "Unmute" } else { "Mute" }).to_string()));
+                        });
+                    })
+                },
+            )
+            .width(Pixels(64.0));
+            Button::new(
+                cx,
+                move |cx| cx.emit(ModMatrixEvent::Delete(index)),
+                |cx| localized_label(cx, "Delete"),
+            )
+            .width(Pixels(64.0));
+        })
+        .height(Pixels(26.0))
+        .col_between(Pixels(6.0));
+    })
+    .row_between(Pixels(2.0));
+}
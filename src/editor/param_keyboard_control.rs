@@ -0,0 +1,83 @@
+use nih_plug::prelude::{Param, ParamPtr};
+use nih_plug_vizia::vizia::prelude::*;
+use nih_plug_vizia::widgets::RawParamEvent;
+use std::sync::Arc;
+
+use super::locale::localized_tooltip;
+use crate::params::MyParams;
+
+const CONTINUOUS_STEPS: f32 = 100.0;    // arrow presses across the whole range of a continuous parameter
+const PAGE_STEPS: f32 = 10.0;           // arrow steps per page up/down
+const KEYBOARD_HINT: &str = "Arrow keys step the value, Home and End jump to the ends";
+
+/// Focusable wrapper around a parameter control so it works without a mouse: the arrow keys
+/// step the value, page up/down take ten steps, home/end jump to the ends and space/enter
+/// flip on/off parameters. Screen readers get the parameter's name and its current value, and
+/// hovering shows the full name with a reminder of the keys.
+pub(super) struct ParamKeyboardControl {
+    param: ParamPtr,
+}
+
+impl ParamKeyboardControl {
+    pub fn new<P, F>(cx: &mut Context, params_to_param: F, content: impl FnOnce(&mut Context)) -> Handle<Self>
+    where
+        P: Param + 'static,
+        F: Fn(&Arc<MyParams>) -> &P + Copy + 'static,
+    {
+        let params = super::ParamsModel::params.get(cx);
+        let param = params_to_param(&params);
+        let name = param.name().to_string();
+        let tooltip = vec![name.clone(), KEYBOARD_HINT.to_string()];
+
+        Self { param: param.as_ptr() }
+            .build(cx, content)
+            .navigable(true)
+            .role(if param.step_count() == Some(1) { Role::CheckBox } else { Role::Slider })
+            .name(name)
+            .text_value(super::ParamsModel::params.map(move |p| params_to_param(p).to_string()))
+            .tooltip(move |cx| localized_tooltip(cx, tooltip.clone()))
+    }
+
+    fn step_size(&self) -> f32 {
+        match unsafe { self.param.step_count() } {
+            Some(steps) if steps > 0 => 1.0 / steps as f32,
+            _ => 1.0 / CONTINUOUS_STEPS,
+        }
+    }
+
+    fn set_normalized(&self, cx: &mut EventContext, value: f32) {
+        cx.emit(RawParamEvent::BeginSetParameter(self.param));
+        cx.emit(RawParamEvent::SetParameterNormalized(self.param, value.clamp(0.0, 1.0)));
+        cx.emit(RawParamEvent::EndSetParameter(self.param));
+    }
+}
+
+impl View for ParamKeyboardControl {
+    fn element(&self) -> Option<&'static str> {
+        Some("param-keyboard-control")
+    }
+
+    fn event(&mut self, cx: &mut EventContext, event: &mut Event) {
+        event.map(|window_event, meta| {
+            let WindowEvent::KeyDown(code, _) = window_event else {
+                return;
+            };
+            let current = unsafe { self.param.unmodulated_normalized_value() };
+            let step = self.step_size();
+            let toggle = unsafe { self.param.step_count() } == Some(1);
+
+            let target = match code {
+                Code::ArrowUp | Code::ArrowRight => current + step,
+                Code::ArrowDown | Code::ArrowLeft => current - step,
+                Code::PageUp => current + step * PAGE_STEPS,
+                Code::PageDown => current - step * PAGE_STEPS,
+                Code::Home => 0.0,
+                Code::End => 1.0,
+                Code::Space | Code::Enter if toggle => if current < 0.5 { 1.0 } else { 0.0 },
+                _ => return,
+            };
+            self.set_normalized(cx, target);
+            meta.consume();
+        });
+    }
+}
@@ -0,0 +1,486 @@
+use nih_plug::prelude::{AsyncExecutor, Params};
+use nih_plug_vizia::vizia::prelude::*;
+use nih_plug_vizia::widgets::RawParamEvent;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use super::edit_history::EditHistoryEvent;
+use super::locale::localized_label;
+use super::mod_matrix_panel::ModMatrixEvent;
+use crate::background::{SynthTask, TaskResult, TaskResults};
+use crate::params::MyParams;
+use crate::preset::{self, Preset, PresetEntry, PresetSource, ProgramChangeInbox};
+use crate::randomize::{PatchDice, PARAM_RANGES, VARIATION_AMOUNT};
+use crate::MySynth;
+
+/// How often the browser checks for a MIDI program change and finished file work.
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+#[derive(Clone, PartialEq, Data)]
+struct PresetRow {
+    index: usize,
+    name: String,
+    tags: String,
+    user: bool,
+    selected: bool,
+}
+
+enum PresetBrowserEvent {
+    Load(usize),
+    ConfirmLoad,
+    CancelLoad,
+    SetName(String),
+    SetTags(String),
+    SetFilter(String),
+    Save,
+    Rename,
+    Refresh,
+    ApplyTemplate(usize),
+    InitPatch,
+    SwitchCompare,
+    CopyCompare,
+    Randomize(f32),
+    PollProgram,
+    PollTasks,
+}
+
+#[derive(Lens)]
+struct PresetBrowserModel {
+    #[lens(ignore)]
+    params: Arc<MyParams>,
+    #[lens(ignore)]
+    program_inbox: Arc<ProgramChangeInbox>,
+    #[lens(ignore)]
+    executor: AsyncExecutor<MySynth>,
+    #[lens(ignore)]
+    task_results: Arc<TaskResults>,
+    #[lens(ignore)]
+    select_after_scan: Option<String>,  // a renamed preset, selected once the new list arrives
+    #[lens(ignore)]
+    entries: Vec<PresetEntry>,
+    rows: Vec<PresetRow>,
+    selected: Option<usize>,
+    name: String,
+    tags: String,
+    filter: String,
+    status: String,
+    pending_load: Option<usize>,    // preset waiting for the user to discard unsaved edits
+    compare_slot: String,           // "A" or "B", the slot the parameters hold
+}
+
+impl PresetBrowserModel {
+    fn rebuild_rows(&mut self) {
+        let filter = self.filter.trim();
+        self.rows = self
+            .entries
+            .iter()
+            .enumerate()
+            .filter(|(_, entry)| filter.is_empty() || entry.preset.has_tag(filter))
+            .map(|(index, entry)| PresetRow {
+                index,
+                name: entry.preset.name.clone(),
+                tags: entry.preset.tags.join(", "),
+                user: matches!(entry.source, PresetSource::User(_)),
+                selected: self.selected == Some(index),
+            })
+            .collect();
+    }
+
+    // Preset files are read and written on the background thread; `poll_tasks` takes the results
+    fn reload(&mut self) {
+        self.executor.execute_background(SynthTask::ScanPresets);
+    }
+
+    fn poll_tasks(&mut self) {
+        // Undoing or redoing an A/B switch changes the active slot behind the browser's back
+        let compare_slot = self.params.compare_slots.read().unwrap_or_else(|e| e.into_inner()).active().label();
+        if self.compare_slot != compare_slot {
+            self.compare_slot = compare_slot.to_string();
+        }
+        for result in self.task_results.take() {
+            match result {
+                TaskResult::PresetsScanned(entries) => {
+                    let selected_name = self
+                        .select_after_scan
+                        .take()
+                        .or_else(|| self.selected.and_then(|i| self.entries.get(i)).map(|e| e.preset.name.clone()));
+                    self.entries = entries;
+                    self.selected = selected_name.and_then(|name| self.entries.iter().position(|e| e.preset.name == name));
+                    self.rebuild_rows();
+                }
+                TaskResult::PresetSaved { preset, result } => {
+                    self.status = match result {
+                        Ok(_) => {
+                            let status = format!("Saved {}", preset.name);
+                            preset::mark_patch_clean(&self.params, preset);
+                            status
+                        }
+                        Err(err) => format!("Save failed: {}", err),
+                    };
+                }
+                TaskResult::PresetRenamed { new_name, result } => {
+                    self.status = match result {
+                        Ok(_) => format!("Renamed to {}", new_name),
+                        Err(err) => format!("Rename failed: {}", err),
+                    };
+                    self.select_after_scan = Some(new_name);
+                }
+            }
+        }
+    }
+
+    fn parsed_tags(&self) -> Vec<String> {
+        self.tags
+            .split(',')
+            .map(str::trim)
+            .filter(|t| !t.is_empty())
+            .map(str::to_string)
+            .collect()
+    }
+
+    // Loads at once over a clean patch; over unsaved edits it asks first
+    fn request_load(&mut self, cx: &mut EventContext, index: usize) {
+        if preset::is_patch_dirty(&self.params) {
+            self.pending_load = Some(index);
+        } else {
+            self.load(cx, index);
+        }
+    }
+
+    fn load(&mut self, cx: &mut EventContext, index: usize) {
+        self.pending_load = None;
+        let Some(entry) = self.entries.get(index) else {
+            return;
+        };
+        let preset = entry.preset.clone();
+        self.apply_preset(cx, &preset, EditHistoryEvent::begin_group(format!("Load {}", preset.name), &self.params));
+
+        preset::mark_patch_clean(&self.params, preset.clone());
+        self.name = preset.name.clone();
+        self.tags = preset.tags.join(", ");
+        self.status = format!("Loaded {}", preset.name);
+        self.selected = Some(index);
+        self.rebuild_rows();
+    }
+
+    // Sets every parameter and the persisted patch data from `preset`, as one undo step
+    // `group` is the undo step's `BeginGroup`, made before anything it covers changed
+    fn apply_preset(&self, cx: &mut EventContext, preset: &Preset, group: EditHistoryEvent) {
+        cx.emit(group);
+        for (id, ptr, _) in self.params.param_map() {
+            let normalized = unsafe {
+                match preset.values.get(&id) {
+                    Some(&value) => ptr.preview_normalized(value),
+                    None => ptr.default_normalized_value(),
+                }
+            };
+            cx.emit(RawParamEvent::BeginSetParameter(ptr));
+            cx.emit(RawParamEvent::SetParameterNormalized(ptr, normalized));
+            cx.emit(RawParamEvent::EndSetParameter(ptr));
+        }
+        *self.params.modulation_routes.write().unwrap_or_else(|e| e.into_inner()) = preset.modulation_routes.clone();
+        if let Some(seed) = preset.noise_seed {
+            *self.params.noise_seed.write().unwrap_or_else(|e| e.into_inner()) = seed;
+        }
+        *self.params.preset_cc_mappings.write().unwrap_or_else(|e| e.into_inner()) = preset.cc_mappings.clone();
+        *self.params.sample_path.write().unwrap_or_else(|e| e.into_inner()) = preset.sample_path.clone();
+        cx.emit_custom(
+            Event::new(ModMatrixEvent::Refresh)
+                .target(Entity::root())
+                .propagate(Propagation::Subtree),
+        );
+        cx.emit(EditHistoryEvent::EndGroup);
+    }
+
+    // The loaded patch's baseline stays, so both slots show as edits of the same preset. The
+    // switch is one undo step with the load, so undoing it goes back to the slot left as well.
+    fn switch_compare_slot(&mut self, cx: &mut EventContext) {
+        let target = self.params.compare_slots.read().unwrap_or_else(|e| e.into_inner()).active().other();
+        let group = EditHistoryEvent::begin_group(format!("Switch to {}", target.label()), &self.params);
+        let preset = self.params.compare_slots.write().unwrap_or_else(|e| e.into_inner()).switch(&self.params);
+        self.apply_preset(cx, &preset, group);
+        self.compare_slot = target.label().to_string();
+        self.status = format!("Comparing {}", self.compare_slot);
+    }
+
+    fn copy_compare_slot(&mut self) {
+        let mut slots = self.params.compare_slots.write().unwrap_or_else(|e| e.into_inner());
+        slots.copy_to_other(&self.params);
+        self.status = format!("Copied {} to {}", slots.active().label(), slots.active().other().label());
+    }
+
+    fn save(&mut self) {
+        let Some(dir) = preset::user_preset_dir() else {
+            self.status = "No user preset directory".to_string();
+            return;
+        };
+        let preset = Preset::capture(self.name.trim(), self.parsed_tags(), &self.params);
+        self.status = format!("Saving {}", preset.name);
+        self.executor.execute_background(SynthTask::SavePreset { dir, preset });
+    }
+
+    fn rename(&mut self) {
+        let Some(PresetSource::User(path)) = self.selected.and_then(|i| self.entries.get(i)).map(|e| e.source.clone()) else {
+            self.status = "Only user presets can be renamed".to_string();
+            return;
+        };
+        let new_name = self.name.trim().to_string();
+        self.executor.execute_background(SynthTask::RenamePreset { path, new_name });
+    }
+}
+
+impl PresetBrowserModel {
+    /// The background thread has already loaded the preset a program change picked; loading
+    /// it here as well moves the parameters, so the host sees the change. Program changes
+    /// load without asking about unsaved edits: the player can't answer a dialog mid-song.
+    /// Program 0 is the first preset of the full, unfiltered list.
+    fn poll_program(&mut self, cx: &mut EventContext) {
+        let Some(program) = self.program_inbox.take() else {
+            return;
+        };
+        if preset::program_preset(&self.entries, program).is_some() {
+            self.load(cx, program as usize);
+            self.status = format!("Program {}: {}", program as u32 + 1, self.name);
+        } else {
+            self.status = format!("No preset for program {}", program as u32 + 1);
+        }
+    }
+
+    // Only the template's parameters move; the result is an edit of the loaded patch
+    fn apply_template(&mut self, cx: &mut EventContext, index: usize) {
+        let Some(template) = preset::PATCH_TEMPLATES.get(index) else {
+            return;
+        };
+        if template.is_applied(&self.params) {
+            self.status = format!("{} is already set", template.name);
+            return;
+        }
+        cx.emit(EditHistoryEvent::begin_group(template.name, &self.params));
+        let values = template.values();
+        for (id, ptr, _) in self.params.param_map() {
+            let Some(&(_, value)) = values.iter().find(|(template_id, _)| *template_id == id) else {
+                continue;
+            };
+            let normalized = unsafe { ptr.preview_normalized(value) };
+            cx.emit(RawParamEvent::BeginSetParameter(ptr));
+            cx.emit(RawParamEvent::SetParameterNormalized(ptr, normalized));
+            cx.emit(RawParamEvent::EndSetParameter(ptr));
+        }
+        cx.emit(EditHistoryEvent::EndGroup);
+        self.status = format!("Applied {}", template.name);
+    }
+
+    // Like the templates, an edit of the loaded patch rather than a load, so it can be saved or
+    // reverted. Only the patch goes back to init; tuning, gain and the rest of the setup stay.
+    fn init_patch(&mut self, cx: &mut EventContext) {
+        let init_preset = preset::init_preset();
+        cx.emit(EditHistoryEvent::begin_group("Init", &self.params));
+        for (id, ptr, _) in self.params.param_map() {
+            if !preset::is_patch_param(&id) {
+                continue;
+            }
+            let init = init_preset.normalized_value(&id, ptr);
+            cx.emit(RawParamEvent::BeginSetParameter(ptr));
+            cx.emit(RawParamEvent::SetParameterNormalized(ptr, init));
+            cx.emit(RawParamEvent::EndSetParameter(ptr));
+        }
+        *self.params.modulation_routes.write().unwrap_or_else(|e| e.into_inner()) = init_preset.modulation_routes.clone();
+        *self.params.sample_path.write().unwrap_or_else(|e| e.into_inner()) = None;
+        cx.emit_custom(
+            Event::new(ModMatrixEvent::Refresh)
+                .target(Entity::root())
+                .propagate(Propagation::Subtree),
+        );
+        cx.emit(EditHistoryEvent::EndGroup);
+        self.status = "Reset to init".to_string();
+    }
+
+    // A fresh seed every time; the seed is shown so a result can be told apart from the next
+    fn randomize(&mut self, cx: &mut EventContext, amount: f32) {
+        let seed = SystemTime::now().duration_since(UNIX_EPOCH).map_or(1, |d| d.as_nanos() as u64);
+        let mut dice = PatchDice::new(seed);
+        cx.emit(EditHistoryEvent::begin_group("Randomize", &self.params));
+        for (id, ptr, _) in self.params.param_map() {
+            let Some(&(_, range)) = PARAM_RANGES.iter().find(|(range_id, _)| *range_id == id) else {
+                continue;
+            };
+            let normalized = unsafe {
+                let value = dice.pick(range, ptr.unmodulated_plain_value(), amount);
+                ptr.preview_normalized(value)
+            };
+            cx.emit(RawParamEvent::BeginSetParameter(ptr));
+            cx.emit(RawParamEvent::SetParameterNormalized(ptr, normalized));
+            cx.emit(RawParamEvent::EndSetParameter(ptr));
+        }
+        cx.emit(EditHistoryEvent::EndGroup);
+        self.status = format!("Randomized {:.0}% (seed {:x})", amount * 100.0, seed & 0xFFFF_FFFF);
+    }
+}
+
+impl Model for PresetBrowserModel {
+    fn event(&mut self, cx: &mut EventContext, event: &mut Event) {
+        event.map(|browser_event, _| match browser_event {
+            PresetBrowserEvent::Load(index) => self.request_load(cx, *index),
+            PresetBrowserEvent::ConfirmLoad => {
+                if let Some(index) = self.pending_load {
+                    self.load(cx, index);
+                }
+            }
+            PresetBrowserEvent::CancelLoad => self.pending_load = None,
+            PresetBrowserEvent::SetName(name) => self.name = name.clone(),
+            PresetBrowserEvent::SetTags(tags) => self.tags = tags.clone(),
+            PresetBrowserEvent::SetFilter(filter) => {
+                self.filter = filter.clone();
+                self.rebuild_rows();
+            }
+            PresetBrowserEvent::Save => self.save(),
+            PresetBrowserEvent::Rename => self.rename(),
+            PresetBrowserEvent::Refresh => self.reload(),
+            PresetBrowserEvent::ApplyTemplate(index) => self.apply_template(cx, *index),
+            PresetBrowserEvent::InitPatch => self.init_patch(cx),
+            PresetBrowserEvent::SwitchCompare => self.switch_compare_slot(cx),
+            PresetBrowserEvent::CopyCompare => self.copy_compare_slot(),
+            PresetBrowserEvent::Randomize(amount) => self.randomize(cx, *amount),
+            PresetBrowserEvent::PollProgram => self.poll_program(cx),
+            PresetBrowserEvent::PollTasks => self.poll_tasks(),
+        });
+    }
+}
+
+/// Factory and user presets filtered by tag, with name/tag fields for saving and renaming.
+/// MIDI program changes select presets from the same list.
+pub fn build(
+    cx: &mut Context,
+    params: Arc<MyParams>,
+    program_inbox: Arc<ProgramChangeInbox>,
+    executor: AsyncExecutor<MySynth>,
+    task_results: Arc<TaskResults>,
+) {
+    // A fresh instance counts as an unedited init patch
+    if params.patch_baseline.read().unwrap_or_else(|e| e.into_inner()).is_none() {
+        preset::mark_patch_clean(&params, Preset::capture("Init", Vec::new(), &params));
+    }
+
+    let compare_slot = params.compare_slots.read().unwrap_or_else(|e| e.into_inner()).active().label().to_string();
+    let mut model = PresetBrowserModel {
+        params,
+        program_inbox,
+        executor,
+        task_results,
+        select_after_scan: None,
+        entries: Vec::new(),
+        rows: Vec::new(),
+        selected: None,
+        name: "New Preset".to_string(),
+        tags: String::new(),
+        filter: String::new(),
+        status: String::new(),
+        pending_load: None,
+        compare_slot,
+    };
+    model.reload();
+    model.build(cx);
+
+    let timer = cx.add_timer(POLL_INTERVAL, None, |cx, action| {
+        if let TimerAction::Tick(_) = action {
+            cx.emit(PresetBrowserEvent::PollProgram);
+            cx.emit(PresetBrowserEvent::PollTasks);
+        }
+    });
+    cx.start_timer(timer);
+
+    HStack::new(cx, |cx| {
+        localized_label(cx, "Tag").width(Pixels(40.0)).hoverable(false);
+        Textbox::new(cx, PresetBrowserModel::filter)
+            .on_submit(|cx, text, _| cx.emit(PresetBrowserEvent::SetFilter(text)))
+            .width(Stretch(1.0));
+        Button::new(cx, |cx| cx.emit(PresetBrowserEvent::Refresh), |cx| localized_label(cx, "Refresh"))
+            .width(Pixels(72.0));
+    })
+    .height(Pixels(26.0))
+    .col_between(Pixels(6.0));
+
+    ScrollView::new(cx, 0.0, 0.0, false, true, |cx| {
+        List::new(cx, PresetBrowserModel::rows, |cx, _, row| {
+            let index = row.get(cx).index;
+            Button::new(
+                cx,
+                move |cx| cx.emit(PresetBrowserEvent::Load(index)),
+                move |cx| {
+                    HStack::new(cx, |cx| {
+                        Label::new(cx, row.map(|r| r.name.clone())).width(Stretch(1.0));
+                        Label::new(cx, row.map(|r| if r.user { r.tags.clone() } else { format!("{} (factory)", r.tags) }))
+                            .opacity(0.6);
+                    })
+                    .height(Auto)
+                },
+            )
+            .width(Stretch(1.0))
+            .opacity(row.map(|r| if r.selected { 1.0 } else { 0.8 }));
+        })
+        .row_between(Pixels(2.0));
+    })
+    .height(Pixels(120.0));
+
+    HStack::new(cx, |cx| {
+        Textbox::new(cx, PresetBrowserModel::name)
+            .on_submit(|cx, text, _| cx.emit(PresetBrowserEvent::SetName(text)))
+            .width(Stretch(1.0));
+        Textbox::new(cx, PresetBrowserModel::tags)
+            .on_submit(|cx, text, _| cx.emit(PresetBrowserEvent::SetTags(text)))
+            .width(Stretch(1.0));
+        Button::new(cx, |cx| cx.emit(PresetBrowserEvent::Save), |cx| localized_label(cx, "Save"))
+            .width(Pixels(64.0));
+        Button::new(cx, |cx| cx.emit(PresetBrowserEvent::Rename), |cx| localized_label(cx, "Rename"))
+            .width(Pixels(64.0));
+    })
+    .height(Pixels(26.0))
+    .col_between(Pixels(6.0));
+
+    HStack::new(cx, |cx| {
+        localized_label(cx, "Template").width(Pixels(64.0)).hoverable(false);
+        for (index, template) in preset::PATCH_TEMPLATES.iter().enumerate() {
+            Button::new(cx, move |cx| cx.emit(PresetBrowserEvent::ApplyTemplate(index)), move |cx| localized_label(cx, template.name))
+                .width(Pixels(96.0));
+        }
+        Element::new(cx).width(Stretch(1.0));
+        Button::new(
+            cx,
+            |cx| cx.emit(PresetBrowserEvent::SwitchCompare),
+            |cx| Label::new(cx, PresetBrowserModel::compare_slot.map(|slot| format!("A/B: {}", slot))),
+        )
+        .width(Pixels(64.0));
+        Button::new(
+            cx,
+            |cx| cx.emit(PresetBrowserEvent::CopyCompare),
+            |cx| Label::new(cx, PresetBrowserModel::compare_slot.map(|slot| if slot == "A" { "A→B" } else { "B→A" })),
+        )
+        .width(Pixels(56.0));
+        Button::new(cx, |cx| cx.emit(PresetBrowserEvent::InitPatch), |cx| localized_label(cx, "Init"))
+            .width(Pixels(64.0));
+        Button::new(cx, |cx| cx.emit(PresetBrowserEvent::Randomize(VARIATION_AMOUNT)), |cx| localized_label(cx, "Vary"))
+            .width(Pixels(64.0));
+        Button::new(cx, |cx| cx.emit(PresetBrowserEvent::Randomize(1.0)), |cx| localized_label(cx, "Randomize"))
+            .width(Pixels(80.0));
+    })
+    .height(Pixels(26.0))
+    .col_between(Pixels(6.0));
+
+    Binding::new(cx, PresetBrowserModel::pending_load, |cx, pending| {
+        if pending.get(cx).is_some() {
+            HStack::new(cx, |cx| {
+                localized_label(cx, "Unsaved changes will be lost")
+                    .width(Stretch(1.0))
+                    .hoverable(false);
+                Button::new(cx, |cx| cx.emit(PresetBrowserEvent::ConfirmLoad), |cx| localized_label(cx, "Discard"))
+                    .width(Pixels(72.0));
+                Button::new(cx, |cx| cx.emit(PresetBrowserEvent::CancelLoad), |cx| localized_label(cx, "Cancel"))
+                    .width(Pixels(72.0));
+            })
+            .height(Pixels(26.0))
+            .col_between(Pixels(6.0));
+        }
+    });
+
+    Label::new(cx, PresetBrowserModel::status).opacity(0.7).hoverable(false);
+}
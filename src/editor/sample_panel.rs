@@ -0,0 +1,100 @@
+use nih_plug_vizia::vizia::prelude::*;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use super::locale::localized_label;
+use crate::background::TaskResults;
+use crate::params::MyParams;
+
+/// How often the panel checks whether the background thread has loaded the file.
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+enum SamplePanelEvent {
+    Poll,
+    SetPath(String),
+    Clear,
+}
+
+#[derive(Lens)]
+struct SamplePanelModel {
+    #[lens(ignore)]
+    params: Arc<MyParams>,
+    #[lens(ignore)]
+    task_results: Arc<TaskResults>,
+    path: String,
+    status: String,
+}
+
+impl SamplePanelModel {
+    // The audio thread asks the background thread for the file once it sees the new path
+    fn set_path(&mut self, path: Option<String>) {
+        self.path = path.clone().unwrap_or_default();
+        *self.params.sample_path.write().unwrap_or_else(|e| e.into_inner()) = path;
+        self.poll();
+    }
+
+    fn poll(&mut self) {
+        let Some(path) = self.params.sample_path.read().unwrap_or_else(|e| e.into_inner()).clone() else {
+            self.path.clear();
+            self.status = "No sample".to_string();
+            return;
+        };
+        let name = Path::new(&path).file_name().map_or(path.clone(), |n| n.to_string_lossy().into_owned());
+        let load = self.task_results.sample_load.lock().unwrap_or_else(|e| e.into_inner());
+        self.status = match load.as_ref().filter(|load| load.path == path).map(|load| &load.result) {
+            Some(Ok((duration_secs, sample_rate))) => format!("{} ({:.2} s, {} Hz)", name, duration_secs, sample_rate),
+            Some(Err(error)) => format!("{}: {}", name, error),
+            None => format!("Loading {}…", name),
+        };
+        // A preset load swaps the file behind the panel's back
+        if self.path != path {
+            self.path = path;
+        }
+    }
+}
+
+impl Model for SamplePanelModel {
+    fn event(&mut self, _cx: &mut EventContext, event: &mut Event) {
+        event.map(|panel_event, _| match panel_event {
+            SamplePanelEvent::Poll => self.poll(),
+            SamplePanelEvent::SetPath(path) => {
+                let path = path.trim();
+                self.set_path((!path.is_empty()).then(|| path.to_string()));
+            }
+            SamplePanelEvent::Clear => self.set_path(None),
+        });
+    }
+}
+
+/// The sample layer's file: type or paste the path of a WAV file and press Enter. The
+/// status line shows the loaded file's length, or why it didn't load.
+pub fn build(cx: &mut Context, params: Arc<MyParams>, task_results: Arc<TaskResults>) {
+    let mut model = SamplePanelModel {
+        params,
+        task_results,
+        path: String::new(),
+        status: String::new(),
+    };
+    model.poll();
+    model.build(cx);
+
+    let timer = cx.add_timer(POLL_INTERVAL, None, |cx, action| {
+        if let TimerAction::Tick(_) = action {
+            cx.emit(SamplePanelEvent::Poll);
+        }
+    });
+    cx.start_timer(timer);
+
+    HStack::new(cx, |cx| {
+        Textbox::new(cx, SamplePanelModel::path)
+            .on_submit(|cx, text, _| cx.emit(SamplePanelEvent::SetPath(text)))
+            .width(Stretch(1.0));
+        Button::new(cx, |cx| cx.emit(SamplePanelEvent::Clear), |cx| localized_label(cx, "Clear"))
+            .width(Pixels(64.0));
+    })
+    .height(Pixels(26.0))
+    .col_between(Pixels(6.0));
+
+    Label::new(cx, SamplePanelModel::status).opacity(0.7).hoverable(false);
+}
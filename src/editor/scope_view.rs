@@ -0,0 +1,259 @@
+use nih_plug_vizia::vizia::prelude::*;
+use nih_plug_vizia::vizia::vg;
+use std::sync::Arc;
+
+use crate::scope::{magnitude_spectrum, ScopeBuffer};
+
+const TIME_BASES_MS: [f32; 6] = [2.0, 5.0, 10.0, 20.0, 50.0, 100.0];
+const DEFAULT_TIME_BASE: usize = 3;
+const MIN_WINDOW: usize = 16;
+const SPECTRUM_SIZE: usize = 2048;
+const SPECTRUM_FLOOR_DB: f32 = -90.0;
+const SPECTRUM_MIN_HZ: f32 = 20.0;
+const CONTROLS_HEIGHT: f32 = 26.0;
+
+#[derive(Clone, Copy, PartialEq, Data)]
+enum TriggerMode {
+    FreeRun,
+    Rising,
+    Falling,
+}
+
+impl TriggerMode {
+    fn next(self) -> Self {
+        match self {
+            TriggerMode::FreeRun => TriggerMode::Rising,
+            TriggerMode::Rising => TriggerMode::Falling,
+            TriggerMode::Falling => TriggerMode::FreeRun,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            TriggerMode::FreeRun => "Free",
+            TriggerMode::Rising => "Rising",
+            TriggerMode::Falling => "Falling",
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Data)]
+struct ScopeSettings {
+    trigger: TriggerMode,
+    time_base: usize,               // index into TIME_BASES_MS
+    frozen: Option<Vec<f32>>,       // snapshot of the whole buffer taken when freezing
+}
+
+enum ScopeEvent {
+    CycleTrigger,
+    CycleTimeBase,
+    ToggleFreeze,
+}
+
+#[derive(Lens)]
+struct ScopeModel {
+    scope: Arc<ScopeBuffer>,
+    settings: ScopeSettings,
+}
+
+impl Model for ScopeModel {
+    fn event(&mut self, _cx: &mut EventContext, event: &mut Event) {
+        event.map(|scope_event, _| match scope_event {
+            ScopeEvent::CycleTrigger => {
+                self.settings.trigger = self.settings.trigger.next();
+            }
+            ScopeEvent::CycleTimeBase => {
+                self.settings.time_base = (self.settings.time_base + 1) % TIME_BASES_MS.len();
+            }
+            ScopeEvent::ToggleFreeze => {
+                self.settings.frozen = match self.settings.frozen.take() {
+                    Some(_) => None,
+                    None => {
+                        let mut snapshot = vec![0.0; self.scope.capacity()];
+                        self.scope.read_latest(&mut snapshot);
+                        Some(snapshot)
+                    }
+                };
+            }
+        });
+    }
+}
+
+/// Oscilloscope and spectrum side by side, both reading the output tap, with trigger,
+/// time-base and freeze controls above them.
+pub fn build(cx: &mut Context, scope: Arc<ScopeBuffer>) {
+    ScopeModel {
+        scope: scope.clone(),
+        settings: ScopeSettings {
+            trigger: TriggerMode::Rising,
+            time_base: DEFAULT_TIME_BASE,
+            frozen: None,
+        },
+    }
+    .build(cx);
+
+    HStack::new(cx, |cx| {
+        Button::new(
+            cx,
+            |cx| cx.emit(ScopeEvent::CycleTrigger),
+            |cx| Label::new(cx, ScopeModel::settings.map(|s| format!("Trigger: {}", s.trigger.label()))),
+        );
+        Button::new(
+            cx,
+            |cx| cx.emit(ScopeEvent::CycleTimeBase),
+            |cx| Label::new(cx, ScopeModel::settings.map(|s| format!("Time: {} ms", TIME_BASES_MS[s.time_base]))),
+        );
+        Button::new(
+            cx,
+            |cx| cx.emit(ScopeEvent::ToggleFreeze),
+            |cx| Label::new(cx, ScopeModel::settings.map(|s| if s.frozen.is_some() { "Run" } else { "Freeze" })),
+        );
+    })
+    .height(Pixels(CONTROLS_HEIGHT))
+    .col_between(Pixels(6.0));
+
+    Binding::new(cx, ScopeModel::settings, move |cx, settings| {
+        let settings = settings.get(cx);
+        if settings.frozen.is_some() {
+            build_views(cx, &scope, &settings);
+        } else {
+            // Rebuilt whenever new audio arrives, which keeps both views redrawing
+            let scope = scope.clone();
+            Binding::new(cx, ScopeModel::scope.map(|s| s.write_position()), move |cx, _| {
+                build_views(cx, &scope, &settings);
+            });
+        }
+    });
+}
+
+fn build_views(cx: &mut Context, scope: &Arc<ScopeBuffer>, settings: &ScopeSettings) {
+    HStack::new(cx, |cx| {
+        Oscilloscope { scope: scope.clone(), settings: settings.clone() }
+            .build(cx, |_| {})
+            .width(Stretch(1.0));
+        SpectrumAnalyzer { scope: scope.clone(), frozen: settings.frozen.clone() }
+            .build(cx, |_| {})
+            .width(Stretch(1.0));
+    })
+    .col_between(Pixels(8.0));
+}
+
+// The latest `count` samples, from the frozen snapshot if there is one
+fn latest_samples(scope: &ScopeBuffer, frozen: &Option<Vec<f32>>, count: usize) -> Vec<f32> {
+    match frozen {
+        Some(snapshot) => {
+            let count = count.min(snapshot.len());
+            snapshot[snapshot.len() - count..].to_vec()
+        }
+        None => {
+            let mut samples = vec![0.0; count];
+            scope.read_latest(&mut samples);
+            samples
+        }
+    }
+}
+
+// Start of the most recent zero crossing in the trigger direction that still leaves a full window after it
+fn find_trigger(samples: &[f32], window: usize, trigger: TriggerMode) -> Option<usize> {
+    let last_start = samples.len().checked_sub(window)?;
+    (1..=last_start).rev().find(|&i| match trigger {
+        TriggerMode::FreeRun => false,
+        TriggerMode::Rising => samples[i - 1] < 0.0 && samples[i] >= 0.0,
+        TriggerMode::Falling => samples[i - 1] > 0.0 && samples[i] <= 0.0,
+    })
+}
+
+fn draw_background(canvas: &mut Canvas, bounds: BoundingBox) {
+    let mut background = vg::Path::new();
+    background.rect(bounds.x, bounds.y, bounds.w, bounds.h);
+    canvas.fill_path(&background, &vg::Paint::color(vg::Color::rgb(24, 25, 30)));
+}
+
+struct Oscilloscope {
+    scope: Arc<ScopeBuffer>,
+    settings: ScopeSettings,
+}
+
+impl View for Oscilloscope {
+    fn element(&self) -> Option<&'static str> {
+        Some("oscilloscope")
+    }
+
+    fn draw(&self, cx: &mut DrawContext, canvas: &mut Canvas) {
+        let bounds = cx.bounds();
+        if bounds.w == 0.0 || bounds.h == 0.0 {
+            return;
+        }
+        draw_background(canvas, bounds);
+
+        // Half the buffer at most, so the trigger search always has the other half to look through
+        let capacity = self.scope.capacity();
+        let window = ((TIME_BASES_MS[self.settings.time_base] / 1000.0 * self.scope.sample_rate()) as usize)
+            .clamp(MIN_WINDOW, (capacity / 2).max(MIN_WINDOW));
+        let history = latest_samples(&self.scope, &self.settings.frozen, capacity);
+        if history.len() < window {
+            return;
+        }
+        let start = find_trigger(&history, window, self.settings.trigger).unwrap_or(history.len() - window);
+        let samples = &history[start..start + window];
+
+        // More samples than pixels are thinned out rather than all drawn
+        let stride = (window / bounds.w.max(1.0) as usize).max(1);
+        let center = bounds.y + bounds.h / 2.0;
+        let mut path = vg::Path::new();
+        for (i, sample) in samples.iter().enumerate().step_by(stride) {
+            let x = bounds.x + bounds.w * i as f32 / (window - 1) as f32;
+            let y = center - sample.clamp(-1.0, 1.0) * bounds.h / 2.0;
+            if i == 0 {
+                path.move_to(x, y);
+            } else {
+                path.line_to(x, y);
+            }
+        }
+        canvas.stroke_path(&path, &vg::Paint::color(vg::Color::rgb(120, 255, 160)).with_line_width(1.5));
+    }
+}
+
+struct SpectrumAnalyzer {
+    scope: Arc<ScopeBuffer>,
+    frozen: Option<Vec<f32>>,
+}
+
+impl View for SpectrumAnalyzer {
+    fn element(&self) -> Option<&'static str> {
+        Some("spectrum-analyzer")
+    }
+
+    fn draw(&self, cx: &mut DrawContext, canvas: &mut Canvas) {
+        let bounds = cx.bounds();
+        if bounds.w == 0.0 || bounds.h == 0.0 {
+            return;
+        }
+        draw_background(canvas, bounds);
+
+        let samples = latest_samples(&self.scope, &self.frozen, SPECTRUM_SIZE);
+        let bins = magnitude_spectrum(&samples);
+
+        let nyquist = self.scope.sample_rate() / 2.0;
+        let bin_width = nyquist / bins.len() as f32;
+        let columns = bounds.w.max(2.0) as usize;
+
+        // Logarithmic frequency axis from 20 Hz to Nyquist
+        let mut path = vg::Path::new();
+        for column in 0..columns {
+            let t = column as f32 / (columns - 1) as f32;
+            let frequency = SPECTRUM_MIN_HZ * (nyquist / SPECTRUM_MIN_HZ).powf(t);
+            let bin = ((frequency / bin_width) as usize).min(bins.len() - 1);
+            let level = ((bins[bin] - SPECTRUM_FLOOR_DB) / -SPECTRUM_FLOOR_DB).clamp(0.0, 1.0);
+
+            let x = bounds.x + column as f32;
+            let y = bounds.y + bounds.h * (1.0 - level);
+            if column == 0 {
+                path.move_to(x, y);
+            } else {
+                path.line_to(x, y);
+            }
+        }
+        canvas.stroke_path(&path, &vg::Paint::color(vg::Color::rgb(255, 200, 90)).with_line_width(1.5));
+    }
+}
@@ -0,0 +1,70 @@
+use nih_plug::prelude::{ParamPtr, Params};
+use nih_plug_vizia::vizia::prelude::*;
+use nih_plug_vizia::widgets::RawParamEvent;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::params::MyParams;
+use crate::sysex::ParamInbox;
+
+/// How often values received over sysex are written to their parameters.
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+enum SysExEvent {
+    Poll,
+}
+
+#[derive(Lens)]
+struct SysExModel {
+    #[lens(ignore)]
+    inbox: Arc<ParamInbox>,
+    #[lens(ignore)]
+    param_ptrs: Vec<ParamPtr>,
+    received: usize,
+    status: String,
+}
+
+impl Model for SysExModel {
+    fn event(&mut self, cx: &mut EventContext, event: &mut Event) {
+        event.map(|sysex_event, _| match sysex_event {
+            SysExEvent::Poll => {
+                let param_ptrs = &self.param_ptrs;
+                let mut received = 0;
+                self.inbox.drain(|index, value| {
+                    if let Some(&ptr) = param_ptrs.get(index) {
+                        cx.emit(RawParamEvent::BeginSetParameter(ptr));
+                        cx.emit(RawParamEvent::SetParameterNormalized(ptr, value));
+                        cx.emit(RawParamEvent::EndSetParameter(ptr));
+                        received += 1;
+                    }
+                });
+                if received > 0 {
+                    self.received += received;
+                    self.status = format!("SysEx: {} values received", self.received);
+                }
+            }
+        });
+    }
+}
+
+/// Writes parameter values sent over sysex (patch loads and remote edits), which the audio
+/// thread already plays, to their parameters so the host sees them, and shows how many have
+/// arrived.
+pub fn build(cx: &mut Context, params: Arc<MyParams>, inbox: Arc<ParamInbox>) {
+    SysExModel {
+        inbox,
+        param_ptrs: params.param_map().into_iter().map(|(_, ptr, _)| ptr).collect(),
+        received: 0,
+        status: "SysEx: nothing received".to_string(),
+    }
+    .build(cx);
+
+    let timer = cx.add_timer(POLL_INTERVAL, None, |cx, action| {
+        if let TimerAction::Tick(_) = action {
+            cx.emit(SysExEvent::Poll);
+        }
+    });
+    cx.start_timer(timer);
+
+    Label::new(cx, SysExModel::status).opacity(0.7).hoverable(false);
+}
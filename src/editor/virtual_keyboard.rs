@@ -0,0 +1,143 @@
+use nih_plug_vizia::vizia::prelude::*;
+use nih_plug_vizia::vizia::vg;
+use std::sync::Arc;
+
+use crate::keyboard::KeyboardState;
+
+const FIRST_NOTE: u8 = 36;
+const OCTAVES: u8 = 4;
+const BLACK_KEY_WIDTH: f32 = 0.6;
+const BLACK_KEY_HEIGHT: f32 = 0.62;
+
+// Semitone offsets of the white keys within an octave, and which white key each black key follows
+const WHITE_KEYS: [u8; 7] = [0, 2, 4, 5, 7, 9, 11];
+const BLACK_KEYS: [(u8, usize); 5] = [(1, 0), (3, 1), (6, 3), (8, 4), (10, 5)];
+
+/// Clickable piano keyboard. Dragging across keys plays a glissando; the held notes are
+/// written to the shared [`KeyboardState`] and picked up by the plugin on the next block.
+pub struct VirtualKeyboard {
+    keyboard: Arc<KeyboardState>,
+    pressed: Option<u8>,
+}
+
+impl VirtualKeyboard {
+    pub fn new(cx: &mut Context, keyboard: Arc<KeyboardState>) -> Handle<Self> {
+        Self {
+            keyboard,
+            pressed: None,
+        }
+        .build(cx, |_| {})
+    }
+
+    fn white_key_width(bounds: BoundingBox) -> f32 {
+        bounds.w / (WHITE_KEYS.len() as f32 * OCTAVES as f32)
+    }
+
+    fn white_keys(bounds: BoundingBox) -> impl Iterator<Item = (u8, BoundingBox)> {
+        let width = Self::white_key_width(bounds);
+        (0..OCTAVES).flat_map(move |octave| {
+            WHITE_KEYS.iter().enumerate().map(move |(i, semitone)| {
+                let index = octave as usize * WHITE_KEYS.len() + i;
+                let x = bounds.x + index as f32 * width;
+                (FIRST_NOTE + octave * 12 + semitone, BoundingBox { x, y: bounds.y, w: width, h: bounds.h })
+            })
+        })
+    }
+
+    fn black_keys(bounds: BoundingBox) -> impl Iterator<Item = (u8, BoundingBox)> {
+        let width = Self::white_key_width(bounds);
+        (0..OCTAVES).flat_map(move |octave| {
+            BLACK_KEYS.iter().map(move |&(semitone, after_white)| {
+                let index = octave as usize * WHITE_KEYS.len() + after_white + 1;
+                let w = width * BLACK_KEY_WIDTH;
+                let x = bounds.x + index as f32 * width - w / 2.0;
+                (FIRST_NOTE + octave * 12 + semitone, BoundingBox { x, y: bounds.y, w, h: bounds.h * BLACK_KEY_HEIGHT })
+            })
+        })
+    }
+
+    fn note_at(bounds: BoundingBox, x: f32, y: f32) -> Option<u8> {
+        let contains = |key: &BoundingBox| x >= key.x && x < key.x + key.w && y >= key.y && y < key.y + key.h;
+        // Black keys sit on top, so they win
+        Self::black_keys(bounds)
+            .find(|(_, key)| contains(key))
+            .or_else(|| Self::white_keys(bounds).find(|(_, key)| contains(key)))
+            .map(|(note, _)| note)
+    }
+
+    fn press(&mut self, note: Option<u8>) {
+        if self.pressed == note {
+            return;
+        }
+        if let Some(previous) = self.pressed.take() {
+            self.keyboard.release(previous);
+        }
+        if let Some(note) = note {
+            self.keyboard.press(note);
+        }
+        self.pressed = note;
+    }
+}
+
+impl View for VirtualKeyboard {
+    fn element(&self) -> Option<&'static str> {
+        Some("virtual-keyboard")
+    }
+
+    fn event(&mut self, cx: &mut EventContext, event: &mut Event) {
+        event.map(|window_event, meta| match *window_event {
+            WindowEvent::MouseDown(MouseButton::Left) => {
+                self.press(Self::note_at(cx.bounds(), cx.mouse().cursorx, cx.mouse().cursory));
+                cx.capture();
+                cx.needs_redraw();
+                meta.consume();
+            }
+            WindowEvent::MouseUp(MouseButton::Left) => {
+                if self.pressed.is_some() {
+                    self.press(None);
+                    cx.release();
+                    cx.needs_redraw();
+                    meta.consume();
+                }
+            }
+            WindowEvent::MouseMove(x, y) => {
+                if self.pressed.is_some() {
+                    self.press(Self::note_at(cx.bounds(), x, y));
+                    cx.needs_redraw();
+                }
+            }
+            _ => {}
+        });
+    }
+
+    fn draw(&self, cx: &mut DrawContext, canvas: &mut Canvas) {
+        let bounds = cx.bounds();
+        if bounds.w == 0.0 || bounds.h == 0.0 {
+            return;
+        }
+
+        let outline = vg::Paint::color(vg::Color::rgb(24, 25, 30)).with_line_width(1.0);
+        let held = vg::Paint::color(vg::Color::rgb(120, 200, 255));
+
+        for (note, key) in Self::white_keys(bounds) {
+            let mut path = vg::Path::new();
+            path.rect(key.x, key.y, key.w, key.h);
+            if self.keyboard.is_held(note) {
+                canvas.fill_path(&path, &held);
+            } else {
+                canvas.fill_path(&path, &vg::Paint::color(vg::Color::rgb(230, 230, 235)));
+            }
+            canvas.stroke_path(&path, &outline);
+        }
+
+        for (note, key) in Self::black_keys(bounds) {
+            let mut path = vg::Path::new();
+            path.rect(key.x, key.y, key.w, key.h);
+            if self.keyboard.is_held(note) {
+                canvas.fill_path(&path, &held);
+            } else {
+                canvas.fill_path(&path, &vg::Paint::color(vg::Color::rgb(30, 30, 34)));
+            }
+        }
+    }
+}
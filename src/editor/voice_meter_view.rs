@@ -0,0 +1,120 @@
+use nih_plug_vizia::vizia::prelude::*;
+use nih_plug_vizia::vizia::vg;
+use std::sync::Arc;
+
+use crate::voice_meter::{VoiceMeter, METER_VOICES};
+
+const BAR_WIDTH: f32 = 160.0;
+const BAR_HEIGHT: f32 = 10.0;
+const LEVELS_HEIGHT: f32 = 48.0;
+const LEVEL_FLOOR_DB: f32 = -60.0;
+
+#[derive(Lens)]
+struct VoiceMeterModel {
+    meter: Arc<VoiceMeter>,
+}
+
+impl Model for VoiceMeterModel {}
+
+/// Bar of the voice pool in use, turning red when every voice is busy, with the
+/// voice/steal counts and a one-letter envelope stage per voice, and a level bar per voice below.
+pub fn build(cx: &mut Context, meter: Arc<VoiceMeter>) {
+    VoiceMeterModel { meter }.build(cx);
+
+    HStack::new(cx, |cx| {
+        ZStack::new(cx, |cx| {
+            Element::new(cx)
+                .height(Stretch(1.0))
+                .width(VoiceMeterModel::meter.map(|m| Pixels(BAR_WIDTH * m.usage())))
+                .background_color(VoiceMeterModel::meter.map(|m| {
+                    if m.usage() >= 1.0 { Color::rgb(220, 80, 70) } else { Color::rgb(90, 180, 220) }
+                }));
+        })
+        .width(Pixels(BAR_WIDTH))
+        .height(Pixels(BAR_HEIGHT))
+        .top(Stretch(1.0))
+        .bottom(Stretch(1.0))
+        .background_color(Color::rgb(40, 44, 52));
+        Label::new(cx, VoiceMeterModel::meter.map(|m| m.describe()))
+            .width(Pixels(170.0))
+            .hoverable(false);
+        Label::new(cx, VoiceMeterModel::meter.map(|m| m.stage_summary()))
+            .width(Stretch(1.0))
+            .opacity(0.8)
+            .hoverable(false);
+    })
+    .height(Pixels(super::ROW_HEIGHT))
+    .col_between(Pixels(10.0));
+
+    // Rebuilt on every published block, which keeps the level bars moving
+    Binding::new(cx, VoiceMeterModel::meter.map(|m| m.generation()), move |cx, _| {
+        let meter = VoiceMeterModel::meter.get(cx);
+        VoiceLevels { meter }.build(cx, |_| {}).height(Pixels(LEVELS_HEIGHT));
+    });
+}
+
+/// One level bar per voice; clicking a bar soloes that voice, clicking it again unsoloes.
+struct VoiceLevels {
+    meter: Arc<VoiceMeter>,
+}
+
+impl VoiceLevels {
+    fn voices(&self) -> usize {
+        self.meter.max_voices().min(METER_VOICES)
+    }
+
+    fn voice_at(&self, bounds: BoundingBox, x: f32) -> Option<usize> {
+        let voices = self.voices();
+        if voices == 0 || bounds.w <= 0.0 || x < bounds.x {
+            return None;
+        }
+        let voice = ((x - bounds.x) / bounds.w * voices as f32) as usize;
+        (voice < voices).then_some(voice)
+    }
+}
+
+impl View for VoiceLevels {
+    fn element(&self) -> Option<&'static str> {
+        Some("voice-levels")
+    }
+
+    fn event(&mut self, cx: &mut EventContext, event: &mut Event) {
+        event.map(|window_event, meta| {
+            if let WindowEvent::MouseDown(MouseButton::Left) = *window_event {
+                if let Some(voice) = self.voice_at(cx.bounds(), cx.mouse().cursorx) {
+                    self.meter.toggle_solo(voice);
+                    cx.needs_redraw();
+                }
+                meta.consume();
+            }
+        });
+    }
+
+    fn draw(&self, cx: &mut DrawContext, canvas: &mut Canvas) {
+        let bounds = cx.bounds();
+        let voices = self.voices();
+        if bounds.w == 0.0 || bounds.h == 0.0 || voices == 0 {
+            return;
+        }
+
+        let mut background = vg::Path::new();
+        background.rect(bounds.x, bounds.y, bounds.w, bounds.h);
+        canvas.fill_path(&background, &vg::Paint::color(vg::Color::rgb(40, 44, 52)));
+
+        let solo = self.meter.solo();
+        let slot = bounds.w / voices as f32;
+        for voice in 0..voices {
+            let db = 20.0 * self.meter.level(voice).max(1e-6).log10();
+            let fill = ((db - LEVEL_FLOOR_DB) / -LEVEL_FLOOR_DB).clamp(0.0, 1.0);
+            let color = match solo {
+                Some(s) if s == voice => vg::Color::rgb(240, 200, 80),
+                Some(_) => vg::Color::rgb(70, 90, 100),
+                None => vg::Color::rgb(90, 180, 220),
+            };
+            let mut bar = vg::Path::new();
+            let height = bounds.h * fill;
+            bar.rect(bounds.x + voice as f32 * slot + 1.0, bounds.y + bounds.h - height, (slot - 2.0).max(1.0), height);
+            canvas.fill_path(&bar, &vg::Paint::color(color));
+        }
+    }
+}
@@ -0,0 +1,104 @@
+use std::error::Error;
+use std::fmt;
+
+pub const INSERT_COUNT: usize = 5;
+
+/// The master bus inserts that can be put in any order.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum InsertEffect {
+    Waveshaper,
+    Phaser,
+    Flanger,
+    Eq,
+    Compressor,
+}
+
+impl InsertEffect {
+    pub const ALL: [InsertEffect; INSERT_COUNT] = [
+        InsertEffect::Waveshaper,
+        InsertEffect::Phaser,
+        InsertEffect::Flanger,
+        InsertEffect::Eq,
+        InsertEffect::Compressor,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            InsertEffect::Waveshaper => "Waveshaper",
+            InsertEffect::Phaser => "Phaser",
+            InsertEffect::Flanger => "Flanger",
+            InsertEffect::Eq => "EQ",
+            InsertEffect::Compressor => "Compressor",
+        }
+    }
+}
+
+/// How the delay and reverb sends feed each other.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum SendOrder {
+    Parallel,
+    DelayIntoReverb,                    // echoes get reverb on top
+    ReverbIntoDelay,                    // the reverb tail gets echoed
+}
+
+impl SendOrder {
+    pub const ALL: [SendOrder; 3] = [SendOrder::Parallel, SendOrder::DelayIntoReverb, SendOrder::ReverbIntoDelay];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            SendOrder::Parallel => "Parallel",
+            SendOrder::DelayIntoReverb => "Delay > Reverb",
+            SendOrder::ReverbIntoDelay => "Reverb > Delay",
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct FxChainConfig {
+    pub inserts: [InsertEffect; INSERT_COUNT],  // processing order, each effect exactly once
+    pub sends: SendOrder,
+}
+
+impl Default for FxChainConfig {
+    fn default() -> Self {
+        Self {
+            inserts: [
+                InsertEffect::Waveshaper,
+                InsertEffect::Eq,
+                InsertEffect::Phaser,
+                InsertEffect::Flanger,
+                InsertEffect::Compressor,
+            ],
+            sends: SendOrder::Parallel,
+        }
+    }
+}
+
+impl FxChainConfig {
+    /// Builds an order from one slot choice per insert. Slot choices are separate parameters,
+    /// so automation can pick the same effect twice; such a set is rejected rather than
+    /// quietly reordered.
+    pub fn from_slots(slots: &[InsertEffect; INSERT_COUNT], sends: SendOrder) -> Result<Self, DuplicateInsert> {
+        for (index, effect) in slots.iter().enumerate() {
+            if slots[..index].contains(effect) {
+                return Err(DuplicateInsert { slot: index + 1, effect: *effect });
+            }
+        }
+        Ok(Self { inserts: *slots, sends })
+    }
+}
+
+/// An insert chosen for more than one slot, reported for the first repeat.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct DuplicateInsert {
+    pub slot: usize,                    // 1-based, the slot that repeats an earlier one
+    pub effect: InsertEffect,
+}
+
+impl fmt::Display for DuplicateInsert {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "FX slot {} repeats {}", self.slot, self.effect.label())
+    }
+}
+
+impl Error for DuplicateInsert {}
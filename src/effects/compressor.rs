@@ -0,0 +1,107 @@
+const MIN_TIME_SECS: f32 = 0.0001;
+const KNEE_DB: f32 = 6.0;               // soft knee width around the threshold
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct CompressorConfig {
+    pub enabled: bool,
+    pub threshold_db: f32,              // level above which gain reduction starts, -60 to 0
+    pub ratio: f32,                     // 1.0 (no compression) to 20.0
+    pub attack_secs: f32,
+    pub release_secs: f32,
+    pub makeup_db: f32,                 // gain added after compression, 0 to 24
+}
+
+impl Default for CompressorConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            threshold_db: -18.0,
+            ratio: 4.0,
+            attack_secs: 0.01,
+            release_secs: 0.15,
+            makeup_db: 0.0,
+        }
+    }
+}
+
+/// Feed-forward stereo compressor on the master bus. Both channels share one peak
+/// detector, so the stereo image doesn't shift when only one side is loud.
+#[derive(Clone)]
+pub struct Compressor {
+    config: CompressorConfig,
+    sample_rate: f32,
+    attack_coeff: f32,
+    release_coeff: f32,
+    reduction_db: f32,              // smoothed gain reduction, 0.0 or positive
+}
+
+impl Compressor {
+    pub fn new(config: CompressorConfig, sample_rate: f32) -> Self {
+        let mut compressor = Self {
+            config,
+            sample_rate,
+            attack_coeff: 0.0,
+            release_coeff: 0.0,
+            reduction_db: 0.0,
+        };
+        compressor.update_coefficients();
+        compressor
+    }
+
+    fn update_coefficients(&mut self) {
+        let coeff = |secs: f32| (-1.0 / (secs.max(MIN_TIME_SECS) * self.sample_rate)).exp();
+        self.attack_coeff = coeff(self.config.attack_secs);
+        self.release_coeff = coeff(self.config.release_secs);
+    }
+
+    pub fn config(&self) -> CompressorConfig {
+        self.config
+    }
+
+    pub fn set_config(&mut self, config: CompressorConfig) {
+        self.config = config;
+        self.update_coefficients();
+    }
+
+    pub fn update_sample_rate(&mut self, new_sample_rate: f32) {
+        self.sample_rate = new_sample_rate;
+        self.update_coefficients();
+    }
+
+    pub fn reset(&mut self) {
+        self.reduction_db = 0.0;
+    }
+
+    /// Current gain reduction in dB, for metering.
+    pub fn reduction_db(&self) -> f32 {
+        self.reduction_db
+    }
+
+    // Static curve: dB of gain reduction for an input at `level_db`
+    fn target_reduction(&self, level_db: f32) -> f32 {
+        let slope = 1.0 - 1.0 / self.config.ratio.max(1.0);
+        let over = level_db - self.config.threshold_db;
+        if over <= -KNEE_DB / 2.0 {
+            0.0
+        } else if over >= KNEE_DB / 2.0 {
+            over * slope
+        } else {
+            slope * (over + KNEE_DB / 2.0).powi(2) / (2.0 * KNEE_DB)
+        }
+    }
+
+    pub fn process(&mut self, left: f32, right: f32) -> (f32, f32) {
+        if !self.config.enabled {
+            return (left, right);
+        }
+
+        let peak = left.abs().max(right.abs());
+        let level_db = 20.0 * peak.max(1e-6).log10();
+        let target = self.target_reduction(level_db);
+        let coeff = if target > self.reduction_db { self.attack_coeff } else { self.release_coeff };
+        self.reduction_db = target + (self.reduction_db - target) * coeff;
+
+        let gain = 10.0_f32.powf((self.config.makeup_db - self.reduction_db) / 20.0);
+        (left * gain, right * gain)
+    }
+}
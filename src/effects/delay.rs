@@ -0,0 +1,187 @@
+use crate::filter::{Filter, FilterParameters, FilterSlope, FilterType, SaturationCurve};
+use crate::tempo::{SyncDivision, TransportInfo};
+
+const MAX_DELAY_SECS: f32 = 2.0;
+const MAX_FEEDBACK: f32 = 0.95;
+const FEEDBACK_FILTER_Q: f32 = 0.707;   // Butterworth, no resonant peak to build up in the loop
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct DelayConfig {
+    pub enabled: bool,
+    pub time_secs: f32,                 // up to 2 seconds
+    pub feedback: f32,                  // 0.0 to 0.95
+    pub tempo_sync: bool,
+    pub division: SyncDivision,         // delay time while tempo_sync is on
+    pub ping_pong: bool,                // echoes alternate between left and right
+    pub low_cut_hz: f32,                // high-pass inside the feedback loop
+    pub high_cut_hz: f32,               // low-pass inside the feedback loop
+}
+
+impl Default for DelayConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            time_secs: 0.375,
+            feedback: 0.35,
+            tempo_sync: false,
+            division: SyncDivision::EighthDotted,
+            ping_pong: false,
+            low_cut_hz: 20.0,
+            high_cut_hz: 20000.0,
+        }
+    }
+}
+
+impl DelayConfig {
+    pub fn delay_secs(&self, tempo_bpm: f32) -> f32 {
+        let secs = if self.tempo_sync {
+            1.0 / self.division.frequency_hz(tempo_bpm)
+        } else {
+            self.time_secs
+        };
+        secs.min(MAX_DELAY_SECS)
+    }
+}
+
+fn feedback_filter(filter_type: FilterType, cutoff_frequency: f32, sample_rate: f32) -> Filter {
+    let parameters = FilterParameters {
+        filter_type,
+        slope: FilterSlope::Slope12dB,
+        cutoff_frequency,
+        resonance_amount: FEEDBACK_FILTER_Q,
+        modulation_amount: 0.0,
+        drive: 0.0,
+        saturation: SaturationCurve::Tanh,
+    };
+    Filter::new(parameters, sample_rate)
+}
+
+// Low and high cut for one channel of the feedback path
+#[derive(Clone)]
+struct FeedbackFilter {
+    low_cut: Filter,
+    high_cut: Filter,
+}
+
+impl FeedbackFilter {
+    fn new(config: &DelayConfig, sample_rate: f32) -> Self {
+        Self {
+            low_cut: feedback_filter(FilterType::HighPass, config.low_cut_hz, sample_rate),
+            high_cut: feedback_filter(FilterType::LowPass, config.high_cut_hz, sample_rate),
+        }
+    }
+
+    fn set_cutoffs(&mut self, config: &DelayConfig) {
+        self.low_cut.set_cutoff_frequency(config.low_cut_hz);
+        self.high_cut.set_cutoff_frequency(config.high_cut_hz);
+    }
+
+    fn reset(&mut self) {
+        self.low_cut.reset();
+        self.high_cut.reset();
+    }
+
+    fn process(&mut self, input: f32) -> f32 {
+        self.high_cut.process_sample(self.low_cut.process_sample(input))
+    }
+}
+
+/// Stereo or ping-pong feedback delay returning only the delayed signal. Every repeat
+/// passes the low and high cut, so the echoes get thinner as they fade.
+#[derive(Clone)]
+pub struct Delay {
+    config: DelayConfig,
+    sample_rate: f32,
+    tempo_bpm: f32,
+    left: Vec<f32>,
+    right: Vec<f32>,
+    filters: [FeedbackFilter; 2],
+    write: usize,
+}
+
+impl Delay {
+    pub fn new(config: DelayConfig, sample_rate: f32) -> Self {
+        let length = Self::buffer_length(sample_rate);
+        Self {
+            config,
+            sample_rate,
+            tempo_bpm: 120.0,
+            left: vec![0.0; length],
+            right: vec![0.0; length],
+            filters: [FeedbackFilter::new(&config, sample_rate), FeedbackFilter::new(&config, sample_rate)],
+            write: 0,
+        }
+    }
+
+    fn buffer_length(sample_rate: f32) -> usize {
+        (MAX_DELAY_SECS * sample_rate) as usize + 1
+    }
+
+    pub fn config(&self) -> DelayConfig {
+        self.config
+    }
+
+    /// Bypassing drops the tail, so re-enabling doesn't bring back old sound.
+    pub fn set_config(&mut self, config: DelayConfig) {
+        if self.config.enabled && !config.enabled {
+            self.reset();
+        }
+        for filter in &mut self.filters {
+            filter.set_cutoffs(&config);
+        }
+        self.config = config;
+    }
+
+    pub fn sync_to_transport(&mut self, transport: &TransportInfo) {
+        self.tempo_bpm = transport.tempo_bpm;
+    }
+
+    /// Reallocates the delay lines, so it does nothing unless the rate really changed.
+    pub fn update_sample_rate(&mut self, new_sample_rate: f32) {
+        if new_sample_rate != self.sample_rate {
+            let tempo_bpm = self.tempo_bpm;
+            *self = Self::new(self.config, new_sample_rate);
+            self.tempo_bpm = tempo_bpm;
+        }
+    }
+
+    /// Longest stretch of silence between two echoes, i.e. one delay time.
+    pub fn echo_gap_samples(&self) -> usize {
+        (self.config.delay_secs(self.tempo_bpm) * self.sample_rate) as usize
+    }
+
+    pub fn reset(&mut self) {
+        self.left.fill(0.0);
+        self.right.fill(0.0);
+        for filter in &mut self.filters {
+            filter.reset();
+        }
+        self.write = 0;
+    }
+
+    pub fn process(&mut self, left: f32, right: f32) -> (f32, f32) {
+        if !self.config.enabled {
+            return (0.0, 0.0);
+        }
+        let length = self.left.len();
+        let delay = ((self.config.delay_secs(self.tempo_bpm) * self.sample_rate) as usize).clamp(1, length - 1);
+        let feedback = self.config.feedback.clamp(0.0, MAX_FEEDBACK);
+        let read = (self.write + length - delay) % length;
+
+        let out_left = self.left[read];
+        let out_right = self.right[read];
+        let feedback_left = self.filters[0].process(out_left * feedback);
+        let feedback_right = self.filters[1].process(out_right * feedback);
+        if self.config.ping_pong {
+            // Mono input starts on the left, then every repeat crosses over
+            self.left[self.write] = 0.5 * (left + right) + feedback_right;
+            self.right[self.write] = feedback_left;
+        } else {
+            self.left[self.write] = left + feedback_left;
+            self.right[self.write] = right + feedback_right;
+        }
+        self.write = (self.write + 1) % length;
+
+        (out_left, out_right)
+    }
+}
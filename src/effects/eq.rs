@@ -0,0 +1,137 @@
+use std::f32::consts::PI;
+
+use crate::filter::{process_filter_stage, FilterStage};
+
+const MIN_FREQ_HZ: f32 = 20.0;
+const SHELF_SLOPE: f32 = 1.0;           // steepest shelf without overshoot
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct EqConfig {
+    pub enabled: bool,
+    pub low_freq: f32,                  // shelf corner in Hz
+    pub low_gain_db: f32,               // -18 to +18
+    pub mid_freq: f32,
+    pub mid_gain_db: f32,
+    pub mid_q: f32,                     // bandwidth of the mid bell, 0.3 to 8.0
+    pub high_freq: f32,
+    pub high_gain_db: f32,
+}
+
+impl Default for EqConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            low_freq: 120.0,
+            low_gain_db: 0.0,
+            mid_freq: 1000.0,
+            mid_gain_db: 0.0,
+            mid_q: 0.7,
+            high_freq: 6000.0,
+            high_gain_db: 0.0,
+        }
+    }
+}
+
+// Normalised biquad coefficients in the order the filter stages take them:
+// feedback1, feedback2, feed0, feed1, feed2
+type Coefficients = (f32, f32, f32, f32, f32);
+
+fn normalise(b0: f32, b1: f32, b2: f32, a0: f32, a1: f32, a2: f32) -> Coefficients {
+    (a1 / a0, a2 / a0, b0 / a0, b1 / a0, b2 / a0)
+}
+
+fn shelf(freq: f32, gain_db: f32, sample_rate: f32, high: bool) -> Coefficients {
+    let amp = 10.0_f32.powf(gain_db / 40.0);
+    let w0 = 2.0 * PI * freq.clamp(MIN_FREQ_HZ, 0.49 * sample_rate) / sample_rate;
+    let (sin, cos) = w0.sin_cos();
+    let alpha = sin / 2.0 * ((amp + 1.0 / amp) * (1.0 / SHELF_SLOPE - 1.0) + 2.0).sqrt();
+    let root = 2.0 * amp.sqrt() * alpha;
+    // The high shelf is the low shelf with the sign of the cosine terms flipped
+    let sign = if high { -1.0 } else { 1.0 };
+    normalise(
+        amp * ((amp + 1.0) - sign * (amp - 1.0) * cos + root),
+        sign * 2.0 * amp * ((amp - 1.0) - sign * (amp + 1.0) * cos),
+        amp * ((amp + 1.0) - sign * (amp - 1.0) * cos - root),
+        (amp + 1.0) + sign * (amp - 1.0) * cos + root,
+        -sign * 2.0 * ((amp - 1.0) + sign * (amp + 1.0) * cos),
+        (amp + 1.0) + sign * (amp - 1.0) * cos - root,
+    )
+}
+
+fn peak(freq: f32, gain_db: f32, q: f32, sample_rate: f32) -> Coefficients {
+    let amp = 10.0_f32.powf(gain_db / 40.0);
+    let w0 = 2.0 * PI * freq.clamp(MIN_FREQ_HZ, 0.49 * sample_rate) / sample_rate;
+    let (sin, cos) = w0.sin_cos();
+    let alpha = sin / (2.0 * q.max(0.1));
+    normalise(
+        1.0 + alpha * amp,
+        -2.0 * cos,
+        1.0 - alpha * amp,
+        1.0 + alpha / amp,
+        -2.0 * cos,
+        1.0 - alpha / amp,
+    )
+}
+
+/// Master bus EQ: low shelf, bell-shaped mid and high shelf, each a biquad stage run
+/// through the same difference equation as the voice filters.
+#[derive(Clone)]
+pub struct Equalizer {
+    config: EqConfig,
+    sample_rate: f32,
+    coefficients: [Coefficients; 3],
+    stages: [[FilterStage; 3]; 2],      // left and right, one stage per band
+}
+
+impl Equalizer {
+    pub fn new(config: EqConfig, sample_rate: f32) -> Self {
+        let mut eq = Self {
+            config,
+            sample_rate,
+            coefficients: [(0.0, 0.0, 1.0, 0.0, 0.0); 3],
+            stages: std::array::from_fn(|_| std::array::from_fn(|_| FilterStage::new())),
+        };
+        eq.update_coefficients();
+        eq
+    }
+
+    fn update_coefficients(&mut self) {
+        let c = &self.config;
+        self.coefficients = [
+            shelf(c.low_freq, c.low_gain_db, self.sample_rate, false),
+            peak(c.mid_freq, c.mid_gain_db, c.mid_q, self.sample_rate),
+            shelf(c.high_freq, c.high_gain_db, self.sample_rate, true),
+        ];
+    }
+
+    pub fn config(&self) -> EqConfig {
+        self.config
+    }
+
+    pub fn set_config(&mut self, config: EqConfig) {
+        self.config = config;
+        self.update_coefficients();
+    }
+
+    pub fn update_sample_rate(&mut self, new_sample_rate: f32) {
+        self.sample_rate = new_sample_rate;
+        self.update_coefficients();
+    }
+
+    pub fn reset(&mut self) {
+        self.stages = std::array::from_fn(|_| std::array::from_fn(|_| FilterStage::new()));
+    }
+
+    pub fn process(&mut self, left: f32, right: f32) -> (f32, f32) {
+        if !self.config.enabled {
+            return (left, right);
+        }
+        let mut out = [left, right];
+        for (sample, stages) in out.iter_mut().zip(self.stages.iter_mut()) {
+            for (stage, &(fb1, fb2, ff0, ff1, ff2)) in stages.iter_mut().zip(self.coefficients.iter()) {
+                *sample = process_filter_stage(stage, *sample, fb1, fb2, ff0, ff1, ff2);
+            }
+        }
+        (out[0], out[1])
+    }
+}
@@ -0,0 +1,120 @@
+use super::sweep::{SweepLfo, SweepRate};
+use crate::tempo::TransportInfo;
+
+const MAX_DELAY_MS: f32 = 20.0;
+const MIN_DELAY_MS: f32 = 0.1;
+const MAX_FEEDBACK: f32 = 0.95;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct FlangerConfig {
+    pub enabled: bool,
+    pub rate: SweepRate,
+    pub delay_ms: f32,                  // centre of the sweep, 0.5 to 10 ms
+    pub depth: f32,                     // 0.0 to 1.0, how far the delay swings around the centre
+    pub feedback: f32,                  // -0.95 to 0.95, negative for a hollower sound
+    pub mix: f32,                       // 0.0 dry to 1.0 for the deepest comb
+}
+
+impl Default for FlangerConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            rate: SweepRate { hz: 0.2, ..SweepRate::default() },
+            delay_ms: 2.5,
+            depth: 0.7,
+            feedback: 0.5,
+            mix: 1.0,
+        }
+    }
+}
+
+/// Stereo flanger: a short delay swept by the LFO and mixed with the dry signal.
+#[derive(Clone)]
+pub struct Flanger {
+    config: FlangerConfig,
+    sample_rate: f32,
+    lfo: SweepLfo,
+    left: Vec<f32>,
+    right: Vec<f32>,
+    write: usize,
+}
+
+impl Flanger {
+    pub fn new(config: FlangerConfig, sample_rate: f32) -> Self {
+        let length = Self::buffer_length(sample_rate);
+        Self {
+            config,
+            sample_rate,
+            lfo: SweepLfo::new(),
+            left: vec![0.0; length],
+            right: vec![0.0; length],
+            write: 0,
+        }
+    }
+
+    fn buffer_length(sample_rate: f32) -> usize {
+        (MAX_DELAY_MS * 0.001 * sample_rate) as usize + 2
+    }
+
+    pub fn config(&self) -> FlangerConfig {
+        self.config
+    }
+
+    pub fn set_config(&mut self, config: FlangerConfig) {
+        self.config = config;
+    }
+
+    /// Reallocates the delay lines, so it does nothing unless the rate really changed.
+    pub fn update_sample_rate(&mut self, new_sample_rate: f32) {
+        if new_sample_rate != self.sample_rate {
+            let lfo = self.lfo.clone();
+            *self = Self::new(self.config, new_sample_rate);
+            self.lfo = lfo;
+        }
+    }
+
+    pub fn sync_to_transport(&mut self, transport: &TransportInfo) {
+        self.lfo.sync_to_transport(&self.config.rate, transport);
+    }
+
+    pub fn reset(&mut self) {
+        self.lfo.reset();
+        self.left.fill(0.0);
+        self.right.fill(0.0);
+        self.write = 0;
+    }
+
+    // Linearly interpolated read `delay` samples behind the write position
+    fn read(buffer: &[f32], write: usize, delay: f32) -> f32 {
+        let length = buffer.len();
+        let whole = delay as usize;
+        let frac = delay - whole as f32;
+        let a = buffer[(write + length - whole) % length];
+        let b = buffer[(write + length - whole - 1) % length];
+        a + (b - a) * frac
+    }
+
+    pub fn process(&mut self, left: f32, right: f32) -> (f32, f32) {
+        if !self.config.enabled {
+            return (left, right);
+        }
+        let (sweep_left, sweep_right) = self.lfo.next_values(&self.config.rate, self.sample_rate);
+        let max_delay = (self.left.len() - 2) as f32;
+        let centre = self.config.delay_ms * 0.001 * self.sample_rate;
+        let min_delay = MIN_DELAY_MS * 0.001 * self.sample_rate;
+        let delay = |sweep: f32| {
+            (centre * (1.0 + self.config.depth * (2.0 * sweep - 1.0))).clamp(min_delay.max(1.0), max_delay)
+        };
+        let (delay_left, delay_right) = (delay(sweep_left), delay(sweep_right));
+
+        let wet_left = Self::read(&self.left, self.write, delay_left);
+        let wet_right = Self::read(&self.right, self.write, delay_right);
+        let feedback = self.config.feedback.clamp(-MAX_FEEDBACK, MAX_FEEDBACK);
+        self.left[self.write] = left + wet_left * feedback;
+        self.right[self.write] = right + wet_right * feedback;
+        self.write = (self.write + 1) % self.left.len();
+
+        let mix = 0.5 * self.config.mix;
+        (left + (wet_left - left) * mix, right + (wet_right - right) * mix)
+    }
+}
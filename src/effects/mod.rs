@@ -0,0 +1,207 @@
+pub mod chain;
+pub mod compressor;
+pub mod delay;
+pub mod eq;
+pub mod flanger;
+pub mod phaser;
+pub mod reverb;
+pub mod sweep;
+pub mod voice_insert;
+pub mod waveshaper;
+
+pub use chain::{DuplicateInsert, FxChainConfig, InsertEffect, SendOrder, INSERT_COUNT};
+pub use compressor::{Compressor, CompressorConfig};
+pub use delay::{Delay, DelayConfig};
+pub use eq::{EqConfig, Equalizer};
+pub use flanger::{Flanger, FlangerConfig};
+pub use phaser::{Phaser, PhaserConfig, PhaserStages};
+pub use reverb::{Reverb, ReverbConfig};
+pub use sweep::SweepRate;
+pub use voice_insert::{VoiceInsert, VoiceInsertConfig, VoiceInsertKind};
+pub use waveshaper::{Oversampling, WaveShape, Waveshaper, WaveshaperConfig};
+
+use crate::tempo::TransportInfo;
+
+const KEY_SCALING_CENTER: f32 = 60.0;   // middle C, where key scaling leaves the sends untouched
+const KEY_SCALING_RANGE: f32 = 48.0;    // semitones from the centre to full key scaling
+
+/// How much of every voice is sent into the delay and reverb.
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct SendConfig {
+    pub delay: f32,                     // 0.0 to 1.0
+    pub reverb: f32,                    // 0.0 to 1.0
+    pub velocity_scaling: f32,          // -1.0 to 1.0, positive makes harder notes wetter, negative softer ones
+    pub key_scaling: f32,               // -1.0 to 1.0, positive makes higher notes wetter
+}
+
+impl SendConfig {
+    /// Delay and reverb send gains for a note of `frequency` played at `velocity`.
+    pub fn voice_levels(&self, frequency: f32, velocity: f32) -> (f32, f32) {
+        let velocity = velocity.clamp(0.0, 1.0);
+        let velocity_factor = if self.velocity_scaling >= 0.0 {
+            1.0 - self.velocity_scaling * (1.0 - velocity)
+        } else {
+            1.0 + self.velocity_scaling * velocity
+        };
+
+        let note = 69.0 + 12.0 * (frequency.max(1.0) / 440.0).log2();
+        let key = ((note - KEY_SCALING_CENTER) / KEY_SCALING_RANGE).clamp(-1.0, 1.0);
+        let key_factor = (1.0 + self.key_scaling * key).max(0.0);
+
+        let scale = velocity_factor * key_factor;
+        ((self.delay * scale).clamp(0.0, 1.0), (self.reverb * scale).clamp(0.0, 1.0))
+    }
+}
+
+/// One frame of effect inputs, summed from every voice's sends.
+#[derive(Clone, Copy, Default)]
+pub struct SendBus {
+    pub delay: (f32, f32),
+    pub reverb: (f32, f32),
+}
+
+impl SendBus {
+    pub fn add(&mut self, (left, right): (f32, f32), (delay, reverb): (f32, f32)) {
+        self.delay.0 += left * delay;
+        self.delay.1 += right * delay;
+        self.reverb.0 += left * reverb;
+        self.reverb.1 += right * reverb;
+    }
+
+    pub fn scaled(self, gain: f32) -> Self {
+        Self {
+            delay: (self.delay.0 * gain, self.delay.1 * gain),
+            reverb: (self.reverb.0 * gain, self.reverb.1 * gain),
+        }
+    }
+}
+
+const TAIL_MARGIN_SECS: f32 = 0.1;
+
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct ModulationFxConfig {
+    pub phaser: PhaserConfig,
+    pub flanger: FlangerConfig,
+}
+
+/// The shared send effects and the master bus inserts. `process` returns only the wet
+/// signal; the dry mix is the caller's, which then runs the whole mix through `process_inserts`.
+/// Both follow the order set with `set_chain`.
+#[derive(Clone)]
+pub struct Effects {
+    pub delay: Delay,
+    pub reverb: Reverb,
+    pub waveshaper: Waveshaper,
+    pub eq: Equalizer,
+    pub compressor: Compressor,
+    pub phaser: Phaser,
+    pub flanger: Flanger,
+    chain: FxChainConfig,
+}
+
+impl Effects {
+    pub fn new(
+        delay: DelayConfig,
+        reverb: ReverbConfig,
+        waveshaper: WaveshaperConfig,
+        eq: EqConfig,
+        compressor: CompressorConfig,
+        modulation: ModulationFxConfig,
+        sample_rate: f32,
+    ) -> Self {
+        Self {
+            delay: Delay::new(delay, sample_rate),
+            reverb: Reverb::new(reverb, sample_rate),
+            waveshaper: Waveshaper::new(waveshaper, sample_rate),
+            eq: Equalizer::new(eq, sample_rate),
+            compressor: Compressor::new(compressor, sample_rate),
+            phaser: Phaser::new(modulation.phaser, sample_rate),
+            flanger: Flanger::new(modulation.flanger, sample_rate),
+            chain: FxChainConfig::default(),
+        }
+    }
+
+    pub fn with_chain(mut self, chain: FxChainConfig) -> Self {
+        self.chain = chain;
+        self
+    }
+
+    pub fn chain(&self) -> FxChainConfig {
+        self.chain
+    }
+
+    pub fn set_chain(&mut self, chain: FxChainConfig) {
+        self.chain = chain;
+    }
+
+    pub fn update_sample_rate(&mut self, new_sample_rate: f32) {
+        self.delay.update_sample_rate(new_sample_rate);
+        self.reverb.update_sample_rate(new_sample_rate);
+        self.waveshaper.update_sample_rate(new_sample_rate);
+        self.eq.update_sample_rate(new_sample_rate);
+        self.compressor.update_sample_rate(new_sample_rate);
+        self.phaser.update_sample_rate(new_sample_rate);
+        self.flanger.update_sample_rate(new_sample_rate);
+    }
+
+    pub fn set_modulation_config(&mut self, config: ModulationFxConfig) {
+        self.phaser.set_config(config.phaser);
+        self.flanger.set_config(config.flanger);
+    }
+
+    /// Follows the transport tempo and locks tempo-synced sweeps to the song position.
+    pub fn sync_to_transport(&mut self, transport: &TransportInfo) {
+        self.delay.sync_to_transport(transport);
+        self.phaser.sync_to_transport(transport);
+        self.flanger.sync_to_transport(transport);
+    }
+
+    pub fn reset(&mut self) {
+        self.delay.reset();
+        self.reverb.reset();
+        self.waveshaper.reset();
+        self.eq.reset();
+        self.compressor.reset();
+        self.phaser.reset();
+        self.flanger.reset();
+    }
+
+    /// How long the wet output can stay silent while a tail is still on its way: an echo
+    /// can be one delay time away, the reverb its pre-delay, plus a little for the comb lines.
+    pub fn silent_gap_samples(&self, sample_rate: f32) -> usize {
+        self.delay.echo_gap_samples() + self.reverb.pre_delay_samples() + (TAIL_MARGIN_SECS * sample_rate) as usize
+    }
+
+    pub fn process(&mut self, bus: SendBus) -> (f32, f32) {
+        let ((delay_l, delay_r), (reverb_l, reverb_r)) = match self.chain.sends {
+            SendOrder::Parallel => (
+                self.delay.process(bus.delay.0, bus.delay.1),
+                self.reverb.process(bus.reverb.0, bus.reverb.1),
+            ),
+            SendOrder::DelayIntoReverb => {
+                let delay = self.delay.process(bus.delay.0, bus.delay.1);
+                (delay, self.reverb.process(bus.reverb.0 + delay.0, bus.reverb.1 + delay.1))
+            }
+            SendOrder::ReverbIntoDelay => {
+                let reverb = self.reverb.process(bus.reverb.0, bus.reverb.1);
+                (self.delay.process(bus.delay.0 + reverb.0, bus.delay.1 + reverb.1), reverb)
+            }
+        };
+        (delay_l + reverb_l, delay_r + reverb_r)
+    }
+
+    /// Runs the dry plus wet mix through the inserts in chain order; disabled ones pass it on.
+    pub fn process_inserts(&mut self, frame: (f32, f32)) -> (f32, f32) {
+        let mut frame = frame;
+        for effect in self.chain.inserts {
+            frame = match effect {
+                InsertEffect::Waveshaper => self.waveshaper.process(frame.0, frame.1),
+                InsertEffect::Phaser => self.phaser.process(frame.0, frame.1),
+                InsertEffect::Flanger => self.flanger.process(frame.0, frame.1),
+                InsertEffect::Eq => self.eq.process(frame.0, frame.1),
+                InsertEffect::Compressor => self.compressor.process(frame.0, frame.1),
+            };
+        }
+        frame
+    }
+}
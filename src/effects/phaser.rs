@@ -0,0 +1,132 @@
+use std::f32::consts::PI;
+
+use super::sweep::{SweepLfo, SweepRate};
+use crate::tempo::TransportInfo;
+
+const MAX_STAGES: usize = 8;
+const MIN_SWEEP_HZ: f32 = 150.0;
+const SWEEP_OCTAVES: f32 = 5.0;         // full depth sweeps the notches this far up
+const MAX_FEEDBACK: f32 = 0.9;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum PhaserStages {
+    Four,
+    Eight,
+}
+
+impl PhaserStages {
+    pub const ALL: [PhaserStages; 2] = [PhaserStages::Four, PhaserStages::Eight];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            PhaserStages::Four => "4 Stages",
+            PhaserStages::Eight => "8 Stages",
+        }
+    }
+
+    pub fn count(self) -> usize {
+        match self {
+            PhaserStages::Four => 4,
+            PhaserStages::Eight => 8,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct PhaserConfig {
+    pub enabled: bool,
+    pub stages: PhaserStages,
+    pub rate: SweepRate,
+    pub depth: f32,                     // 0.0 to 1.0 of the sweep range
+    pub feedback: f32,                  // 0.0 to 0.9
+    pub mix: f32,                       // 0.0 dry to 1.0 for the deepest notches
+}
+
+impl Default for PhaserConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            stages: PhaserStages::Four,
+            rate: SweepRate::default(),
+            depth: 0.6,
+            feedback: 0.4,
+            mix: 1.0,
+        }
+    }
+}
+
+/// Stereo phaser: a chain of swept first-order all-pass stages mixed back with the dry
+/// signal, so notches move through the spectrum.
+#[derive(Clone)]
+pub struct Phaser {
+    config: PhaserConfig,
+    sample_rate: f32,
+    lfo: SweepLfo,
+    stages: [[f32; MAX_STAGES]; 2],     // all-pass state per channel
+    last_wet: [f32; 2],                 // fed back into the chain input
+}
+
+impl Phaser {
+    pub fn new(config: PhaserConfig, sample_rate: f32) -> Self {
+        Self {
+            config,
+            sample_rate,
+            lfo: SweepLfo::new(),
+            stages: [[0.0; MAX_STAGES]; 2],
+            last_wet: [0.0; 2],
+        }
+    }
+
+    pub fn config(&self) -> PhaserConfig {
+        self.config
+    }
+
+    pub fn set_config(&mut self, config: PhaserConfig) {
+        self.config = config;
+    }
+
+    pub fn update_sample_rate(&mut self, new_sample_rate: f32) {
+        self.sample_rate = new_sample_rate;
+    }
+
+    pub fn sync_to_transport(&mut self, transport: &TransportInfo) {
+        self.lfo.sync_to_transport(&self.config.rate, transport);
+    }
+
+    pub fn reset(&mut self) {
+        self.lfo.reset();
+        self.stages = [[0.0; MAX_STAGES]; 2];
+        self.last_wet = [0.0; 2];
+    }
+
+    // All-pass coefficient that puts the stage's 90° point at `frequency`
+    fn coefficient(&self, sweep: f32) -> f32 {
+        let max_hz = 0.45 * self.sample_rate;
+        let frequency = (MIN_SWEEP_HZ * 2.0f32.powf(sweep * self.config.depth * SWEEP_OCTAVES)).min(max_hz);
+        let t = (PI * frequency / self.sample_rate).tan();
+        (t - 1.0) / (t + 1.0)
+    }
+
+    pub fn process(&mut self, left: f32, right: f32) -> (f32, f32) {
+        if !self.config.enabled {
+            return (left, right);
+        }
+        let (sweep_left, sweep_right) = self.lfo.next_values(&self.config.rate, self.sample_rate);
+        let coefficients = [self.coefficient(sweep_left), self.coefficient(sweep_right)];
+        let feedback = self.config.feedback.clamp(0.0, MAX_FEEDBACK);
+        let count = self.config.stages.count();
+        let mut out = [left, right];
+        for channel in 0..2 {
+            let a = coefficients[channel];
+            let mut sample = out[channel] + self.last_wet[channel] * feedback;
+            for state in &mut self.stages[channel][..count] {
+                let y = a * sample + *state;
+                *state = sample - a * y;
+                sample = y;
+            }
+            self.last_wet[channel] = sample;
+            out[channel] += (sample - out[channel]) * 0.5 * self.config.mix;
+        }
+        (out[0], out[1])
+    }
+}
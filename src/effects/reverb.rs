@@ -0,0 +1,185 @@
+// Comb and all-pass lengths in samples at 44.1 kHz, from Freeverb
+const COMB_LENGTHS: [usize; 8] = [1116, 1188, 1277, 1356, 1422, 1491, 1557, 1617];
+const ALLPASS_LENGTHS: [usize; 4] = [556, 441, 341, 225];
+const STEREO_SPREAD: usize = 23;        // extra samples on the right channel to decorrelate it
+const INPUT_GAIN: f32 = 0.015;
+const ALLPASS_FEEDBACK: f32 = 0.5;
+const ROOM_OFFSET: f32 = 0.7;
+const ROOM_SCALE: f32 = 0.28;
+const DAMPING_SCALE: f32 = 0.4;
+const MAX_PRE_DELAY_SECS: f32 = 0.25;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct ReverbConfig {
+    pub enabled: bool,
+    pub size: f32,                      // 0.0 to 1.0, longer tails when larger
+    pub damping: f32,                   // 0.0 to 1.0, darker tails when larger
+    pub pre_delay_secs: f32,            // gap before the tail starts, up to 0.25 s
+    pub width: f32,                     // 0.0 mono to 1.0 full stereo
+    pub mix: f32,                       // 0.0 to 1.0 level of the returned tail
+}
+
+impl Default for ReverbConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            size: 0.6,
+            damping: 0.4,
+            pre_delay_secs: 0.0,
+            width: 1.0,
+            mix: 1.0,
+        }
+    }
+}
+
+#[derive(Clone)]
+struct Comb {
+    buffer: Vec<f32>,
+    index: usize,
+    damped: f32,
+}
+
+impl Comb {
+    fn new(length: usize) -> Self {
+        Self { buffer: vec![0.0; length.max(1)], index: 0, damped: 0.0 }
+    }
+
+    fn process(&mut self, input: f32, feedback: f32, damping: f32) -> f32 {
+        let output = self.buffer[self.index];
+        self.damped = output * (1.0 - damping) + self.damped * damping;
+        self.buffer[self.index] = input + self.damped * feedback;
+        self.index = (self.index + 1) % self.buffer.len();
+        output
+    }
+}
+
+#[derive(Clone)]
+struct AllPass {
+    buffer: Vec<f32>,
+    index: usize,
+}
+
+impl AllPass {
+    fn new(length: usize) -> Self {
+        Self { buffer: vec![0.0; length.max(1)], index: 0 }
+    }
+
+    fn process(&mut self, input: f32) -> f32 {
+        let buffered = self.buffer[self.index];
+        self.buffer[self.index] = input + buffered * ALLPASS_FEEDBACK;
+        self.index = (self.index + 1) % self.buffer.len();
+        buffered - input
+    }
+}
+
+#[derive(Clone)]
+struct Channel {
+    combs: Vec<Comb>,
+    allpasses: Vec<AllPass>,
+}
+
+impl Channel {
+    fn new(sample_rate: f32, spread: usize) -> Self {
+        let scale = sample_rate / 44100.0;
+        let scaled = |length: usize| ((length + spread) as f32 * scale) as usize;
+        Self {
+            combs: COMB_LENGTHS.iter().map(|&l| Comb::new(scaled(l))).collect(),
+            allpasses: ALLPASS_LENGTHS.iter().map(|&l| AllPass::new(scaled(l))).collect(),
+        }
+    }
+
+    fn reset(&mut self) {
+        for comb in &mut self.combs {
+            comb.buffer.fill(0.0);
+            comb.damped = 0.0;
+        }
+        for allpass in &mut self.allpasses {
+            allpass.buffer.fill(0.0);
+        }
+    }
+
+    fn process(&mut self, input: f32, feedback: f32, damping: f32) -> f32 {
+        let combed = self.combs.iter_mut().map(|c| c.process(input, feedback, damping)).sum::<f32>();
+        self.allpasses.iter_mut().fold(combed, |signal, a| a.process(signal))
+    }
+}
+
+/// Freeverb: eight parallel damped combs into four series all-passes per channel, fed a
+/// mono sum after the pre-delay. Returns only the wet signal.
+#[derive(Clone)]
+pub struct Reverb {
+    config: ReverbConfig,
+    sample_rate: f32,
+    pre_delay: Vec<f32>,
+    pre_delay_write: usize,
+    left: Channel,
+    right: Channel,
+}
+
+impl Reverb {
+    pub fn new(config: ReverbConfig, sample_rate: f32) -> Self {
+        Self {
+            config,
+            sample_rate,
+            pre_delay: vec![0.0; (MAX_PRE_DELAY_SECS * sample_rate) as usize + 1],
+            pre_delay_write: 0,
+            left: Channel::new(sample_rate, 0),
+            right: Channel::new(sample_rate, STEREO_SPREAD),
+        }
+    }
+
+    pub fn config(&self) -> ReverbConfig {
+        self.config
+    }
+
+    /// Bypassing drops the tail, so re-enabling doesn't bring back old sound.
+    pub fn set_config(&mut self, config: ReverbConfig) {
+        if self.config.enabled && !config.enabled {
+            self.reset();
+        }
+        self.config = config;
+    }
+
+    /// Reallocates the delay lines, so it does nothing unless the rate really changed.
+    pub fn update_sample_rate(&mut self, new_sample_rate: f32) {
+        if new_sample_rate != self.sample_rate {
+            *self = Self::new(self.config, new_sample_rate);
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.pre_delay.fill(0.0);
+        self.pre_delay_write = 0;
+        self.left.reset();
+        self.right.reset();
+    }
+
+    /// Samples the pre-delay holds the input back before the tail starts.
+    pub fn pre_delay_samples(&self) -> usize {
+        ((self.config.pre_delay_secs.clamp(0.0, MAX_PRE_DELAY_SECS) * self.sample_rate) as usize)
+            .min(self.pre_delay.len() - 1)
+    }
+
+    pub fn process(&mut self, left: f32, right: f32) -> (f32, f32) {
+        if !self.config.enabled {
+            return (0.0, 0.0);
+        }
+        let length = self.pre_delay.len();
+        self.pre_delay[self.pre_delay_write] = (left + right) * INPUT_GAIN;
+        let read = (self.pre_delay_write + length - self.pre_delay_samples()) % length;
+        let input = self.pre_delay[read];
+        self.pre_delay_write = (self.pre_delay_write + 1) % length;
+
+        let feedback = ROOM_OFFSET + ROOM_SCALE * self.config.size.clamp(0.0, 1.0);
+        let damping = DAMPING_SCALE * self.config.damping.clamp(0.0, 1.0);
+        let wet_left = self.left.process(input, feedback, damping);
+        let wet_right = self.right.process(input, feedback, damping);
+
+        // Freeverb's width: each side gets a little of the other as the image narrows
+        let width = self.config.width.clamp(0.0, 1.0);
+        let mix = self.config.mix.clamp(0.0, 1.0);
+        let direct = mix * (0.5 + 0.5 * width);
+        let cross = mix * (0.5 - 0.5 * width);
+        (wet_left * direct + wet_right * cross, wet_right * direct + wet_left * cross)
+    }
+}
@@ -0,0 +1,65 @@
+use std::f32::consts::PI;
+
+use crate::tempo::{SyncDivision, TransportInfo};
+
+const STEREO_OFFSET: f64 = 0.25;        // right channel a quarter cycle behind the left
+
+/// Speed of a phaser or flanger sweep: free-running in Hz, or locked to a note division.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct SweepRate {
+    pub hz: f32,
+    pub tempo_sync: bool,
+    pub division: SyncDivision,         // cycle length while tempo_sync is on
+}
+
+impl Default for SweepRate {
+    fn default() -> Self {
+        Self {
+            hz: 0.5,
+            tempo_sync: false,
+            division: SyncDivision::Whole,
+        }
+    }
+}
+
+impl SweepRate {
+    pub fn frequency_hz(&self, tempo_bpm: f32) -> f32 {
+        if self.tempo_sync {
+            self.division.frequency_hz(tempo_bpm)
+        } else {
+            self.hz
+        }
+    }
+}
+
+// Stereo sine sweep shared by the modulation effects; yields 0.0 to 1.0 per channel
+#[derive(Clone)]
+pub(crate) struct SweepLfo {
+    tempo_bpm: f32,
+    phase: f64,
+}
+
+impl SweepLfo {
+    pub(crate) fn new() -> Self {
+        Self { tempo_bpm: 120.0, phase: 0.0 }
+    }
+
+    pub(crate) fn reset(&mut self) {
+        self.phase = 0.0;
+    }
+
+    pub(crate) fn sync_to_transport(&mut self, rate: &SweepRate, transport: &TransportInfo) {
+        self.tempo_bpm = transport.tempo_bpm;
+        if rate.tempo_sync && transport.playing {
+            self.phase = (transport.position_beats / rate.division.beats()).rem_euclid(1.0);
+        }
+    }
+
+    pub(crate) fn next_values(&mut self, rate: &SweepRate, sample_rate: f32) -> (f32, f32) {
+        let value = |phase: f64| 0.5 - 0.5 * (2.0 * PI * phase as f32).cos();
+        let values = (value(self.phase), value((self.phase + STEREO_OFFSET).rem_euclid(1.0)));
+        let cycles_per_sample = rate.frequency_hz(self.tempo_bpm) / sample_rate;
+        self.phase = (self.phase + cycles_per_sample as f64).rem_euclid(1.0);
+        values
+    }
+}
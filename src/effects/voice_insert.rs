@@ -0,0 +1,151 @@
+use std::f32::consts::PI;
+
+use crate::filter::{drive_gains, SaturationCurve};
+use crate::oversampling::MAX_FACTOR;
+
+const DRIVE_CURVE: SaturationCurve = SaturationCurve::Tanh;
+const CHORUS_BUFFER_SECS: f32 = 0.02;
+const CHORUS_DELAY_SECS: f32 = 0.007;
+const CHORUS_DEPTH_SECS: f32 = 0.003;
+const CHORUS_RATE_HZ: f32 = 0.8;
+
+/// Effect run inside every voice, after its filters and before the mix bus.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum VoiceInsertKind {
+    Off,
+    Drive,
+    Chorus,
+}
+
+impl VoiceInsertKind {
+    pub const ALL: [VoiceInsertKind; 3] = [VoiceInsertKind::Off, VoiceInsertKind::Drive, VoiceInsertKind::Chorus];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            VoiceInsertKind::Off => "Off",
+            VoiceInsertKind::Drive => "Drive",
+            VoiceInsertKind::Chorus => "Chorus",
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct VoiceInsertConfig {
+    pub kind: VoiceInsertKind,
+    pub amount: f32,                    // 0.0 to 1.0, drive gain or chorus mix and depth
+}
+
+impl Default for VoiceInsertConfig {
+    fn default() -> Self {
+        Self {
+            kind: VoiceInsertKind::Off,
+            amount: 0.5,
+        }
+    }
+}
+
+/// Per-voice drive or micro-chorus. Saturating each note on its own keeps chords clean
+/// where bus saturation would intermodulate them, and a chorus per voice drifts each note
+/// independently.
+#[derive(Clone)]
+pub struct VoiceInsert {
+    config: VoiceInsertConfig,
+    sample_rate: f32,
+    drive_gains: (f32, f32),        // drive input gain and output compensation
+    buffer: Vec<(f32, f32)>,        // chorus delay line, allocated once for the highest oversampled rate
+    length: usize,                  // part of the buffer in use at the current rate
+    write: usize,
+    phase: f32,                     // chorus LFO, 0.0 to 1.0
+}
+
+// Delay line frames the chorus needs at `sample_rate`
+fn chorus_length(sample_rate: f32) -> usize {
+    (CHORUS_BUFFER_SECS * sample_rate) as usize + 2
+}
+
+impl VoiceInsert {
+    /// Allocates the chorus line whatever the kind, so switching to the chorus later
+    /// doesn't allocate on the audio thread.
+    pub fn new(config: VoiceInsertConfig, sample_rate: f32) -> Self {
+        Self {
+            config,
+            sample_rate,
+            drive_gains: drive_gains(config.amount, DRIVE_CURVE),
+            buffer: vec![(0.0, 0.0); chorus_length(sample_rate * MAX_FACTOR as f32)],
+            length: chorus_length(sample_rate),
+            write: 0,
+            phase: 0.0,
+        }
+    }
+
+    pub fn set_config(&mut self, config: VoiceInsertConfig) {
+        self.config = config;
+        self.drive_gains = drive_gains(config.amount, DRIVE_CURVE);
+    }
+
+    /// Only allocates when the rate climbs past what the line was built for, e.g. when
+    /// the host switches to a higher sample rate.
+    pub fn update_sample_rate(&mut self, new_sample_rate: f32) {
+        if new_sample_rate != self.sample_rate {
+            self.sample_rate = new_sample_rate;
+            self.length = chorus_length(new_sample_rate);
+            if self.length > self.buffer.len() {
+                self.buffer = vec![(0.0, 0.0); chorus_length(new_sample_rate * MAX_FACTOR as f32)];
+            }
+            self.buffer[..self.length].fill((0.0, 0.0));
+            self.write = 0;
+        }
+    }
+
+    /// Clears the chorus line and starts the LFO at `phase`, so notes don't chorus in step.
+    pub fn reset(&mut self, phase: f32) {
+        self.buffer[..self.length].fill((0.0, 0.0));
+        self.phase = phase.rem_euclid(1.0);
+    }
+
+    pub fn process(&mut self, left: f32, right: f32) -> (f32, f32) {
+        let amount = self.config.amount.clamp(0.0, 1.0);
+        match self.config.kind {
+            VoiceInsertKind::Off => (left, right),
+            VoiceInsertKind::Drive => {
+                // Unity gain for a full-scale input at any drive setting
+                let (gain, compensation) = self.drive_gains;
+                (DRIVE_CURVE.apply(left * gain) * compensation, DRIVE_CURVE.apply(right * gain) * compensation)
+            }
+            VoiceInsertKind::Chorus => self.chorus(left, right, amount),
+        }
+    }
+
+    // Left and right taps swing in quadrature for width; the amount sets depth and mix
+    fn chorus(&mut self, left: f32, right: f32, amount: f32) -> (f32, f32) {
+        let len = self.length;
+        self.buffer[self.write] = (left, right);
+
+        let lfo = 2.0 * PI * self.phase;
+        let depth = CHORUS_DEPTH_SECS * amount;
+        let wet_left = self.read(CHORUS_DELAY_SECS + depth * lfo.sin(), true);
+        let wet_right = self.read(CHORUS_DELAY_SECS + depth * lfo.cos(), false);
+
+        self.write = (self.write + 1) % len;
+        self.phase = (self.phase + CHORUS_RATE_HZ / self.sample_rate) % 1.0;
+
+        let mix = 0.5 * amount;
+        (left + (wet_left - left) * mix, right + (wet_right - right) * mix)
+    }
+
+    // Linearly interpolated read `delay_secs` behind the write position
+    fn read(&self, delay_secs: f32, left: bool) -> f32 {
+        let len = self.length;
+        let delay = (delay_secs * self.sample_rate).clamp(1.0, (len - 2) as f32);
+        let position = (self.write as f32 - delay).rem_euclid(len as f32);
+        let index = position as usize % len;
+        let next = (index + 1) % len;
+        let frac = position.fract();
+        let (a, b) = if left {
+            (self.buffer[index].0, self.buffer[next].0)
+        } else {
+            (self.buffer[index].1, self.buffer[next].1)
+        };
+        a + (b - a) * frac
+    }
+}
@@ -0,0 +1,234 @@
+use std::f32::consts::PI;
+
+use crate::filter::SaturationCurve;
+use crate::oversampling::{halfband_taps, HalfbandFir, HALFBAND_TAPS};
+
+const MAX_DRIVE_GAIN: f32 = 32.0;       // about +30 dB into the shaper
+const TONE_MIN_HZ: f32 = 800.0;
+const TONE_MAX_HZ: f32 = 18000.0;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum WaveShape {
+    Tanh,
+    HardClip,
+    Foldback,
+    Asymmetric,
+}
+
+impl WaveShape {
+    pub const ALL: [WaveShape; 4] = [WaveShape::Tanh, WaveShape::HardClip, WaveShape::Foldback, WaveShape::Asymmetric];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            WaveShape::Tanh => "Tanh",
+            WaveShape::HardClip => "Hard Clip",
+            WaveShape::Foldback => "Foldback",
+            WaveShape::Asymmetric => "Asymmetric",
+        }
+    }
+
+    pub fn apply(self, x: f32) -> f32 {
+        match self {
+            WaveShape::Tanh => SaturationCurve::Tanh.apply(x),
+            WaveShape::HardClip => x.clamp(-1.0, 1.0),
+            // Reflects everything beyond ±1 back into range, for metallic overtones
+            WaveShape::Foldback => {
+                let folded = (x + 1.0).rem_euclid(4.0);
+                if folded < 2.0 { folded - 1.0 } else { 3.0 - folded }
+            }
+            // Even harmonics from treating the two half-waves differently, the same curve
+            // as the filter drive's
+            WaveShape::Asymmetric => SaturationCurve::Asymmetric.apply(x),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Oversampling {
+    X2,
+    X4,
+}
+
+impl Oversampling {
+    pub const ALL: [Oversampling; 2] = [Oversampling::X2, Oversampling::X4];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Oversampling::X2 => "2x",
+            Oversampling::X4 => "4x",
+        }
+    }
+
+    fn stages(self) -> usize {
+        match self {
+            Oversampling::X2 => 1,
+            Oversampling::X4 => 2,
+        }
+    }
+
+    // Each stage's two halfband filters delay by half their length at that stage's rate,
+    // less the one sample at that rate the output it keeps is ahead
+    fn latency_samples(self) -> f32 {
+        let filter_delay = (HALFBAND_TAPS - 1) as f32 / 2.0;
+        (1..=self.stages()).map(|stage| (2.0 * filter_delay - 1.0) / (1 << stage) as f32).sum()
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct WaveshaperConfig {
+    pub enabled: bool,
+    pub drive: f32,                     // 0.0 to 1.0
+    pub shape: WaveShape,
+    pub tone: f32,                      // 0.0 to 1.0, dark to bright
+    pub oversampling: Oversampling,
+}
+
+impl Default for WaveshaperConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            drive: 0.3,
+            shape: WaveShape::Tanh,
+            tone: 0.7,
+            oversampling: Oversampling::X2,
+        }
+    }
+}
+
+/// One 2x step: zero-stuffing plus lowpass on the way up, lowpass plus decimation down.
+#[derive(Clone)]
+struct OversamplingStage {
+    up: HalfbandFir,
+    down: HalfbandFir,
+}
+
+impl OversamplingStage {
+    fn new() -> Self {
+        Self { up: HalfbandFir::new(), down: HalfbandFir::new() }
+    }
+}
+
+#[derive(Clone)]
+struct ShaperChannel {
+    stages: [OversamplingStage; 2],
+    tone_state: f32,
+}
+
+impl ShaperChannel {
+    fn new() -> Self {
+        Self { stages: [OversamplingStage::new(), OversamplingStage::new()], tone_state: 0.0 }
+    }
+}
+
+/// Drive into a static curve at 2x or 4x the sample rate, so the harmonics it creates
+/// above the original Nyquist are filtered out instead of folding back as aliasing,
+/// followed by a one-pole tone control. The halfband filters delay the signal, see
+/// `latency_samples`.
+#[derive(Clone)]
+pub struct Waveshaper {
+    config: WaveshaperConfig,
+    sample_rate: f32,
+    taps: [f32; HALFBAND_TAPS],
+    channels: [ShaperChannel; 2],
+    gain: f32,
+    compensation: f32,
+    tone_coeff: f32,
+}
+
+impl Waveshaper {
+    pub fn new(config: WaveshaperConfig, sample_rate: f32) -> Self {
+        let mut shaper = Self {
+            config,
+            sample_rate,
+            taps: halfband_taps(),
+            channels: [ShaperChannel::new(), ShaperChannel::new()],
+            gain: 1.0,
+            compensation: 1.0,
+            tone_coeff: 0.0,
+        };
+        shaper.update_coefficients();
+        shaper
+    }
+
+    fn update_coefficients(&mut self) {
+        self.gain = 1.0 + self.config.drive.clamp(0.0, 1.0) * (MAX_DRIVE_GAIN - 1.0);
+        // Roughly level-matched: a full-scale input comes out near full scale at any drive
+        let peak = self.config.shape.apply(self.gain).abs();
+        self.compensation = if peak > 1e-6 { 1.0 / peak } else { 1.0 };
+        let cutoff = TONE_MIN_HZ * (TONE_MAX_HZ / TONE_MIN_HZ).powf(self.config.tone.clamp(0.0, 1.0));
+        self.tone_coeff = (-2.0 * PI * cutoff.min(0.45 * self.sample_rate) / self.sample_rate).exp();
+    }
+
+    pub fn config(&self) -> WaveshaperConfig {
+        self.config
+    }
+
+    pub fn set_config(&mut self, config: WaveshaperConfig) {
+        self.config = config;
+        self.update_coefficients();
+    }
+
+    pub fn update_sample_rate(&mut self, new_sample_rate: f32) {
+        self.sample_rate = new_sample_rate;
+        self.update_coefficients();
+    }
+
+    /// Delay through the oversampling filters in samples at the base rate, rounded: 15 at
+    /// 2x (14.5), 22 at 4x (21.75), none while bypassed.
+    pub fn latency_samples(&self) -> usize {
+        if self.config.enabled { self.config.oversampling.latency_samples().round() as usize } else { 0 }
+    }
+
+    pub fn reset(&mut self) {
+        self.channels = [ShaperChannel::new(), ShaperChannel::new()];
+    }
+
+    pub fn process(&mut self, left: f32, right: f32) -> (f32, f32) {
+        if !self.config.enabled {
+            return (left, right);
+        }
+        (self.process_channel(0, left), self.process_channel(1, right))
+    }
+
+    /// Single-channel use, e.g. a voice's oscillator mix before its filter.
+    pub fn process_mono(&mut self, input: f32) -> f32 {
+        if !self.config.enabled {
+            return input;
+        }
+        self.process_channel(0, input)
+    }
+
+    fn process_channel(&mut self, channel: usize, input: f32) -> f32 {
+        let stages = self.config.oversampling.stages();
+        let (gain, compensation, shape) = (self.gain, self.compensation, self.config.shape);
+        let taps = &self.taps;
+        let state = &mut self.channels[channel];
+
+        let shaped = shape_oversampled(&mut state.stages[..stages], taps, input, &|x| {
+            shape.apply(x * gain) * compensation
+        });
+
+        state.tone_state = shaped + (state.tone_state - shaped) * self.tone_coeff;
+        state.tone_state
+    }
+}
+
+// Recursively doubles the rate once per stage, shapes at the innermost rate and
+// comes back down the same way
+fn shape_oversampled(
+    stages: &mut [OversamplingStage],
+    taps: &[f32; HALFBAND_TAPS],
+    input: f32,
+    shape: &dyn Fn(f32) -> f32,
+) -> f32 {
+    let Some((stage, inner)) = stages.split_first_mut() else {
+        return shape(input);
+    };
+    // Zero-stuffing halves the level, so the interpolator runs at double gain
+    let first = stage.up.process(2.0 * input, taps);
+    let second = stage.up.process(0.0, taps);
+    let first = shape_oversampled(inner, taps, first, shape);
+    let second = shape_oversampled(inner, taps, second, shape);
+    stage.down.process(first, taps);
+    stage.down.process(second, taps)
+}
@@ -1,5 +1,44 @@
+use std::error::Error;
+use std::fmt;
+use std::sync::atomic::{AtomicU32, Ordering};
+
 use crate::filter::ModulationSource;
 
+/// Assignable envelopes per voice on top of the amp and filter envelopes (Mod Env 3 and 4).
+pub const MOD_ENVELOPE_COUNT: usize = 2;
+
+const DEFAULT_RETRIGGER_FADE_SECS: f32 = 0.003;
+const MAX_RETRIGGER_FADE_SECS: f32 = 0.005;
+const RETRIGGER_CROSSFADE_SECS: f32 = 0.0015;
+const RELEASE_SCALING_OCTAVES: f32 = 2.0;  // full amount stretches or shrinks the release up to 4x
+
+/// Shortest attack, decay or release a new config allows before the floor is changed; short
+/// enough to sound instant, long enough that the stage never clicks.
+pub const DEFAULT_MIN_STAGE_SECS: f32 = 0.001;
+const ABSOLUTE_MIN_STAGE_SECS: f32 = 1e-5;     // well under a sample, keeps increments finite
+
+/// Note-off velocity for releases that come without one, where scaling leaves the time as set.
+pub const NEUTRAL_RELEASE_VELOCITY: f32 = 0.5;
+
+/// An envelope's level as last published by the audio thread, readable from any thread
+/// without locking the voice that owns it.
+#[derive(Default)]
+pub struct EnvelopeLevel(AtomicU32);   // f32 bits
+
+impl EnvelopeLevel {
+    pub fn new(value: f32) -> Self {
+        Self(AtomicU32::new(value.to_bits()))
+    }
+
+    pub fn publish(&self, value: f32) {
+        self.0.store(value.to_bits(), Ordering::Relaxed);
+    }
+
+    pub fn get(&self) -> f32 {
+        f32::from_bits(self.0.load(Ordering::Relaxed))
+    }
+}
+
 #[derive(Clone)]
 pub struct Envelope {
     config: EnvelopeConfig,
@@ -9,13 +48,19 @@ pub struct Envelope {
     attack_increment: f32,
     decay_increment: f32,
     release_increment: f32,
+    release_scale: f32,         // release time multiplier for the current note, see `release_scaled`
+    silence_fade: Option<(f32, f32)>,   // seconds and starting level of a `fade_to_silence` under way
+    fade_increment: f32,        // per-sample step of the fade-out before a hard retrigger
+    crossfade_from: f32,        // value the envelope had when it was hard retriggered
+    crossfade_position: f32,    // 0.0 to 1.0 through the retrigger crossfade, 1.0 when done
+    crossfade_increment: f32,
 }
 
 impl Envelope {
     pub fn new(config: EnvelopeConfig, sample_rate: f32) -> Self {
-        let attack_increment = 1.0 / (config.attack_time * sample_rate);
-        let decay_increment = (1.0 - config.sustain_level) / (config.decay_time * sample_rate);
-        let release_increment = config.sustain_level / (config.release_time * sample_rate);
+        let attack_increment = 1.0 / (config.stage_secs(config.attack_time) * sample_rate);
+        let decay_increment = (1.0 - config.sustain_level) / (config.stage_secs(config.decay_time) * sample_rate);
+        let release_increment = config.sustain_level / (config.stage_secs(config.release_time) * sample_rate);
 
         Self {
             config,
@@ -25,28 +70,125 @@ impl Envelope {
             attack_increment,
             decay_increment,
             release_increment,
+            release_scale: 1.0,
+            silence_fade: None,
+            fade_increment: 0.0,
+            crossfade_from: 0.0,
+            crossfade_position: 1.0,
+            crossfade_increment: 0.0,
         }
     }
 
     pub fn current_value(&self) -> f32 {
-        self.current_value
+        self.blend_crossfade(self.current_value)
     }
 
+    /// Starts the attack. Without `other_value` (or with retrigger on) a still-sounding
+    /// envelope restarts from zero but crossfades from its old value over 1.5 ms, so the
+    /// output never jumps.
     pub fn trigger(&mut self, other_value: Option<f32>) {
         if !self.config.retrigger && other_value.is_some() {
-            self.current_value = other_value.unwrap();
-            self.attack_increment = (1.0 - self.current_value) / (self.config.attack_time * self.sample_rate);
+            self.continue_from(other_value.unwrap());
+            return;
+        }
+
+        let sounding = self.current_state != EnvelopeState::Idle;
+        self.crossfade_from = if sounding { self.current_value() } else { 0.0 };
+        self.crossfade_position = if self.crossfade_from > 0.0 { 0.0 } else { 1.0 };
+        self.crossfade_increment = 1.0 / (RETRIGGER_CROSSFADE_SECS * self.sample_rate);
+
+        self.attack_increment = 1.0 / (self.config.stage_secs(self.config.attack_time) * self.sample_rate);
+        self.reset_release();
+        self.current_value = 0.0;
+        self.current_state = EnvelopeState::Attack;
+    }
+
+    /// Like `trigger`, but a still-sounding envelope first fades all the way to zero over the
+    /// retrigger fade time. Used when a voice is reused for another note, so the old pitch
+    /// is silent before the new one starts.
+    pub fn trigger_with_fade_out(&mut self, other_value: Option<f32>) {
+        let fade_time = self.config.retrigger_fade_time.min(MAX_RETRIGGER_FADE_SECS);
+        if !self.config.retrigger && other_value.is_some() {
+            self.continue_from(other_value.unwrap());
+            return;
+        }
+
+        self.current_value = self.current_value();
+        self.crossfade_position = 1.0;
+        self.attack_increment = 1.0 / (self.config.stage_secs(self.config.attack_time) * self.sample_rate);
+        self.reset_release();
+        if self.current_state != EnvelopeState::Idle && self.current_value > 0.0 && fade_time > 0.0 {
+            self.fade_increment = self.current_value / (fade_time * self.sample_rate);
+            self.current_state = EnvelopeState::FadeOut;
         } else {
             self.current_value = 0.0;
+            self.current_state = EnvelopeState::Attack;
         }
+    }
+
+    fn continue_from(&mut self, value: f32) {
+        self.current_value = value;
+        self.crossfade_position = 1.0;
+        self.attack_increment = (1.0 - self.current_value) / (self.config.stage_secs(self.config.attack_time) * self.sample_rate);
+        self.reset_release();
         self.current_state = EnvelopeState::Attack;
     }
 
+    fn blend_crossfade(&self, value: f32) -> f32 {
+        if self.crossfade_position >= 1.0 {
+            value
+        } else {
+            self.crossfade_from + (value - self.crossfade_from) * self.crossfade_position
+        }
+    }
+
+    /// Whether the envelope is still fading out the previous note before its attack.
+    pub fn is_fading_out(&self) -> bool {
+        self.current_state == EnvelopeState::FadeOut
+    }
+
+    pub fn state(&self) -> EnvelopeState {
+        self.current_state
+    }
+
+    pub fn set_config(&mut self, config: EnvelopeConfig) {
+        self.config = config;
+        self.update_sample_rate(self.sample_rate);
+    }
+
+    pub fn config(&self) -> &EnvelopeConfig {
+        &self.config
+    }
+
     pub fn update_sample_rate(&mut self, new_sample_rate: f32) {
         self.sample_rate = new_sample_rate;
-        self.attack_increment = 1.0 / (self.config.attack_time * new_sample_rate);
-        self.decay_increment = (1.0 - self.config.sustain_level) / (self.config.decay_time * new_sample_rate);
-        self.release_increment = self.config.sustain_level / (self.config.release_time * new_sample_rate);
+        let config = &self.config;
+        self.attack_increment = 1.0 / (config.stage_secs(config.attack_time) * new_sample_rate);
+        self.decay_increment = (1.0 - config.sustain_level) / (config.stage_secs(config.decay_time) * new_sample_rate);
+        self.update_release_increment();
+    }
+
+    // Keeps the note's release scale, or a fade to silence, across every recompute, e.g. a
+    // release time edit while the note is already releasing
+    fn update_release_increment(&mut self) {
+        self.release_increment = match self.silence_fade {
+            Some((secs, from)) => from / (secs * self.sample_rate),
+            None => {
+                let release_time = self.config.stage_secs(self.config.release_time * self.release_scale);
+                self.config.sustain_level / (release_time * self.sample_rate)
+            }
+        };
+    }
+
+    // A new note starts with the patch's own release
+    fn reset_release(&mut self) {
+        self.silence_fade = None;
+        self.set_release_scale(1.0);
+    }
+
+    fn set_release_scale(&mut self, time_scale: f32) {
+        self.release_scale = time_scale.max(0.01);
+        self.update_release_increment();
     }
 
     pub fn is_active(&self) -> bool {
@@ -58,15 +200,58 @@ impl Envelope {
     }
 
     pub fn release(&mut self) {
+        self.release_scaled(1.0);
+    }
+
+    /// Releases with the release time multiplied by `time_scale` for this note only.
+    /// A fade to silence under way is kept.
+    pub fn release_scaled(&mut self, time_scale: f32) {
+        if self.current_state != EnvelopeState::Idle && self.silence_fade.is_none() {
+            self.set_release_scale(time_scale);
+            self.current_state = EnvelopeState::Release;
+        }
+    }
+
+    /// Ramps from the current level to silence over `secs`, from any stage; for panics,
+    /// where a hard cut would click but the patch's own release may be far too long.
+    pub fn fade_to_silence(&mut self, secs: f32) {
         if self.current_state != EnvelopeState::Idle {
+            self.current_value = self.current_value();
+            self.crossfade_position = 1.0;
+            self.silence_fade = Some((secs.max(ABSOLUTE_MIN_STAGE_SECS), self.current_value));
+            self.update_release_increment();
             self.current_state = EnvelopeState::Release;
         }
     }
 
+    /// Cuts the envelope to silence immediately, skipping the release.
+    pub fn stop(&mut self) {
+        self.current_state = EnvelopeState::Idle;
+        self.current_value = 0.0;
+        self.crossfade_position = 1.0;
+    }
+
     pub fn next_value(&mut self) -> f32 {
+        let value = self.advance();
+        if self.crossfade_position < 1.0 {
+            self.crossfade_position = (self.crossfade_position + self.crossfade_increment).min(1.0);
+        }
+        self.blend_crossfade(value)
+    }
+
+    fn advance(&mut self) -> f32 {
         match self.current_state {
             EnvelopeState::Idle => 0.0,
 
+            EnvelopeState::FadeOut => {
+                self.current_value -= self.fade_increment;
+                if self.current_value <= 0.0 {
+                    self.current_value = 0.0;
+                    self.current_state = EnvelopeState::Attack;
+                }
+                self.current_value
+            }
+
             EnvelopeState::Attack => {
                 self.current_value = (self.current_value + self.attack_increment)
                     .clamp(0.0, 1.0);
@@ -82,7 +267,12 @@ impl Envelope {
                     .clamp(self.config.sustain_level, 1.0);
 
                 if self.current_value <= self.config.sustain_level {
-                    self.current_state = EnvelopeState::Sustain;
+                    if self.config.looping {
+                        self.attack_increment = 1.0 / (self.config.stage_secs(self.config.attack_time) * self.sample_rate);
+                        self.current_state = EnvelopeState::Attack;
+                    } else {
+                        self.current_state = EnvelopeState::Sustain;
+                    }
                 }
                 self.current_value
             }
@@ -106,15 +296,39 @@ impl Envelope {
     }
 }
 
-#[derive(PartialEq, Clone)]
-enum EnvelopeState {
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum EnvelopeState {
     Idle,
+    FadeOut,
     Attack,
     Decay,
     Sustain,
     Release,
 }
 
+impl EnvelopeState {
+    pub const ALL: [EnvelopeState; 6] = [
+        EnvelopeState::Idle,
+        EnvelopeState::FadeOut,
+        EnvelopeState::Attack,
+        EnvelopeState::Decay,
+        EnvelopeState::Sustain,
+        EnvelopeState::Release,
+    ];
+
+    /// One-letter tag for compact displays such as the voice meter.
+    pub fn short_label(self) -> char {
+        match self {
+            EnvelopeState::Idle => '-',
+            EnvelopeState::FadeOut => 'F',
+            EnvelopeState::Attack => 'A',
+            EnvelopeState::Decay => 'D',
+            EnvelopeState::Sustain => 'S',
+            EnvelopeState::Release => 'R',
+        }
+    }
+}
+
 impl ModulationSource for Envelope {
     fn next_value(&mut self) -> f32 {
         self.next_value()
@@ -129,23 +343,153 @@ impl ModulationSource for Envelope {
     }
 }
 
-#[derive(Clone)]
+/// Where the release-time scaling takes its velocity from.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ReleaseVelocitySource {
+    NoteOff,
+    Fixed,
+}
+
+impl ReleaseVelocitySource {
+    pub const ALL: [ReleaseVelocitySource; 2] = [ReleaseVelocitySource::NoteOff, ReleaseVelocitySource::Fixed];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ReleaseVelocitySource::NoteOff => "Note Off",
+            ReleaseVelocitySource::Fixed => "Fixed",
+        }
+    }
+}
+
+/// Scales every envelope's release per note: fast key lifts (high note-off velocity)
+/// shorten the tail, slow lifts lengthen it.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct ReleaseVelocityConfig {
+    pub source: ReleaseVelocitySource,
+    pub amount: f32,                    // 0.0 to 1.0, 0.0 leaves release times alone
+    pub fixed_velocity: f32,            // used instead of the note-off velocity with Fixed
+}
+
+impl Default for ReleaseVelocityConfig {
+    fn default() -> Self {
+        Self {
+            source: ReleaseVelocitySource::NoteOff,
+            amount: 0.0,
+            fixed_velocity: NEUTRAL_RELEASE_VELOCITY,
+        }
+    }
+}
+
+impl ReleaseVelocityConfig {
+    /// Release time multiplier for a note let go with `velocity`.
+    pub fn time_scale(&self, velocity: f32) -> f32 {
+        let velocity = match self.source {
+            ReleaseVelocitySource::NoteOff => velocity,
+            ReleaseVelocitySource::Fixed => self.fixed_velocity,
+        };
+        let offset = velocity.clamp(0.0, 1.0) - NEUTRAL_RELEASE_VELOCITY;
+        2.0f32.powf(-2.0 * offset * self.amount * RELEASE_SCALING_OCTAVES)
+    }
+}
+
+/// Why an envelope config was rejected.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum EnvelopeConfigError {
+    /// A stage time that is zero, negative or not a number.
+    InvalidTime { stage: &'static str, seconds: f32 },
+    /// A sustain level outside 0.0 to 1.0 or not a number.
+    InvalidSustain(f32),
+}
+
+impl fmt::Display for EnvelopeConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EnvelopeConfigError::InvalidTime { stage, seconds } => {
+                write!(f, "{} time must be a positive number of seconds, got {}", stage, seconds)
+            }
+            EnvelopeConfigError::InvalidSustain(level) => {
+                write!(f, "sustain level must be between 0.0 and 1.0, got {}", level)
+            }
+        }
+    }
+}
+
+impl Error for EnvelopeConfigError {}
+
+#[derive(Clone, PartialEq)]
 pub struct EnvelopeConfig {
     pub attack_time: f32,
     pub decay_time: f32,
     pub sustain_level: f32,
     pub release_time: f32,
     pub retrigger: bool,
+    pub retrigger_fade_time: f32,   // seconds, up to 5 ms; 0.0 jumps straight to the new attack
+    pub looping: bool,              // cycle between attack and decay for as long as the note is held
+    pub min_stage_time: f32,        // seconds; shorter attack, decay and release times run this long
 }
 
-impl EnvelopeConfig {
-    pub fn new(attack_time: f32, decay_time: f32, sustain_level: f32, release_time: f32, retrigger: bool) -> Self {
+impl Default for EnvelopeConfig {
+    fn default() -> Self {
         Self {
+            attack_time: 0.01,
+            decay_time: 0.3,
+            sustain_level: 0.7,
+            release_time: 0.5,
+            retrigger: false,
+            retrigger_fade_time: DEFAULT_RETRIGGER_FADE_SECS,
+            looping: false,
+            min_stage_time: DEFAULT_MIN_STAGE_SECS,
+        }
+    }
+}
+
+impl EnvelopeConfig {
+    /// Checks the times and sustain level, so a bad value can't turn into an infinite or
+    /// NaN step in the audio path.
+    pub fn new(
+        attack_time: f32,
+        decay_time: f32,
+        sustain_level: f32,
+        release_time: f32,
+        retrigger: bool,
+    ) -> Result<Self, EnvelopeConfigError> {
+        for (stage, seconds) in [("attack", attack_time), ("decay", decay_time), ("release", release_time)] {
+            if !(seconds.is_finite() && seconds > 0.0) {
+                return Err(EnvelopeConfigError::InvalidTime { stage, seconds });
+            }
+        }
+        if !(0.0..=1.0).contains(&sustain_level) {
+            return Err(EnvelopeConfigError::InvalidSustain(sustain_level));
+        }
+
+        Ok(Self {
             attack_time,
             decay_time,
             sustain_level,
             release_time,
             retrigger,
-        }
+            ..Self::default()
+        })
     }
-}
\ No newline at end of file
+
+    pub fn with_looping(mut self, looping: bool) -> Self {
+        self.looping = looping;
+        self
+    }
+
+    pub fn with_retrigger_fade_time(mut self, seconds: f32) -> Self {
+        self.retrigger_fade_time = seconds.clamp(0.0, MAX_RETRIGGER_FADE_SECS);
+        self
+    }
+
+    /// Changes the anti-click floor; 0.0 lets stages be as short as a fraction of a sample.
+    pub fn with_min_stage_time(mut self, seconds: f32) -> Self {
+        self.min_stage_time = if seconds.is_nan() { DEFAULT_MIN_STAGE_SECS } else { seconds.max(0.0) };
+        self
+    }
+
+    // A stage time raised to the floor; also catches times edited into the public fields
+    fn stage_secs(&self, seconds: f32) -> f32 {
+        seconds.max(self.min_stage_time).max(ABSOLUTE_MIN_STAGE_SECS)
+    }
+}
@@ -0,0 +1,51 @@
+use std::f32::consts::PI;
+
+/// Default corner of the DC blocker, low enough to leave the audible bass alone.
+pub const DC_BLOCKER_CUTOFF_HZ: f32 = 10.0;
+
+/// One-pole high-pass (y = x - x[n-1] + r * y[n-1]) that removes DC offset left by
+/// asymmetric waveforms, noise and saturation. Mono; use one per channel.
+#[derive(Clone)]
+pub struct DcBlocker {
+    cutoff_hz: f32,
+    pole: f32,
+    prev_input: f32,
+    prev_output: f32,
+}
+
+impl DcBlocker {
+    pub fn new(cutoff_hz: f32, sample_rate: f32) -> Self {
+        Self {
+            cutoff_hz,
+            pole: pole_for(cutoff_hz, sample_rate),
+            prev_input: 0.0,
+            prev_output: 0.0,
+        }
+    }
+
+    pub fn update_sample_rate(&mut self, new_sample_rate: f32) {
+        self.pole = pole_for(self.cutoff_hz, new_sample_rate);
+    }
+
+    pub fn reset(&mut self) {
+        self.prev_input = 0.0;
+        self.prev_output = 0.0;
+    }
+
+    pub fn process_sample(&mut self, input: f32) -> f32 {
+        let output = input - self.prev_input + self.pole * self.prev_output;
+        self.prev_input = input;
+        self.prev_output = output;
+        output
+    }
+
+    pub fn process_block(&mut self, buffer: &mut [f32]) {
+        for sample in buffer.iter_mut() {
+            *sample = self.process_sample(*sample);
+        }
+    }
+}
+
+fn pole_for(cutoff_hz: f32, sample_rate: f32) -> f32 {
+    (-2.0 * PI * cutoff_hz / sample_rate).exp()
+}
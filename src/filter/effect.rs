@@ -0,0 +1,80 @@
+use super::{Filter, FilterParameters};
+
+const SMOOTHING_SECS: f32 = 0.02;
+
+/// The voice filter as a self-contained stereo insert: block processing, its own
+/// state per channel and smoothed cutoff and resonance so automation doesn't zipper.
+#[derive(Clone)]
+pub struct FilterEffect {
+    left: Filter,
+    right: Filter,
+    target: FilterParameters,
+    cutoff: f32,
+    resonance: f32,
+    smoothing_coeff: f32,
+}
+
+impl FilterEffect {
+    pub fn new(parameters: FilterParameters, sample_rate: f32) -> Self {
+        let filter = Filter::new(parameters.clone(), sample_rate);
+        Self {
+            left: filter.clone(),
+            right: filter,
+            cutoff: parameters.cutoff_frequency,
+            resonance: parameters.resonance_amount,
+            target: parameters,
+            smoothing_coeff: smoothing_coeff(sample_rate),
+        }
+    }
+
+    pub fn parameters(&self) -> &FilterParameters {
+        &self.target
+    }
+
+    /// Type and slope switch immediately; cutoff and resonance glide to the new values.
+    pub fn set_parameters(&mut self, parameters: FilterParameters) {
+        let mut immediate = parameters.clone();
+        immediate.cutoff_frequency = self.cutoff;
+        immediate.resonance_amount = self.resonance;
+        self.left.set_parameters(immediate.clone());
+        self.right.set_parameters(immediate);
+        self.target = parameters;
+    }
+
+    pub fn update_sample_rate(&mut self, new_sample_rate: f32) {
+        self.left.update_sample_rate(new_sample_rate);
+        self.right.update_sample_rate(new_sample_rate);
+        self.smoothing_coeff = smoothing_coeff(new_sample_rate);
+    }
+
+    pub fn reset(&mut self) {
+        self.left.reset();
+        self.right.reset();
+        self.cutoff = self.target.cutoff_frequency;
+        self.resonance = self.target.resonance_amount;
+    }
+
+    pub fn process_frame(&mut self, left: f32, right: f32) -> (f32, f32) {
+        // Cutoff glides in the log domain so sweeps sound even across the range
+        let cutoff_ratio = self.target.cutoff_frequency / self.cutoff.max(1.0);
+        self.cutoff *= cutoff_ratio.powf(self.smoothing_coeff);
+        self.resonance += (self.target.resonance_amount - self.resonance) * self.smoothing_coeff;
+
+        for filter in [&mut self.left, &mut self.right] {
+            filter.set_cutoff_frequency(self.cutoff);
+            filter.set_resonance(self.resonance);
+        }
+        (self.left.process_sample(left), self.right.process_sample(right))
+    }
+
+    /// Filters two equally long channel buffers in place.
+    pub fn process_block(&mut self, left: &mut [f32], right: &mut [f32]) {
+        for (l, r) in left.iter_mut().zip(right.iter_mut()) {
+            (*l, *r) = self.process_frame(*l, *r);
+        }
+    }
+}
+
+fn smoothing_coeff(sample_rate: f32) -> f32 {
+    1.0 - (-1.0 / (SMOOTHING_SECS * sample_rate)).exp()
+}
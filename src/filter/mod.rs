@@ -1,4 +1,11 @@
-use std::sync::{Arc, Mutex};
+pub mod dc_blocker;
+pub mod effect;
+
+pub use dc_blocker::{DcBlocker, DC_BLOCKER_CUTOFF_HZ};
+pub use effect::FilterEffect;
+
+use crate::denormal::ANTI_DENORMAL;
+use crate::modulation::{ModulationSourceId, ModulationValues};
 
 #[derive(Clone, Copy, PartialEq)]
 pub enum FilterType {
@@ -13,31 +20,147 @@ pub enum FilterSlope {
     Slope24dB   // 4-pole
 }
 
-#[derive(Clone)]
+impl FilterType {
+    pub const ALL: [FilterType; 2] = [FilterType::LowPass, FilterType::HighPass];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            FilterType::LowPass => "Low Pass",
+            FilterType::HighPass => "High Pass",
+        }
+    }
+}
+
+impl FilterSlope {
+    pub const ALL: [FilterSlope; 3] = [FilterSlope::Slope6dB, FilterSlope::Slope12dB, FilterSlope::Slope24dB];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            FilterSlope::Slope6dB => "6 dB",
+            FilterSlope::Slope12dB => "12 dB",
+            FilterSlope::Slope24dB => "24 dB",
+        }
+    }
+}
+
+/// Curve of the drive stage in front of the filter.
+#[derive(Clone, Copy, PartialEq)]
+pub enum SaturationCurve {
+    Tanh,
+    SoftClip,       // cubic, hard limit at +-1
+    Asymmetric,     // softer on the negative half, adds even harmonics
+}
+
+impl SaturationCurve {
+    pub const ALL: [SaturationCurve; 3] = [SaturationCurve::Tanh, SaturationCurve::SoftClip, SaturationCurve::Asymmetric];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            SaturationCurve::Tanh => "Tanh",
+            SaturationCurve::SoftClip => "Soft Clip",
+            SaturationCurve::Asymmetric => "Asymmetric",
+        }
+    }
+
+    pub fn apply(self, x: f32) -> f32 {
+        match self {
+            SaturationCurve::Tanh => x.tanh(),
+            SaturationCurve::SoftClip => {
+                let x = x.clamp(-1.0, 1.0);
+                1.5 * x - 0.5 * x * x * x
+            }
+            SaturationCurve::Asymmetric => {
+                if x >= 0.0 { x.tanh() } else { 1.5 * (x / 1.5).tanh() }
+            }
+        }
+    }
+}
+
+/// How the second filter of a voice is wired to the first.
+#[derive(Clone, Copy, PartialEq)]
+pub enum FilterRouting {
+    Single,     // filter 2 bypassed
+    Serial,     // filter 1 feeds filter 2
+    Parallel,   // both filters get the same input, blended by the mix
+    Split,      // oscillator 1 through filter 1, the other oscillators through filter 2
+}
+
+impl FilterRouting {
+    pub const ALL: [FilterRouting; 4] = [
+        FilterRouting::Single,
+        FilterRouting::Serial,
+        FilterRouting::Parallel,
+        FilterRouting::Split,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            FilterRouting::Single => "Single",
+            FilterRouting::Serial => "Serial",
+            FilterRouting::Parallel => "Parallel",
+            FilterRouting::Split => "Split",
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub struct FilterRoutingConfig {
+    pub routing: FilterRouting,
+    pub parallel_mix: f32,      // 0.0 is filter 1 only, 1.0 filter 2 only
+}
+
+impl Default for FilterRoutingConfig {
+    fn default() -> Self {
+        Self {
+            routing: FilterRouting::Single,
+            parallel_mix: 0.5,
+        }
+    }
+}
+
+#[derive(Clone, PartialEq)]
 pub struct FilterParameters {
     pub filter_type: FilterType,
     pub slope: FilterSlope,
     pub cutoff_frequency: f32,      // Hz
     pub resonance_amount: f32,      // 0.0 to 1.0
     pub modulation_amount: f32,     // Amount of modulation applied
+    pub drive: f32,                 // 0.0 (clean) to 1.0, up to +24 dB into the saturation curve
+    pub saturation: SaturationCurve,
+}
+
+const MAX_DRIVE_DB: f32 = 24.0;
+
+/// Input gain for a drive amount, 0.0 to 1.0, and the gain that brings a full-scale input
+/// back to 1.0 through `curve`. Shared by every drive stage so they all feel the same.
+pub(crate) fn drive_gains(drive: f32, curve: SaturationCurve) -> (f32, f32) {
+    let gain = 10f32.powf(drive.clamp(0.0, 1.0) * MAX_DRIVE_DB / 20.0);
+    (gain, 1.0 / curve.apply(gain))
 }
 
 pub trait ModulationSource: Send + Sync {
     fn next_value(&mut self) -> f32;  // Returns value between 0.0 and 1.0
     fn is_active(&self) -> bool;
     fn reset(&mut self);
+    /// Called on the audio thread when the rate `next_value` runs at changes, e.g. with the
+    /// voice oversampling, so a source that counts samples can rescale instead of being rebuilt.
+    fn set_sample_rate(&mut self, _sample_rate: f32) {}
 }
 
 #[derive(Clone)]
 pub struct Filter {
     parameters: FilterParameters,
     sample_rate: f32,
-    modulation_sources: Vec<Arc<Mutex<dyn ModulationSource>>>,
-    filter_stages: Vec<FilterStage>,
+    modulation_sources: Vec<ModulationSourceId>,   // resolved to values by the owning voice
+    modulation_offset: f32,         // Octaves, from the sources above as of the last update
+    filter_stages: [FilterStage; MAX_STAGES],   // only as many as the slope needs are used
+    cutoff_offset: f32,             // Octaves, set per voice by the modulation routes
+    coefficients: Option<(f32, (f32, f32, f32, f32, f32))>,    // last cutoff and its coefficients
+    drive_gains: (f32, f32),        // drive input gain and output compensation
 }
 
 #[derive(Clone)]
-struct FilterStage {
+pub(crate) struct FilterStage {
     prev_input: f32,         // Previous input sample
     prev_prev_input: f32,    // Second previous input sample
     prev_output: f32,        // Previous output sample
@@ -45,7 +168,7 @@ struct FilterStage {
 }
 
 impl FilterStage {
-    fn new() -> Self {
+    pub(crate) fn new() -> Self {
         Self {
             prev_input: 0.0,
             prev_prev_input: 0.0,
@@ -55,50 +178,98 @@ impl FilterStage {
     }
 }
 
+const MAX_STAGES: usize = 4;
+
+fn stage_count(slope: FilterSlope) -> usize {
+    match slope {
+        FilterSlope::Slope6dB => 1,
+        FilterSlope::Slope12dB => 2,
+        FilterSlope::Slope24dB => MAX_STAGES,
+    }
+}
+
 impl Filter {
     pub fn new(parameters: FilterParameters, sample_rate: f32) -> Self {
-        let stages_count = match parameters.slope {
-            FilterSlope::Slope6dB => 1,
-            FilterSlope::Slope12dB => 2,
-            FilterSlope::Slope24dB => 4,
-        };
-
         Self {
+            filter_stages: std::array::from_fn(|_| FilterStage::new()),
+            drive_gains: drive_gains(parameters.drive, parameters.saturation),
             parameters,
             sample_rate,
             modulation_sources: Vec::new(),
-            filter_stages: (0..stages_count).map(|_| FilterStage::new()).collect(),
+            modulation_offset: 0.0,
+            cutoff_offset: 0.0,
+            coefficients: None,
+        }
+    }
+
+    pub fn parameters(&self) -> &FilterParameters {
+        &self.parameters
+    }
+
+    /// Changes the filter settings live; the stage state is only reset when the slope changes.
+    pub fn set_parameters(&mut self, parameters: FilterParameters) {
+        if parameters.slope != self.parameters.slope {
+            self.reset();
         }
+        self.drive_gains = drive_gains(parameters.drive, parameters.saturation);
+        self.parameters = parameters;
+        self.coefficients = None;
+    }
+
+    pub fn set_cutoff_offset(&mut self, octaves: f32) {
+        self.cutoff_offset = octaves;
+    }
+
+    pub fn set_cutoff_frequency(&mut self, frequency: f32) {
+        self.parameters.cutoff_frequency = frequency;
     }
 
-    pub fn add_modulation_source(&mut self, source: Arc<Mutex<dyn ModulationSource>>) {
+    pub fn set_resonance(&mut self, amount: f32) {
+        self.parameters.resonance_amount = amount;
+        self.coefficients = None;
+    }
+
+    /// Clears the stage history, e.g. before reusing the filter on unrelated audio.
+    pub fn reset(&mut self) {
+        self.filter_stages = std::array::from_fn(|_| FilterStage::new());
+    }
+
+    /// Adds a source that sweeps the cutoff by up to 10 octaves times the modulation amount.
+    pub fn add_modulation_source(&mut self, source: ModulationSourceId) {
         self.modulation_sources.push(source);
     }
 
+    pub fn modulation_sources(&self) -> &[ModulationSourceId] {
+        &self.modulation_sources
+    }
+
+    /// Reads the current value of every added source; called by the voice at control rate,
+    /// so the per-sample path needs no locking or dynamic dispatch.
+    pub fn update_modulation(&mut self, values: &ModulationValues) {
+        let amount = self.parameters.modulation_amount * 10.0;
+        self.modulation_offset = self.modulation_sources.iter().map(|&s| values.get(s) * amount).sum();
+    }
+
     pub fn process_sample(&mut self, input_sample: f32) -> f32 {
-        // Calculate modulated cutoff frequency
-        let mut modulated_freq = self.parameters.cutoff_frequency;
-        
-        // Apply all modulation sources
-        for source in &mut self.modulation_sources {
-            if let Ok(mut source) = source.lock() {
-                if source.is_active() {
-                    let mod_value = source.next_value();
-                    let scaled_modulation = mod_value * self.parameters.modulation_amount;
-                    // Exponential frequency modulation
-                    modulated_freq *= 2.0f32.powf(scaled_modulation * 10.0);
-                }
-            }
-        }
+        // Exponential frequency modulation from the routes and the filter's own sources
+        let modulated_freq = self.parameters.cutoff_frequency * 2.0f32.powf(self.cutoff_offset + self.modulation_offset);
 
         // Clamp frequency between 20Hz and Nyquist
         let clamped_freq = modulated_freq.clamp(20.0, self.sample_rate * 0.49);
-        let (feedback1, feedback2, feed0, feed1, feed2) = 
-            self.calculate_coefficients(clamped_freq);
+
+        // Coefficients are only recalculated when the cutoff actually moved
+        let (feedback1, feedback2, feed0, feed1, feed2) = match self.coefficients {
+            Some((freq, coefficients)) if freq == clamped_freq => coefficients,
+            _ => {
+                let coefficients = self.calculate_coefficients(clamped_freq);
+                self.coefficients = Some((clamped_freq, coefficients));
+                coefficients
+            }
+        };
 
         // Process through all stages in series
-        let mut processed_sample = input_sample;
-        for stage in &mut self.filter_stages {
+        let mut processed_sample = self.drive(input_sample);
+        for stage in &mut self.filter_stages[..stage_count(self.parameters.slope)] {
             processed_sample = process_filter_stage(
                 stage, 
                 processed_sample, 
@@ -113,6 +284,24 @@ impl Filter {
         processed_sample
     }
 
+    // Saturates the input; the result is blended in by the drive amount so 0.0 stays clean
+    fn drive(&self, input_sample: f32) -> f32 {
+        let drive = self.parameters.drive;
+        if drive <= 0.0 {
+            return input_sample;
+        }
+        let (gain, compensation) = self.drive_gains;
+        let saturated = self.parameters.saturation.apply(input_sample * gain) * compensation;
+        input_sample + (saturated - input_sample) * drive.min(1.0)
+    }
+
+    /// Filters a mono buffer in place.
+    pub fn process_block(&mut self, buffer: &mut [f32]) {
+        for sample in buffer.iter_mut() {
+            *sample = self.process_sample(*sample);
+        }
+    }
+
     fn calculate_coefficients(&self, cutoff_freq: f32) -> (f32, f32, f32, f32, f32) {
         let angular_freq = 2.0 * std::f32::consts::PI * cutoff_freq / self.sample_rate;
         let cosine = angular_freq.cos();
@@ -155,11 +344,14 @@ impl Filter {
     }
 
     pub fn update_sample_rate(&mut self, new_sample_rate: f32) {
+        if new_sample_rate != self.sample_rate {
+            self.coefficients = None;
+        }
         self.sample_rate = new_sample_rate;
     }
 }
 
-fn process_filter_stage(
+pub(crate) fn process_filter_stage(
     stage: &mut FilterStage, 
     input_sample: f32, 
     feedback1: f32, 
@@ -168,6 +360,7 @@ fn process_filter_stage(
     feed1: f32, 
     feed2: f32
 ) -> f32 {
+    let input_sample = input_sample + ANTI_DENORMAL;
     let output = feed0 * input_sample + 
                 feed1 * stage.prev_input + 
                 feed2 * stage.prev_prev_input -
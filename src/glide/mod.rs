@@ -0,0 +1,177 @@
+/// How the pitch travels from the previous note to the new one.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum GlideMode {
+    /// Continuous portamento sweep.
+    Smooth,
+    /// Glissando through every semitone on the way.
+    Chromatic,
+    /// Glissando through the notes of C major.
+    Major,
+    /// Glissando through the notes of C natural minor.
+    Minor,
+}
+
+impl GlideMode {
+    pub const ALL: [GlideMode; 4] = [GlideMode::Smooth, GlideMode::Chromatic, GlideMode::Major, GlideMode::Minor];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            GlideMode::Smooth => "Portamento",
+            GlideMode::Chromatic => "Gliss Chromatic",
+            GlideMode::Major => "Gliss Major",
+            GlideMode::Minor => "Gliss Minor",
+        }
+    }
+
+    // Pitch classes the glissando may land on, None for an unquantized sweep
+    fn scale(self) -> Option<&'static [u8]> {
+        match self {
+            GlideMode::Smooth => None,
+            GlideMode::Chromatic => Some(&[0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11]),
+            GlideMode::Major => Some(&[0, 2, 4, 5, 7, 9, 11]),
+            GlideMode::Minor => Some(&[0, 2, 3, 5, 7, 8, 10]),
+        }
+    }
+}
+
+/// What the glide time measures.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum GlideRate {
+    /// Every glide takes the glide time, however far it goes.
+    ConstantTime,
+    /// The glide time is per octave, so wider intervals take longer.
+    ConstantRate,
+}
+
+impl GlideRate {
+    pub const ALL: [GlideRate; 2] = [GlideRate::ConstantTime, GlideRate::ConstantRate];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            GlideRate::ConstantTime => "Constant Time",
+            GlideRate::ConstantRate => "Constant Rate",
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct GlideConfig {
+    pub mode: GlideMode,
+    pub rate: GlideRate,
+    pub time_secs: f32,         // 0.0 turns glide off; per octave with GlideRate::ConstantRate
+    pub legato: bool,           // only glide when the new note overlaps a held one
+}
+
+impl Default for GlideConfig {
+    fn default() -> Self {
+        Self {
+            mode: GlideMode::Smooth,
+            rate: GlideRate::ConstantTime,
+            time_secs: 0.0,
+            legato: false,
+        }
+    }
+}
+
+/// Per-voice pitch glide, worked out in (fractional) MIDI note numbers so sweeps are even
+/// in pitch and glissando steps land on real notes.
+#[derive(Clone)]
+pub struct Glide {
+    config: GlideConfig,
+    sample_rate: f32,
+    from_note: f32,
+    to_note: f32,
+    progress: f32,              // 0.0 to 1.0, 1.0 once the target is reached
+    increment: f32,             // progress per sample
+}
+
+impl Glide {
+    pub fn new(config: GlideConfig, sample_rate: f32) -> Self {
+        Self {
+            config,
+            sample_rate,
+            from_note: 0.0,
+            to_note: 0.0,
+            progress: 1.0,
+            increment: 1.0,
+        }
+    }
+
+    pub fn set_config(&mut self, config: GlideConfig) {
+        self.config = config;
+    }
+
+    pub fn config(&self) -> &GlideConfig {
+        &self.config
+    }
+
+    pub fn update_sample_rate(&mut self, new_sample_rate: f32) {
+        self.sample_rate = new_sample_rate;
+    }
+
+    /// Starts gliding towards `to_hz`, from `from_hz` if there is a previous pitch. Returns
+    /// the frequency the oscillators should start at.
+    pub fn start(&mut self, from_hz: Option<f32>, to_hz: f32) -> f32 {
+        self.to_note = hz_to_note(to_hz);
+        match from_hz {
+            Some(from_hz) if self.config.time_secs > 0.0 && from_hz > 0.0 => {
+                self.from_note = hz_to_note(from_hz);
+                let duration_secs = match self.config.rate {
+                    GlideRate::ConstantTime => self.config.time_secs,
+                    GlideRate::ConstantRate => self.config.time_secs * (self.to_note - self.from_note).abs() / 12.0,
+                };
+                self.increment = 1.0 / (duration_secs * self.sample_rate).max(1.0);
+                self.progress = 0.0;
+                from_hz
+            }
+            _ => {
+                self.progress = 1.0;
+                to_hz
+            }
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.progress < 1.0
+    }
+
+    pub fn next_frequency(&mut self) -> f32 {
+        let frequency = self.current_frequency();
+        if self.is_active() {
+            self.progress = (self.progress + self.increment).min(1.0);
+        }
+        frequency
+    }
+
+    /// Where the glide has got to, without moving it on.
+    pub fn current_frequency(&self) -> f32 {
+        if !self.is_active() {
+            return note_to_hz(self.to_note);
+        }
+
+        let note = self.from_note + (self.to_note - self.from_note) * self.progress;
+        let note = match self.config.mode.scale() {
+            Some(scale) => self.quantize(note, scale),
+            None => note,
+        };
+        note_to_hz(note)
+    }
+
+    // Holds the last scale note passed in the direction of travel, starting on the note we came from
+    fn quantize(&self, note: f32, scale: &[u8]) -> f32 {
+        let upwards = self.to_note >= self.from_note;
+        let mut candidate = if upwards { note.floor() } else { note.ceil() };
+        while !scale.contains(&((candidate as i32).rem_euclid(12) as u8)) {
+            candidate += if upwards { -1.0 } else { 1.0 };
+        }
+        if upwards { candidate.max(self.from_note) } else { candidate.min(self.from_note) }
+    }
+}
+
+fn hz_to_note(hz: f32) -> f32 {
+    69.0 + 12.0 * (hz / 440.0).log2()
+}
+
+fn note_to_hz(note: f32) -> f32 {
+    440.0 * 2.0f32.powf((note - 69.0) / 12.0)
+}
@@ -0,0 +1,85 @@
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+/// Notes currently held on the on-screen keyboard, one bit per MIDI note. The GUI sets
+/// and clears bits; the audio thread diffs them against the last snapshot it applied.
+/// The GUI's panic and chord capture buttons are passed along the same way.
+pub struct KeyboardState {
+    held: [AtomicU64; 2],
+    panic: AtomicBool,
+    capture_chord: AtomicBool,
+}
+
+impl Default for KeyboardState {
+    fn default() -> Self {
+        Self {
+            held: [AtomicU64::new(0), AtomicU64::new(0)],
+            panic: AtomicBool::new(false),
+            capture_chord: AtomicBool::new(false),
+        }
+    }
+}
+
+impl KeyboardState {
+    pub fn press(&self, note: u8) {
+        let (word, bit) = Self::location(note);
+        self.held[word].fetch_or(bit, Ordering::AcqRel);
+    }
+
+    pub fn release(&self, note: u8) {
+        let (word, bit) = Self::location(note);
+        self.held[word].fetch_and(!bit, Ordering::AcqRel);
+    }
+
+    pub fn release_all(&self) {
+        for word in &self.held {
+            word.store(0, Ordering::Release);
+        }
+    }
+
+    /// Lets go of every on-screen key and asks the audio thread to silence the synth.
+    pub fn request_panic(&self) {
+        self.release_all();
+        self.panic.store(true, Ordering::Release);
+    }
+
+    /// Whether a panic was requested since the last call.
+    pub fn take_panic(&self) -> bool {
+        self.panic.swap(false, Ordering::AcqRel)
+    }
+
+    /// Asks the audio thread to remember the notes held right now as the chord memory shape.
+    pub fn request_chord_capture(&self) {
+        self.capture_chord.store(true, Ordering::Release);
+    }
+
+    /// Whether a chord capture was requested since the last call.
+    pub fn take_chord_capture(&self) -> bool {
+        self.capture_chord.swap(false, Ordering::AcqRel)
+    }
+
+    pub fn is_held(&self, note: u8) -> bool {
+        let (word, bit) = Self::location(note);
+        self.held[word].load(Ordering::Acquire) & bit != 0
+    }
+
+    pub fn snapshot(&self) -> u128 {
+        let low = self.held[0].load(Ordering::Acquire) as u128;
+        let high = self.held[1].load(Ordering::Acquire) as u128;
+        low | (high << 64)
+    }
+
+    fn location(note: u8) -> (usize, u64) {
+        let note = note.min(127);
+        ((note / 64) as usize, 1 << (note % 64))
+    }
+}
+
+/// Calls `on_change(note, pressed)` for every note that differs between two snapshots.
+pub fn diff_snapshots(previous: u128, current: u128, mut on_change: impl FnMut(u8, bool)) {
+    let mut changed = previous ^ current;
+    while changed != 0 {
+        let note = changed.trailing_zeros() as u8;
+        on_change(note, current & (1 << note) != 0);
+        changed &= changed - 1;
+    }
+}
@@ -0,0 +1,172 @@
+/// Oscillator slots a zone can pick from; any further oscillators play in every zone.
+pub const ZONE_OSCILLATORS: usize = 3;
+
+/// How a part's two zones share the keyboard.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ZoneMode {
+    Off,        // every key plays the whole patch
+    Split,      // keys below the split point play zone A, the rest zone B
+    Layer,      // every key plays both zones, each on its own voice
+}
+
+impl ZoneMode {
+    pub const ALL: [ZoneMode; 3] = [ZoneMode::Off, ZoneMode::Split, ZoneMode::Layer];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ZoneMode::Off => "Off",
+            ZoneMode::Split => "Split",
+            ZoneMode::Layer => "Layer",
+        }
+    }
+}
+
+/// The oscillators a zone plays. The third slot is the plugin's sub oscillator.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum OscillatorGroup {
+    All,
+    Osc1,
+    Osc2,
+    Osc3,
+    Osc1And2,
+    Osc1And3,
+    Osc2And3,
+}
+
+impl OscillatorGroup {
+    pub const ALL: [OscillatorGroup; 7] = [
+        OscillatorGroup::All,
+        OscillatorGroup::Osc1,
+        OscillatorGroup::Osc2,
+        OscillatorGroup::Osc3,
+        OscillatorGroup::Osc1And2,
+        OscillatorGroup::Osc1And3,
+        OscillatorGroup::Osc2And3,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            OscillatorGroup::All => "All",
+            OscillatorGroup::Osc1 => "Osc 1",
+            OscillatorGroup::Osc2 => "Osc 2",
+            OscillatorGroup::Osc3 => "Sub",
+            OscillatorGroup::Osc1And2 => "Osc 1 + 2",
+            OscillatorGroup::Osc1And3 => "Osc 1 + Sub",
+            OscillatorGroup::Osc2And3 => "Osc 2 + Sub",
+        }
+    }
+
+    /// Whether the oscillator at `index` sounds in this group.
+    pub fn includes(self, index: usize) -> bool {
+        let slots: [bool; ZONE_OSCILLATORS] = match self {
+            OscillatorGroup::All => [true, true, true],
+            OscillatorGroup::Osc1 => [true, false, false],
+            OscillatorGroup::Osc2 => [false, true, false],
+            OscillatorGroup::Osc3 => [false, false, true],
+            OscillatorGroup::Osc1And2 => [true, true, false],
+            OscillatorGroup::Osc1And3 => [true, false, true],
+            OscillatorGroup::Osc2And3 => [false, true, true],
+        };
+        slots.get(index).copied().unwrap_or(true)
+    }
+}
+
+/// One half of a split or layer: which oscillators play, transposed and scaled.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct KeyZone {
+    pub oscillators: OscillatorGroup,
+    pub transpose_semitones: i32,   // -24 to 24
+    pub level: f32,                 // 0.0 to 1.0
+}
+
+impl Default for KeyZone {
+    fn default() -> Self {
+        Self {
+            oscillators: OscillatorGroup::All,
+            transpose_semitones: 0,
+            level: 1.0,
+        }
+    }
+}
+
+impl KeyZone {
+    pub fn pitch_ratio(&self) -> f32 {
+        2.0f32.powf(self.transpose_semitones as f32 / 12.0)
+    }
+}
+
+/// The keys a whole part answers, with a transpose and level of its own. Parts on the
+/// same channel with separate ranges split the keyboard; overlapping ranges layer them.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct PartZone {
+    pub low_key: u8,
+    pub high_key: u8,               // inclusive
+    pub transpose_semitones: i32,
+    pub level: f32,
+}
+
+impl Default for PartZone {
+    fn default() -> Self {
+        Self {
+            low_key: 0,
+            high_key: 127,
+            transpose_semitones: 0,
+            level: 1.0,
+        }
+    }
+}
+
+impl PartZone {
+    pub fn contains(&self, key: u8) -> bool {
+        (self.low_key..=self.high_key).contains(&key)
+    }
+
+    pub fn pitch_ratio(&self) -> f32 {
+        2.0f32.powf(self.transpose_semitones as f32 / 12.0)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct KeyZoneConfig {
+    pub mode: ZoneMode,
+    pub split_key: u8,              // lowest key of zone B in split mode
+    pub zones: [KeyZone; 2],        // A and B
+    pub part: PartZone,
+}
+
+impl Default for KeyZoneConfig {
+    fn default() -> Self {
+        Self {
+            mode: ZoneMode::Off,
+            split_key: 60,
+            zones: [KeyZone::default(); 2],
+            part: PartZone::default(),
+        }
+    }
+}
+
+impl KeyZoneConfig {
+    /// The zones `key` plays, one voice each; none when the part doesn't answer the key.
+    pub fn zones_for(&self, key: u8) -> &[KeyZone] {
+        if !self.part.contains(key) {
+            return &[];
+        }
+        match self.mode {
+            ZoneMode::Off => std::slice::from_ref(&FULL_ZONE),
+            ZoneMode::Split if key < self.split_key => &self.zones[..1],
+            ZoneMode::Split => &self.zones[1..],
+            ZoneMode::Layer => &self.zones,
+        }
+    }
+}
+
+static FULL_ZONE: KeyZone = KeyZone {
+    oscillators: OscillatorGroup::All,
+    transpose_semitones: 0,
+    level: 1.0,
+};
+
+/// The nearest MIDI key to `frequency`, ignoring master tuning.
+pub fn frequency_to_key(frequency: f32) -> u8 {
+    (69.0 + 12.0 * (frequency.max(1.0) / 440.0).log2()).round().clamp(0.0, 127.0) as u8
+}
@@ -0,0 +1,106 @@
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+
+/// How fast a displayed peak falls once the signal drops, in dB per second.
+const PEAK_FALL_DB_PER_SEC: f32 = 20.0;
+/// Seconds a peak stays put before it starts falling.
+const PEAK_HOLD_SECS: f32 = 1.0;
+/// Time constant of the RMS average, close to a VU meter's 300 ms integration.
+const RMS_TIME_CONSTANT_SECS: f32 = 0.3;
+
+/// Peak and RMS of the master output per channel, published by the audio thread for the
+/// GUI meter. Only the audio thread writes, so the ballistics state lives in the atomics too.
+pub struct LevelMeter {
+    channels: [ChannelLevel; 2],
+    generation: AtomicU64,              // bumped by every publish, so views know to redraw
+}
+
+#[derive(Default)]
+struct ChannelLevel {
+    peak: AtomicU32,                    // f32 bits, linear, falling after the hold time
+    hold_secs: AtomicU32,               // f32 bits, hold time left on the current peak
+    mean_square: AtomicU32,             // f32 bits, exponentially averaged
+}
+
+impl Default for LevelMeter {
+    fn default() -> Self {
+        Self {
+            channels: [ChannelLevel::default(), ChannelLevel::default()],
+            generation: AtomicU64::new(0),
+        }
+    }
+}
+
+impl ChannelLevel {
+    fn measure(&self, samples: &[f32], sample_rate: f32) {
+        let block_secs = samples.len() as f32 / sample_rate.max(1.0);
+        let load = |value: &AtomicU32| f32::from_bits(value.load(Ordering::Relaxed));
+
+        let block_peak = samples.iter().fold(0.0f32, |peak, s| peak.max(s.abs()));
+        let (mut peak, mut hold) = (load(&self.peak), load(&self.hold_secs));
+        if block_peak >= peak {
+            peak = block_peak;
+            hold = PEAK_HOLD_SECS;
+        } else if hold > 0.0 {
+            hold = (hold - block_secs).max(0.0);
+        } else {
+            peak = (peak * 10.0f32.powf(-PEAK_FALL_DB_PER_SEC * block_secs / 20.0)).max(block_peak);
+        }
+        self.peak.store(peak.to_bits(), Ordering::Relaxed);
+        self.hold_secs.store(hold.to_bits(), Ordering::Relaxed);
+
+        let coefficient = 1.0 - (-1.0 / (RMS_TIME_CONSTANT_SECS * sample_rate.max(1.0))).exp();
+        let mean_square = samples.iter().fold(load(&self.mean_square), |ms, s| ms + coefficient * (s * s - ms));
+        self.mean_square.store(mean_square.to_bits(), Ordering::Relaxed);
+    }
+
+    fn reset(&self) {
+        for value in [&self.peak, &self.hold_secs, &self.mean_square] {
+            value.store(0, Ordering::Relaxed);
+        }
+    }
+}
+
+impl LevelMeter {
+    /// Measures one rendered block. A mono output shows the same level on both channels.
+    pub fn publish(&self, left: &[f32], right: &[f32], sample_rate: f32) {
+        self.channels[0].measure(left, sample_rate);
+        self.channels[1].measure(right, sample_rate);
+        self.generation.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Drops the held levels, e.g. when playback restarts at a new sample rate.
+    pub fn reset(&self) {
+        self.channels.iter().for_each(ChannelLevel::reset);
+        self.generation.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn generation(&self) -> u64 {
+        self.generation.load(Ordering::Relaxed)
+    }
+
+    /// Held and falling peak of channel 0 (left) or 1 (right), linear.
+    pub fn peak(&self, channel: usize) -> f32 {
+        self.channels.get(channel).map_or(0.0, |c| f32::from_bits(c.peak.load(Ordering::Relaxed)))
+    }
+
+    /// Averaged RMS level of channel 0 (left) or 1 (right), linear.
+    pub fn rms(&self, channel: usize) -> f32 {
+        self.channels.get(channel).map_or(0.0, |c| f32::from_bits(c.mean_square.load(Ordering::Relaxed)).sqrt())
+    }
+
+    /// Whether either channel's held peak reached full scale.
+    pub fn clipping(&self) -> bool {
+        (0..2).any(|channel| self.peak(channel) >= 1.0)
+    }
+
+    pub fn describe(&self) -> String {
+        let db = |level: f32| 20.0 * level.max(1e-6).log10();
+        format!(
+            "L {:.1} / {:.1} dB  R {:.1} / {:.1} dB",
+            db(self.peak(0)),
+            db(self.rms(0)),
+            db(self.peak(1)),
+            db(self.rms(1)),
+        )
+    }
+}
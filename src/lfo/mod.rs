@@ -0,0 +1,139 @@
+use std::f32::consts::PI;
+
+use crate::tempo::{SyncDivision, TransportInfo};
+
+pub const LFO_COUNT: usize = 2;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum LfoShape {
+    Sine,
+    Triangle,
+    SawUp,
+    Square,
+    SampleHold,     // a new random level every cycle
+    RandomGlide,    // glides from one random level to the next over each cycle
+}
+
+impl LfoShape {
+    pub const ALL: [LfoShape; 6] = [
+        LfoShape::Sine,
+        LfoShape::Triangle,
+        LfoShape::SawUp,
+        LfoShape::Square,
+        LfoShape::SampleHold,
+        LfoShape::RandomGlide,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            LfoShape::Sine => "Sine",
+            LfoShape::Triangle => "Triangle",
+            LfoShape::SawUp => "Saw Up",
+            LfoShape::Square => "Square",
+            LfoShape::SampleHold => "S&H",
+            LfoShape::RandomGlide => "Random Glide",
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct LfoConfig {
+    pub shape: LfoShape,
+    pub division: SyncDivision,         // length of one cycle
+    pub phase_offset_degrees: f32,      // 0 to 360, shifts the cycle against the beat
+    pub seed: u64,                      // picks the sequence of the random shapes
+}
+
+impl Default for LfoConfig {
+    fn default() -> Self {
+        Self {
+            shape: LfoShape::Sine,
+            division: SyncDivision::Quarter,
+            phase_offset_degrees: 0.0,
+            seed: 1,
+        }
+    }
+}
+
+/// Tempo-synced LFO with a 0.0 to 1.0 output. Every shape starts its cycle at 0.0 on
+/// the beat, so two LFOs at the same rate differ only by their phase offsets. The random
+/// shapes draw one level per cycle from the seed and the cycle number, so a synced LFO
+/// repeats the same pattern at the same song position.
+#[derive(Clone)]
+pub struct Lfo {
+    config: LfoConfig,
+    sample_rate: f32,
+    tempo_bpm: f32,
+    phase: f64,             // in cycles, before the phase offset
+    cycle: u64,             // whole cycles completed, for the random shapes
+}
+
+impl Lfo {
+    pub fn new(config: LfoConfig, sample_rate: f32) -> Self {
+        Self {
+            config,
+            sample_rate,
+            tempo_bpm: 120.0,
+            phase: 0.0,
+            cycle: 0,
+        }
+    }
+
+    pub fn set_config(&mut self, config: LfoConfig) {
+        self.config = config;
+    }
+
+    pub fn config(&self) -> &LfoConfig {
+        &self.config
+    }
+
+    pub fn update_sample_rate(&mut self, new_sample_rate: f32) {
+        self.sample_rate = new_sample_rate;
+    }
+
+    /// Follows the transport tempo and, while it is playing, locks the phase to the song position.
+    pub fn sync_to_transport(&mut self, transport: &TransportInfo) {
+        self.tempo_bpm = transport.tempo_bpm;
+        if transport.playing {
+            let cycles = transport.position_beats / self.config.division.beats();
+            self.phase = cycles.rem_euclid(1.0);
+            self.cycle = cycles.floor() as i64 as u64;
+        }
+    }
+
+    pub fn next_value(&mut self) -> f32 {
+        let offset = (self.config.phase_offset_degrees / 360.0) as f64;
+        let shifted = self.phase + offset;
+        let phase = shifted.rem_euclid(1.0) as f32;
+        let cycle = self.cycle.wrapping_add(shifted.floor() as u64);
+        let value = match self.config.shape {
+            LfoShape::Sine => 0.5 - 0.5 * (2.0 * PI * phase).cos(),
+            LfoShape::Triangle => 1.0 - (2.0 * phase - 1.0).abs(),
+            LfoShape::SawUp => phase,
+            LfoShape::Square => if phase < 0.5 { 1.0 } else { 0.0 },
+            LfoShape::SampleHold => random_level(self.config.seed, cycle),
+            LfoShape::RandomGlide => {
+                let from = random_level(self.config.seed, cycle);
+                let to = random_level(self.config.seed, cycle.wrapping_add(1));
+                from + (to - from) * (0.5 - 0.5 * (PI * phase).cos())
+            }
+        };
+
+        let cycles_per_sample = self.config.division.frequency_hz(self.tempo_bpm) / self.sample_rate;
+        let next = self.phase + cycles_per_sample as f64;
+        self.cycle = self.cycle.wrapping_add(next.floor() as u64);
+        self.phase = next.rem_euclid(1.0);
+        value
+    }
+}
+
+// 0.0 to 1.0, the same for the same seed and cycle
+fn random_level(seed: u64, cycle: u64) -> f32 {
+    let mut rng = seed ^ cycle.wrapping_mul(0x9E3779B97F4A7C15);
+    for _ in 0..2 {
+        rng = rng
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+    }
+    ((rng >> 32) as f32) / ((u32::MAX as f32) + 1.0)
+}
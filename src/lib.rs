@@ -1,51 +1,323 @@
 pub mod voice_configuration;
+pub mod background;
+pub mod chord_memory;
+pub mod cpu_meter;
+pub mod denormal;
+pub mod drift;
+pub mod dynamics;
+pub mod effects;
 pub mod envelope;
 pub mod oscillator;
 pub mod synthesizer;
 pub mod filter;
+pub mod glide;
+pub mod keyboard;
+pub mod keyzone;
+pub mod level_meter;
+pub mod lfo;
+pub mod midi_file;
+pub mod midi_mapping;
+pub mod midi_monitor;
+pub mod modulation;
+pub mod oversampling;
+pub mod params;
+pub mod preset;
+pub mod quality;
+pub mod randomize;
+pub mod sample;
+pub mod scope;
+pub mod sequencer;
+pub mod simd;
+pub mod sysex;
+pub mod tempo;
+pub mod vibrato;
+pub mod voice;
+pub mod voice_meter;
 
-mod voice;
+mod editor;
 
 use nih_plug::prelude::*;
 use std::sync::Arc;
+use std::time::Instant;
 use nih_plug_vizia::ViziaState;
-use nih_plug_vizia::ViziaTheming;
-use nih_plug_vizia::widgets::*;
-use nih_plug_vizia::vizia::prelude::*;
-use nih_plug_vizia::widgets::ParamSlider;
+use background::{SynthTask, TaskResults};
+use cpu_meter::CpuMeter;
+use keyboard::KeyboardState;
+use level_meter::LevelMeter;
+use midi_mapping::CcInbox;
+use midi_monitor::{MidiActivity, MidiEventKind, MidiMonitor};
+use voice_meter::VoiceMeter;
+use oscillator::Harmonics;
+use params::{MyParams, PatchSync};
+use preset::ProgramChangeInbox;
+use sample::{SampleConfig, SampleLayer};
+use scope::ScopeBuffer;
+use sysex::{ParamAddresses, ParamInbox, SysExCommand};
+use synthesizer::{Synthesizer, SynthesizerConfig, VoiceStats};
+use tempo::TransportInfo;
 
 pub struct MySynth {
     params: Arc<MyParams>,
     vizia_state: Arc<ViziaState>,
+    synth: Synthesizer,
+    scope: Arc<ScopeBuffer>,
+    keyboard: Arc<KeyboardState>,
+    midi_monitor: Arc<MidiMonitor>,
+    cc_inbox: Arc<CcInbox>,
+    program_inbox: Arc<ProgramChangeInbox>,
+    sysex_inbox: Arc<ParamInbox>,
+    task_results: Arc<TaskResults>,
+    sysex_replies: Vec<SysExCommand>,
+    pending_program: Option<u8>,    // program change received this block
+    param_ptrs: Vec<ParamPtr>,      // in `param_map` order, like the overrides
+    sysex_addresses: ParamAddresses,
+    voice_meter: Arc<VoiceMeter>,
+    level_meter: Arc<LevelMeter>,
+    cpu_meter: Arc<CpuMeter>,
+    voice_stats: VoiceStats,
+    voice_solo: Option<usize>,
+    last_keyboard: u128,
+    // Set while the host bounces offline; quality is forced to the highest mode
+    offline: bool,
+    patch_sync: PatchSync,
+    // Last values pushed into the engine, so only real edits touch the voices
+    last_noise_seed: Option<u64>,
+    building_noise_seed: Option<u64>,   // tables asked of the background thread, not ready yet
+    last_harmonics: Option<Harmonics>,
+    building_harmonics: Option<Harmonics>,  // tables asked of the background thread, not ready yet
+    last_sample: Option<(Option<String>, SampleConfig)>,
+    loading_sample: String,             // file asked of the background thread and not loaded yet, or empty
+    last_latency: Option<usize>,
 }
 
+const KEYBOARD_VELOCITY: f32 = 0.8;
+
 impl Default for MySynth {
     fn default() -> Self {
+        let params = Arc::new(MyParams::default());
+        params.overrides();     // built here so the audio thread never has to
+        let param_map = params.param_map();
+        let param_ptrs: Vec<ParamPtr> = param_map.iter().map(|(_, ptr, _)| *ptr).collect();
+        let sysex_addresses = ParamAddresses::new(param_map.iter().map(|(id, _, _)| id.as_str()));
         Self {
-            params: Arc::new(MyParams::default()),
-            vizia_state: ViziaState::new(|| (520, 360)),
+            params,
+            vizia_state: editor::default_state(),
+            synth: Synthesizer::new(SynthesizerConfig::default()),
+            scope: Arc::new(ScopeBuffer::new(editor::SCOPE_CAPACITY)),
+            keyboard: Arc::new(KeyboardState::default()),
+            midi_monitor: Arc::new(MidiMonitor::default()),
+            cc_inbox: Arc::new(CcInbox::new()),
+            program_inbox: Arc::new(ProgramChangeInbox::new()),
+            sysex_inbox: Arc::new(ParamInbox::new(param_ptrs.len())),
+            task_results: Arc::new(TaskResults::default()),
+            // A full dump, so answering one never allocates on the audio thread
+            sysex_replies: Vec::with_capacity(param_ptrs.len().div_ceil(sysex::BLOCK_PARAMS)),
+            pending_program: None,
+            param_ptrs,
+            sysex_addresses,
+            voice_meter: Arc::new(VoiceMeter::default()),
+            level_meter: Arc::new(LevelMeter::default()),
+            cpu_meter: Arc::new(CpuMeter::default()),
+            voice_stats: VoiceStats::default(),
+            voice_solo: None,
+            last_keyboard: 0,
+            offline: false,
+            patch_sync: PatchSync::default(),
+            last_noise_seed: None,
+            building_noise_seed: None,
+            last_harmonics: None,
+            building_harmonics: None,
+            last_sample: None,
+            loading_sample: String::new(),
+            last_latency: None,
         }
     }
 }
 
-#[derive(Params)]
-pub struct MyParams {
-    #[id = "gain"]
-    pub gain: FloatParam,
-}
+impl MySynth {
+    fn handle_event(&mut self, event: NoteEvent<SysExCommand>) {
+        let to_byte = |value: f32| (value * 127.0).round().clamp(0.0, 127.0) as u8;
+        let activity = |kind, channel, data1, data2| MidiActivity { kind, channel, data1, data2 };
+        match event {
+            NoteEvent::NoteOn { channel, note, velocity, .. } => {
+                self.midi_monitor.record(activity(MidiEventKind::NoteOn, channel, note, to_byte(velocity)));
+                self.synth.note_on_channel(channel, util::midi_note_to_freq(note), velocity)
+            }
+            NoteEvent::NoteOff { channel, note, velocity, .. } => {
+                self.midi_monitor.record(activity(MidiEventKind::NoteOff, channel, note, to_byte(velocity)));
+                self.synth.note_off_channel(channel, util::midi_note_to_freq(note), velocity)
+            }
+            NoteEvent::PolyPressure { channel, note, pressure, .. } => {
+                self.midi_monitor.record(activity(MidiEventKind::PolyPressure, channel, note, to_byte(pressure)));
+                self.synth.poly_pressure_channel(channel, util::midi_note_to_freq(note), pressure)
+            }
+            NoteEvent::MidiChannelPressure { channel, pressure, .. } => {
+                self.midi_monitor.record(activity(MidiEventKind::ChannelPressure, channel, to_byte(pressure), 0));
+                self.synth.channel_pressure_channel(channel, pressure)
+            }
+            NoteEvent::MidiCC { channel, cc, value, .. } => {
+                self.midi_monitor.record(activity(MidiEventKind::ControlChange, channel, cc, to_byte(value)));
+                self.cc_inbox.record(channel, cc, value);
+                let overrides = self.params.overrides();
+                self.cc_inbox.apply_mappings(channel, cc, value, |mapping, normalized| {
+                    if let Some(index) = overrides.index_of(&mapping.param_id) {
+                        overrides.set(index, normalized);
+                    }
+                });
+                self.synth.control_change_channel(channel, cc, value);
+            }
+            NoteEvent::MidiProgramChange { channel, program, .. } => {
+                self.midi_monitor.record(activity(MidiEventKind::ProgramChange, channel, program, 0));
+                self.pending_program = Some(program);
+            }
+            NoteEvent::MidiSysEx { message, .. } => self.handle_sysex(message),
+            _ => (),
+        }
+    }
 
-impl Default for MyParams {
-    fn default() -> Self {
-        Self {
-            gain: FloatParam::new(
-                "Gain",
-                0.8,
-                FloatRange::Linear { min: 0.0, max: 1.0 },
-            )
-            .with_unit("%")
-            .with_value_to_string(formatters::v2s_f32_percentage(2))
-            .with_string_to_value(formatters::s2v_f32_percentage()),
+    /// Answers dump and parameter requests by queueing replies, and sets received parameter
+    /// values as overrides, handing them on to the editor as well.
+    fn handle_sysex(&mut self, message: SysExCommand) {
+        let overrides = self.params.overrides();
+        let param_ptrs = &self.param_ptrs;
+        let param_value = |index: usize| {
+            overrides.normalized(index).unwrap_or_else(|| unsafe { param_ptrs[index].unmodulated_normalized_value() })
+        };
+        let addresses = &self.sysex_addresses;
+        match message {
+            SysExCommand::DumpRequest => {
+                let replies = &mut self.sysex_replies;
+                let values = (0..addresses.len()).map(|index| (addresses.address(index), param_value(index)));
+                sysex::dump(values, |reply| replies.push(reply));
+            }
+            SysExCommand::ParamRequest { address } => {
+                if let Some(index) = addresses.index_of(address) {
+                    self.sysex_replies.push(SysExCommand::single_value(address, param_value(index)));
+                }
+            }
+            SysExCommand::ParamBlock { .. } => {
+                for &(address, value) in message.block_values() {
+                    if let Some(index) = addresses.index_of(address) {
+                        overrides.set(index, value);
+                        self.sysex_inbox.record(index, value);
+                    }
+                }
+            }
+        }
+    }
+
+    fn sync_keyboard(&mut self) {
+        if self.keyboard.take_panic() {
+            self.synth.panic();
+        }
+        let held = self.keyboard.snapshot();
+        let synth = &mut self.synth;
+        keyboard::diff_snapshots(self.last_keyboard, held, |note, pressed| {
+            if pressed {
+                synth.note_on(util::midi_note_to_freq(note), KEYBOARD_VELOCITY);
+            } else {
+                synth.note_off(util::midi_note_to_freq(note));
+            }
+        });
+        self.last_keyboard = held;
+
+        // The captured shape goes into the saved state; the next patch sync hands it back to the synth
+        if self.keyboard.take_chord_capture() {
+            if let Some(shape) = self.synth.capture_chord() {
+                if let Ok(mut stored) = self.params.chord_shape.try_write() {
+                    *stored = shape.intervals().to_vec();
+                }
+            }
+        }
+    }
+
+    // A new seed only switches once the background thread has built its tables; until then
+    // the voices keep the old ones. Set before the oscillators so the first block already
+    // builds them with the saved seed.
+    fn sync_noise_seed(&mut self, context: &mut impl ProcessContext<Self>) {
+        let Some(seed) = self.params.noise_seed.try_read().ok().map(|seed| *seed) else {
+            return;
+        };
+        if self.last_noise_seed == Some(seed) {
+            return;
+        }
+        let synth = &mut self.synth;
+        if self.task_results.noise_tables.take(&seed, |tables| synth.set_noise_tables(tables)) {
+            self.last_noise_seed = Some(seed);
+            self.building_noise_seed = None;
+        } else if self.building_noise_seed != Some(seed) {
+            context.execute_background(SynthTask::BuildNoiseTables(seed));
+            self.building_noise_seed = Some(seed);
+        }
+    }
+
+    // Like a new noise seed, new harmonic levels only switch in once the background thread
+    // has built their tables. While a slider moves, every step asks for tables, but only the
+    // latest levels count; the voices keep the last built ones until those are ready.
+    fn sync_harmonics(&mut self, context: &mut impl ProcessContext<Self>) {
+        let harmonics = self.params.harmonics();
+        if self.last_harmonics == Some(harmonics) {
+            return;
+        }
+        let synth = &mut self.synth;
+        if self.task_results.additive_tables.take(&harmonics, |tables| synth.set_additive_tables(tables)) {
+            self.last_harmonics = Some(harmonics);
+            self.building_harmonics = None;
+        } else if self.building_harmonics != Some(harmonics) {
+            context.execute_background(SynthTask::BuildAdditiveTables(harmonics));
+            self.building_harmonics = Some(harmonics);
+        }
+    }
+
+    // Like a new noise seed, a new file only switches in once the background thread has
+    // loaded it; until then the voices keep the old one. A file that fails to load is never
+    // switched to, and the editor shows why. Paths are copied into the strings kept from
+    // earlier ones, so nothing is allocated unless a path is longer than any before it.
+    fn sync_sample(&mut self, context: &mut impl ProcessContext<Self>) {
+        let config = self.params.sample.config(self.params.overrides());
+        let Ok(path) = self.params.sample_path.try_read() else {
+            return;
+        };
+        let same_path = self.last_sample.as_ref().is_some_and(|(last_path, _)| *last_path == *path);
+        if same_path && self.last_sample.as_ref().is_some_and(|(_, last_config)| *last_config == config) {
+            return;
+        }
+        match path.as_ref() {
+            None => {
+                // The file switched away from may be the last copy, too big to free on this thread
+                if let Some(previous) = self.synth.set_sample_layer(None) {
+                    context.execute_background(SynthTask::FreeSample(previous));
+                }
+            }
+            // Only the settings changed
+            Some(_) if same_path => {
+                let data = self.synth.sample_data();
+                self.synth.set_sample_layer(data.map(|data| SampleLayer { data, config }));
+            }
+            Some(file) => {
+                let synth = &mut self.synth;
+                let swapped = self.task_results.samples.take(file, |data| {
+                    // The file switched away from goes back to be freed off this thread; with
+                    // nothing to switch away from, a second handle on the new one does
+                    synth.set_sample_layer(Some(SampleLayer { data: data.clone(), config })).unwrap_or(data)
+                });
+                if !swapped {
+                    if self.loading_sample != *file {
+                        context.execute_background(SynthTask::LoadSample);
+                        self.loading_sample.clone_from(file);
+                    }
+                    return;
+                }
+            }
+        }
+        match &mut self.last_sample {
+            Some((last_path, last_config)) => {
+                last_path.clone_from(&*path);
+                *last_config = config;
+            }
+            last_sample => *last_sample = Some((path.clone(), config)),
         }
+        self.loading_sample.clear();
     }
 }
 
@@ -64,38 +336,64 @@ impl Plugin for MySynth {
         names: PortNames::const_default(),
     }];
 
-    const MIDI_INPUT: MidiConfig = MidiConfig::Basic;
-    type SysExMessage = ();
-    type BackgroundTask = ();
+    const MIDI_INPUT: MidiConfig = MidiConfig::MidiCCs;
+    const MIDI_OUTPUT: MidiConfig = MidiConfig::Basic;     // sysex replies
+    type SysExMessage = SysExCommand;
+    type BackgroundTask = SynthTask;
 
     fn params(&self) -> Arc<dyn Params> {
         self.params.clone()
     }
 
-    fn editor(&mut self, _async_executor: AsyncExecutor<Self>) -> Option<Box<dyn Editor>> {
+    fn task_executor(&mut self) -> TaskExecutor<Self> {
+        let results = self.task_results.clone();
         let params = self.params.clone();
-        nih_plug_vizia::create_vizia_editor(
+        let cc_inbox = self.cc_inbox.clone();
+        let program_inbox = self.program_inbox.clone();
+        Box::new(move |task| background::run(task, &results, &params, &cc_inbox, &program_inbox))
+    }
+
+    fn initialize(
+        &mut self,
+        _audio_io_layout: &AudioIOLayout,
+        buffer_config: &BufferConfig,
+        context: &mut impl InitContext<Self>,
+    ) -> bool {
+        // Restored state may carry a seed; build its tables now rather than in the first block
+        let seed = *self.params.noise_seed.read().unwrap_or_else(|e| e.into_inner());
+        context.execute(SynthTask::BuildNoiseTables(seed));
+        context.execute(SynthTask::BuildAdditiveTables(self.params.harmonics()));
+        // The task reads the path itself, so the lock can't still be held here
+        let sample_path = self.params.sample_path.read().unwrap_or_else(|e| e.into_inner()).clone();
+        if let Some(path) = sample_path {
+            context.execute(SynthTask::LoadSample);
+            self.loading_sample = path;
+        }
+        let preset_mappings = self.params.preset_cc_mappings.read().unwrap_or_else(|e| e.into_inner()).clone();
+        context.execute(SynthTask::UpdateCcMappings(preset_mappings));
+        self.synth.set_sample_rate(buffer_config.sample_rate);
+        self.scope.set_sample_rate(buffer_config.sample_rate);
+        self.level_meter.reset();
+        // Hosts re-initialize when switching between realtime and offline rendering
+        self.offline = buffer_config.process_mode == ProcessMode::Offline;
+        true
+    }
+
+    fn editor(&mut self, async_executor: AsyncExecutor<Self>) -> Option<Box<dyn Editor>> {
+        editor::create(
+            self.params.clone(),
+            async_executor,
+            self.task_results.clone(),
             self.vizia_state.clone(),
-            ViziaTheming::Custom, // or Builtin if you prefer (no font reg needed)
-            move |cx, _| {
-                // Only needed for Custom theming:
-                nih_plug_vizia::assets::register_noto_sans_light(cx);
-
-                // 1) Build the model into the VIZIA context
-                ParamsModel { params: params.clone() }.build(cx);
-
-                // 2) Build your UI using lenses into that model
-                VStack::new(cx, |cx| {
-                    Label::new(cx, "My Rust Synth").hoverable(false);
-
-                    // 3) Pass a LENS (ParamsModel::params), not Arc<MyParams>
-                    ParamSlider::new(cx, ParamsModel::params, |p: &Arc<MyParams>| &p.gain)
-                        .height(Pixels(50.0));
-                })
-                    .space(Pixels(4.0));
-
-                ResizeHandle::new(cx);
-            },
+            self.scope.clone(),
+            self.keyboard.clone(),
+            self.midi_monitor.clone(),
+            self.cc_inbox.clone(),
+            self.program_inbox.clone(),
+            self.sysex_inbox.clone(),
+            self.voice_meter.clone(),
+            self.level_meter.clone(),
+            self.cpu_meter.clone(),
         )
     }
 
@@ -103,22 +401,95 @@ impl Plugin for MySynth {
         &mut self,
         buffer: &mut Buffer,
         _aux: &mut AuxiliaryBuffers,
-        _context: &mut impl ProcessContext<Self>,
+        context: &mut impl ProcessContext<Self>,
     ) -> ProcessStatus {
-        let gain = self.params.gain.value();
+        let started = Instant::now();
+        self.sync_keyboard();
+        self.sync_noise_seed(context);
+        self.sync_harmonics(context);
+        self.sync_sample(context);
+        self.patch_sync.sync(&self.params, &mut self.synth, self.offline);
+
+        // Auto-gain's lookahead and the oversampling of the voices and the master waveshaper
+        // delay the output, so the host has to compensate
+        let latency = self.synth.latency_samples();
+        if self.last_latency != Some(latency) {
+            context.set_latency_samples(latency as u32);
+            self.last_latency = Some(latency);
+        }
 
-        for channel_samples in buffer.as_slice() {
-            for sample in channel_samples.iter_mut() {
-                *sample *= gain;
+        let transport = context.transport();
+        let transport = TransportInfo {
+            tempo_bpm: transport.tempo.unwrap_or(120.0) as f32,
+            time_signature_numerator: transport.time_sig_numerator.unwrap_or(4) as u32,
+            time_signature_denominator: transport.time_sig_denominator.unwrap_or(4) as u32,
+            position_beats: transport.pos_beats().unwrap_or(0.0),
+            playing: transport.playing,
+        };
+        let beats_per_sample = transport.tempo_bpm as f64 / 60.0 / context.transport().sample_rate as f64;
+
+        // Render up to each event's timestamp before applying it, so notes land on the right sample
+        let num_samples = buffer.samples();
+        let channels = buffer.as_slice();
+        let mut next_event = context.next_event();
+        let mut segment_start = 0;
+        while segment_start < num_samples {
+            while let Some(event) = next_event {
+                if event.timing() as usize > segment_start {
+                    break;
+                }
+                self.handle_event(event);
+                next_event = context.next_event();
+            }
+            let segment_end = next_event.map_or(num_samples, |e| (e.timing() as usize).min(num_samples));
+
+            let position_beats = transport.position_beats + segment_start as f64 * beats_per_sample;
+            self.synth.set_transport(TransportInfo { position_beats, ..transport });
+            if let [left, right, ..] = channels {
+                self.synth.render_stereo(&mut left[segment_start..segment_end], &mut right[segment_start..segment_end]);
+            } else if let [mono] = channels {
+                self.synth.render(&mut mono[segment_start..segment_end]);
             }
+            segment_start = segment_end;
+        }
+        // Events stamped past the end of the buffer still count
+        while let Some(event) = next_event {
+            self.handle_event(event);
+            next_event = context.next_event();
+        }
+        for message in self.sysex_replies.drain(..) {
+            context.send_event(NoteEvent::MidiSysEx { timing: 0, message });
+        }
+        // Finding and reading the preset takes the background thread; the next blocks play it
+        if let Some(program) = self.pending_program.take() {
+            context.execute_background(SynthTask::ProgramChange(program));
         }
 
-        ProcessStatus::Normal
-    }
-}
+        let sample_rate = context.transport().sample_rate;
+        if let [left, right, ..] = channels {
+            for (l, r) in left.iter().zip(right.iter()) {
+                self.scope.push(0.5 * (*l + *r));
+            }
+            self.level_meter.publish(left, right, sample_rate);
+        } else if let [mono] = channels {
+            self.scope.push_slice(mono);
+            self.level_meter.publish(mono, mono, sample_rate);
+        }
+        self.synth.read_voice_stats(&mut self.voice_stats);
+        self.voice_meter.publish(&self.voice_stats);
+        let voice_solo = self.voice_meter.solo();
+        if voice_solo != self.voice_solo {
+            self.synth.set_voice_solo(voice_solo);
+            self.voice_solo = voice_solo;
+        }
 
-#[derive(Lens)]
-struct ParamsModel {
-    params: Arc<MyParams>,
+        self.cpu_meter.record(started.elapsed(), num_samples, sample_rate);
+
+        // Keep the host from suspending the plugin while releases or effect tails still ring
+        if self.synth.is_sounding() {
+            ProcessStatus::KeepAlive
+        } else {
+            ProcessStatus::Normal
+        }
+    }
 }
-impl Model for ParamsModel {}
\ No newline at end of file
@@ -1,19 +1,208 @@
 use std::sync::{Arc, Mutex};
 use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 use std::io::{stdin, stdout, Write};
-use midir::{MidiInput, MidiInputConnection};
-use rust_vst_synth::envelope::{Envelope, EnvelopeConfig};
-use rust_vst_synth::filter::{Filter, FilterParameters, FilterSlope, FilterType};
-use rust_vst_synth::oscillator::OscillatorConfig;
-use rust_vst_synth::synthesizer::{Synthesizer, SynthesizerConfig};
+use cpal::traits::{DeviceTrait, HostTrait};
+use midir::{Ignore, MidiInput, MidiInputConnection, MidiOutput};
+use nih_plug::prelude::Params;
+use rust_vst_synth::drift::DriftConfig;
+use rust_vst_synth::dynamics::OutputNormalization;
+use rust_vst_synth::effects::{DelayConfig, ReverbConfig, SendConfig};
+use rust_vst_synth::envelope::{Envelope, EnvelopeConfig, NEUTRAL_RELEASE_VELOCITY};
+use rust_vst_synth::filter::{Filter, FilterParameters, FilterSlope, FilterType, SaturationCurve};
+use rust_vst_synth::glide::{GlideConfig, GlideMode};
+use rust_vst_synth::lfo::{LfoConfig, LFO_COUNT};
+use rust_vst_synth::midi_file::MidiFile;
+use rust_vst_synth::midi_mapping::{self, EngineMappings, EngineParam};
+use rust_vst_synth::midi_monitor::MidiActivity;
+use rust_vst_synth::modulation::{ModulationDestination, ModulationRoute, ModulationSourceId};
+use rust_vst_synth::oversampling::VoiceOversampling;
+use rust_vst_synth::params::MyParams;
+use rust_vst_synth::oscillator::{Footage, OscillatorConfig};
+use rust_vst_synth::preset;
+use rust_vst_synth::quality::QualityMode;
+use rust_vst_synth::sequencer::{StepSequencerConfig, STEP_COUNT};
+use rust_vst_synth::synthesizer::{AudioOutput, StreamConfigOptions, StreamInfo, Synthesizer, SynthesizerConfig};
+use rust_vst_synth::sysex::{self, ParamAddresses, SysExCommand};
 use rust_vst_synth::voice_configuration::Waveform;
 
 fn midi_note_to_freq(note: u8) -> f32 {
     440.0 * 2.0_f32.powf((note as f32 - 69.0) / 12.0)
 }
 
+fn arg_value<'a>(args: &'a [String], flag: &str) -> Option<&'a str> {
+    args.iter()
+        .position(|a| a == flag)
+        .and_then(|i| args.get(i + 1))
+        .map(|v| v.as_str())
+}
+
+fn list_audio_devices() -> Result<(), Box<dyn Error>> {
+    for host_id in cpal::available_hosts() {
+        println!("{}:", host_id.name());
+        let host = cpal::host_from_id(host_id)?;
+        for device in host.output_devices()? {
+            println!("    {}", device.name()?);
+        }
+    }
+    Ok(())
+}
+
+/// Picks the audio host and output device from `--host` and `--device` (case-insensitive,
+/// device names match on a substring), falling back to the defaults.
+fn select_output_device(args: &[String]) -> Result<cpal::Device, Box<dyn Error>> {
+    let host = match arg_value(args, "--host") {
+        Some(name) => {
+            let host_id = cpal::available_hosts()
+                .into_iter()
+                .find(|id| id.name().eq_ignore_ascii_case(name))
+                .ok_or_else(|| format!("audio host '{}' is not available (try --list-devices)", name))?;
+            cpal::host_from_id(host_id)?
+        }
+        None => cpal::default_host(),
+    };
+
+    match arg_value(args, "--device") {
+        Some(name) => {
+            let wanted = name.to_lowercase();
+            host.output_devices()?
+                .find(|d| d.name().map(|n| n.to_lowercase().contains(&wanted)).unwrap_or(false))
+                .ok_or_else(|| format!("no output device matching '{}' on {}", name, host.id().name()).into())
+        }
+        None => host.default_output_device().ok_or_else(|| "no output device available".into()),
+    }
+}
+
+fn stream_options(args: &[String]) -> StreamConfigOptions {
+    StreamConfigOptions {
+        buffer_size: arg_value(args, "--buffer-size").and_then(|v| v.parse().ok()),
+        sample_rate: arg_value(args, "--sample-rate").and_then(|v| v.parse().ok()),
+        channels: arg_value(args, "--channels").and_then(|v| v.parse().ok()),
+    }
+}
+
+fn print_latency(info: &StreamInfo) {
+    if let Some((min, max)) = info.supported_buffer_frames {
+        println!("Device buffer range: {}..={} frames", min, max);
+    }
+    match (info.callback_frames(), info.latency_secs()) {
+        (Some(frames), Some(latency)) => println!("Buffer: {} frames ({:.1} ms)", frames, latency * 1000.0),
+        (None, Some(latency)) => println!("Buffer: requested {:.1} ms", latency * 1000.0),
+        _ => println!("Buffer: device default"),
+    }
+}
+
+/// Applies one raw MIDI message, from a controller or a MIDI file, to the synth.
+fn handle_midi_message(synth: &mut Synthesizer, message: &[u8]) {
+    let Some(&status) = message.first() else {
+        return;
+    };
+    let command = status & 0xF0;
+    let channel = status & 0x0F;
+    let data1 = message.get(1).copied().unwrap_or(0);
+    let data2 = message.get(2).copied().unwrap_or(0);
+
+    match command {
+        0x90 if data2 > 0 => {
+            // Note On
+            synth.note_on_channel(channel, midi_note_to_freq(data1), data2 as f32 / 127.0);
+        },
+        0x80 => {
+            // Note Off, with its release velocity
+            synth.note_off_channel(channel, midi_note_to_freq(data1), data2 as f32 / 127.0);
+        },
+        0x90 => {
+            // Note On with velocity 0 carries no release velocity
+            synth.note_off_channel(channel, midi_note_to_freq(data1), NEUTRAL_RELEASE_VELOCITY);
+        },
+        0xA0 => {
+            // Polyphonic aftertouch
+            synth.poly_pressure_channel(channel, midi_note_to_freq(data1), data2 as f32 / 127.0);
+        },
+        0xB0 => {
+            // Control change; expression and breath feed the mod matrix
+            synth.control_change_channel(channel, data1, data2 as f32 / 127.0);
+        },
+        0xD0 => {
+            // Channel pressure (only one data byte)
+            synth.channel_pressure_channel(channel, data1 as f32 / 127.0);
+        },
+        _ => (),
+    }
+}
+
+/// The standalone side of the sysex protocol. Parameters are addressed like in the plugin;
+/// those the bare engine can't set (see `EngineParam`) are reported at their defaults and
+/// ignored when received. Returns the replies to send.
+fn handle_sysex(
+    synth: &mut Synthesizer,
+    params: &[(String, f32)],
+    addresses: &ParamAddresses,
+    message: SysExCommand,
+) -> Vec<SysExCommand> {
+    let value = |index: usize| {
+        let (id, default) = &params[index];
+        EngineParam::from_id(id).map_or(*default, |param| param.normalized_value(synth))
+    };
+    let mut replies = Vec::new();
+    match message {
+        SysExCommand::DumpRequest => {
+            let values = (0..params.len()).map(|index| (addresses.address(index), value(index)));
+            sysex::dump(values, |reply| replies.push(reply));
+        }
+        SysExCommand::ParamRequest { address } => {
+            if let Some(index) = addresses.index_of(address) {
+                replies.push(SysExCommand::single_value(address, value(index)));
+            }
+        }
+        SysExCommand::ParamBlock { .. } => {
+            for &(address, normalized) in message.block_values() {
+                let param = addresses.index_of(address).and_then(|index| EngineParam::from_id(&params[index].0));
+                if let Some(param) = param {
+                    param.apply(synth, normalized);
+                }
+            }
+        }
+    }
+    replies
+}
+
+// Seconds of audio the engine has rendered, read from its internal clock
+fn clock_secs(synth: &Synthesizer) -> f64 {
+    let transport = synth.transport();
+    transport.position_beats * 60.0 / transport.tempo_bpm as f64
+}
+
+/// Plays `file` through the running synth, dispatching each event once the engine's
+/// clock has reached it. Blocks until the song ends.
+fn play_midi_file(synth: &Mutex<Synthesizer>, file: &MidiFile) {
+    println!("Playing {:.1} s of MIDI...", file.duration_secs());
+    let start = clock_secs(&synth.lock().unwrap());
+
+    for event in &file.events {
+        loop {
+            let now = clock_secs(&synth.lock().unwrap()) - start;
+            if now >= event.time_secs {
+                break;
+            }
+            let wait = (event.time_secs - now).min(0.002);
+            std::thread::sleep(Duration::from_secs_f64(wait));
+        }
+        handle_midi_message(&mut synth.lock().unwrap(), &event.message);
+    }
+
+    // Let the last releases ring out
+    std::thread::sleep(Duration::from_secs(2));
+}
+
 fn main() -> Result<(), Box<dyn Error>> {
     let sample_rate = 44100.0;
+    let args: Vec<String> = std::env::args().collect();
+
+    if args.iter().any(|a| a == "--list-devices") {
+        return list_audio_devices();
+    }
 
     let envelope_config = EnvelopeConfig::new(
         0.5,    // attack time
@@ -21,7 +210,7 @@ fn main() -> Result<(), Box<dyn Error>> {
         0.7,    // sustain level
         3.0,    // release time
         false,  // retrigger off - will continue from current value
-    );
+    )?;
 
     let filter_envelope_config = EnvelopeConfig::new(
         0.3,    // attack time
@@ -29,7 +218,7 @@ fn main() -> Result<(), Box<dyn Error>> {
         0.7,    // sustain level
         3.0,    // release time
         false,   // retrigger on - will start from beginning
-    );
+    )?;
 
     let filter_config = FilterParameters {
         filter_type: FilterType::LowPass,
@@ -37,26 +226,22 @@ fn main() -> Result<(), Box<dyn Error>> {
         cutoff_frequency: 2000.0,
         resonance_amount: 0.8,
         modulation_amount: 0.6,
+        drive: 0.0,
+        saturation: SaturationCurve::Tanh,
     };
 
     let oscillator_configs = vec![
-        OscillatorConfig {
-            waveform: Waveform::SQUARE,
-            detune_semitones: 0.0,
-            volume: 1.0,
-        },
-        OscillatorConfig {
-            waveform: Waveform::SAW,
-            detune_semitones: 7.0,
-            volume: 0.6,
-        },
+        OscillatorConfig::with_waveform(Waveform::SQUARE),
+        OscillatorConfig { detune_semitones: 7.0, volume: 0.6, ..OscillatorConfig::with_waveform(Waveform::SAW) },
         // OscillatorConfig {
         //     waveform: Waveform::SQUARE,
+        //     octave: Footage::Feet8,
         //     detune_semitones: -12.0,
         //     volume: 0.5,
         // },
         // OscillatorConfig {
         //     waveform: Waveform::WHITE_NOISE,
+        //     octave: Footage::Feet8,
         //     detune_semitones: 0.0,
         //     volume: 0.2,
         // }
@@ -64,22 +249,122 @@ fn main() -> Result<(), Box<dyn Error>> {
 
     let filter = Filter::new(filter_config, sample_rate);
 
+    let tempo_bpm = arg_value(&args, "--bpm")
+        .and_then(|v| v.parse::<f32>().ok())
+        .unwrap_or(120.0);
+
+    // --sequencer sweeps the cutoff, --sequencer-notes also plays the pattern
+    let mut sequencer = StepSequencerConfig {
+        enabled: args.iter().any(|a| a == "--sequencer" || a == "--sequencer-notes"),
+        drive_notes: args.iter().any(|a| a == "--sequencer-notes"),
+        ..StepSequencerConfig::default()
+    };
+    if let Some(probability) = arg_value(&args, "--sequencer-probability").and_then(|v| v.parse::<f32>().ok()) {
+        sequencer.probability = [probability.clamp(0.0, 1.0); STEP_COUNT];
+    }
+    // e.g. "1,1,2,4" repeats across the pattern
+    if let Some(ratchets) = arg_value(&args, "--sequencer-ratchets") {
+        let pattern: Vec<u8> = ratchets.split(',').filter_map(|r| r.trim().parse().ok()).collect();
+        if !pattern.is_empty() {
+            sequencer.ratchets = std::array::from_fn(|i| pattern[i % pattern.len()]);
+        }
+    }
+
+    // --glide SECS, with --gliss for a chromatic glissando instead of a smooth sweep
+    // and --legato to glide only between overlapping notes
+    let glide_time = arg_value(&args, "--glide")
+        .and_then(|v| v.parse::<f32>().ok())
+        .unwrap_or(0.0);
+    let glide_mode = if args.iter().any(|a| a == "--gliss") { GlideMode::Chromatic } else { GlideMode::Smooth };
+
     let config = SynthesizerConfig {
         oscillator_configs,
         envelope_config,
         filter,
         filter_envelope_config,
+        mod_envelope_configs: [
+            EnvelopeConfig::new(0.2, 0.2, 0.0, 0.3, false)?.with_looping(true),
+            EnvelopeConfig::new(0.5, 1.0, 0.3, 1.0, false)?,
+        ],
+        modulation_routes: vec![
+            ModulationRoute::new(ModulationSourceId::ChannelPressure, ModulationDestination::Vibrato, 0.5),
+            ModulationRoute::new(ModulationSourceId::PolyPressure, ModulationDestination::Cutoff, 2.0),
+            ModulationRoute::new(ModulationSourceId::StepSequencer, ModulationDestination::Cutoff, 3.0),
+            ModulationRoute::new(ModulationSourceId::Expression, ModulationDestination::Amplitude, 1.0),
+        ],
+        freeze_modulation_on_release: false,
+        drift: if args.iter().any(|a| a == "--analog") {
+            DriftConfig {
+                pitch_drift_cents: 4.0,
+                note_detune_cents: 3.0,
+                cutoff_wobble_octaves: 0.05,
+            }
+        } else {
+            DriftConfig::default()
+        },
+        // High, hard-played notes get the most reverb
+        sends: SendConfig {
+            delay: 0.2,
+            reverb: 0.3,
+            velocity_scaling: 0.5,
+            key_scaling: 0.5,
+        },
+        delay: DelayConfig::default(),
+        reverb: ReverbConfig::default(),
+        glide: GlideConfig {
+            mode: glide_mode,
+            time_secs: glide_time,
+            legato: args.iter().any(|a| a == "--legato"),
+            ..GlideConfig::default()
+        },
+        stereo_filter_spread: 0.3,
+        sequencer,
+        lfos: [LfoConfig::default(); LFO_COUNT],
+        tempo_bpm,
+        normalization: OutputNormalization::FixedHeadroom,
+        quality: if args.iter().any(|a| a == "--eco") { QualityMode::Eco } else { QualityMode::Normal },
+        oversampling: match arg_value(&args, "--oversample") {
+            Some("2") => VoiceOversampling::X2,
+            Some("4") => VoiceOversampling::X4,
+            _ => VoiceOversampling::Off,
+        },
         max_voices: 16,
+        render_threads: arg_value(&args, "--render-threads").and_then(|v| v.parse().ok()).unwrap_or(1),
         sample_rate,
+        ..SynthesizerConfig::default()
     };
 
 
     // Create and start the synthesizer
+    let device = select_output_device(&args)?;
+
     let synth = Arc::new(Mutex::new(Synthesizer::new(config)));
-    synth.lock().unwrap().start_audio()?;
 
-    // Initialize MIDI
-    let midi_in = MidiInput::new("rust-synth-input")?;
+    // --multitimbral: MIDI channel 1 plays the patch above, channel 2 a sub bass
+    if args.iter().any(|a| a == "--multitimbral") {
+        let bass = SynthesizerConfig {
+            oscillator_configs: vec![OscillatorConfig { octave: Footage::Feet16, ..OscillatorConfig::with_waveform(Waveform::SINE) }],
+            max_voices: 4,
+            sample_rate,
+            ..SynthesizerConfig::default()
+        };
+        let mut synth = synth.lock().unwrap();
+        synth.edit_part(0, |part| part.set_midi_channel(Some(0)));
+        synth.add_part(&bass, Some(1));
+    }
+    // Audio plays until `output` is dropped at the end of main
+    let output = AudioOutput::start_on(&synth.lock().unwrap(), &device, stream_options(&args))?;
+    print_latency(output.info());
+
+    if let Some(path) = arg_value(&args, "--play") {
+        let file = MidiFile::load(Path::new(path))?;
+        play_midi_file(&synth, &file);
+        return Ok(());
+    }
+
+    // Initialize MIDI; sysex has to be let through explicitly
+    let mut midi_in = MidiInput::new("rust-synth-input")?;
+    midi_in.ignore(Ignore::None);
     
     // Get available MIDI input ports
     let ports = midi_in.ports();
@@ -102,38 +387,106 @@ fn main() -> Result<(), Box<dyn Error>> {
     stdin().read_line(&mut input)?;
     let port_number = input.trim().parse::<usize>()?.min(in_ports_len - 1);
 
+    // Controller mappings from the shared setup; --learn ID maps the next controller to ID
+    let setup_path = midi_mapping::setup_path();
+    let mut setup = setup_path.as_deref().map(midi_mapping::load_setup).unwrap_or_default();
+    let mut mappings = EngineMappings::new(setup.mappings.clone());
+    if let Some(id) = arg_value(&args, "--learn") {
+        let param = EngineParam::from_id(id).ok_or_else(|| {
+            let ids: Vec<&str> = EngineParam::ALL.iter().map(|p| p.id()).collect();
+            format!("unknown parameter '{}' for --learn (one of: {})", id, ids.join(", "))
+        })?;
+        mappings.learn(param);
+        println!("Move a controller to map it to '{}'", id);
+    }
+
+    // Program changes pick presets from --bank DIR, by default the user preset directory
+    let bank_dir = arg_value(&args, "--bank").map(PathBuf::from).or_else(preset::user_preset_dir);
+    let bank = bank_dir.as_deref().map(preset::load_user_presets).unwrap_or_default();
+    if let Some(dir) = &bank_dir {
+        println!("Program change bank: {} presets in {}", bank.len(), dir.display());
+    }
+
+    // Sysex addresses the plugin's parameters by ID; --sysex-out N sends replies to output port N
+    let sysex_params: Vec<(String, f32)> = MyParams::default()
+        .param_map()
+        .into_iter()
+        .map(|(id, ptr, _)| (id, unsafe { ptr.default_normalized_value() }))
+        .collect();
+    let sysex_addresses = ParamAddresses::new(sysex_params.iter().map(|(id, _)| id.as_str()));
+    let mut sysex_out = match arg_value(&args, "--sysex-out").and_then(|v| v.parse::<usize>().ok()) {
+        Some(port) => {
+            let midi_out = MidiOutput::new("rust-synth-output")?;
+            let ports = midi_out.ports();
+            let port = ports.get(port).ok_or("no MIDI output port with that number")?;
+            Some(midi_out.connect(port, "sysex-out").map_err(|e| e.to_string())?)
+        }
+        None => None,
+    };
+
     let synth_clone = synth.clone();
+    let cpu_meter = output.info().cpu_meter();
     
     // Create MIDI connection and handle incoming messages
+    let mut reported_stuck_voices = 0;
     let _conn = midi_in.connect(
         &ports[port_number],
         "midi-read",
         move |_stamp, message, _| {
-            let command = message[0] & 0xF0;
-            let note = message[1];
-            let velocity = message[2];
-
-            match command {
-                0x90 if velocity > 0 => {
-                    // Note On
-                    let freq = midi_note_to_freq(note);
-                    if let Ok(mut synth) = synth_clone.lock() {
-                        synth.note_on(freq);
+            if let Ok(mut synth) = synth_clone.lock() {
+                handle_midi_message(&mut synth, message);
+                if let Some(command) = SysExCommand::from_bytes(message) {
+                    for reply in handle_sysex(&mut synth, &sysex_params, &sysex_addresses, command) {
+                        match sysex_out.as_mut() {
+                            Some(out) => {
+                                let _ = out.send(&reply.to_bytes());
+                            }
+                            None => println!("\nSysex reply dropped; pass --sysex-out to send replies"),
+                        }
+                    }
+                }
+                if let [status, program] = *message {
+                    if status & 0xF0 == 0xC0 {
+                        match preset::program_preset(&bank, program) {
+                            Some(entry) => {
+                                entry.preset.apply_to_engine(&mut synth);
+                                println!("\nProgram {}: {}", program as u32 + 1, entry.preset.name);
+                            }
+                            None => println!("\nNo preset for program {}", program as u32 + 1),
+                        }
                     }
-                },
-                0x80 | 0x90 => {
-                    // Note Off (0x80 or 0x90 with velocity 0)
-                    let freq = midi_note_to_freq(note);
-                    if let Ok(mut synth) = synth_clone.lock() {
-                        synth.note_off(freq);
+                }
+                if let [status, cc, value] = *message {
+                    if status & 0xF0 == 0xB0 {
+                        let learned = mappings.control_change(&mut synth, status & 0x0F, cc, value as f32 / 127.0);
+                        if let (Some(mapping), Some(path)) = (learned, setup_path.as_deref()) {
+                            setup.mappings = mappings.mappings.clone();
+                            match midi_mapping::save_setup(path, &setup) {
+                                Ok(()) => println!("\nMapped {} to '{}'", mapping.describe_source(), mapping.param_id),
+                                Err(err) => eprintln!("\nCould not save the controller setup: {}", err),
+                            }
+                        }
                     }
-                },
-                _ => (),
+                }
+                // The watchdog only counts on the audio thread; report it from here
+                let stuck_voices = synth.voice_stats().stuck_voices;
+                if stuck_voices > reported_stuck_voices {
+                    println!("\nWatchdog released {} voice(s) left sounding with no held note", stuck_voices - reported_stuck_voices);
+                    reported_stuck_voices = stuck_voices;
+                }
+            }
+            // Status line showing that MIDI arrives and what it was
+            if let Some(activity) = MidiActivity::from_bytes(message) {
+                print!("\r\x1b[2K[MIDI *] {} | {}", activity.describe(), cpu_meter.describe());
+                let _ = stdout().flush();
             }
         },
         (),
     )?;
 
+    // By now the stream has run a few callbacks, so this is the size the device really uses
+    print_latency(output.info());
+
     println!("\nReading MIDI input... Press Enter to exit.");
     input.clear();
     stdin().read_line(&mut input)?;
@@ -0,0 +1,222 @@
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+const DEFAULT_TEMPO_US_PER_QUARTER: u32 = 500_000;
+
+/// A channel voice message from a MIDI file, timed in seconds from the start of the song.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MidiFileEvent {
+    pub time_secs: f64,
+    pub message: Vec<u8>,
+}
+
+/// A parsed Standard MIDI File (formats 0 and 1) with all tracks merged into one
+/// time-ordered list and the tempo map already applied.
+#[derive(Clone, Debug, Default)]
+pub struct MidiFile {
+    pub events: Vec<MidiFileEvent>,
+}
+
+#[derive(Clone, Copy)]
+enum Division {
+    TicksPerQuarter(u16),
+    TicksPerSecond(f64),
+}
+
+// Event in ticks before the tempo map is applied
+struct TickEvent {
+    tick: u64,
+    order: usize,               // keeps events on the same tick in file order
+    kind: TickEventKind,
+}
+
+enum TickEventKind {
+    Tempo(u32),
+    Message(Vec<u8>),
+}
+
+impl MidiFile {
+    pub fn load(path: &Path) -> Result<Self, Box<dyn Error>> {
+        Self::parse(&fs::read(path)?)
+    }
+
+    pub fn parse(bytes: &[u8]) -> Result<Self, Box<dyn Error>> {
+        let mut reader = Reader::new(bytes);
+
+        if reader.take(4)? != b"MThd" {
+            return Err("not a standard MIDI file".into());
+        }
+        let header_length = reader.u32()? as usize;
+        let mut header = Reader::new(reader.take(header_length)?);
+        let format = header.u16()?;
+        let track_count = header.u16()?;
+        let raw_division = header.u16()?;
+        if format > 1 {
+            return Err(format!("MIDI file format {} is not supported", format).into());
+        }
+
+        let division = if raw_division & 0x8000 != 0 {
+            // SMPTE: negative frames per second in the high byte, ticks per frame in the low one
+            let frames_per_second = ((raw_division >> 8) as i8).checked_neg()
+                .ok_or("invalid SMPTE frame rate")?;
+            let ticks_per_frame = raw_division & 0xFF;
+            if ticks_per_frame == 0 {
+                return Err("SMPTE division with no ticks per frame".into());
+            }
+            Division::TicksPerSecond(frames_per_second as f64 * ticks_per_frame as f64)
+        } else {
+            Division::TicksPerQuarter(raw_division.max(1))
+        };
+
+        let mut tick_events = Vec::new();
+        for _ in 0..track_count {
+            let id = reader.take(4)?;
+            let length = reader.u32()? as usize;
+            let chunk = reader.take(length)?;
+            if id == b"MTrk" {
+                parse_track(chunk, &mut tick_events)?;
+            }
+        }
+        tick_events.sort_by_key(|e| (e.tick, e.order));
+
+        Ok(Self { events: apply_tempo_map(tick_events, division) })
+    }
+
+    /// Length of the song up to its last event.
+    pub fn duration_secs(&self) -> f64 {
+        self.events.last().map_or(0.0, |e| e.time_secs)
+    }
+}
+
+fn parse_track(bytes: &[u8], events: &mut Vec<TickEvent>) -> Result<(), Box<dyn Error>> {
+    let mut reader = Reader::new(bytes);
+    let mut tick = 0;
+    let mut running_status = None;
+
+    while !reader.is_empty() {
+        tick += reader.variable_length()? as u64;
+        let order = events.len();
+
+        let mut status = reader.u8()?;
+        let first_data = if status < 0x80 {
+            // Running status: this byte is already the first data byte
+            let data = status;
+            status = running_status.ok_or("data byte without a running status")?;
+            Some(data)
+        } else {
+            None
+        };
+
+        match status {
+            // Meta and system exclusive events cancel any running status
+            0xFF => {
+                running_status = None;
+                let meta_type = reader.u8()?;
+                let length = reader.variable_length()? as usize;
+                let data = reader.take(length)?;
+                match meta_type {
+                    0x2F => break,
+                    0x51 if data.len() == 3 => {
+                        let tempo = ((data[0] as u32) << 16) | ((data[1] as u32) << 8) | data[2] as u32;
+                        events.push(TickEvent { tick, order, kind: TickEventKind::Tempo(tempo) });
+                    }
+                    _ => {}
+                }
+            }
+            0xF0 | 0xF7 => {
+                running_status = None;
+                let length = reader.variable_length()? as usize;
+                reader.take(length)?;
+            }
+            _ => {
+                running_status = Some(status);
+                let data_length = match status & 0xF0 {
+                    0xC0 | 0xD0 => 1,
+                    _ => 2,
+                };
+                let mut message = vec![status];
+                if let Some(data) = first_data {
+                    message.push(data);
+                }
+                while message.len() < data_length + 1 {
+                    message.push(reader.u8()?);
+                }
+                events.push(TickEvent { tick, order, kind: TickEventKind::Message(message) });
+            }
+        }
+    }
+    Ok(())
+}
+
+fn apply_tempo_map(tick_events: Vec<TickEvent>, division: Division) -> Vec<MidiFileEvent> {
+    let mut events = Vec::new();
+    let mut tempo = DEFAULT_TEMPO_US_PER_QUARTER;
+    let mut last_tick = 0;
+    let mut time_secs = 0.0;
+
+    for event in tick_events {
+        let delta = (event.tick - last_tick) as f64;
+        time_secs += match division {
+            Division::TicksPerQuarter(ppq) => delta * tempo as f64 / 1e6 / ppq as f64,
+            Division::TicksPerSecond(rate) => delta / rate,
+        };
+        last_tick = event.tick;
+
+        match event.kind {
+            TickEventKind::Tempo(new_tempo) => tempo = new_tempo.max(1),
+            TickEventKind::Message(message) => events.push(MidiFileEvent { time_secs, message }),
+        }
+    }
+    events
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, position: 0 }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.position >= self.bytes.len()
+    }
+
+    fn take(&mut self, length: usize) -> Result<&'a [u8], Box<dyn Error>> {
+        let end = self.position.checked_add(length).filter(|&end| end <= self.bytes.len())
+            .ok_or("unexpected end of MIDI file")?;
+        let slice = &self.bytes[self.position..end];
+        self.position = end;
+        Ok(slice)
+    }
+
+    fn u8(&mut self) -> Result<u8, Box<dyn Error>> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, Box<dyn Error>> {
+        let bytes = self.take(2)?;
+        Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn u32(&mut self) -> Result<u32, Box<dyn Error>> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    // At most four bytes, seven bits each
+    fn variable_length(&mut self) -> Result<u32, Box<dyn Error>> {
+        let mut value = 0u32;
+        for _ in 0..4 {
+            let byte = self.u8()?;
+            value = (value << 7) | (byte & 0x7F) as u32;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err("variable-length quantity is too long".into())
+    }
+}
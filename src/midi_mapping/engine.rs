@@ -0,0 +1,139 @@
+use nih_plug::prelude::FloatRange;
+
+use super::{assign, CcMapping};
+use crate::effects::{DelayConfig, ReverbConfig};
+use crate::glide::GlideConfig;
+use crate::params::{cutoff_range, delay_feedback_range, glide_time_range, resonance_range, unit_range};
+use crate::synthesizer::Synthesizer;
+
+/// Parameters the standalone app can drive from controllers, without the plugin's
+/// parameter set. IDs and ranges match the plugin's, so one controller setup serves both.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum EngineParam {
+    Gain,
+    Cutoff,
+    Resonance,
+    GlideTime,
+    DelayFeedback,
+    ReverbMix,
+}
+
+impl EngineParam {
+    pub const ALL: [EngineParam; 6] = [
+        EngineParam::Gain,
+        EngineParam::Cutoff,
+        EngineParam::Resonance,
+        EngineParam::GlideTime,
+        EngineParam::DelayFeedback,
+        EngineParam::ReverbMix,
+    ];
+
+    /// The plugin parameter ID this stands for.
+    pub fn id(self) -> &'static str {
+        match self {
+            EngineParam::Gain => "gain",
+            EngineParam::Cutoff => "cutoff",
+            EngineParam::Resonance => "res",
+            EngineParam::GlideTime => "glide",
+            EngineParam::DelayFeedback => "dly_fb",
+            EngineParam::ReverbMix => "rev_mix",
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|p| p.id() == id)
+    }
+
+    fn range(self) -> FloatRange {
+        match self {
+            EngineParam::Gain | EngineParam::ReverbMix => unit_range(),
+            EngineParam::Cutoff => cutoff_range(),
+            EngineParam::Resonance => resonance_range(),
+            EngineParam::GlideTime => glide_time_range(),
+            EngineParam::DelayFeedback => delay_feedback_range(),
+        }
+    }
+
+    /// The parameter's current normalized value on `synth`.
+    pub fn normalized_value(self, synth: &Synthesizer) -> f32 {
+        let config = synth.config();
+        let value = match self {
+            EngineParam::Gain => config.master_gain,
+            EngineParam::Cutoff => config.filter.parameters().cutoff_frequency,
+            EngineParam::Resonance => config.filter.parameters().resonance_amount,
+            EngineParam::GlideTime => config.glide.time_secs,
+            EngineParam::DelayFeedback => config.delay.feedback,
+            EngineParam::ReverbMix => config.reverb.mix,
+        };
+        self.range().normalize(value)
+    }
+
+    /// Sets the parameter on `synth` from a normalized value.
+    pub fn apply(self, synth: &mut Synthesizer, normalized: f32) {
+        self.apply_plain(synth, self.range().unnormalize(normalized.clamp(0.0, 1.0)));
+    }
+
+    /// Sets the parameter on `synth` from a value in the parameter's own unit.
+    pub fn apply_plain(self, synth: &mut Synthesizer, value: f32) {
+        match self {
+            EngineParam::Gain => synth.set_master_gain(value),
+            EngineParam::Cutoff | EngineParam::Resonance => {
+                let mut parameters = synth.config().filter.parameters().clone();
+                if self == EngineParam::Cutoff {
+                    parameters.cutoff_frequency = value;
+                } else {
+                    parameters.resonance_amount = value;
+                }
+                synth.set_filter_parameters(parameters);
+            }
+            EngineParam::GlideTime => {
+                synth.set_glide_config(GlideConfig { time_secs: value, ..synth.config().glide });
+            }
+            EngineParam::DelayFeedback => {
+                synth.set_delay_config(DelayConfig { feedback: value, ..synth.config().delay });
+            }
+            EngineParam::ReverbMix => {
+                synth.set_reverb_config(ReverbConfig { mix: value, ..synth.config().reverb });
+            }
+        }
+    }
+}
+
+/// Controller mappings applied straight to the engine, for hosts without a parameter
+/// set such as the standalone app. Mappings to IDs it doesn't know are kept but ignored.
+#[derive(Clone, Debug, Default)]
+pub struct EngineMappings {
+    pub mappings: Vec<CcMapping>,
+    learn: Option<EngineParam>,
+}
+
+impl EngineMappings {
+    pub fn new(mappings: Vec<CcMapping>) -> Self {
+        Self { mappings, learn: None }
+    }
+
+    /// Maps the next controller that arrives to `param`.
+    pub fn learn(&mut self, param: EngineParam) {
+        self.learn = Some(param);
+    }
+
+    pub fn is_learning(&self) -> bool {
+        self.learn.is_some()
+    }
+
+    /// Applies a controller to every parameter mapped to it. Returns the new mapping if
+    /// this controller completed a learn.
+    pub fn control_change(&mut self, synth: &mut Synthesizer, channel: u8, cc: u8, value: f32) -> Option<CcMapping> {
+        let learned = self.learn.take().map(|param| {
+            let mapping = CcMapping::new(cc, Some(channel), param.id());
+            assign(&mut self.mappings, mapping.clone());
+            mapping
+        });
+        for mapping in self.mappings.iter().filter(|m| m.matches(channel, cc)) {
+            if let Some(param) = EngineParam::from_id(&mapping.param_id) {
+                param.apply(synth, mapping.scale(value));
+            }
+        }
+        learned
+    }
+}
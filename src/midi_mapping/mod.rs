@@ -0,0 +1,342 @@
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::Mutex;
+
+use crate::preset;
+
+mod engine;
+
+pub use engine::{EngineMappings, EngineParam};
+
+const CHANNELS: usize = 16;
+const CONTROLLERS: usize = 128;
+const SETUP_FILE: &str = "midi_mappings.json";
+const NONE: u32 = u32::MAX;
+
+/// Where a mapping is stored: with the controller setup on this machine, or inside the patch.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum MappingScope {
+    Global,
+    Preset,
+}
+
+/// How global and per-preset mappings combine when both claim the same controller.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+pub enum MergePolicy {
+    /// Preset mappings win; the rest of the controller template stays active.
+    #[default]
+    PresetFirst,
+    /// The controller template wins; presets only fill unmapped controllers.
+    GlobalFirst,
+    /// Preset mappings are ignored.
+    GlobalOnly,
+}
+
+impl MergePolicy {
+    pub const ALL: [MergePolicy; 3] = [MergePolicy::PresetFirst, MergePolicy::GlobalFirst, MergePolicy::GlobalOnly];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            MergePolicy::PresetFirst => "Preset First",
+            MergePolicy::GlobalFirst => "Global First",
+            MergePolicy::GlobalOnly => "Global Only",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        let index = Self::ALL.iter().position(|&p| p == self).unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+}
+
+/// How the controller's travel is bent before it is spread over the mapping's range.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+pub enum MappingCurve {
+    #[default]
+    Linear,
+    Exponential,    // fine control at the bottom of the range
+    Logarithmic,    // fine control at the top of the range
+}
+
+impl MappingCurve {
+    pub const ALL: [MappingCurve; 3] = [MappingCurve::Linear, MappingCurve::Exponential, MappingCurve::Logarithmic];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            MappingCurve::Linear => "Lin",
+            MappingCurve::Exponential => "Exp",
+            MappingCurve::Logarithmic => "Log",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        let index = Self::ALL.iter().position(|&c| c == self).unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+
+    pub fn apply(self, value: f32) -> f32 {
+        let value = value.clamp(0.0, 1.0);
+        match self {
+            MappingCurve::Linear => value,
+            MappingCurve::Exponential => value * value,
+            MappingCurve::Logarithmic => value.sqrt(),
+        }
+    }
+}
+
+fn full_scale() -> f32 {
+    1.0
+}
+
+/// Routes one controller to a parameter, by parameter ID. `channel` is 0-based; `None`
+/// listens on every channel. The controller sweeps the normalized parameter from `min`
+/// to `max`; `min` above `max` turns the controller around.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct CcMapping {
+    pub cc: u8,
+    #[serde(default)]
+    pub channel: Option<u8>,
+    pub param_id: String,
+    #[serde(default)]
+    pub min: f32,
+    #[serde(default = "full_scale")]
+    pub max: f32,
+    #[serde(default)]
+    pub curve: MappingCurve,
+}
+
+impl CcMapping {
+    /// A mapping over the parameter's whole range.
+    pub fn new(cc: u8, channel: Option<u8>, param_id: impl Into<String>) -> Self {
+        Self {
+            cc,
+            channel,
+            param_id: param_id.into(),
+            min: 0.0,
+            max: 1.0,
+            curve: MappingCurve::Linear,
+        }
+    }
+
+    pub fn with_range(mut self, min: f32, max: f32) -> Self {
+        self.min = min.clamp(0.0, 1.0);
+        self.max = max.clamp(0.0, 1.0);
+        self
+    }
+
+    pub fn with_curve(mut self, curve: MappingCurve) -> Self {
+        self.curve = curve;
+        self
+    }
+
+    /// Normalized parameter value for a normalized controller value.
+    pub fn scale(&self, value: f32) -> f32 {
+        self.min + (self.max - self.min) * self.curve.apply(value)
+    }
+
+    /// e.g. "0-100% Lin".
+    pub fn describe_range(&self) -> String {
+        format!("{:.0}-{:.0}% {}", self.min * 100.0, self.max * 100.0, self.curve.name())
+    }
+
+    pub fn matches(&self, channel: u8, cc: u8) -> bool {
+        self.cc == cc && self.channel.map_or(true, |c| c == channel)
+    }
+
+    /// Whether both mappings can respond to the same message.
+    pub fn overlaps(&self, other: &CcMapping) -> bool {
+        self.cc == other.cc
+            && match (self.channel, other.channel) {
+                (Some(a), Some(b)) => a == b,
+                _ => true,
+            }
+    }
+
+    /// e.g. "CC 74 ch 1" or "CC 1 any".
+    pub fn describe_source(&self) -> String {
+        match self.channel {
+            Some(channel) => format!("CC {} ch {}", self.cc, channel + 1),
+            None => format!("CC {} any", self.cc),
+        }
+    }
+}
+
+/// The hardware controller template; kept outside presets so it survives patch changes.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ControllerSetup {
+    #[serde(default)]
+    pub mappings: Vec<CcMapping>,
+    #[serde(default)]
+    pub merge_policy: MergePolicy,
+}
+
+impl ControllerSetup {
+    pub fn from_json(json: &str) -> Result<Self, Box<dyn Error>> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    pub fn to_json(&self) -> Result<String, Box<dyn Error>> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+}
+
+/// Adds `mapping` to `mappings`, replacing any mapping it overlaps.
+pub fn assign(mappings: &mut Vec<CcMapping>, mapping: CcMapping) {
+    mappings.retain(|m| !m.overlaps(&mapping));
+    mappings.push(mapping);
+}
+
+/// The mappings in effect, each tagged with where it came from. Mappings from the
+/// losing side are dropped wherever they overlap one from the winning side.
+pub fn merge(global: &[CcMapping], preset: &[CcMapping], policy: MergePolicy) -> Vec<(MappingScope, CcMapping)> {
+    let tagged = |scope: MappingScope, mappings: &[CcMapping]| {
+        mappings.iter().cloned().map(move |m| (scope, m)).collect::<Vec<_>>()
+    };
+    let (first, second) = match policy {
+        MergePolicy::PresetFirst => (tagged(MappingScope::Preset, preset), tagged(MappingScope::Global, global)),
+        MergePolicy::GlobalFirst => (tagged(MappingScope::Global, global), tagged(MappingScope::Preset, preset)),
+        MergePolicy::GlobalOnly => (tagged(MappingScope::Global, global), Vec::new()),
+    };
+
+    let mut merged = first;
+    for (scope, mapping) in second {
+        if !merged.iter().any(|(_, m)| m.overlaps(&mapping)) {
+            merged.push((scope, mapping));
+        }
+    }
+    merged
+}
+
+/// The mappings in effect for a patch with `preset` mappings, under the controller setup
+/// saved on this machine. Reads the setup file.
+pub fn active_mappings(preset: &[CcMapping]) -> Vec<CcMapping> {
+    let setup = setup_path().map(|path| load_setup(&path)).unwrap_or_default();
+    merge(&setup.mappings, preset, setup.merge_policy).into_iter().map(|(_, mapping)| mapping).collect()
+}
+
+/// Location of the controller setup, next to the user preset directory.
+pub fn setup_path() -> Option<PathBuf> {
+    preset::user_preset_dir().and_then(|dir| dir.parent().map(|base| base.join(SETUP_FILE)))
+}
+
+/// Reads the controller setup; a missing or malformed file gives an empty one.
+pub fn load_setup(path: &Path) -> ControllerSetup {
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|json| ControllerSetup::from_json(&json).ok())
+        .unwrap_or_default()
+}
+
+pub fn save_setup(path: &Path, setup: &ControllerSetup) -> Result<(), Box<dyn Error>> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, setup.to_json()?)?;
+    Ok(())
+}
+
+/// Latest value of every controller on every channel, written by the audio thread and
+/// drained by the editor, plus the mappings in effect. The audio thread applies mapped
+/// controllers itself; the editor, while open, also moves the parameters so the host
+/// sees them. While learn is armed, the next controller to arrive is captured for the
+/// editor to map.
+pub struct CcInbox {
+    values: Vec<AtomicU32>,
+    pending: Vec<AtomicBool>,
+    last_received: AtomicU32,
+    learn_armed: AtomicBool,
+    learned: AtomicU32,
+    mappings: Mutex<Vec<CcMapping>>,
+}
+
+impl Default for CcInbox {
+    fn default() -> Self {
+        Self {
+            values: (0..CHANNELS * CONTROLLERS).map(|_| AtomicU32::new(0)).collect(),
+            pending: (0..CHANNELS * CONTROLLERS).map(|_| AtomicBool::new(false)).collect(),
+            last_received: AtomicU32::new(NONE),
+            learn_armed: AtomicBool::new(false),
+            learned: AtomicU32::new(NONE),
+            mappings: Mutex::new(Vec::new()),
+        }
+    }
+}
+
+impl CcInbox {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stores a normalized controller value; called from the audio thread.
+    pub fn record(&self, channel: u8, cc: u8, value: f32) {
+        let index = (channel as usize % CHANNELS) * CONTROLLERS + (cc as usize % CONTROLLERS);
+        self.values[index].store(value.to_bits(), Ordering::Relaxed);
+        self.pending[index].store(true, Ordering::Release);
+        self.last_received.store(index as u32, Ordering::Relaxed);
+        if self.learn_armed.swap(false, Ordering::AcqRel) {
+            self.learned.store(index as u32, Ordering::Release);
+        }
+    }
+
+    /// Replaces the mappings the audio thread applies; never called from the audio thread.
+    pub fn set_mappings(&self, mappings: Vec<CcMapping>) {
+        let previous = std::mem::replace(&mut *self.mappings.lock().unwrap_or_else(|e| e.into_inner()), mappings);
+        drop(previous);     // after the lock is released
+    }
+
+    /// Calls `f(mapping, normalized)` for every mapping that listens to the controller;
+    /// called from the audio thread. A message that arrives while the mappings are being
+    /// replaced is skipped rather than waited for.
+    pub fn apply_mappings(&self, channel: u8, cc: u8, value: f32, mut f: impl FnMut(&CcMapping, f32)) {
+        let Ok(mappings) = self.mappings.try_lock() else {
+            return;
+        };
+        for mapping in mappings.iter().filter(|m| m.matches(channel, cc)) {
+            f(mapping, mapping.scale(value));
+        }
+    }
+
+    /// Waits for the next controller; see `take_learned`.
+    pub fn arm_learn(&self) {
+        self.learned.store(NONE, Ordering::Release);
+        self.learn_armed.store(true, Ordering::Release);
+    }
+
+    pub fn cancel_learn(&self) {
+        self.learn_armed.store(false, Ordering::Release);
+        self.learned.store(NONE, Ordering::Release);
+    }
+
+    pub fn is_learning(&self) -> bool {
+        self.learn_armed.load(Ordering::Acquire)
+    }
+
+    /// Channel and controller captured since `arm_learn`, once.
+    pub fn take_learned(&self) -> Option<(u8, u8)> {
+        let index = self.learned.swap(NONE, Ordering::AcqRel);
+        (index != NONE).then(|| split_index(index))
+    }
+
+    /// Calls `f(channel, cc, value)` for every controller that moved since the last drain.
+    pub fn drain(&self, mut f: impl FnMut(u8, u8, f32)) {
+        for (index, pending) in self.pending.iter().enumerate() {
+            if pending.swap(false, Ordering::Acquire) {
+                let value = f32::from_bits(self.values[index].load(Ordering::Relaxed));
+                f((index / CONTROLLERS) as u8, (index % CONTROLLERS) as u8, value);
+            }
+        }
+    }
+
+    /// Channel and controller of the most recent message, for MIDI learn.
+    pub fn last_received(&self) -> Option<(u8, u8)> {
+        let index = self.last_received.load(Ordering::Relaxed);
+        (index != NONE).then(|| split_index(index))
+    }
+}
+
+fn split_index(index: u32) -> (u8, u8) {
+    ((index as usize / CONTROLLERS) as u8, (index as usize % CONTROLLERS) as u8)
+}
@@ -0,0 +1,142 @@
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+/// How long the activity light stays on after an event.
+pub const ACTIVITY_HOLD: Duration = Duration::from_millis(150);
+
+const NOTE_NAMES: [&str; 12] = ["C", "C#", "D", "D#", "E", "F", "F#", "G", "G#", "A", "A#", "B"];
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum MidiEventKind {
+    NoteOn,
+    NoteOff,
+    PolyPressure,
+    ControlChange,
+    ChannelPressure,
+    ProgramChange,
+    Other,
+}
+
+impl MidiEventKind {
+    fn from_code(code: u32) -> Self {
+        match code {
+            1 => MidiEventKind::NoteOn,
+            2 => MidiEventKind::NoteOff,
+            3 => MidiEventKind::PolyPressure,
+            4 => MidiEventKind::ControlChange,
+            5 => MidiEventKind::ChannelPressure,
+            7 => MidiEventKind::ProgramChange,
+            _ => MidiEventKind::Other,
+        }
+    }
+
+    fn code(self) -> u32 {
+        match self {
+            MidiEventKind::NoteOn => 1,
+            MidiEventKind::NoteOff => 2,
+            MidiEventKind::PolyPressure => 3,
+            MidiEventKind::ControlChange => 4,
+            MidiEventKind::ChannelPressure => 5,
+            MidiEventKind::Other => 6,
+            MidiEventKind::ProgramChange => 7,
+        }
+    }
+}
+
+/// One received event; `channel` is 0-based.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct MidiActivity {
+    pub kind: MidiEventKind,
+    pub channel: u8,
+    pub data1: u8,
+    pub data2: u8,
+}
+
+impl MidiActivity {
+    /// Parses a raw MIDI message; running status and system messages are not handled.
+    pub fn from_bytes(message: &[u8]) -> Option<Self> {
+        let &status = message.first()?;
+        let data1 = message.get(1).copied().unwrap_or(0);
+        let data2 = message.get(2).copied().unwrap_or(0);
+        let kind = match status & 0xF0 {
+            0x90 if data2 > 0 => MidiEventKind::NoteOn,
+            0x80 | 0x90 => MidiEventKind::NoteOff,
+            0xA0 => MidiEventKind::PolyPressure,
+            0xB0 => MidiEventKind::ControlChange,
+            0xC0 => MidiEventKind::ProgramChange,
+            0xD0 => MidiEventKind::ChannelPressure,
+            _ => MidiEventKind::Other,
+        };
+        Some(Self { kind, channel: status & 0x0F, data1, data2 })
+    }
+
+    /// e.g. "Note On C4 vel 100 ch 1" or "CC 74 = 64 ch 2".
+    pub fn describe(&self) -> String {
+        let channel = self.channel + 1;
+        match self.kind {
+            MidiEventKind::NoteOn => format!("Note On {} vel {} ch {}", note_name(self.data1), self.data2, channel),
+            MidiEventKind::NoteOff => format!("Note Off {} ch {}", note_name(self.data1), channel),
+            MidiEventKind::PolyPressure => format!("Poly Pressure {} = {} ch {}", note_name(self.data1), self.data2, channel),
+            MidiEventKind::ControlChange => format!("CC {} = {} ch {}", self.data1, self.data2, channel),
+            MidiEventKind::ChannelPressure => format!("Channel Pressure {} ch {}", self.data1, channel),
+            MidiEventKind::ProgramChange => format!("Program {} ch {}", self.data1 as u32 + 1, channel),
+            MidiEventKind::Other => format!("Other ch {}", channel),
+        }
+    }
+}
+
+/// Note name with octave, middle C (60) being C4.
+pub fn note_name(note: u8) -> String {
+    format!("{}{}", NOTE_NAMES[note as usize % 12], note as i32 / 12 - 1)
+}
+
+/// Last received MIDI event and when it arrived, written by the audio or MIDI thread and
+/// read by the GUI without locking.
+pub struct MidiMonitor {
+    created: Instant,
+    last_event: AtomicU32,          // kind code, channel, data1, data2, one byte each
+    last_event_millis: AtomicU64,   // since `created`, 0 until the first event
+}
+
+impl Default for MidiMonitor {
+    fn default() -> Self {
+        Self {
+            created: Instant::now(),
+            last_event: AtomicU32::new(0),
+            last_event_millis: AtomicU64::new(0),
+        }
+    }
+}
+
+impl MidiMonitor {
+    pub fn record(&self, activity: MidiActivity) {
+        let packed = activity.kind.code() << 24
+            | (activity.channel as u32) << 16
+            | (activity.data1 as u32) << 8
+            | activity.data2 as u32;
+        self.last_event.store(packed, Ordering::Release);
+        let millis = self.created.elapsed().as_millis() as u64;
+        self.last_event_millis.store(millis.max(1), Ordering::Release);
+    }
+
+    pub fn last(&self) -> Option<MidiActivity> {
+        let packed = self.last_event.load(Ordering::Acquire);
+        (packed != 0).then(|| MidiActivity {
+            kind: MidiEventKind::from_code(packed >> 24),
+            channel: (packed >> 16) as u8,
+            data1: (packed >> 8) as u8,
+            data2: packed as u8,
+        })
+    }
+
+    /// Whether an event arrived within the last `ACTIVITY_HOLD`.
+    pub fn is_active(&self) -> bool {
+        let last = self.last_event_millis.load(Ordering::Acquire);
+        last != 0 && self.created.elapsed().as_millis() as u64 <= last + ACTIVITY_HOLD.as_millis() as u64
+    }
+
+    /// Readout for the last event, or a hint that nothing arrived yet.
+    pub fn describe_last(&self) -> String {
+        self.last().map_or_else(|| "No MIDI received".to_string(), |a| a.describe())
+    }
+}
@@ -0,0 +1,233 @@
+use serde::{Deserialize, Serialize};
+
+pub mod registry;
+
+pub use registry::{custom_source, register_modulation_source, registered_sources, MAX_CUSTOM_SOURCES};
+
+pub const BREATH_CC: u8 = 2;
+pub const EXPRESSION_CC: u8 = 11;
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum ModulationSourceId {
+    ChannelPressure,
+    PolyPressure,
+    StepSequencer,
+    Lfo1,
+    Lfo2,
+    ModEnv3,
+    ModEnv4,
+    /// CC 11; full until the pedal is first moved, so routing it doesn't silence a patch.
+    Expression,
+    /// CC 2 from a breath or wind controller.
+    Breath,
+    /// A source added through [`register_modulation_source`]; patches store it by name.
+    #[serde(serialize_with = "registry::serialize_slot", deserialize_with = "registry::deserialize_slot")]
+    Custom(u8),
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum ModulationDestination {
+    Vibrato,    // semitones of pitch vibrato depth
+    Cutoff,     // octaves of filter cutoff offset
+    Amplitude,  // 0.0 to 1.0, how much the source controls the voice level
+}
+
+impl ModulationSourceId {
+    pub fn label(self) -> &'static str {
+        match self {
+            ModulationSourceId::ChannelPressure => "Channel Pressure",
+            ModulationSourceId::PolyPressure => "Poly Pressure",
+            ModulationSourceId::StepSequencer => "Step Sequencer",
+            ModulationSourceId::Lfo1 => "LFO 1",
+            ModulationSourceId::Lfo2 => "LFO 2",
+            ModulationSourceId::ModEnv3 => "Mod Env 3",
+            ModulationSourceId::ModEnv4 => "Mod Env 4",
+            ModulationSourceId::Expression => "Expression",
+            ModulationSourceId::Breath => "Breath",
+            ModulationSourceId::Custom(slot) => registry::name(slot).unwrap_or("Custom"),
+        }
+    }
+}
+
+/// The modulation source a MIDI controller number drives, if any.
+pub fn controller_source(cc: u8) -> Option<ModulationSourceId> {
+    match cc {
+        EXPRESSION_CC => Some(ModulationSourceId::Expression),
+        BREATH_CC => Some(ModulationSourceId::Breath),
+        _ => None,
+    }
+}
+
+impl ModulationDestination {
+    pub fn label(self) -> &'static str {
+        match self {
+            ModulationDestination::Vibrato => "Vibrato",
+            ModulationDestination::Cutoff => "Cutoff",
+            ModulationDestination::Amplitude => "Amplitude",
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub struct ModulationRoute {
+    pub source: ModulationSourceId,
+    pub destination: ModulationDestination,
+    pub amount: f32,
+    #[serde(default)]
+    pub muted: bool,
+}
+
+impl ModulationRoute {
+    pub fn new(source: ModulationSourceId, destination: ModulationDestination, amount: f32) -> Self {
+        Self {
+            source,
+            destination,
+            amount,
+            muted: false,
+        }
+    }
+
+    /// Human readable form, e.g. "Poly Pressure → Cutoff @ +2.00".
+    pub fn description(&self) -> String {
+        format!("{} → {} @ {:+.2}", self.source.label(), self.destination.label(), self.amount)
+    }
+}
+
+/// Routes every new patch starts with. Expression follows the volume; the LFO, mod
+/// envelope and breath routes start muted, ready to be switched on.
+pub fn default_routes() -> Vec<ModulationRoute> {
+    vec![
+        ModulationRoute::new(ModulationSourceId::ChannelPressure, ModulationDestination::Vibrato, 0.5),
+        ModulationRoute::new(ModulationSourceId::PolyPressure, ModulationDestination::Cutoff, 2.0),
+        ModulationRoute::new(ModulationSourceId::StepSequencer, ModulationDestination::Cutoff, 3.0),
+        ModulationRoute {
+            muted: true,
+            ..ModulationRoute::new(ModulationSourceId::Lfo1, ModulationDestination::Cutoff, 1.0)
+        },
+        ModulationRoute {
+            muted: true,
+            ..ModulationRoute::new(ModulationSourceId::Lfo2, ModulationDestination::Amplitude, 0.5)
+        },
+        ModulationRoute {
+            muted: true,
+            ..ModulationRoute::new(ModulationSourceId::ModEnv3, ModulationDestination::Cutoff, 2.0)
+        },
+        ModulationRoute {
+            muted: true,
+            ..ModulationRoute::new(ModulationSourceId::ModEnv4, ModulationDestination::Vibrato, 0.5)
+        },
+        ModulationRoute::new(ModulationSourceId::Expression, ModulationDestination::Amplitude, 1.0),
+        ModulationRoute {
+            muted: true,
+            ..ModulationRoute::new(ModulationSourceId::Breath, ModulationDestination::Cutoff, 3.0)
+        },
+        ModulationRoute {
+            muted: true,
+            ..ModulationRoute::new(ModulationSourceId::Breath, ModulationDestination::Amplitude, 1.0)
+        },
+    ]
+}
+
+/// Current value (0.0 to 1.0) of every modulation source as seen by a single voice.
+#[derive(Clone, Copy)]
+pub struct ModulationValues {
+    pub channel_pressure: f32,
+    pub poly_pressure: f32,
+    pub step_sequencer: f32,
+    pub lfo1: f32,
+    pub lfo2: f32,
+    pub mod_env3: f32,
+    pub mod_env4: f32,
+    pub expression: f32,
+    pub breath: f32,
+    pub custom: [f32; MAX_CUSTOM_SOURCES],
+}
+
+impl Default for ModulationValues {
+    fn default() -> Self {
+        Self {
+            channel_pressure: 0.0,
+            poly_pressure: 0.0,
+            step_sequencer: 0.0,
+            lfo1: 0.0,
+            lfo2: 0.0,
+            mod_env3: 0.0,
+            mod_env4: 0.0,
+            expression: 1.0,
+            breath: 0.0,
+            custom: [0.0; MAX_CUSTOM_SOURCES],
+        }
+    }
+}
+
+impl ModulationValues {
+    pub fn get(&self, source: ModulationSourceId) -> f32 {
+        match source {
+            ModulationSourceId::ChannelPressure => self.channel_pressure,
+            ModulationSourceId::PolyPressure => self.poly_pressure,
+            ModulationSourceId::StepSequencer => self.step_sequencer,
+            ModulationSourceId::Lfo1 => self.lfo1,
+            ModulationSourceId::Lfo2 => self.lfo2,
+            ModulationSourceId::ModEnv3 => self.mod_env3,
+            ModulationSourceId::ModEnv4 => self.mod_env4,
+            ModulationSourceId::Expression => self.expression,
+            ModulationSourceId::Breath => self.breath,
+            ModulationSourceId::Custom(slot) => self.custom.get(slot as usize).copied().unwrap_or(0.0),
+        }
+    }
+
+    pub fn set(&mut self, source: ModulationSourceId, value: f32) {
+        match source {
+            ModulationSourceId::ChannelPressure => self.channel_pressure = value,
+            ModulationSourceId::PolyPressure => self.poly_pressure = value,
+            ModulationSourceId::StepSequencer => self.step_sequencer = value,
+            ModulationSourceId::Lfo1 => self.lfo1 = value,
+            ModulationSourceId::Lfo2 => self.lfo2 = value,
+            ModulationSourceId::ModEnv3 => self.mod_env3 = value,
+            ModulationSourceId::ModEnv4 => self.mod_env4 = value,
+            ModulationSourceId::Expression => self.expression = value,
+            ModulationSourceId::Breath => self.breath = value,
+            ModulationSourceId::Custom(slot) => {
+                if let Some(custom) = self.custom.get_mut(slot as usize) {
+                    *custom = value;
+                }
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct ModulationOutputs {
+    pub vibrato: f32,
+    pub cutoff: f32,
+    pub amplitude: f32,
+}
+
+impl Default for ModulationOutputs {
+    fn default() -> Self {
+        Self {
+            vibrato: 0.0,
+            cutoff: 0.0,
+            amplitude: 1.0,
+        }
+    }
+}
+
+/// Sums all routes into per-destination values. Amplitude routes multiply, so an
+/// amount of 1.0 makes the voice level follow the source completely.
+pub fn apply_routes(routes: &[ModulationRoute], values: &ModulationValues) -> ModulationOutputs {
+    let mut outputs = ModulationOutputs::default();
+
+    for route in routes.iter().filter(|r| !r.muted) {
+        let value = values.get(route.source);
+        match route.destination {
+            ModulationDestination::Vibrato => outputs.vibrato += value * route.amount,
+            ModulationDestination::Cutoff => outputs.cutoff += value * route.amount,
+            ModulationDestination::Amplitude => {
+                outputs.amplitude *= 1.0 - route.amount.clamp(0.0, 1.0) * (1.0 - value);
+            }
+        }
+    }
+
+    outputs
+}
@@ -0,0 +1,97 @@
+use serde::de::Error as _;
+use serde::ser::Error as _;
+use serde::{Deserialize, Deserializer, Serializer};
+use std::error::Error;
+use std::sync::{Arc, OnceLock, RwLock};
+
+use super::ModulationSourceId;
+use crate::filter::ModulationSource;
+
+/// Custom sources a process can register; each takes one `ModulationSourceId::Custom` slot.
+pub const MAX_CUSTOM_SOURCES: usize = 8;
+
+/// Builds a fresh instance of a custom source for one voice at the given sample rate.
+pub type SourceFactory = Arc<dyn Fn(f32) -> Box<dyn ModulationSource> + Send + Sync>;
+
+struct Entry {
+    name: &'static str,
+    factory: Option<SourceFactory>,     // None while a loaded patch names a source not registered yet
+}
+
+fn entries() -> &'static RwLock<Vec<Entry>> {
+    static ENTRIES: OnceLock<RwLock<Vec<Entry>>> = OnceLock::new();
+    ENTRIES.get_or_init(|| RwLock::new(Vec::new()))
+}
+
+// Slot already holding `name`, or a new one for it
+fn slot_for(entries: &mut Vec<Entry>, name: &str) -> Result<u8, Box<dyn Error>> {
+    if let Some(slot) = entries.iter().position(|e| e.name == name) {
+        return Ok(slot as u8);
+    }
+    if entries.len() >= MAX_CUSTOM_SOURCES {
+        return Err(format!("no free custom modulation slot for \"{}\"", name).into());
+    }
+    // Names live as long as the process, so labels can be handed out as &'static str
+    entries.push(Entry { name: Box::leak(name.to_owned().into_boxed_str()), factory: None });
+    Ok((entries.len() - 1) as u8)
+}
+
+/// Makes a custom source available to every voice under `name`, e.g. a breath-controller
+/// follower. Routes refer to it through the returned id and patches store it by name, so
+/// registering the same name again replaces the factory but keeps existing routes working.
+/// Voices create their instances when they are built; synths that already exist pick the
+/// source up through `Synthesizer::refresh_modulation_sources`.
+pub fn register_modulation_source(
+    name: &str,
+    factory: impl Fn(f32) -> Box<dyn ModulationSource> + Send + Sync + 'static,
+) -> Result<ModulationSourceId, Box<dyn Error>> {
+    let mut entries = entries().write().unwrap_or_else(|e| e.into_inner());
+    let slot = slot_for(&mut entries, name)?;
+    entries[slot as usize].factory = Some(Arc::new(factory));
+    Ok(ModulationSourceId::Custom(slot))
+}
+
+/// Id of the custom source registered as `name`.
+pub fn custom_source(name: &str) -> Option<ModulationSourceId> {
+    let entries = entries().read().unwrap_or_else(|e| e.into_inner());
+    entries.iter().position(|e| e.name == name).map(|slot| ModulationSourceId::Custom(slot as u8))
+}
+
+/// Every custom source that currently has a factory, in registration order.
+pub fn registered_sources() -> Vec<ModulationSourceId> {
+    let entries = entries().read().unwrap_or_else(|e| e.into_inner());
+    entries
+        .iter()
+        .enumerate()
+        .filter(|(_, e)| e.factory.is_some())
+        .map(|(slot, _)| ModulationSourceId::Custom(slot as u8))
+        .collect()
+}
+
+pub(super) fn name(slot: u8) -> Option<&'static str> {
+    let entries = entries().read().unwrap_or_else(|e| e.into_inner());
+    entries.get(slot as usize).map(|e| e.name)
+}
+
+/// New instance of the source in `slot`, or `None` if nothing is registered there.
+pub fn create_custom_source(slot: u8, sample_rate: f32) -> Option<Box<dyn ModulationSource>> {
+    let factory = {
+        let entries = entries().read().unwrap_or_else(|e| e.into_inner());
+        entries.get(slot as usize)?.factory.clone()?
+    };
+    Some(factory(sample_rate))
+}
+
+pub(super) fn serialize_slot<S: Serializer>(slot: &u8, serializer: S) -> Result<S::Ok, S::Error> {
+    match name(*slot) {
+        Some(name) => serializer.serialize_str(name),
+        None => Err(S::Error::custom(format!("custom modulation slot {} has no name", slot))),
+    }
+}
+
+// A name nobody registered yet gets a slot reserved, and reads as 0.0 until it is
+pub(super) fn deserialize_slot<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u8, D::Error> {
+    let name = String::deserialize(deserializer)?;
+    let mut entries = entries().write().unwrap_or_else(|e| e.into_inner());
+    slot_for(&mut entries, &name).map_err(D::Error::custom)
+}
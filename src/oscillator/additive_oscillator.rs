@@ -0,0 +1,217 @@
+use super::{OscillatorConfig, PhaseMode, WaveformGenerator};
+use crate::voice_configuration::Waveform;
+use std::f32::consts::TAU;
+use std::sync::{Arc, OnceLock};
+
+/// Harmonics with a level of their own, the fundamental first.
+pub const HARMONIC_COUNT: usize = 16;
+const WAVETABLE_SIZE: usize = 2048;
+/// Share of the Nyquist frequency the highest harmonic of a note may reach.
+const BAND_LIMIT: f32 = 0.9;
+
+/// Level of each harmonic of the additive waveform, 0.0 to 1.0.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Harmonics(pub [f32; HARMONIC_COUNT]);
+
+impl Default for Harmonics {
+    /// A plain sine.
+    fn default() -> Self {
+        let mut levels = [0.0; HARMONIC_COUNT];
+        levels[0] = 1.0;
+        Self(levels)
+    }
+}
+
+/// Starting points for the harmonic levels: organ drawbar registrations and the basic
+/// waveforms built from harmonics.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum DrawbarPreset {
+    Sine,
+    Flute,          // 8' and 4'
+    Hollow,         // odd drawbars only, reedy like a clarinet
+    FullOrgan,      // every drawbar out
+    Saw,            // harmonics at 1/n
+    Square,         // odd harmonics at 1/n
+}
+
+impl DrawbarPreset {
+    pub const ALL: [DrawbarPreset; 6] = [
+        DrawbarPreset::Sine,
+        DrawbarPreset::Flute,
+        DrawbarPreset::Hollow,
+        DrawbarPreset::FullOrgan,
+        DrawbarPreset::Saw,
+        DrawbarPreset::Square,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            DrawbarPreset::Sine => "Sine",
+            DrawbarPreset::Flute => "Flute",
+            DrawbarPreset::Hollow => "Hollow",
+            DrawbarPreset::FullOrgan => "Full Organ",
+            DrawbarPreset::Saw => "Saw",
+            DrawbarPreset::Square => "Square",
+        }
+    }
+
+    pub fn harmonics(self) -> Harmonics {
+        // The drawbars at 8', 4', 2 2/3', 2', 1 3/5', 1 1/3' and 1' are harmonics 1 to 6
+        // and 8, each pulled out 0 to 8; the sub-octave drawbars fall below the fundamental
+        let drawbars = |bars: [f32; 7]| {
+            let mut levels = [0.0; HARMONIC_COUNT];
+            for (harmonic, bar) in [1, 2, 3, 4, 5, 6, 8].into_iter().zip(bars) {
+                levels[harmonic - 1] = bar / 8.0;
+            }
+            Harmonics(levels)
+        };
+        match self {
+            DrawbarPreset::Sine => Harmonics::default(),
+            DrawbarPreset::Flute => drawbars([8.0, 4.0, 0.0, 0.0, 0.0, 0.0, 0.0]),
+            DrawbarPreset::Hollow => drawbars([8.0, 0.0, 8.0, 0.0, 6.0, 0.0, 0.0]),
+            DrawbarPreset::FullOrgan => drawbars([8.0; 7]),
+            DrawbarPreset::Saw => Harmonics(std::array::from_fn(|i| 1.0 / (i + 1) as f32)),
+            DrawbarPreset::Square => Harmonics(std::array::from_fn(|i| if i % 2 == 0 { 1.0 / (i + 1) as f32 } else { 0.0 })),
+        }
+    }
+}
+
+fn sine_tables() -> Arc<[Vec<f32>]> {
+    static SINE_TABLES: OnceLock<Arc<[Vec<f32>]>> = OnceLock::new();
+    SINE_TABLES.get_or_init(|| build_tables(&Harmonics::default())).clone()
+}
+
+/// One single-cycle table per harmonic count: table `n` holds harmonics 1 to `n + 1`, so a
+/// high note can play a table with nothing above the Nyquist frequency. Building them is
+/// slow, so a synth builds them once, off the audio thread, and its voices share them.
+pub struct AdditiveTables {
+    harmonics: Harmonics,
+    tables: Arc<[Vec<f32>]>,
+}
+
+impl AdditiveTables {
+    /// Too slow for the audio thread, except for a plain sine, whose tables are built once
+    /// and shared by every synth.
+    pub fn generate(harmonics: &Harmonics) -> Self {
+        let tables = if *harmonics == Harmonics::default() { sine_tables() } else { build_tables(harmonics) };
+        Self { harmonics: *harmonics, tables }
+    }
+
+    pub fn harmonics(&self) -> Harmonics {
+        self.harmonics
+    }
+
+    // The fullest table whose top harmonic stays under the band limit at `increment`
+    // cycles per sample
+    fn for_increment(&self, increment: f32) -> &[f32] {
+        let harmonics = if increment > 0.0 { (0.5 * BAND_LIMIT / increment) as usize } else { HARMONIC_COUNT };
+        &self.tables[harmonics.clamp(1, HARMONIC_COUNT) - 1]
+    }
+}
+
+fn build_tables(harmonics: &Harmonics) -> Arc<[Vec<f32>]> {
+    let levels = harmonics.0.map(|level| level.clamp(0.0, 1.0));
+    // One scale for every table, so a note keeps its level as it moves between them
+    let mut full = vec![0.0_f32; WAVETABLE_SIZE];
+    let mut tables = Vec::with_capacity(HARMONIC_COUNT);
+    let mut peak = 0.0_f32;
+    for (index, level) in levels.iter().enumerate() {
+        if *level > 0.0 {
+            let step = TAU * (index + 1) as f32 / WAVETABLE_SIZE as f32;
+            for (i, sample) in full.iter_mut().enumerate() {
+                *sample += level * (step * i as f32).sin();
+            }
+        }
+        peak = peak.max(full.iter().copied().map(f32::abs).fold(0.0, f32::max));
+        tables.push(full.clone());
+    }
+    let scale = if peak > 0.0 { 1.0 / peak } else { 0.0 };
+    for table in &mut tables {
+        table.iter_mut().for_each(|sample| *sample *= scale);
+    }
+    tables.into()
+}
+
+/// Wavetable oscillator playing the sum of harmonics at their set levels.
+#[derive(Clone)]
+pub struct AdditiveOscillator {
+    config: OscillatorConfig,
+    sample_rate: f32,
+    frequency: f32,
+    pitch_ratio: f32,          // octave and detune, with the detune spread applied
+    phase: f32,
+    tables: Arc<AdditiveTables>,   // shared between clones, so voice pools don't copy them
+}
+
+impl AdditiveOscillator {
+    /// Plays `tables`, taken from the synth so they are never built here.
+    pub fn new(sample_rate: f32, base_frequency: f32, config: OscillatorConfig, tables: Arc<AdditiveTables>) -> Self {
+        Self {
+            config,
+            sample_rate,
+            frequency: base_frequency * config.pitch_ratio(),
+            pitch_ratio: config.pitch_ratio(),
+            phase: config.start_phase.rem_euclid(1.0),
+            tables,
+        }
+    }
+}
+
+impl WaveformGenerator for AdditiveOscillator {
+    fn next_sample(&mut self) -> f32 {
+        let increment = self.frequency / self.sample_rate;
+        let table = self.tables.for_increment(increment);
+
+        let index_f = self.phase * WAVETABLE_SIZE as f32;
+        let index = index_f as usize % WAVETABLE_SIZE;
+        let frac = index_f - index_f.floor();
+        let x0 = table[index];
+        let x1 = table[(index + 1) % WAVETABLE_SIZE];
+
+        self.phase = (self.phase + increment) % 1.0;
+        (x0 + frac * (x1 - x0)) * self.config.volume
+    }
+
+    fn update_sample_rate(&mut self, new_sample_rate: f32) {
+        self.sample_rate = new_sample_rate;
+    }
+
+    fn set_frequency(&mut self, freq_hz: f32) {
+        self.frequency = freq_hz * self.pitch_ratio;
+    }
+
+    fn set_detune_spread(&mut self, spread: f32) {
+        let pitch_ratio = self.config.spread_pitch_ratio(spread);
+        self.frequency *= pitch_ratio / self.pitch_ratio;
+        self.pitch_ratio = pitch_ratio;
+    }
+
+    fn set_config(&mut self, config: OscillatorConfig) -> bool {
+        if config.waveform != Waveform::ADDITIVE {
+            return false;
+        }
+        let pitch_ratio = config.pitch_ratio();
+        self.frequency *= pitch_ratio / self.pitch_ratio;
+        self.pitch_ratio = pitch_ratio;
+        self.config = config;
+        true
+    }
+
+    fn volume(&self) -> f32 {
+        self.config.volume
+    }
+
+    fn box_clone(&self) -> Box<dyn WaveformGenerator> {
+        Box::new(self.clone())
+    }
+
+    fn set_additive_tables(&mut self, tables: &Arc<AdditiveTables>) {
+        self.tables = tables.clone();
+    }
+
+    fn retrigger(&mut self) {
+        if self.config.phase_mode == PhaseMode::Retrigger {
+            self.phase = self.config.start_phase.rem_euclid(1.0);
+        }
+    }
+}
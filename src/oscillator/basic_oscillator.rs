@@ -1,4 +1,6 @@
-use super::{OscillatorConfig, WaveformGenerator};
+use super::{OscillatorConfig, PhaseMode, WaveformGenerator};
+#[cfg(feature = "simd")]
+use crate::simd::F32x4;
 use crate::voice_configuration::Waveform;
 
 #[derive(Clone)]
@@ -6,6 +8,7 @@ pub struct BasicOscillator {
     config: OscillatorConfig,
     sample_rate: f32,
     frequency: f32,
+    pitch_ratio: f32,          // octave and detune, with the detune spread applied
     phase: f32,
     rng: u64,
 }
@@ -15,12 +18,18 @@ impl BasicOscillator {
         Self {
             config,
             sample_rate,
-            frequency: base_frequency * (2.0f32.powf(config.detune_semitones / 12.0)),
-            phase: 0.0,
+            frequency: base_frequency * config.pitch_ratio(),
+            pitch_ratio: config.pitch_ratio(),
+            phase: config.start_phase.rem_euclid(1.0),
             rng: 12345,
         }
     }
 
+    // The waveforms `make_oscillator` builds this oscillator for
+    fn plays(waveform: Waveform) -> bool {
+        !matches!(waveform, Waveform::RANDOM | Waveform::PLUCK | Waveform::ADDITIVE | Waveform::SUPERSAW)
+    }
+
     fn next_random(&mut self) -> f32 {
         self.rng = self.rng
             .wrapping_mul(6364136223846793005)
@@ -35,7 +44,10 @@ impl WaveformGenerator for BasicOscillator {
             Waveform::SINE => (self.phase * 2.0 * std::f32::consts::PI).sin(),
             Waveform::SAW => 2.0 * (self.phase - 0.5),
             Waveform::SQUARE => if self.phase < 0.5 { 1.0 } else { -1.0 },
-            Waveform::RANDOM => (self.phase * 2.0 * std::f32::consts::PI).sin(),
+            // Random, pluck and additive have oscillators of their own; a sine stands in
+            Waveform::RANDOM | Waveform::PLUCK | Waveform::ADDITIVE => (self.phase * 2.0 * std::f32::consts::PI).sin(),
+            // As does the supersaw's, with a single saw
+            Waveform::SUPERSAW => 2.0 * (self.phase - 0.5),
             Waveform::WHITE_NOISE => self.next_random(),
         };
 
@@ -48,7 +60,25 @@ impl WaveformGenerator for BasicOscillator {
     }
 
     fn set_frequency(&mut self, freq_hz: f32) {
-        self.frequency = freq_hz * 2.0f32.powf(self.config.detune_semitones / 12.0);
+        self.frequency = freq_hz * self.pitch_ratio;
+    }
+
+    fn set_detune_spread(&mut self, spread: f32) {
+        let pitch_ratio = self.config.spread_pitch_ratio(spread);
+        self.frequency *= pitch_ratio / self.pitch_ratio;
+        self.pitch_ratio = pitch_ratio;
+    }
+
+    // Keeps the phase, so even a switch between its waveforms doesn't restart the cycle
+    fn set_config(&mut self, config: OscillatorConfig) -> bool {
+        if !Self::plays(config.waveform) {
+            return false;
+        }
+        let pitch_ratio = config.pitch_ratio();
+        self.frequency *= pitch_ratio / self.pitch_ratio;
+        self.pitch_ratio = pitch_ratio;
+        self.config = config;
+        true
     }
 
     fn volume(&self) -> f32 {
@@ -58,4 +88,38 @@ impl WaveformGenerator for BasicOscillator {
     fn box_clone(&self) -> Box<dyn WaveformGenerator> {
         Box::new(self.clone())
     }
+
+    fn retrigger(&mut self) {
+        if self.config.phase_mode == PhaseMode::Retrigger {
+            self.phase = self.config.start_phase.rem_euclid(1.0);
+        }
+    }
+
+    // Four consecutive phases per vector; noise and any leftover samples go through next_sample
+    #[cfg(feature = "simd")]
+    fn fill_block(&mut self, out: &mut [f32]) {
+        if self.config.waveform == Waveform::WHITE_NOISE {
+            for sample in out.iter_mut() {
+                *sample = self.next_sample();
+            }
+            return;
+        }
+
+        let increment = self.frequency / self.sample_rate;
+        let offsets = F32x4::ramp() * increment;
+        let mut chunks = out.chunks_exact_mut(F32x4::LANES);
+        for chunk in &mut chunks {
+            let phase = (F32x4::splat(self.phase) + offsets).fract();
+            let value = match self.config.waveform {
+                Waveform::SAW => (phase - F32x4::splat(0.5)) * 2.0,
+                Waveform::SQUARE => phase.map(|p| if p < 0.5 { 1.0 } else { -1.0 }),
+                _ => phase.sin_turns(),
+            };
+            (value * self.config.volume).write_to_slice(chunk);
+            self.phase = (self.phase + F32x4::LANES as f32 * increment) % 1.0;
+        }
+        for sample in chunks.into_remainder() {
+            *sample = self.next_sample();
+        }
+    }
 }
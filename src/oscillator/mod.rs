@@ -1,9 +1,22 @@
+pub mod additive_oscillator;
 pub mod basic_oscillator;
-pub mod random_oscillator;
+pub mod noise_table_oscillator;
+pub mod pluck_oscillator;
+pub mod sample_oscillator;
+pub mod supersaw_oscillator;
 
+pub use additive_oscillator::{
+    AdditiveOscillator, AdditiveTables, DrawbarPreset, Harmonics, HARMONIC_COUNT,
+};
 pub use basic_oscillator::BasicOscillator;
-pub use random_oscillator::RandomOscillator;
+pub use noise_table_oscillator::{NoiseTableOscillator, NoiseTables, DEFAULT_NOISE_SEED};
+pub use pluck_oscillator::PluckOscillator;
+pub use sample_oscillator::SampleOscillator;
+pub use supersaw_oscillator::SupersawOscillator;
 
+use std::sync::Arc;
+
+use crate::sample::SampleLayer;
 use crate::voice_configuration::Waveform;
 
 pub trait WaveformGenerator: Send + Sync {
@@ -12,23 +25,166 @@ pub trait WaveformGenerator: Send + Sync {
     fn set_frequency(&mut self, freq_hz: f32);          // NEW: allow retuning on note-on
     fn volume(&self) -> f32;
     fn box_clone(&self) -> Box<dyn WaveformGenerator>;
+
+    /// Called when a new note starts; in retrigger mode the cycle restarts at the start phase.
+    fn retrigger(&mut self) {}
+
+    /// Scales the configured detune, 1.0 plays it as set and 0.0 collapses it to the octave.
+    fn set_detune_spread(&mut self, _spread: f32) {}
+
+    /// Takes new settings in place, so what is sounding carries on. False when it can't, e.g.
+    /// for another waveform, and the oscillator has to be built again.
+    fn set_config(&mut self, _config: OscillatorConfig) -> bool {
+        false
+    }
+
+    /// Switches a sample player to another file or other settings without restarting it;
+    /// other generators ignore it.
+    fn set_sample_layer(&mut self, _layer: &SampleLayer) {}
+
+    /// Switches a random oscillator to the table of a new noise seed without restarting it;
+    /// other generators ignore it.
+    fn set_noise_table(&mut self, _table: &Arc<[f32]>) {}
+
+    /// Switches an additive oscillator to the tables of new harmonic levels without
+    /// restarting it; other generators ignore it.
+    fn set_additive_tables(&mut self, _tables: &Arc<AdditiveTables>) {}
+
+    /// Renders `out.len()` samples at the current frequency. Generators with a vectorised
+    /// path override this; the default is the per-sample loop.
+    fn fill_block(&mut self, out: &mut [f32]) {
+        for sample in out.iter_mut() {
+            *sample = self.next_sample();
+        }
+    }
+}
+
+/// Organ-style octave footage; 8' plays at the played pitch.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Footage {
+    Feet32,
+    Feet16,
+    Feet8,
+    Feet4,
+}
+
+impl Footage {
+    pub const ALL: [Footage; 4] = [Footage::Feet32, Footage::Feet16, Footage::Feet8, Footage::Feet4];
+
+    pub fn semitones(self) -> f32 {
+        match self {
+            Footage::Feet32 => -24.0,
+            Footage::Feet16 => -12.0,
+            Footage::Feet8 => 0.0,
+            Footage::Feet4 => 12.0,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Footage::Feet32 => "32'",
+            Footage::Feet16 => "16'",
+            Footage::Feet8 => "8'",
+            Footage::Feet4 => "4'",
+        }
+    }
 }
 
-#[derive(Clone, Copy)]
+/// Whether an oscillator's cycle restarts on every note or keeps running between notes.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum PhaseMode {
+    FreeRun,
+    Retrigger,
+}
+
+impl PhaseMode {
+    pub const ALL: [PhaseMode; 2] = [PhaseMode::FreeRun, PhaseMode::Retrigger];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            PhaseMode::FreeRun => "Free Run",
+            PhaseMode::Retrigger => "Retrigger",
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
 pub struct OscillatorConfig {
     pub waveform: Waveform,
+    pub octave: Footage,
     pub detune_semitones: f32,
     pub volume: f32,
+    pub start_phase: f32,       // 0.0 to 1.0 of a cycle, where retriggered notes begin
+    pub phase_mode: PhaseMode,
+    pub damping: f32,           // pluck only: 0.0 rings long and bright, 1.0 dies out fast and dull
+    pub supersaw_detune: f32,   // supersaw only: 0.0 to 1.0 along the JP-8000 detune curve
+    pub supersaw_mix: f32,      // supersaw only: 0.0 is the centre saw alone, 1.0 the side saws loudest
+    pub muted: bool,
+    pub solo: bool,             // while any oscillator is soloed, only soloed ones sound
+}
+
+impl Default for OscillatorConfig {
+    fn default() -> Self {
+        Self {
+            waveform: Waveform::SAW,
+            octave: Footage::Feet8,
+            detune_semitones: 0.0,
+            volume: 1.0,
+            start_phase: 0.0,
+            phase_mode: PhaseMode::FreeRun,
+            damping: 0.5,
+            supersaw_detune: 0.5,
+            supersaw_mix: 0.5,
+            muted: false,
+            solo: false,
+        }
+    }
+}
+
+impl OscillatorConfig {
+    /// The default oscillator playing `waveform`.
+    pub fn with_waveform(waveform: Waveform) -> Self {
+        Self { waveform, ..Self::default() }
+    }
+
+    /// Frequency multiplier from the octave switch and the fine detune combined.
+    pub fn pitch_ratio(&self) -> f32 {
+        self.spread_pitch_ratio(1.0)
+    }
+
+    /// Like `pitch_ratio`, with the detune scaled by `spread`.
+    pub fn spread_pitch_ratio(&self, spread: f32) -> f32 {
+        2.0f32.powf((self.octave.semitones() + self.detune_semitones * spread) / 12.0)
+    }
+}
+
+/// Bit mask of the oscillators the voice mixer leaves out: the muted ones, and while any
+/// oscillator is soloed, every one that isn't.
+pub fn silenced_oscillators(configs: &[OscillatorConfig]) -> u64 {
+    let any_solo = configs.iter().any(|c| c.solo);
+    configs
+        .iter()
+        .take(64)
+        .enumerate()
+        .filter(|(_, c)| c.muted || (any_solo && !c.solo))
+        .fold(0, |mask, (i, _)| mask | (1 << i))
 }
 
-/// Small factory so Voice can construct polymorphic oscillators cleanly.
+/// Small factory so Voice can construct polymorphic oscillators cleanly. `noise_table` is
+/// what a random oscillator plays and `additive_tables` what an additive one plays, both
+/// taken from the synth so they are never built here.
 pub fn make_oscillator(
     cfg: OscillatorConfig,
     sample_rate: f32,
     init_freq_hz: f32,
+    noise_table: &Arc<[f32]>,
+    additive_tables: &Arc<AdditiveTables>,
 ) -> Box<dyn WaveformGenerator> {
     match cfg.waveform {
-        Waveform::RANDOM => Box::new(RandomOscillator::new(sample_rate, init_freq_hz, cfg)),
+        Waveform::RANDOM => Box::new(NoiseTableOscillator::with_table(sample_rate, init_freq_hz, cfg, noise_table.clone())),
+        Waveform::PLUCK => Box::new(PluckOscillator::new(sample_rate, init_freq_hz, cfg)),
+        Waveform::ADDITIVE => Box::new(AdditiveOscillator::new(sample_rate, init_freq_hz, cfg, additive_tables.clone())),
+        Waveform::SUPERSAW => Box::new(SupersawOscillator::new(sample_rate, init_freq_hz, cfg)),
         _ => Box::new(BasicOscillator::new(sample_rate, init_freq_hz, cfg)),
     }
 }
@@ -0,0 +1,184 @@
+use super::{OscillatorConfig, PhaseMode, WaveformGenerator};
+use crate::voice_configuration::Waveform;
+use std::f32::consts::PI;
+use std::sync::{Arc, OnceLock};
+
+const WAVETABLE_SIZE: usize = 2048;
+const HARMONICS: usize = 32;    // highest partial in the table, keeps it band-limited
+pub const DEFAULT_NOISE_SEED: u64 = 0x5eed_1234_abcd_0001;
+const TABLE_SLOTS: usize = 3;   // oscillator 1, oscillator 2 and the sub
+
+fn default_wavetable() -> Arc<[f32]> {
+    static DEFAULT_TABLE: OnceLock<Arc<[f32]>> = OnceLock::new();
+    DEFAULT_TABLE.get_or_init(|| generate_wavetable(DEFAULT_NOISE_SEED)).clone()
+}
+
+/// The random oscillators' tables for one noise seed, one per oscillator slot so the
+/// oscillators of a patch don't all play the same wave. Building them is slow, so a synth
+/// builds them once, off the audio thread, and its voices share them.
+pub struct NoiseTables {
+    seed: u64,
+    tables: Vec<Arc<[f32]>>,
+}
+
+impl NoiseTables {
+    /// Too slow for the audio thread, except for the default seed, whose single table is
+    /// built once and shared by every slot and every synth.
+    pub fn generate(seed: u64) -> Self {
+        let tables = if seed == DEFAULT_NOISE_SEED {
+            vec![default_wavetable()]
+        } else {
+            (0..TABLE_SLOTS).map(|slot| generate_wavetable(seed.wrapping_add(slot as u64))).collect()
+        };
+        Self { seed, tables }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// The table of the random oscillator in slot `index`.
+    pub fn table(&self, index: usize) -> &Arc<[f32]> {
+        &self.tables[index % self.tables.len()]
+    }
+}
+
+/// Single-cycle wavetable built from random harmonic amplitudes and phases. The same seed
+/// always gives the same table.
+#[derive(Clone)]
+pub struct NoiseTableOscillator {
+    config: OscillatorConfig,
+    sample_rate: f32,
+    frequency: f32,
+    pitch_ratio: f32,          // octave and detune, with the detune spread applied
+    phase: f32,
+    wavetable: Arc<[f32]>,  // shared between clones, so voice pools don't copy the table
+    wavetable_size: usize,
+}
+
+impl NoiseTableOscillator {
+    /// Uses the default seed's table.
+    pub fn new(sample_rate: f32, base_frequency: f32, config: OscillatorConfig) -> Self {
+        Self::with_table(sample_rate, base_frequency, config, default_wavetable())
+    }
+
+    /// Builds the table for `seed` itself, so it is too slow for the audio thread; voices
+    /// use `with_table` with their synth's `NoiseTables`.
+    pub fn with_seed(sample_rate: f32, base_frequency: f32, config: OscillatorConfig, seed: u64) -> Self {
+        Self::with_table(sample_rate, base_frequency, config, generate_wavetable(seed))
+    }
+
+    pub fn with_table(sample_rate: f32, base_frequency: f32, config: OscillatorConfig, wavetable: Arc<[f32]>) -> Self {
+        Self {
+            config,
+            sample_rate,
+            frequency: base_frequency * config.pitch_ratio(),
+            pitch_ratio: config.pitch_ratio(),
+            phase: config.start_phase.rem_euclid(1.0),
+            wavetable_size: wavetable.len(),
+            wavetable,
+        }
+    }
+}
+
+fn generate_wavetable(seed: u64) -> Arc<[f32]> {
+    let mut rng = seed;
+    let mut random = move || {
+        rng = rng
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        ((rng >> 32) as f32) / ((u32::MAX as f32) + 1.0)
+    };
+
+    // Random amplitudes with a 1/k roll-off and random phases; no DC term
+    let mut wavetable = vec![0.0_f32; WAVETABLE_SIZE];
+    for harmonic in 1..=HARMONICS {
+        let amplitude = random() / harmonic as f32;
+        let phase_offset = random() * 2.0 * PI;
+        let step = 2.0 * PI * harmonic as f32 / WAVETABLE_SIZE as f32;
+        for (i, sample) in wavetable.iter_mut().enumerate() {
+            *sample += amplitude * (step * i as f32 + phase_offset).sin();
+        }
+    }
+
+    // Smooth the waveform slightly to reduce aliasing
+    let smoothed: Vec<f32> = (0..WAVETABLE_SIZE)
+        .map(|i| {
+            let prev = wavetable[(i + WAVETABLE_SIZE - 1) % WAVETABLE_SIZE];
+            let next = wavetable[(i + 1) % WAVETABLE_SIZE];
+            0.25 * prev + 0.5 * wavetable[i] + 0.25 * next
+        })
+        .collect();
+
+    // Normalize to a peak of 1.0
+    let max_amplitude = smoothed.iter().copied().map(f32::abs).fold(0.0_f32, f32::max);
+    let scale = if max_amplitude > 0.0 { 1.0 / max_amplitude } else { 0.0 };
+    smoothed.into_iter().map(|sample| sample * scale).collect()
+}
+
+impl WaveformGenerator for NoiseTableOscillator {
+    fn next_sample(&mut self) -> f32 {
+        let index_f = self.phase * self.wavetable_size as f32;
+        let index = index_f as usize % self.wavetable_size;
+        let frac = index_f - index_f.floor();
+
+        let x0 = self.wavetable[(index + self.wavetable_size - 1) % self.wavetable_size];
+        let x1 = self.wavetable[index];
+        let x2 = self.wavetable[(index + 1) % self.wavetable_size];
+        let x3 = self.wavetable[(index + 2) % self.wavetable_size];
+
+        let c0 = x1;
+        let c1 = 0.5 * (x2 - x0);
+        let c2 = x0 - 2.5 * x1 + 2.0 * x2 - 0.5 * x3;
+        let c3 = 0.5 * (x3 - x0) + 1.5 * (x1 - x2);
+
+        let interpolated = ((c3 * frac + c2) * frac + c1) * frac + c0;
+
+        self.phase = (self.phase + self.frequency / self.sample_rate) % 1.0;
+        interpolated * self.config.volume
+    }
+
+    fn update_sample_rate(&mut self, new_sample_rate: f32) {
+        self.sample_rate = new_sample_rate;
+    }
+
+    fn set_frequency(&mut self, freq_hz: f32) {
+        self.frequency = freq_hz * self.pitch_ratio;
+    }
+
+    fn set_detune_spread(&mut self, spread: f32) {
+        let pitch_ratio = self.config.spread_pitch_ratio(spread);
+        self.frequency *= pitch_ratio / self.pitch_ratio;
+        self.pitch_ratio = pitch_ratio;
+    }
+
+    fn set_config(&mut self, config: OscillatorConfig) -> bool {
+        if config.waveform != Waveform::RANDOM {
+            return false;
+        }
+        let pitch_ratio = config.pitch_ratio();
+        self.frequency *= pitch_ratio / self.pitch_ratio;
+        self.pitch_ratio = pitch_ratio;
+        self.config = config;
+        true
+    }
+
+    fn set_noise_table(&mut self, table: &Arc<[f32]>) {
+        self.wavetable_size = table.len();
+        self.wavetable = table.clone();
+    }
+
+    fn volume(&self) -> f32 {
+        self.config.volume
+    }
+
+    fn box_clone(&self) -> Box<dyn WaveformGenerator> {
+        Box::new(self.clone())
+    }
+
+    fn retrigger(&mut self) {
+        if self.config.phase_mode == PhaseMode::Retrigger {
+            self.phase = self.config.start_phase.rem_euclid(1.0);
+        }
+    }
+}
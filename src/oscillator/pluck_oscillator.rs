@@ -0,0 +1,169 @@
+use super::{OscillatorConfig, WaveformGenerator};
+use crate::filter::{Filter, FilterParameters, FilterSlope, FilterType, SaturationCurve};
+use crate::voice_configuration::Waveform;
+use std::f32::consts::{FRAC_1_SQRT_2, SQRT_2, TAU};
+
+/// Lowest pitch the delay line holds a full period of.
+const MIN_FREQUENCY_HZ: f32 = 20.0;
+/// Harmonics of the note the damping filter lets through, from no damping to full damping.
+const BRIGHT_HARMONICS: f32 = 32.0;
+const DULL_HARMONICS: f32 = 1.5;
+/// Share of the level kept on every trip round the loop, so even undamped strings die out.
+const LOOP_GAIN: f32 = 0.999;
+
+/// Karplus-Strong plucked string: every note fills one period of a delay line with a noise
+/// burst, which then circulates through a low-pass damping filter. The filter takes off
+/// more of the highs on every trip, so the tone dulls as it decays, as a string's does.
+#[derive(Clone)]
+pub struct PluckOscillator {
+    config: OscillatorConfig,
+    sample_rate: f32,
+    frequency: f32,
+    pitch_ratio: f32,          // octave and detune, with the detune spread applied
+    line: Vec<f32>,            // circular delay line
+    write: usize,
+    period: f32,               // delay in samples, less the damping filter's own delay
+    damping: Filter,
+    rng: u64,
+}
+
+impl PluckOscillator {
+    pub fn new(sample_rate: f32, base_frequency: f32, config: OscillatorConfig) -> Self {
+        let damping = Filter::new(
+            FilterParameters {
+                filter_type: FilterType::LowPass,
+                slope: FilterSlope::Slope6dB,
+                cutoff_frequency: 20000.0,
+                resonance_amount: FRAC_1_SQRT_2,
+                modulation_amount: 0.0,
+                drive: 0.0,
+                saturation: SaturationCurve::Tanh,
+            },
+            sample_rate,
+        );
+        let mut oscillator = Self {
+            config,
+            sample_rate,
+            frequency: base_frequency * config.pitch_ratio(),
+            pitch_ratio: config.pitch_ratio(),
+            line: Vec::new(),
+            write: 0,
+            period: 1.0,
+            damping,
+            rng: 12345,
+        };
+        oscillator.resize_line();
+        oscillator.tune();
+        oscillator
+    }
+
+    fn resize_line(&mut self) {
+        self.line = vec![0.0; (self.sample_rate / MIN_FREQUENCY_HZ) as usize + 2];
+        self.write = 0;
+    }
+
+    // The damping filter sits at a fixed number of harmonics above the note, so it dulls
+    // every pitch alike. Its phase delay at the fundamental is taken off the delay line's,
+    // or the loop would run long and play flat.
+    fn tune(&mut self) {
+        let frequency = self.frequency.clamp(MIN_FREQUENCY_HZ, self.sample_rate * 0.45);
+        let harmonics = BRIGHT_HARMONICS * (DULL_HARMONICS / BRIGHT_HARMONICS).powf(self.config.damping.clamp(0.0, 1.0));
+        self.damping.set_cutoff_frequency(frequency * harmonics);
+
+        let x = 1.0 / harmonics;
+        let filter_delay = (SQRT_2 * x).atan2(1.0 - x * x) / TAU;   // in periods, second-order Butterworth
+        let max_period = (self.line.len() - 2) as f32;
+        self.period = (self.sample_rate / frequency * (1.0 - filter_delay)).clamp(1.0, max_period);
+    }
+
+    fn next_random(&mut self) -> f32 {
+        self.rng = self.rng
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        ((self.rng >> 32) as f32) / ((u32::MAX as f32) + 1.0) * 2.0 - 1.0
+    }
+
+    // One period of noise behind the write position, without DC, which the loop would
+    // otherwise hold on to
+    fn pluck(&mut self) {
+        let length = self.line.len();
+        let burst = (self.period as usize).max(1);
+        let start = self.write + length - burst;
+        self.line.iter_mut().for_each(|sample| *sample = 0.0);
+        let mut sum = 0.0;
+        for i in 0..burst {
+            let value = self.next_random();
+            self.line[(start + i) % length] = value;
+            sum += value;
+        }
+        let mean = sum / burst as f32;
+        for i in 0..burst {
+            self.line[(start + i) % length] -= mean;
+        }
+        self.damping.reset();
+    }
+}
+
+impl WaveformGenerator for PluckOscillator {
+    fn next_sample(&mut self) -> f32 {
+        let length = self.line.len();
+        let read = self.write as f32 + length as f32 - self.period;
+        let index = read as usize;
+        let frac = read - index as f32;
+        let x0 = self.line[index % length];
+        let x1 = self.line[(index + 1) % length];
+        let delayed = x0 + frac * (x1 - x0);
+
+        let output = self.damping.process_sample(delayed) * LOOP_GAIN;
+        self.line[self.write] = output;
+        self.write = (self.write + 1) % length;
+        output * self.config.volume
+    }
+
+    fn update_sample_rate(&mut self, new_sample_rate: f32) {
+        if new_sample_rate != self.sample_rate {
+            self.sample_rate = new_sample_rate;
+            self.damping.update_sample_rate(new_sample_rate);
+            self.resize_line();
+        }
+        self.tune();
+    }
+
+    fn set_frequency(&mut self, freq_hz: f32) {
+        self.frequency = freq_hz * self.pitch_ratio;
+        self.tune();
+    }
+
+    fn set_detune_spread(&mut self, spread: f32) {
+        let pitch_ratio = self.config.spread_pitch_ratio(spread);
+        self.frequency *= pitch_ratio / self.pitch_ratio;
+        self.pitch_ratio = pitch_ratio;
+        self.tune();
+    }
+
+    // Keeps the delay line and the string ringing in it; the damping and pitch change from here on
+    fn set_config(&mut self, config: OscillatorConfig) -> bool {
+        if config.waveform != Waveform::PLUCK {
+            return false;
+        }
+        let pitch_ratio = config.pitch_ratio();
+        self.frequency *= pitch_ratio / self.pitch_ratio;
+        self.pitch_ratio = pitch_ratio;
+        self.config = config;
+        self.tune();
+        true
+    }
+
+    fn volume(&self) -> f32 {
+        self.config.volume
+    }
+
+    fn box_clone(&self) -> Box<dyn WaveformGenerator> {
+        Box::new(self.clone())
+    }
+
+    // A string is plucked on every note, whatever the phase mode
+    fn retrigger(&mut self) {
+        self.pluck();
+    }
+}
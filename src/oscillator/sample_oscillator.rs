@@ -0,0 +1,108 @@
+use super::WaveformGenerator;
+use crate::sample::{SampleConfig, SampleData, SampleLayer, SampleLoop};
+use std::sync::Arc;
+
+/// Plays a loaded file pitched to the note: at the root note it plays at the file's own
+/// speed, an octave up twice as fast. Every note starts it again at the start offset; a
+/// one-shot then falls silent at the end, a loop repeats between its loop points.
+#[derive(Clone)]
+pub struct SampleOscillator {
+    data: Arc<SampleData>,  // shared between clones, so voice pools don't copy the file
+    config: SampleConfig,
+    sample_rate: f32,
+    frequency: f32,
+    position: f64,          // in frames of the file
+    step: f64,              // frames of the file per output sample
+    loop_range: (f64, f64), // in frames of the file, at least one frame long
+    playing: bool,
+}
+
+impl SampleOscillator {
+    /// Starts out playing from the start offset, so a layer added while a note is held is
+    /// heard straight away.
+    pub fn new(sample_rate: f32, base_frequency: f32, layer: &SampleLayer) -> Self {
+        let mut oscillator = Self {
+            data: layer.data.clone(),
+            config: layer.config,
+            sample_rate,
+            frequency: base_frequency,
+            position: 0.0,
+            step: 0.0,
+            loop_range: (0.0, 1.0),
+            playing: false,
+        };
+        oscillator.set_sample_layer(layer);
+        oscillator.retrigger();
+        oscillator
+    }
+
+    fn update_step(&mut self) {
+        let speed = self.frequency / self.config.root_frequency();
+        self.step = (speed * self.data.sample_rate / self.sample_rate) as f64;
+    }
+
+    fn frame(&self, index: usize) -> f32 {
+        self.data.frames.get(index).copied().unwrap_or(0.0)
+    }
+}
+
+impl WaveformGenerator for SampleOscillator {
+    fn next_sample(&mut self) -> f32 {
+        if !self.playing {
+            return 0.0;
+        }
+        let looping = self.config.loop_mode == SampleLoop::Loop;
+        let (loop_start, loop_end) = self.loop_range;
+
+        // Linear interpolation; inside a loop the last frame leads back to the loop start
+        let index = self.position as usize;
+        let frac = (self.position - index as f64) as f32;
+        let next = if looping && index + 1 >= loop_end as usize { loop_start as usize } else { index + 1 };
+        let (x0, x1) = (self.frame(index), self.frame(next));
+        let sample = x0 + frac * (x1 - x0);
+
+        self.position += self.step;
+        if looping && self.position >= loop_end {
+            self.position = loop_start + (self.position - loop_end) % (loop_end - loop_start);
+        } else if self.position >= self.data.frames.len() as f64 {
+            self.playing = false;
+        }
+        sample * self.config.level
+    }
+
+    fn update_sample_rate(&mut self, new_sample_rate: f32) {
+        self.sample_rate = new_sample_rate;
+        self.update_step();
+    }
+
+    fn set_frequency(&mut self, freq_hz: f32) {
+        self.frequency = freq_hz;
+        self.update_step();
+    }
+
+    fn volume(&self) -> f32 {
+        self.config.level
+    }
+
+    fn box_clone(&self) -> Box<dyn WaveformGenerator> {
+        Box::new(self.clone())
+    }
+
+    // Keeps the play position, so moving the loop points doesn't restart held notes; past
+    // the end of a shorter file a one-shot falls silent and a loop wraps into its loop
+    fn set_sample_layer(&mut self, layer: &SampleLayer) {
+        self.data = layer.data.clone();
+        self.config = layer.config;
+        let length = self.data.frames.len() as f64;
+        let loop_start = (self.config.loop_start.clamp(0.0, 1.0) as f64 * length).min(length - 1.0).max(0.0);
+        let loop_end = (self.config.loop_end.clamp(0.0, 1.0) as f64 * length).clamp(loop_start + 1.0, length.max(1.0));
+        self.loop_range = (loop_start, loop_end);
+        self.update_step();
+    }
+
+    // A sample always restarts, whatever the oscillators' phase mode
+    fn retrigger(&mut self) {
+        self.position = self.config.start.clamp(0.0, 1.0) as f64 * self.data.frames.len() as f64;
+        self.playing = self.position < self.data.frames.len() as f64;
+    }
+}
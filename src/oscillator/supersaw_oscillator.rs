@@ -0,0 +1,143 @@
+use super::{OscillatorConfig, PhaseMode, WaveformGenerator};
+use crate::voice_configuration::Waveform;
+
+const SAW_COUNT: usize = 7;
+const CENTRE: usize = 3;
+/// Pitch offset of each saw at full detune, as a share of the note's frequency; the centre
+/// saw stays on the note. Measured from the JP-8000, which is why they aren't symmetric.
+const OFFSETS: [f32; SAW_COUNT] = [-0.11002313, -0.06288439, -0.01952356, 0.0, 0.01991221, 0.06216538, 0.10745242];
+/// Starting phases as shares of a cycle after the centre saw's, spread so the saws never
+/// start out in step and sum to one loud saw.
+const PHASE_SPREAD: [f32; SAW_COUNT] = [0.618, 0.236, 0.854, 0.0, 0.472, 0.090, 0.708];
+
+/// The JP-8000's detune knob, 0.0 to 1.0, to the scale of `OFFSETS`: barely any movement
+/// over most of the range, then a steep climb towards the top.
+fn detune_curve(amount: f32) -> f32 {
+    // The terms cancel out by several orders of magnitude, too many for f32
+    const COEFFICIENTS: [f64; 12] = [
+        10028.7312891634, -50818.8652045924, 111363.4808729368, -138150.6761080548,
+        106649.6679158292, -53046.9642751875, 17019.9518580080, -3425.0836591318,
+        404.2703938388, -24.1878824391, 0.6717417634, 0.0030115596,
+    ];
+    let x = amount.clamp(0.0, 1.0) as f64;
+    COEFFICIENTS.iter().fold(0.0, |sum, c| sum * x + c).clamp(0.0, 1.0) as f32
+}
+
+/// The JP-8000's mix knob, 0.0 to 1.0, to the gains of the centre saw and of each side saw:
+/// the centre fades a little as the sides come up.
+fn mix_gains(mix: f32) -> (f32, f32) {
+    let x = mix.clamp(0.0, 1.0);
+    (-0.55366 * x + 0.99785, -0.73764 * x * x + 1.2841 * x + 0.044372)
+}
+
+// Smooths the step of a saw at `phase`, `increment` cycles per sample, so it doesn't alias
+fn poly_blep(phase: f32, increment: f32) -> f32 {
+    if phase < increment {
+        let t = phase / increment;
+        t + t - t * t - 1.0
+    } else if phase > 1.0 - increment {
+        let t = (phase - 1.0) / increment;
+        t * t + t + t + 1.0
+    } else {
+        0.0
+    }
+}
+
+/// Seven band-limited saws around the note, detuned and mixed along the JP-8000's curves.
+/// One oscillator instead of seven stacked configs: the pitch math is shared and the
+/// output stays at about a single saw's level whatever the mix.
+#[derive(Clone)]
+pub struct SupersawOscillator {
+    config: OscillatorConfig,
+    sample_rate: f32,
+    frequency: f32,
+    pitch_ratio: f32,          // octave and detune, with the detune spread applied
+    phases: [f32; SAW_COUNT],
+    spread: f32,               // the detune curve's output
+    gains: [f32; SAW_COUNT],   // mix gains, scaled to the output level
+}
+
+impl SupersawOscillator {
+    pub fn new(sample_rate: f32, base_frequency: f32, config: OscillatorConfig) -> Self {
+        let mut oscillator = Self {
+            config,
+            sample_rate,
+            frequency: base_frequency * config.pitch_ratio(),
+            pitch_ratio: config.pitch_ratio(),
+            phases: [0.0; SAW_COUNT],
+            spread: 0.0,
+            gains: [0.0; SAW_COUNT],
+        };
+        oscillator.set_detune_and_mix();
+        oscillator.reset_phases();
+        oscillator
+    }
+
+    fn set_detune_and_mix(&mut self) {
+        self.spread = detune_curve(self.config.supersaw_detune);
+        let (centre, side) = mix_gains(self.config.supersaw_mix);
+        // Seven free-running saws sum like noise, so by power rather than by amplitude
+        let scale = 1.0 / (centre * centre + (SAW_COUNT - 1) as f32 * side * side).sqrt();
+        self.gains = [side * scale; SAW_COUNT];
+        self.gains[CENTRE] = centre * scale;
+    }
+
+    fn reset_phases(&mut self) {
+        let start = self.config.start_phase.rem_euclid(1.0);
+        self.phases = PHASE_SPREAD.map(|offset| (start + offset) % 1.0);
+    }
+}
+
+impl WaveformGenerator for SupersawOscillator {
+    fn next_sample(&mut self) -> f32 {
+        let base = self.frequency / self.sample_rate;
+        let mut sum = 0.0;
+        for ((phase, offset), gain) in self.phases.iter_mut().zip(OFFSETS).zip(self.gains) {
+            let increment = (base * (1.0 + offset * self.spread)).min(0.5);
+            sum += (2.0 * *phase - 1.0 - poly_blep(*phase, increment)) * gain;
+            *phase = (*phase + increment) % 1.0;
+        }
+        sum * self.config.volume
+    }
+
+    fn update_sample_rate(&mut self, new_sample_rate: f32) {
+        self.sample_rate = new_sample_rate;
+    }
+
+    fn set_frequency(&mut self, freq_hz: f32) {
+        self.frequency = freq_hz * self.pitch_ratio;
+    }
+
+    fn set_detune_spread(&mut self, spread: f32) {
+        let pitch_ratio = self.config.spread_pitch_ratio(spread);
+        self.frequency *= pitch_ratio / self.pitch_ratio;
+        self.pitch_ratio = pitch_ratio;
+    }
+
+    // Keeps the saws' phases, so turning the detune or mix knob doesn't restart them
+    fn set_config(&mut self, config: OscillatorConfig) -> bool {
+        if config.waveform != Waveform::SUPERSAW {
+            return false;
+        }
+        let pitch_ratio = config.pitch_ratio();
+        self.frequency *= pitch_ratio / self.pitch_ratio;
+        self.pitch_ratio = pitch_ratio;
+        self.config = config;
+        self.set_detune_and_mix();
+        true
+    }
+
+    fn volume(&self) -> f32 {
+        self.config.volume
+    }
+
+    fn box_clone(&self) -> Box<dyn WaveformGenerator> {
+        Box::new(self.clone())
+    }
+
+    fn retrigger(&mut self) {
+        if self.config.phase_mode == PhaseMode::Retrigger {
+            self.reset_phases();
+        }
+    }
+}
@@ -0,0 +1,124 @@
+use std::f32::consts::PI;
+use std::sync::LazyLock;
+
+pub const HALFBAND_TAPS: usize = 31;
+/// Highest factor a voice renders at.
+pub const MAX_FACTOR: usize = 4;
+
+static HALFBAND: LazyLock<[f32; HALFBAND_TAPS]> = LazyLock::new(halfband_taps);
+
+/// Rate the voices render at, as a multiple of the output rate. Oscillators, sync, FM,
+/// drive and the filters alias less the higher it is, at about that many times the CPU.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum VoiceOversampling {
+    Off,
+    X2,
+    X4,
+}
+
+impl VoiceOversampling {
+    pub const ALL: [VoiceOversampling; 3] = [VoiceOversampling::Off, VoiceOversampling::X2, VoiceOversampling::X4];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            VoiceOversampling::Off => "Off",
+            VoiceOversampling::X2 => "2x",
+            VoiceOversampling::X4 => "4x",
+        }
+    }
+
+    pub fn factor(self) -> usize {
+        match self {
+            VoiceOversampling::Off => 1,
+            VoiceOversampling::X2 => 2,
+            VoiceOversampling::X4 => 4,
+        }
+    }
+}
+
+// Windowed-sinc lowpass at a quarter of the oversampled rate, i.e. the original Nyquist
+pub fn halfband_taps() -> [f32; HALFBAND_TAPS] {
+    let centre = (HALFBAND_TAPS - 1) as f32 / 2.0;
+    let mut taps = std::array::from_fn(|n| {
+        let t = n as f32 - centre;
+        let sinc = if t == 0.0 { 1.0 } else { (0.5 * PI * t).sin() / (0.5 * PI * t) };
+        let window = 0.42 - 0.5 * (2.0 * PI * n as f32 / (HALFBAND_TAPS - 1) as f32).cos()
+            + 0.08 * (4.0 * PI * n as f32 / (HALFBAND_TAPS - 1) as f32).cos();
+        sinc * window
+    });
+    let sum: f32 = taps.iter().sum();
+    for tap in &mut taps {
+        *tap /= sum;
+    }
+    taps
+}
+
+#[derive(Clone)]
+pub struct HalfbandFir {
+    history: [f32; HALFBAND_TAPS],
+    pos: usize,
+}
+
+impl HalfbandFir {
+    pub fn new() -> Self {
+        Self { history: [0.0; HALFBAND_TAPS], pos: 0 }
+    }
+
+    pub fn process(&mut self, input: f32, taps: &[f32; HALFBAND_TAPS]) -> f32 {
+        self.history[self.pos] = input;
+        let mut out = 0.0;
+        for (i, tap) in taps.iter().enumerate() {
+            out += tap * self.history[(self.pos + HALFBAND_TAPS - i) % HALFBAND_TAPS];
+        }
+        self.pos = (self.pos + 1) % HALFBAND_TAPS;
+        out
+    }
+
+    // Filters two samples and keeps the second
+    fn decimate(&mut self, first: f32, second: f32, taps: &[f32; HALFBAND_TAPS]) -> f32 {
+        self.process(first, taps);
+        self.process(second, taps)
+    }
+}
+
+impl Default for HalfbandFir {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Brings one channel rendered at 2x or 4x back to the output rate, one halfband stage per
+/// halving. Delays the signal by 7 output samples at 2x and 10.5 at 4x, see `latency_samples`.
+#[derive(Clone, Default)]
+pub struct Decimator {
+    stages: [HalfbandFir; 2],   // the output-rate stage first
+}
+
+impl Decimator {
+    /// Takes the 1, 2 or 4 oversampled samples of one output sample, oldest first.
+    pub fn process(&mut self, samples: &[f32]) -> f32 {
+        let taps = &*HALFBAND;
+        match *samples {
+            [a, b] => self.stages[0].decimate(a, b, taps),
+            [a, b, c, d] => {
+                let first = self.stages[1].decimate(a, b, taps);
+                let second = self.stages[1].decimate(c, d, taps);
+                self.stages[0].decimate(first, second, taps)
+            }
+            _ => samples.first().copied().unwrap_or(0.0),
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.stages = Default::default();
+    }
+
+    /// Delay in output samples when bringing `factor` times the output rate back down, next
+    /// to rendering at the output rate: each stage delays by half its filter's length at the
+    /// rate it runs at, less the fraction of a frame the newest sample, the one kept, is ahead.
+    pub fn latency_samples(factor: usize) -> f32 {
+        let filter_delay = (HALFBAND_TAPS - 1) as f32 / 2.0;
+        let filters: f32 = (1..=factor.trailing_zeros()).map(|stage| filter_delay / (1 << stage) as f32).sum();
+        filters - (factor - 1) as f32 / factor as f32
+    }
+}
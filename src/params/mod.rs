@@ -0,0 +1,1414 @@
+use nih_plug::prelude::*;
+use std::sync::{Arc, OnceLock, RwLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::drift::DriftConfig;
+use crate::dynamics::OutputNormalization;
+use crate::effects::{
+    CompressorConfig, DelayConfig, DuplicateInsert, EqConfig, FlangerConfig, FxChainConfig, InsertEffect, ModulationFxConfig,
+    Oversampling, PhaserConfig, PhaserStages, ReverbConfig, SendConfig, SendOrder, SweepRate, VoiceInsertConfig,
+    VoiceInsertKind, WaveShape, WaveshaperConfig,
+};
+use crate::chord_memory::{ChordMemoryConfig, ChordShape};
+use crate::envelope::{EnvelopeConfig, ReleaseVelocityConfig, ReleaseVelocitySource, MOD_ENVELOPE_COUNT};
+use crate::filter::{FilterParameters, FilterRouting, FilterRoutingConfig, FilterSlope, FilterType, SaturationCurve};
+use crate::glide::{GlideConfig, GlideMode, GlideRate};
+use crate::keyzone::{KeyZone, KeyZoneConfig, OscillatorGroup, PartZone, ZoneMode};
+use crate::lfo::{LfoConfig, LfoShape, LFO_COUNT};
+use crate::midi_mapping::CcMapping;
+use crate::midi_monitor::note_name;
+use crate::modulation::{default_routes, ModulationRoute};
+use crate::oscillator::{Footage, Harmonics, OscillatorConfig, PhaseMode, HARMONIC_COUNT};
+use crate::preset::{CompareSlots, Preset};
+use crate::oversampling::VoiceOversampling;
+use crate::quality::QualityMode;
+use crate::sample::{SampleConfig, SampleLoop};
+use crate::sequencer::StepSequencerConfig;
+use crate::tempo::SyncDivision;
+use crate::vibrato::VibratoConfig;
+use crate::synthesizer::VoiceMode;
+use crate::voice_configuration::Waveform;
+
+mod overrides;
+mod sync;
+
+pub use overrides::ParamOverrides;
+pub use sync::PatchSync;
+
+#[derive(Params)]
+pub struct MyParams {
+    #[id = "gain"]
+    pub gain: FloatParam,
+    #[id = "normalize"]
+    pub normalization: IntParam,
+    #[id = "quality"]
+    pub quality: IntParam,
+    #[id = "oversample"]
+    pub oversampling: IntParam,
+    #[id = "master_tune"]
+    pub master_tune: FloatParam,
+    #[id = "transpose"]
+    pub transpose: IntParam,
+
+    #[nested(id_prefix = "osc1", group = "Oscillator 1")]
+    pub osc1: OscillatorParams,
+    #[nested(id_prefix = "osc2", group = "Oscillator 2")]
+    pub osc2: OscillatorParams,
+    /// Square one octave below oscillator 1.
+    #[id = "sub_level"]
+    pub sub_level: FloatParam,
+    #[nested(id_prefix = "sample", group = "Sample")]
+    pub sample: SampleParams,
+    /// Levels of the Additive waveform's harmonics, IDs `harm_1` to `harm_16`.
+    #[nested(array, group = "Harmonics")]
+    pub harmonics: [HarmonicParams; HARMONIC_COUNT],
+
+    #[id = "glide"]
+    pub glide_time: FloatParam,
+    #[id = "glide_mode"]
+    pub glide_mode: IntParam,
+    #[id = "glide_rate"]
+    pub glide_rate: IntParam,
+    #[id = "legato"]
+    pub glide_legato: BoolParam,
+    #[id = "voice_mode"]
+    pub voice_mode: IntParam,
+
+    #[id = "zone_mode"]
+    pub zone_mode: IntParam,
+    #[id = "split_key"]
+    pub split_key: IntParam,
+    #[nested(id_prefix = "zone_a", group = "Zone A")]
+    pub zone_a: ZoneParams,
+    #[nested(id_prefix = "zone_b", group = "Zone B")]
+    pub zone_b: ZoneParams,
+    /// Lowest and highest key the synth answers.
+    #[id = "key_low"]
+    pub key_low: IntParam,
+    #[id = "key_high"]
+    pub key_high: IntParam,
+
+    #[id = "chord_mem"]
+    pub chord_memory: BoolParam,
+
+    #[id = "vib_rate"]
+    pub vibrato_rate: FloatParam,
+    #[id = "vib_depth"]
+    pub vibrato_depth: FloatParam,
+    #[id = "vib_delay"]
+    pub vibrato_delay: FloatParam,
+    #[id = "vib_fade"]
+    pub vibrato_fade_in: FloatParam,
+
+    #[id = "drift"]
+    pub drift_pitch: FloatParam,
+    #[id = "drift_detune"]
+    pub drift_detune: FloatParam,
+    #[id = "drift_cutoff"]
+    pub drift_cutoff: FloatParam,
+
+    #[id = "flt_type"]
+    pub filter_type: IntParam,
+    #[id = "flt_slope"]
+    pub filter_slope: IntParam,
+    #[id = "cutoff"]
+    pub cutoff: FloatParam,
+    #[id = "res"]
+    pub resonance: FloatParam,
+    #[id = "flt_drive"]
+    pub filter_drive: FloatParam,
+    #[id = "flt_sat"]
+    pub filter_saturation: IntParam,
+
+    #[id = "flt_env"]
+    pub filter_env_amount: FloatParam,
+    #[id = "flt_keytrack"]
+    pub filter_key_tracking: FloatParam,
+    #[id = "flt_spread"]
+    pub filter_stereo_spread: FloatParam,
+
+    #[id = "flt_routing"]
+    pub filter_routing: IntParam,
+    #[id = "flt_mix"]
+    pub filter_mix: FloatParam,
+    #[id = "flt2_type"]
+    pub filter2_type: IntParam,
+    #[id = "flt2_slope"]
+    pub filter2_slope: IntParam,
+    #[id = "cutoff2"]
+    pub cutoff2: FloatParam,
+    #[id = "res2"]
+    pub resonance2: FloatParam,
+    #[id = "flt2_env"]
+    pub filter2_env_amount: FloatParam,
+
+    #[nested(group = "Amp Envelope")]
+    pub amp_envelope: EnvelopeParams,
+    #[nested(id_prefix = "fenv", group = "Filter Envelope")]
+    pub filter_envelope: EnvelopeParams,
+    #[nested(id_prefix = "menv3", group = "Mod Envelope 3")]
+    pub mod_envelope3: EnvelopeParams,
+    #[nested(id_prefix = "menv4", group = "Mod Envelope 4")]
+    pub mod_envelope4: EnvelopeParams,
+    #[id = "rel_vel_src"]
+    pub release_velocity_source: IntParam,
+    #[id = "rel_vel_amount"]
+    pub release_velocity_amount: FloatParam,
+    #[id = "rel_vel_fixed"]
+    pub release_velocity_fixed: FloatParam,
+    #[id = "menv3_loop"]
+    pub mod_envelope3_loop: BoolParam,
+    #[id = "menv4_loop"]
+    pub mod_envelope4_loop: BoolParam,
+
+    #[id = "audition"]
+    pub audition: BoolParam,
+    #[id = "dc_voice"]
+    pub voice_dc_blocking: BoolParam,
+    #[id = "osc_gain_comp"]
+    pub oscillator_gain_compensation: BoolParam,
+    #[id = "detune_spread"]
+    pub detune_spread: FloatParam,
+    #[id = "vfx_type"]
+    pub voice_insert: IntParam,
+    #[id = "vfx_amount"]
+    pub voice_insert_amount: FloatParam,
+
+    #[id = "send_delay"]
+    pub delay_send: FloatParam,
+    #[id = "send_reverb"]
+    pub reverb_send: FloatParam,
+    #[id = "send_vel"]
+    pub send_velocity_scaling: FloatParam,
+    #[id = "send_key"]
+    pub send_key_scaling: FloatParam,
+    #[id = "dly_on"]
+    pub delay_enabled: BoolParam,
+    #[id = "dly_time"]
+    pub delay_time: FloatParam,
+    #[id = "dly_fb"]
+    pub delay_feedback: FloatParam,
+    #[id = "dly_sync"]
+    pub delay_tempo_sync: BoolParam,
+    #[id = "dly_div"]
+    pub delay_division: IntParam,
+    #[id = "dly_pingpong"]
+    pub delay_ping_pong: BoolParam,
+    #[id = "dly_lowcut"]
+    pub delay_low_cut: FloatParam,
+    #[id = "dly_highcut"]
+    pub delay_high_cut: FloatParam,
+    #[id = "rev_on"]
+    pub reverb_enabled: BoolParam,
+    #[id = "rev_size"]
+    pub reverb_size: FloatParam,
+    #[id = "rev_damp"]
+    pub reverb_damping: FloatParam,
+    #[id = "rev_predelay"]
+    pub reverb_pre_delay: FloatParam,
+    #[id = "rev_width"]
+    pub reverb_width: FloatParam,
+    #[id = "rev_mix"]
+    pub reverb_mix: FloatParam,
+    #[nested(id_prefix = "ws", group = "Waveshaper")]
+    pub waveshaper: WaveshaperParams,
+    #[nested(id_prefix = "vws", group = "Voice Waveshaper")]
+    pub voice_waveshaper: WaveshaperParams,
+    #[nested(id_prefix = "phs", group = "Phaser")]
+    pub phaser: PhaserParams,
+    #[nested(id_prefix = "flg", group = "Flanger")]
+    pub flanger: FlangerParams,
+
+    #[id = "fx_slot1"]
+    pub fx_slot1: IntParam,
+    #[id = "fx_slot2"]
+    pub fx_slot2: IntParam,
+    #[id = "fx_slot3"]
+    pub fx_slot3: IntParam,
+    #[id = "fx_slot4"]
+    pub fx_slot4: IntParam,
+    #[id = "fx_slot5"]
+    pub fx_slot5: IntParam,
+    #[id = "send_order"]
+    pub send_order: IntParam,
+
+    #[id = "eq_on"]
+    pub eq_enabled: BoolParam,
+    #[id = "eq_low_freq"]
+    pub eq_low_freq: FloatParam,
+    #[id = "eq_low_gain"]
+    pub eq_low_gain: FloatParam,
+    #[id = "eq_mid_freq"]
+    pub eq_mid_freq: FloatParam,
+    #[id = "eq_mid_gain"]
+    pub eq_mid_gain: FloatParam,
+    #[id = "eq_mid_q"]
+    pub eq_mid_q: FloatParam,
+    #[id = "eq_high_freq"]
+    pub eq_high_freq: FloatParam,
+    #[id = "eq_high_gain"]
+    pub eq_high_gain: FloatParam,
+    #[id = "comp_on"]
+    pub compressor_enabled: BoolParam,
+    #[id = "comp_thresh"]
+    pub compressor_threshold: FloatParam,
+    #[id = "comp_ratio"]
+    pub compressor_ratio: FloatParam,
+    #[id = "comp_attack"]
+    pub compressor_attack: FloatParam,
+    #[id = "comp_release"]
+    pub compressor_release: FloatParam,
+    #[id = "comp_makeup"]
+    pub compressor_makeup: FloatParam,
+
+    #[id = "seq_on"]
+    pub sequencer_enabled: BoolParam,
+    #[id = "seq_notes"]
+    pub sequencer_notes: BoolParam,
+    #[id = "seq_glide"]
+    pub sequencer_glide: FloatParam,
+    #[id = "seq_swing"]
+    pub sequencer_swing: FloatParam,
+    #[id = "seq_gate"]
+    pub sequencer_gate: FloatParam,
+    #[id = "seq_human"]
+    pub sequencer_humanize: FloatParam,
+    #[id = "seq_div"]
+    pub sequencer_division: IntParam,
+
+    #[nested(id_prefix = "lfo1", group = "LFO 1")]
+    pub lfo1: LfoParams,
+    #[nested(id_prefix = "lfo2", group = "LFO 2")]
+    pub lfo2: LfoParams,
+
+    #[id = "mod_freeze"]
+    pub freeze_modulation: BoolParam,
+
+    /// The patch's mod matrix, edited from the GUI and stored with the plugin state.
+    #[persist = "mod_routes"]
+    pub modulation_routes: RwLock<Vec<ModulationRoute>>,
+
+    /// Seed of the random oscillator tables, picked once per instance and saved with it so a
+    /// project sounds the same on reload.
+    #[persist = "noise_seed"]
+    pub noise_seed: RwLock<u64>,
+
+    /// Titles of the editor sections the user collapsed, so reopening the GUI looks the same.
+    #[persist = "collapsed_sections"]
+    pub collapsed_sections: RwLock<Vec<String>>,
+
+    /// Language code of the GUI text, e.g. "en".
+    #[persist = "language"]
+    pub language: RwLock<String>,
+
+    /// The preset last loaded or saved, kept with the project so unsaved edits are still
+    /// flagged after a reload.
+    #[persist = "patch_baseline"]
+    pub patch_baseline: RwLock<Option<Preset>>,
+
+    /// Controller mappings that belong to the current patch; the global controller setup
+    /// lives in its own file.
+    #[persist = "cc_mappings"]
+    pub preset_cc_mappings: RwLock<Vec<CcMapping>>,
+
+    /// The captured chord-memory shape, as semitones above its lowest note.
+    #[persist = "chord_shape"]
+    pub chord_shape: RwLock<Vec<u8>>,
+
+    /// The A/B compare snapshots, kept with the project like the baseline.
+    #[persist = "compare_slots"]
+    pub compare_slots: RwLock<CompareSlots>,
+
+    /// The sample layer's WAV file, or `None` for no sample layer.
+    #[persist = "sample_path"]
+    pub sample_path: RwLock<Option<String>>,
+
+    /// Values MIDI set on the audio thread; see `overrides`.
+    overrides: OnceLock<ParamOverrides>,
+}
+
+#[derive(Params)]
+pub struct OscillatorParams {
+    #[id = "wave"]
+    pub waveform: IntParam,
+    #[id = "octave"]
+    pub octave: IntParam,
+    #[id = "detune"]
+    pub detune: FloatParam,
+    #[id = "volume"]
+    pub volume: FloatParam,
+    #[id = "phase"]
+    pub phase: FloatParam,
+    #[id = "phase_mode"]
+    pub phase_mode: IntParam,
+    /// Only heard with the Pluck waveform.
+    #[id = "damping"]
+    pub damping: FloatParam,
+    /// Only heard with the Supersaw waveform, as is the mix.
+    #[id = "ss_detune"]
+    pub supersaw_detune: FloatParam,
+    #[id = "ss_mix"]
+    pub supersaw_mix: FloatParam,
+    #[id = "mute"]
+    pub mute: BoolParam,
+    #[id = "solo"]
+    pub solo: BoolParam,
+}
+
+impl OscillatorParams {
+    fn new(waveform: Waveform, volume: f32) -> Self {
+        Self {
+            waveform: choice_param("Waveform", &Waveform::ALL, waveform, Waveform::label),
+            octave: choice_param("Octave", &Footage::ALL, Footage::Feet8, Footage::label),
+            detune: FloatParam::new(
+                "Detune",
+                0.0,
+                FloatRange::Linear { min: -24.0, max: 24.0 },
+            )
+            .with_step_size(0.01)
+            .with_unit(" st"),
+            volume: percentage_param("Volume", volume),
+            phase: FloatParam::new("Phase", 0.0, FloatRange::Linear { min: 0.0, max: 360.0 })
+                .with_step_size(1.0)
+                .with_unit("°"),
+            phase_mode: choice_param("Phase Mode", &PhaseMode::ALL, PhaseMode::FreeRun, PhaseMode::label),
+            damping: percentage_param("Damping", 0.5),
+            supersaw_detune: percentage_param("Saw Detune", 0.5),
+            supersaw_mix: percentage_param("Saw Mix", 0.5),
+            mute: BoolParam::new("Mute", false),
+            solo: BoolParam::new("Solo", false),
+        }
+    }
+
+    pub fn config(&self, overrides: &ParamOverrides) -> OscillatorConfig {
+        OscillatorConfig {
+            waveform: choice(&Waveform::ALL, overrides.value(&self.waveform)),
+            octave: choice(&Footage::ALL, overrides.value(&self.octave)),
+            detune_semitones: overrides.value(&self.detune),
+            volume: overrides.value(&self.volume),
+            start_phase: overrides.value(&self.phase) / 360.0,
+            phase_mode: choice(&PhaseMode::ALL, overrides.value(&self.phase_mode)),
+            damping: overrides.value(&self.damping),
+            supersaw_detune: overrides.value(&self.supersaw_detune),
+            supersaw_mix: overrides.value(&self.supersaw_mix),
+            muted: overrides.value(&self.mute),
+            solo: overrides.value(&self.solo),
+        }
+    }
+}
+
+#[derive(Params)]
+pub struct HarmonicParams {
+    #[id = "harm"]
+    pub level: FloatParam,
+}
+
+impl HarmonicParams {
+    fn new(index: usize, level: f32) -> Self {
+        Self {
+            level: percentage_param(&format!("Harmonic {}", index + 1), level),
+        }
+    }
+}
+
+#[derive(Params)]
+pub struct SampleParams {
+    #[id = "level"]
+    pub level: FloatParam,
+    #[id = "root"]
+    pub root_note: IntParam,
+    #[id = "start"]
+    pub start: FloatParam,
+    #[id = "loop"]
+    pub loop_mode: IntParam,
+    #[id = "loop_start"]
+    pub loop_start: FloatParam,
+    #[id = "loop_end"]
+    pub loop_end: FloatParam,
+}
+
+impl SampleParams {
+    fn new() -> Self {
+        let defaults = SampleConfig::default();
+        Self {
+            level: percentage_param("Sample Level", defaults.level),
+            root_note: key_param("Root Key", defaults.root_note as i32),
+            start: percentage_param("Sample Start", defaults.start),
+            loop_mode: choice_param("Sample Loop", &SampleLoop::ALL, defaults.loop_mode, SampleLoop::label),
+            loop_start: percentage_param("Loop Start", defaults.loop_start),
+            loop_end: percentage_param("Loop End", defaults.loop_end),
+        }
+    }
+
+    pub fn config(&self, overrides: &ParamOverrides) -> SampleConfig {
+        SampleConfig {
+            level: overrides.value(&self.level),
+            root_note: overrides.value(&self.root_note) as u8,
+            start: overrides.value(&self.start),
+            loop_mode: choice(&SampleLoop::ALL, overrides.value(&self.loop_mode)),
+            loop_start: overrides.value(&self.loop_start),
+            loop_end: overrides.value(&self.loop_end).max(overrides.value(&self.loop_start)),
+        }
+    }
+}
+
+#[derive(Params)]
+pub struct ZoneParams {
+    #[id = "oscs"]
+    pub oscillators: IntParam,
+    #[id = "transpose"]
+    pub transpose: IntParam,
+    #[id = "level"]
+    pub level: FloatParam,
+}
+
+impl ZoneParams {
+    fn new(oscillators: OscillatorGroup) -> Self {
+        Self {
+            oscillators: choice_param("Oscillators", &OscillatorGroup::ALL, oscillators, OscillatorGroup::label),
+            transpose: IntParam::new("Transpose", 0, IntRange::Linear { min: -24, max: 24 }).with_unit(" st"),
+            level: percentage_param("Level", 1.0),
+        }
+    }
+
+    pub fn config(&self, overrides: &ParamOverrides) -> KeyZone {
+        KeyZone {
+            oscillators: choice(&OscillatorGroup::ALL, overrides.value(&self.oscillators)),
+            transpose_semitones: overrides.value(&self.transpose),
+            level: overrides.value(&self.level),
+        }
+    }
+}
+
+#[derive(Params)]
+pub struct WaveshaperParams {
+    #[id = "on"]
+    pub enabled: BoolParam,
+    #[id = "drive"]
+    pub drive: FloatParam,
+    #[id = "shape"]
+    pub shape: IntParam,
+    #[id = "tone"]
+    pub tone: FloatParam,
+    #[id = "os"]
+    pub oversampling: IntParam,
+}
+
+impl WaveshaperParams {
+    fn new(name: &str) -> Self {
+        let defaults = WaveshaperConfig::default();
+        Self {
+            enabled: BoolParam::new(name, false),
+            drive: percentage_param(&format!("{} Drive", name), defaults.drive),
+            shape: choice_param(&format!("{} Shape", name), &WaveShape::ALL, defaults.shape, WaveShape::label),
+            tone: percentage_param(&format!("{} Tone", name), defaults.tone),
+            oversampling: choice_param(
+                &format!("{} Oversampling", name),
+                &Oversampling::ALL,
+                defaults.oversampling,
+                Oversampling::label,
+            ),
+        }
+    }
+
+    pub fn config(&self, overrides: &ParamOverrides) -> WaveshaperConfig {
+        WaveshaperConfig {
+            enabled: overrides.value(&self.enabled),
+            drive: overrides.value(&self.drive),
+            shape: choice(&WaveShape::ALL, overrides.value(&self.shape)),
+            tone: overrides.value(&self.tone),
+            oversampling: choice(&Oversampling::ALL, overrides.value(&self.oversampling)),
+        }
+    }
+}
+
+#[derive(Params)]
+pub struct PhaserParams {
+    #[id = "on"]
+    pub enabled: BoolParam,
+    #[id = "stages"]
+    pub stages: IntParam,
+    #[id = "rate"]
+    pub rate: FloatParam,
+    #[id = "sync"]
+    pub tempo_sync: BoolParam,
+    #[id = "div"]
+    pub division: IntParam,
+    #[id = "depth"]
+    pub depth: FloatParam,
+    #[id = "fb"]
+    pub feedback: FloatParam,
+    #[id = "mix"]
+    pub mix: FloatParam,
+}
+
+impl PhaserParams {
+    fn new() -> Self {
+        let defaults = PhaserConfig::default();
+        Self {
+            enabled: BoolParam::new("Phaser", defaults.enabled),
+            stages: choice_param("Phaser Stages", &PhaserStages::ALL, defaults.stages, PhaserStages::label),
+            rate: sweep_rate_param("Phaser Rate", defaults.rate.hz),
+            tempo_sync: BoolParam::new("Phaser Sync", defaults.rate.tempo_sync),
+            division: choice_param("Phaser Division", &SyncDivision::ALL, defaults.rate.division, SyncDivision::label),
+            depth: percentage_param("Phaser Depth", defaults.depth),
+            feedback: FloatParam::new(
+                "Phaser Feedback",
+                defaults.feedback,
+                FloatRange::Linear { min: 0.0, max: 0.9 },
+            )
+            .with_unit("%")
+            .with_value_to_string(formatters::v2s_f32_percentage(0))
+            .with_string_to_value(formatters::s2v_f32_percentage()),
+            mix: percentage_param("Phaser Mix", defaults.mix),
+        }
+    }
+
+    pub fn config(&self, overrides: &ParamOverrides) -> PhaserConfig {
+        PhaserConfig {
+            enabled: overrides.value(&self.enabled),
+            stages: choice(&PhaserStages::ALL, overrides.value(&self.stages)),
+            rate: SweepRate {
+                hz: overrides.value(&self.rate),
+                tempo_sync: overrides.value(&self.tempo_sync),
+                division: choice(&SyncDivision::ALL, overrides.value(&self.division)),
+            },
+            depth: overrides.value(&self.depth),
+            feedback: overrides.value(&self.feedback),
+            mix: overrides.value(&self.mix),
+        }
+    }
+}
+
+#[derive(Params)]
+pub struct FlangerParams {
+    #[id = "on"]
+    pub enabled: BoolParam,
+    #[id = "rate"]
+    pub rate: FloatParam,
+    #[id = "sync"]
+    pub tempo_sync: BoolParam,
+    #[id = "div"]
+    pub division: IntParam,
+    #[id = "delay"]
+    pub delay: FloatParam,
+    #[id = "depth"]
+    pub depth: FloatParam,
+    #[id = "fb"]
+    pub feedback: FloatParam,
+    #[id = "mix"]
+    pub mix: FloatParam,
+}
+
+impl FlangerParams {
+    fn new() -> Self {
+        let defaults = FlangerConfig::default();
+        Self {
+            enabled: BoolParam::new("Flanger", defaults.enabled),
+            rate: sweep_rate_param("Flanger Rate", defaults.rate.hz),
+            tempo_sync: BoolParam::new("Flanger Sync", defaults.rate.tempo_sync),
+            division: choice_param("Flanger Division", &SyncDivision::ALL, defaults.rate.division, SyncDivision::label),
+            delay: FloatParam::new(
+                "Flanger Delay",
+                defaults.delay_ms,
+                FloatRange::Skewed { min: 0.5, max: 10.0, factor: FloatRange::skew_factor(-1.0) },
+            )
+            .with_value_to_string(formatters::v2s_f32_rounded(2))
+            .with_unit(" ms"),
+            depth: percentage_param("Flanger Depth", defaults.depth),
+            feedback: FloatParam::new(
+                "Flanger Feedback",
+                defaults.feedback,
+                FloatRange::Linear { min: -0.95, max: 0.95 },
+            )
+            .with_unit("%")
+            .with_value_to_string(formatters::v2s_f32_percentage(0))
+            .with_string_to_value(formatters::s2v_f32_percentage()),
+            mix: percentage_param("Flanger Mix", defaults.mix),
+        }
+    }
+
+    pub fn config(&self, overrides: &ParamOverrides) -> FlangerConfig {
+        FlangerConfig {
+            enabled: overrides.value(&self.enabled),
+            rate: SweepRate {
+                hz: overrides.value(&self.rate),
+                tempo_sync: overrides.value(&self.tempo_sync),
+                division: choice(&SyncDivision::ALL, overrides.value(&self.division)),
+            },
+            delay_ms: overrides.value(&self.delay),
+            depth: overrides.value(&self.depth),
+            feedback: overrides.value(&self.feedback),
+            mix: overrides.value(&self.mix),
+        }
+    }
+}
+
+#[derive(Params)]
+pub struct LfoParams {
+    #[id = "shape"]
+    pub shape: IntParam,
+    #[id = "rate"]
+    pub division: IntParam,
+    #[id = "phase"]
+    pub phase_offset: FloatParam,
+    #[id = "seed"]
+    pub seed: IntParam,
+}
+
+impl LfoParams {
+    fn new(seed: i32) -> Self {
+        Self {
+            shape: choice_param("LFO Shape", &LfoShape::ALL, LfoShape::Sine, LfoShape::label),
+            division: choice_param("LFO Rate", &SyncDivision::ALL, SyncDivision::Quarter, SyncDivision::label),
+            phase_offset: FloatParam::new(
+                "LFO Phase",
+                0.0,
+                FloatRange::Linear { min: 0.0, max: 360.0 },
+            )
+            .with_step_size(1.0)
+            .with_unit("°"),
+            seed: IntParam::new("LFO Seed", seed, IntRange::Linear { min: 1, max: 999 }),
+        }
+    }
+
+    pub fn config(&self, overrides: &ParamOverrides) -> LfoConfig {
+        LfoConfig {
+            shape: choice(&LfoShape::ALL, overrides.value(&self.shape)),
+            division: choice(&SyncDivision::ALL, overrides.value(&self.division)),
+            phase_offset_degrees: overrides.value(&self.phase_offset),
+            seed: overrides.value(&self.seed) as u64,
+        }
+    }
+}
+
+#[derive(Params)]
+pub struct EnvelopeParams {
+    #[id = "attack"]
+    pub attack: FloatParam,
+    #[id = "decay"]
+    pub decay: FloatParam,
+    #[id = "sustain"]
+    pub sustain: FloatParam,
+    #[id = "release"]
+    pub release: FloatParam,
+}
+
+impl EnvelopeParams {
+    fn new(attack: f32, decay: f32, sustain: f32, release: f32) -> Self {
+        Self {
+            attack: envelope_time_param("Attack", attack),
+            decay: envelope_time_param("Decay", decay),
+            sustain: percentage_param("Sustain", sustain),
+            release: envelope_time_param("Release", release),
+        }
+    }
+
+    /// The parameter ranges only hold valid times, so the default is never reached in practice.
+    pub fn config(&self, overrides: &ParamOverrides) -> EnvelopeConfig {
+        EnvelopeConfig::new(
+            overrides.value(&self.attack),
+            overrides.value(&self.decay),
+            overrides.value(&self.sustain),
+            overrides.value(&self.release),
+            false,
+        )
+        .unwrap_or_default()
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum EnvelopeKind {
+    Amp,
+    Filter,
+    ModEnv3,
+    ModEnv4,
+}
+
+impl Default for MyParams {
+    fn default() -> Self {
+        let delay_defaults = DelayConfig::default();
+        let vibrato_defaults = VibratoConfig::default();
+        Self {
+            gain: FloatParam::new(
+                "Gain",
+                0.8,
+                unit_range(),
+            )
+            .with_unit("%")
+            .with_value_to_string(formatters::v2s_f32_percentage(2))
+            .with_string_to_value(formatters::s2v_f32_percentage()),
+            normalization: choice_param("Output Level", &OutputNormalization::ALL, OutputNormalization::FixedHeadroom, OutputNormalization::label),
+            quality: choice_param("Quality", &QualityMode::ALL, QualityMode::Normal, QualityMode::label),
+            oversampling: choice_param("Oversampling", &VoiceOversampling::ALL, VoiceOversampling::Off, VoiceOversampling::label),
+            master_tune: FloatParam::new(
+                "Master Tune",
+                0.0,
+                FloatRange::Linear { min: -100.0, max: 100.0 },
+            )
+            .with_step_size(0.1)
+            .with_unit(" ct"),
+            transpose: IntParam::new("Transpose", 0, IntRange::Linear { min: -24, max: 24 }).with_unit(" st"),
+
+            osc1: OscillatorParams::new(Waveform::SAW, 1.0),
+            osc2: OscillatorParams::new(Waveform::SQUARE, 0.0),
+            sub_level: percentage_param("Sub Level", 0.0),
+            sample: SampleParams::new(),
+            harmonics: std::array::from_fn(|i| HarmonicParams::new(i, Harmonics::default().0[i])),
+
+            glide_time: FloatParam::new(
+                "Glide Time",
+                0.0,
+                glide_time_range(),
+            )
+            .with_unit(" s")
+            .with_value_to_string(formatters::v2s_f32_rounded(3)),
+            glide_mode: choice_param("Glide Mode", &GlideMode::ALL, GlideMode::Smooth, GlideMode::label),
+            glide_rate: choice_param("Glide Rate", &GlideRate::ALL, GlideRate::ConstantTime, GlideRate::label),
+            glide_legato: BoolParam::new("Legato Glide", false),
+            voice_mode: choice_param("Voice Mode", &VoiceMode::ALL, VoiceMode::Poly, VoiceMode::label),
+
+            zone_mode: choice_param("Zone Mode", &ZoneMode::ALL, ZoneMode::Off, ZoneMode::label),
+            split_key: key_param("Split Key", 60),
+            zone_a: ZoneParams::new(OscillatorGroup::Osc1),
+            zone_b: ZoneParams::new(OscillatorGroup::Osc2),
+            key_low: key_param("Key Low", 0),
+            key_high: key_param("Key High", 127),
+
+            chord_memory: BoolParam::new("Chord Memory", false),
+
+            vibrato_rate: FloatParam::new(
+                "Vibrato Rate",
+                vibrato_defaults.rate_hz,
+                FloatRange::Skewed { min: 0.5, max: 12.0, factor: FloatRange::skew_factor(-1.0) },
+            )
+            .with_unit(" Hz")
+            .with_value_to_string(formatters::v2s_f32_rounded(2)),
+            vibrato_depth: FloatParam::new(
+                "Vibrato Depth",
+                vibrato_defaults.depth_semitones,
+                FloatRange::Skewed { min: 0.0, max: 2.0, factor: FloatRange::skew_factor(-1.0) },
+            )
+            .with_unit(" st")
+            .with_value_to_string(formatters::v2s_f32_rounded(2)),
+            vibrato_delay: vibrato_time_param("Vibrato Delay", vibrato_defaults.delay_secs),
+            vibrato_fade_in: vibrato_time_param("Vibrato Fade In", vibrato_defaults.fade_in_secs),
+
+            drift_pitch: cents_param("Pitch Drift", 0.0),
+            drift_detune: cents_param("Note Detune Spread", 0.0),
+            drift_cutoff: FloatParam::new(
+                "Cutoff Wobble",
+                0.0,
+                FloatRange::Linear { min: 0.0, max: 0.5 },
+            )
+            .with_step_size(0.01)
+            .with_unit(" oct"),
+
+            filter_type: choice_param("Filter Type", &FilterType::ALL, FilterType::LowPass, FilterType::label),
+            filter_slope: choice_param("Filter Slope", &FilterSlope::ALL, FilterSlope::Slope24dB, FilterSlope::label),
+            cutoff: FloatParam::new(
+                "Cutoff",
+                2000.0,
+                cutoff_range(),
+            )
+            .with_unit(" Hz")
+            .with_value_to_string(formatters::v2s_f32_hz_then_khz(1))
+            .with_string_to_value(formatters::s2v_f32_hz_then_khz()),
+            resonance: FloatParam::new(
+                "Resonance",
+                0.8,
+                resonance_range(),
+            ),
+
+            filter_drive: percentage_param("Filter Drive", 0.0),
+            filter_saturation: choice_param("Saturation", &SaturationCurve::ALL, SaturationCurve::Tanh, SaturationCurve::label),
+            filter_env_amount: percentage_param("Filter Env Amount", 0.2),
+            filter_key_tracking: percentage_param("Filter Key Tracking", 0.0),
+            filter_stereo_spread: FloatParam::new(
+                "Filter Stereo Spread",
+                0.0,
+                FloatRange::Linear { min: 0.0, max: 2.0 },
+            )
+            .with_step_size(0.01)
+            .with_unit(" oct"),
+
+            filter_routing: choice_param("Filter Routing", &FilterRouting::ALL, FilterRouting::Single, FilterRouting::label),
+            filter_mix: percentage_param("Filter Mix", 0.5),
+            filter2_type: choice_param("Filter 2 Type", &FilterType::ALL, FilterType::HighPass, FilterType::label),
+            filter2_slope: choice_param("Filter 2 Slope", &FilterSlope::ALL, FilterSlope::Slope12dB, FilterSlope::label),
+            cutoff2: FloatParam::new(
+                "Cutoff 2",
+                200.0,
+                cutoff_range(),
+            )
+            .with_unit(" Hz")
+            .with_value_to_string(formatters::v2s_f32_hz_then_khz(1))
+            .with_string_to_value(formatters::s2v_f32_hz_then_khz()),
+            resonance2: FloatParam::new(
+                "Resonance 2",
+                0.7,
+                resonance_range(),
+            ),
+            filter2_env_amount: percentage_param("Filter 2 Env Amount", 0.0),
+
+            amp_envelope: EnvelopeParams::new(0.01, 0.3, 0.7, 0.5),
+            filter_envelope: EnvelopeParams::new(0.01, 0.3, 0.7, 0.5),
+            mod_envelope3: EnvelopeParams::new(0.2, 0.2, 0.0, 0.3),
+            mod_envelope4: EnvelopeParams::new(0.2, 0.2, 0.0, 0.3),
+            release_velocity_source: choice_param(
+                "Release Velocity Source",
+                &ReleaseVelocitySource::ALL,
+                ReleaseVelocitySource::NoteOff,
+                ReleaseVelocitySource::label,
+            ),
+            release_velocity_amount: percentage_param("Release Velocity Amount", 0.0),
+            release_velocity_fixed: percentage_param("Fixed Release Velocity", 0.5),
+            mod_envelope3_loop: BoolParam::new("Mod Env 3 Loop", false),
+            mod_envelope4_loop: BoolParam::new("Mod Env 4 Loop", false),
+
+            audition: BoolParam::new("Audition On Edit", false),
+            voice_dc_blocking: BoolParam::new("Voice DC Blocker", false),
+            oscillator_gain_compensation: BoolParam::new("Osc Gain Compensation", true),
+            detune_spread: FloatParam::new(
+                "Detune Spread",
+                1.0,
+                FloatRange::Linear { min: 0.0, max: 2.0 },
+            )
+            .with_unit("%")
+            .with_value_to_string(formatters::v2s_f32_percentage(0))
+            .with_string_to_value(formatters::s2v_f32_percentage()),
+            voice_insert: choice_param("Voice FX", &VoiceInsertKind::ALL, VoiceInsertKind::Off, VoiceInsertKind::label),
+            voice_insert_amount: percentage_param("Voice FX Amount", 0.5),
+
+            delay_send: percentage_param("Delay Send", 0.0),
+            reverb_send: percentage_param("Reverb Send", 0.0),
+            send_velocity_scaling: bipolar_percentage_param("Send Velocity Scaling"),
+            send_key_scaling: bipolar_percentage_param("Send Key Scaling"),
+            delay_enabled: BoolParam::new("Delay", true),
+            delay_time: FloatParam::new(
+                "Delay Time",
+                0.375,
+                FloatRange::Skewed { min: 0.01, max: 2.0, factor: FloatRange::skew_factor(-1.0) },
+            )
+            .with_unit(" s")
+            .with_value_to_string(formatters::v2s_f32_rounded(3)),
+            delay_feedback: FloatParam::new(
+                "Delay Feedback",
+                0.35,
+                delay_feedback_range(),
+            )
+            .with_unit("%")
+            .with_value_to_string(formatters::v2s_f32_percentage(0))
+            .with_string_to_value(formatters::s2v_f32_percentage()),
+            delay_tempo_sync: BoolParam::new("Delay Sync", delay_defaults.tempo_sync),
+            delay_division: choice_param("Delay Division", &SyncDivision::ALL, delay_defaults.division, SyncDivision::label),
+            delay_ping_pong: BoolParam::new("Ping-Pong", delay_defaults.ping_pong),
+            delay_low_cut: eq_freq_param("Delay Low Cut", delay_defaults.low_cut_hz, 20.0, 2000.0),
+            delay_high_cut: eq_freq_param("Delay High Cut", delay_defaults.high_cut_hz, 1000.0, 20000.0),
+            reverb_enabled: BoolParam::new("Reverb", true),
+            reverb_size: percentage_param("Reverb Size", 0.6),
+            reverb_damping: percentage_param("Reverb Damping", 0.4),
+            reverb_pre_delay: FloatParam::new(
+                "Reverb Pre-Delay",
+                0.0,
+                FloatRange::Skewed { min: 0.0, max: 0.25, factor: FloatRange::skew_factor(-1.0) },
+            )
+            .with_unit(" s")
+            .with_value_to_string(formatters::v2s_f32_rounded(3)),
+            reverb_width: percentage_param("Reverb Width", 1.0),
+            reverb_mix: percentage_param("Reverb Mix", 1.0),
+            waveshaper: WaveshaperParams::new("Waveshaper"),
+            voice_waveshaper: WaveshaperParams::new("Voice Waveshaper"),
+            phaser: PhaserParams::new(),
+            flanger: FlangerParams::new(),
+
+            fx_slot1: fx_slot_param(1),
+            fx_slot2: fx_slot_param(2),
+            fx_slot3: fx_slot_param(3),
+            fx_slot4: fx_slot_param(4),
+            fx_slot5: fx_slot_param(5),
+            send_order: choice_param("Send Order", &SendOrder::ALL, SendOrder::Parallel, SendOrder::label),
+
+            eq_enabled: BoolParam::new("EQ", false),
+            eq_low_freq: eq_freq_param("EQ Low Freq", 120.0, 20.0, 1000.0),
+            eq_low_gain: eq_gain_param("EQ Low Gain"),
+            eq_mid_freq: eq_freq_param("EQ Mid Freq", 1000.0, 100.0, 10000.0),
+            eq_mid_gain: eq_gain_param("EQ Mid Gain"),
+            eq_mid_q: FloatParam::new(
+                "EQ Mid Q",
+                0.7,
+                FloatRange::Skewed { min: 0.3, max: 8.0, factor: FloatRange::skew_factor(-1.0) },
+            )
+            .with_value_to_string(formatters::v2s_f32_rounded(2)),
+            eq_high_freq: eq_freq_param("EQ High Freq", 6000.0, 1000.0, 20000.0),
+            eq_high_gain: eq_gain_param("EQ High Gain"),
+            compressor_enabled: BoolParam::new("Compressor", false),
+            compressor_threshold: FloatParam::new(
+                "Comp Threshold",
+                -18.0,
+                FloatRange::Linear { min: -60.0, max: 0.0 },
+            )
+            .with_step_size(0.1)
+            .with_unit(" dB"),
+            compressor_ratio: FloatParam::new(
+                "Comp Ratio",
+                4.0,
+                FloatRange::Skewed { min: 1.0, max: 20.0, factor: FloatRange::skew_factor(-1.0) },
+            )
+            .with_value_to_string(formatters::v2s_f32_rounded(1))
+            .with_unit(":1"),
+            compressor_attack: FloatParam::new(
+                "Comp Attack",
+                0.01,
+                FloatRange::Skewed { min: 0.0001, max: 0.2, factor: FloatRange::skew_factor(-2.0) },
+            )
+            .with_unit(" s")
+            .with_value_to_string(formatters::v2s_f32_rounded(4)),
+            compressor_release: FloatParam::new(
+                "Comp Release",
+                0.15,
+                FloatRange::Skewed { min: 0.01, max: 2.0, factor: FloatRange::skew_factor(-1.0) },
+            )
+            .with_unit(" s")
+            .with_value_to_string(formatters::v2s_f32_rounded(3)),
+            compressor_makeup: FloatParam::new(
+                "Comp Makeup",
+                0.0,
+                FloatRange::Linear { min: 0.0, max: 24.0 },
+            )
+            .with_step_size(0.1)
+            .with_unit(" dB"),
+
+            sequencer_enabled: BoolParam::new("Sequencer", false),
+            sequencer_notes: BoolParam::new("Sequencer Notes", false),
+            sequencer_glide: percentage_param("Sequencer Glide", 0.0),
+            sequencer_swing: FloatParam::new(
+                "Sequencer Swing",
+                0.0,
+                FloatRange::Linear { min: 0.0, max: 0.5 },
+            )
+            .with_unit("%")
+            .with_value_to_string(formatters::v2s_f32_percentage(0))
+            .with_string_to_value(formatters::s2v_f32_percentage()),
+            sequencer_gate: FloatParam::new(
+                "Sequencer Gate",
+                0.5,
+                FloatRange::Linear { min: 0.05, max: 1.0 },
+            )
+            .with_unit("%")
+            .with_value_to_string(formatters::v2s_f32_percentage(0))
+            .with_string_to_value(formatters::s2v_f32_percentage()),
+            sequencer_humanize: FloatParam::new(
+                "Sequencer Humanize",
+                0.0,
+                FloatRange::Linear { min: 0.0, max: 0.5 },
+            )
+            .with_unit("%")
+            .with_value_to_string(formatters::v2s_f32_percentage(0))
+            .with_string_to_value(formatters::s2v_f32_percentage()),
+            sequencer_division: choice_param("Sequencer Rate", &SyncDivision::ALL, SyncDivision::Sixteenth, SyncDivision::label),
+
+            lfo1: LfoParams::new(1),
+            lfo2: LfoParams::new(2),
+
+            freeze_modulation: BoolParam::new("Freeze Mod On Release", false),
+            modulation_routes: RwLock::new(default_routes()),
+            noise_seed: RwLock::new(fresh_noise_seed()),
+            collapsed_sections: RwLock::new(Vec::new()),
+            language: RwLock::new("en".to_string()),
+            patch_baseline: RwLock::new(None),
+            preset_cc_mappings: RwLock::new(Vec::new()),
+            chord_shape: RwLock::new(vec![0]),
+            compare_slots: RwLock::new(CompareSlots::default()),
+            sample_path: RwLock::new(None),
+            overrides: OnceLock::new(),
+        }
+    }
+}
+
+impl MyParams {
+    /// Built on first use, which has to come once the params have their final address, e.g.
+    /// inside an `Arc`. The plugin builds it up front so the audio thread never does.
+    pub fn overrides(&self) -> &ParamOverrides {
+        self.overrides.get_or_init(|| ParamOverrides::new(self))
+    }
+
+    /// `param`'s value, or the value MIDI left in its override.
+    pub fn value<P: Param>(&self, param: &P) -> P::Plain {
+        self.overrides().value(param)
+    }
+
+    /// Both oscillators plus the sub, which follows oscillator 1's footage an octave down.
+    pub fn oscillator_configs(&self) -> [OscillatorConfig; 3] {
+        let osc1 = self.osc1.config(self.overrides());
+        let sub_index = Footage::ALL.iter().position(|&f| f == osc1.octave).unwrap_or(0).saturating_sub(1);
+        let sub = OscillatorConfig {
+            octave: Footage::ALL[sub_index],
+            detune_semitones: osc1.detune_semitones,
+            volume: self.value(&self.sub_level),
+            phase_mode: PhaseMode::Retrigger,
+            ..OscillatorConfig::with_waveform(Waveform::SQUARE)
+        };
+        [osc1, self.osc2.config(self.overrides()), sub]
+    }
+
+    pub fn harmonics(&self) -> Harmonics {
+        Harmonics(std::array::from_fn(|i| self.value(&self.harmonics[i].level)))
+    }
+
+    pub fn voice_mode(&self) -> VoiceMode {
+        choice(&VoiceMode::ALL, self.value(&self.voice_mode))
+    }
+
+    pub fn key_zone_config(&self) -> KeyZoneConfig {
+        let (low, high) = (self.value(&self.key_low), self.value(&self.key_high));
+        KeyZoneConfig {
+            mode: choice(&ZoneMode::ALL, self.value(&self.zone_mode)),
+            split_key: self.value(&self.split_key) as u8,
+            zones: [self.zone_a.config(self.overrides()), self.zone_b.config(self.overrides())],
+            part: PartZone {
+                low_key: low.min(high) as u8,
+                high_key: low.max(high) as u8,
+                ..PartZone::default()
+            },
+        }
+    }
+
+    /// `None` while the GUI is writing a new shape; the audio thread tries again next block.
+    pub fn chord_memory_config(&self) -> Option<ChordMemoryConfig> {
+        let shape = self.chord_shape.try_read().ok()?;
+        Some(ChordMemoryConfig {
+            enabled: self.value(&self.chord_memory),
+            shape: ChordShape::from_intervals(&shape),
+        })
+    }
+
+    pub fn lfo_configs(&self) -> [LfoConfig; LFO_COUNT] {
+        [self.lfo1.config(self.overrides()), self.lfo2.config(self.overrides())]
+    }
+
+    pub fn mod_envelope_configs(&self) -> [EnvelopeConfig; MOD_ENVELOPE_COUNT] {
+        [
+            self.mod_envelope3.config(self.overrides()).with_looping(self.value(&self.mod_envelope3_loop)),
+            self.mod_envelope4.config(self.overrides()).with_looping(self.value(&self.mod_envelope4_loop)),
+        ]
+    }
+
+    pub fn filter_parameters(&self) -> FilterParameters {
+        FilterParameters {
+            filter_type: choice(&FilterType::ALL, self.value(&self.filter_type)),
+            slope: choice(&FilterSlope::ALL, self.value(&self.filter_slope)),
+            cutoff_frequency: self.value(&self.cutoff),
+            resonance_amount: self.value(&self.resonance),
+            modulation_amount: self.value(&self.filter_env_amount),
+            drive: self.value(&self.filter_drive),
+            saturation: choice(&SaturationCurve::ALL, self.value(&self.filter_saturation)),
+        }
+    }
+
+    pub fn filter2_parameters(&self) -> FilterParameters {
+        FilterParameters {
+            filter_type: choice(&FilterType::ALL, self.value(&self.filter2_type)),
+            slope: choice(&FilterSlope::ALL, self.value(&self.filter2_slope)),
+            cutoff_frequency: self.value(&self.cutoff2),
+            resonance_amount: self.value(&self.resonance2),
+            modulation_amount: self.value(&self.filter2_env_amount),
+            // The drive sits in front of filter 1 only
+            drive: 0.0,
+            saturation: SaturationCurve::Tanh,
+        }
+    }
+
+    pub fn filter_routing_config(&self) -> FilterRoutingConfig {
+        FilterRoutingConfig {
+            routing: choice(&FilterRouting::ALL, self.value(&self.filter_routing)),
+            parallel_mix: self.value(&self.filter_mix),
+        }
+    }
+
+    pub fn normalization(&self) -> OutputNormalization {
+        choice(&OutputNormalization::ALL, self.value(&self.normalization))
+    }
+
+    pub fn quality(&self) -> QualityMode {
+        choice(&QualityMode::ALL, self.value(&self.quality))
+    }
+
+    pub fn voice_oversampling(&self) -> VoiceOversampling {
+        choice(&VoiceOversampling::ALL, self.value(&self.oversampling))
+    }
+
+    pub fn release_velocity_config(&self) -> ReleaseVelocityConfig {
+        ReleaseVelocityConfig {
+            source: choice(&ReleaseVelocitySource::ALL, self.value(&self.release_velocity_source)),
+            amount: self.value(&self.release_velocity_amount),
+            fixed_velocity: self.value(&self.release_velocity_fixed),
+        }
+    }
+
+    pub fn glide_config(&self) -> GlideConfig {
+        GlideConfig {
+            mode: choice(&GlideMode::ALL, self.value(&self.glide_mode)),
+            rate: choice(&GlideRate::ALL, self.value(&self.glide_rate)),
+            time_secs: self.value(&self.glide_time),
+            legato: self.value(&self.glide_legato),
+        }
+    }
+
+    pub fn vibrato_config(&self) -> VibratoConfig {
+        VibratoConfig {
+            rate_hz: self.value(&self.vibrato_rate),
+            depth_semitones: self.value(&self.vibrato_depth),
+            delay_secs: self.value(&self.vibrato_delay),
+            fade_in_secs: self.value(&self.vibrato_fade_in),
+        }
+    }
+
+    pub fn drift_config(&self) -> DriftConfig {
+        DriftConfig {
+            pitch_drift_cents: self.value(&self.drift_pitch),
+            note_detune_cents: self.value(&self.drift_detune),
+            cutoff_wobble_octaves: self.value(&self.drift_cutoff),
+        }
+    }
+
+    pub fn send_config(&self) -> SendConfig {
+        SendConfig {
+            delay: self.value(&self.delay_send),
+            reverb: self.value(&self.reverb_send),
+            velocity_scaling: self.value(&self.send_velocity_scaling),
+            key_scaling: self.value(&self.send_key_scaling),
+        }
+    }
+
+    pub fn delay_config(&self) -> DelayConfig {
+        DelayConfig {
+            enabled: self.value(&self.delay_enabled),
+            time_secs: self.value(&self.delay_time),
+            feedback: self.value(&self.delay_feedback),
+            tempo_sync: self.value(&self.delay_tempo_sync),
+            division: choice(&SyncDivision::ALL, self.value(&self.delay_division)),
+            ping_pong: self.value(&self.delay_ping_pong),
+            low_cut_hz: self.value(&self.delay_low_cut),
+            high_cut_hz: self.value(&self.delay_high_cut),
+        }
+    }
+
+    pub fn voice_insert_config(&self) -> VoiceInsertConfig {
+        VoiceInsertConfig {
+            kind: choice(&VoiceInsertKind::ALL, self.value(&self.voice_insert)),
+            amount: self.value(&self.voice_insert_amount),
+        }
+    }
+
+    pub fn eq_config(&self) -> EqConfig {
+        EqConfig {
+            enabled: self.value(&self.eq_enabled),
+            low_freq: self.value(&self.eq_low_freq),
+            low_gain_db: self.value(&self.eq_low_gain),
+            mid_freq: self.value(&self.eq_mid_freq),
+            mid_gain_db: self.value(&self.eq_mid_gain),
+            mid_q: self.value(&self.eq_mid_q),
+            high_freq: self.value(&self.eq_high_freq),
+            high_gain_db: self.value(&self.eq_high_gain),
+        }
+    }
+
+    pub fn modulation_fx_config(&self) -> ModulationFxConfig {
+        ModulationFxConfig {
+            phaser: self.phaser.config(self.overrides()),
+            flanger: self.flanger.config(self.overrides()),
+        }
+    }
+
+    /// Fails while two slots hold the same effect.
+    pub fn fx_chain_config(&self) -> Result<FxChainConfig, DuplicateInsert> {
+        let slots = [&self.fx_slot1, &self.fx_slot2, &self.fx_slot3, &self.fx_slot4, &self.fx_slot5]
+            .map(|slot| choice(&InsertEffect::ALL, self.value(slot)));
+        FxChainConfig::from_slots(&slots, choice(&SendOrder::ALL, self.value(&self.send_order)))
+    }
+
+    pub fn compressor_config(&self) -> CompressorConfig {
+        CompressorConfig {
+            enabled: self.value(&self.compressor_enabled),
+            threshold_db: self.value(&self.compressor_threshold),
+            ratio: self.value(&self.compressor_ratio),
+            attack_secs: self.value(&self.compressor_attack),
+            release_secs: self.value(&self.compressor_release),
+            makeup_db: self.value(&self.compressor_makeup),
+        }
+    }
+
+    pub fn reverb_config(&self) -> ReverbConfig {
+        ReverbConfig {
+            enabled: self.value(&self.reverb_enabled),
+            size: self.value(&self.reverb_size),
+            damping: self.value(&self.reverb_damping),
+            pre_delay_secs: self.value(&self.reverb_pre_delay),
+            width: self.value(&self.reverb_width),
+            mix: self.value(&self.reverb_mix),
+        }
+    }
+
+    pub fn envelope(&self, kind: EnvelopeKind) -> &EnvelopeParams {
+        match kind {
+            EnvelopeKind::Amp => &self.amp_envelope,
+            EnvelopeKind::Filter => &self.filter_envelope,
+            EnvelopeKind::ModEnv3 => &self.mod_envelope3,
+            EnvelopeKind::ModEnv4 => &self.mod_envelope4,
+        }
+    }
+
+    pub fn sequencer_config(&self) -> StepSequencerConfig {
+        StepSequencerConfig {
+            enabled: self.value(&self.sequencer_enabled),
+            drive_notes: self.value(&self.sequencer_notes),
+            glide: self.value(&self.sequencer_glide),
+            swing: self.value(&self.sequencer_swing),
+            gate: self.value(&self.sequencer_gate),
+            humanize: self.value(&self.sequencer_humanize),
+            division: choice(&SyncDivision::ALL, self.value(&self.sequencer_division)),
+            ..StepSequencerConfig::default()
+        }
+    }
+}
+
+fn fresh_noise_seed() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(1, |d| d.as_nanos() as u64)
+}
+
+// The ranges below are shared with `midi_mapping::EngineParam`, so the standalone app
+// scales controllers and preset values exactly like the plugin
+
+pub(crate) fn unit_range() -> FloatRange {
+    FloatRange::Linear { min: 0.0, max: 1.0 }
+}
+
+pub(crate) fn cutoff_range() -> FloatRange {
+    FloatRange::Skewed { min: 20.0, max: 20000.0, factor: FloatRange::skew_factor(-2.0) }
+}
+
+pub(crate) fn resonance_range() -> FloatRange {
+    FloatRange::Skewed { min: 0.5, max: 10.0, factor: FloatRange::skew_factor(-1.0) }
+}
+
+pub(crate) fn glide_time_range() -> FloatRange {
+    FloatRange::Skewed { min: 0.0, max: 5.0, factor: FloatRange::skew_factor(-2.0) }
+}
+
+pub(crate) fn delay_feedback_range() -> FloatRange {
+    FloatRange::Linear { min: 0.0, max: 0.95 }
+}
+
+fn percentage_param(name: &str, default: f32) -> FloatParam {
+    FloatParam::new(
+        name,
+        default,
+        unit_range(),
+    )
+    .with_unit("%")
+    .with_value_to_string(formatters::v2s_f32_percentage(0))
+    .with_string_to_value(formatters::s2v_f32_percentage())
+}
+
+fn key_param(name: &str, default: i32) -> IntParam {
+    IntParam::new(name, default, IntRange::Linear { min: 0, max: 127 })
+        .with_value_to_string(Arc::new(|v| note_name(v as u8)))
+}
+
+fn bipolar_percentage_param(name: &str) -> FloatParam {
+    FloatParam::new(
+        name,
+        0.0,
+        FloatRange::Linear { min: -1.0, max: 1.0 },
+    )
+    .with_unit("%")
+    .with_value_to_string(formatters::v2s_f32_percentage(0))
+    .with_string_to_value(formatters::s2v_f32_percentage())
+}
+
+fn cents_param(name: &str, default: f32) -> FloatParam {
+    FloatParam::new(
+        name,
+        default,
+        FloatRange::Linear { min: 0.0, max: 25.0 },
+    )
+    .with_step_size(0.1)
+    .with_unit(" ct")
+}
+
+// Slot `number` (1-based) of the insert chain, defaulting to the default chain order
+fn fx_slot_param(number: usize) -> IntParam {
+    let default = FxChainConfig::default().inserts[number - 1];
+    choice_param(&format!("FX Slot {}", number), &InsertEffect::ALL, default, InsertEffect::label)
+}
+
+fn sweep_rate_param(name: &str, default: f32) -> FloatParam {
+    FloatParam::new(
+        name,
+        default,
+        FloatRange::Skewed { min: 0.02, max: 10.0, factor: FloatRange::skew_factor(-2.0) },
+    )
+    .with_value_to_string(formatters::v2s_f32_rounded(2))
+    .with_unit(" Hz")
+}
+
+fn vibrato_time_param(name: &str, default: f32) -> FloatParam {
+    FloatParam::new(
+        name,
+        default,
+        FloatRange::Skewed { min: 0.0, max: 3.0, factor: FloatRange::skew_factor(-1.0) },
+    )
+    .with_unit(" s")
+    .with_value_to_string(formatters::v2s_f32_rounded(2))
+}
+
+fn eq_freq_param(name: &str, default: f32, min: f32, max: f32) -> FloatParam {
+    FloatParam::new(
+        name,
+        default,
+        FloatRange::Skewed { min, max, factor: FloatRange::skew_factor(-2.0) },
+    )
+    .with_value_to_string(formatters::v2s_f32_hz_then_khz(1))
+    .with_string_to_value(formatters::s2v_f32_hz_then_khz())
+}
+
+fn eq_gain_param(name: &str) -> FloatParam {
+    FloatParam::new(
+        name,
+        0.0,
+        FloatRange::Linear { min: -18.0, max: 18.0 },
+    )
+    .with_step_size(0.1)
+    .with_unit(" dB")
+}
+
+fn envelope_time_param(name: &str, default: f32) -> FloatParam {
+    FloatParam::new(
+        name,
+        default,
+        FloatRange::Skewed { min: 0.001, max: 10.0, factor: FloatRange::skew_factor(-2.0) },
+    )
+    .with_unit(" s")
+    .with_value_to_string(formatters::v2s_f32_rounded(3))
+}
+
+/// Stepped parameter selecting one entry of `options`, shown with its label.
+fn choice_param<T: Copy + PartialEq + Send + Sync + 'static>(
+    name: &str,
+    options: &'static [T],
+    default: T,
+    label: fn(T) -> &'static str,
+) -> IntParam {
+    let default_index = options.iter().position(|o| *o == default).unwrap_or(0);
+    IntParam::new(
+        name,
+        default_index as i32,
+        IntRange::Linear { min: 0, max: options.len() as i32 - 1 },
+    )
+    .with_value_to_string(Arc::new(move |v| label(options[v as usize]).to_string()))
+}
+
+fn choice<T: Copy>(options: &[T], index: i32) -> T {
+    options[(index.max(0) as usize).min(options.len() - 1)]
+}
@@ -0,0 +1,86 @@
+use nih_plug::prelude::{Param, ParamPtr, Params};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
+
+const UNSET: u32 = u32::MAX;    // a NaN, which no normalized value ever is
+
+/// Parameter values that arrive over MIDI on the audio thread. nih-plug only lets the editor
+/// write parameters, so mapped controllers, program changes and sysex edits land here instead
+/// and are read in place of their parameter's own value until that parameter moves, by host
+/// automation or by the editor catching up. Until then the host doesn't see the new value
+/// and it isn't saved with the project.
+pub struct ParamOverrides {
+    ptrs: Vec<ParamPtr>,                // in `param_map` order
+    indices: HashMap<ParamPtr, usize>,
+    ids: HashMap<String, usize>,
+    values: Vec<AtomicU32>,             // normalized value bits, UNSET when not overridden
+    bases: Vec<AtomicU32>,              // the parameter's own normalized value when it was overridden
+    active: AtomicUsize,                // how many are set, so reads skip the lookup while none are
+}
+
+impl ParamOverrides {
+    /// `params` must stay where they are, e.g. inside an `Arc`, for as long as this lives.
+    pub fn new(params: &impl Params) -> Self {
+        let param_map = params.param_map();
+        Self {
+            ptrs: param_map.iter().map(|(_, ptr, _)| *ptr).collect(),
+            indices: param_map.iter().enumerate().map(|(index, (_, ptr, _))| (*ptr, index)).collect(),
+            ids: param_map.iter().enumerate().map(|(index, (id, _, _))| (id.clone(), index)).collect(),
+            values: param_map.iter().map(|_| AtomicU32::new(UNSET)).collect(),
+            bases: param_map.iter().map(|_| AtomicU32::new(0)).collect(),
+            active: AtomicUsize::new(0),
+        }
+    }
+
+    /// Position of the parameter with ID `id` in `param_map` order.
+    pub fn index_of(&self, id: &str) -> Option<usize> {
+        self.ids.get(id).copied()
+    }
+
+    /// Overrides the parameter at `index` with a normalized value. Never blocks or allocates.
+    pub fn set(&self, index: usize, normalized: f32) {
+        let Some(ptr) = self.ptrs.get(index) else {
+            return;
+        };
+        let base = unsafe { ptr.unmodulated_normalized_value() };
+        self.bases[index].store(base.to_bits(), Ordering::Relaxed);
+        if self.values[index].swap(normalized.clamp(0.0, 1.0).to_bits(), Ordering::Release) == UNSET {
+            self.active.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Like `set`, with a value in the parameter's own unit.
+    pub fn set_plain(&self, index: usize, plain: f32) {
+        if let Some(ptr) = self.ptrs.get(index) {
+            self.set(index, unsafe { ptr.preview_normalized(plain) });
+        }
+    }
+
+    /// The normalized override of the parameter at `index`. An override is dropped once its
+    /// parameter has moved away from where it was when the override was set.
+    pub fn normalized(&self, index: usize) -> Option<f32> {
+        let bits = self.values.get(index)?.load(Ordering::Acquire);
+        if bits == UNSET {
+            return None;
+        }
+        let current = unsafe { self.ptrs[index].unmodulated_normalized_value() };
+        if current.to_bits() != self.bases[index].load(Ordering::Relaxed) {
+            if self.values[index].swap(UNSET, Ordering::AcqRel) != UNSET {
+                self.active.fetch_sub(1, Ordering::Relaxed);
+            }
+            return None;
+        }
+        Some(f32::from_bits(bits))
+    }
+
+    /// `param`'s value, or its override while it has one.
+    pub fn value<P: Param>(&self, param: &P) -> P::Plain {
+        if self.active.load(Ordering::Relaxed) > 0 {
+            let normalized = self.indices.get(&param.as_ptr()).and_then(|&index| self.normalized(index));
+            if let Some(normalized) = normalized {
+                return param.preview_plain(normalized);
+            }
+        }
+        param.modulated_plain_value()
+    }
+}
@@ -0,0 +1,302 @@
+use super::MyParams;
+use crate::chord_memory::ChordMemoryConfig;
+use crate::drift::DriftConfig;
+use crate::dynamics::OutputNormalization;
+use crate::effects::{
+    CompressorConfig, DelayConfig, EqConfig, FxChainConfig, ModulationFxConfig, ReverbConfig, SendConfig, VoiceInsertConfig,
+    WaveshaperConfig,
+};
+use crate::envelope::{EnvelopeConfig, ReleaseVelocityConfig, MOD_ENVELOPE_COUNT};
+use crate::filter::{FilterParameters, FilterRoutingConfig};
+use crate::glide::GlideConfig;
+use crate::keyzone::KeyZoneConfig;
+use crate::modulation::ModulationRoute;
+use crate::oscillator::OscillatorConfig;
+use crate::oversampling::VoiceOversampling;
+use crate::quality::QualityMode;
+use crate::synthesizer::{Synthesizer, VoiceMode};
+use crate::vibrato::VibratoConfig;
+
+const AUDITION_NOTE_HZ: f32 = 261.63;
+const AUDITION_SECS: f32 = 0.6;
+
+/// Carries the patch from the parameters into an engine. Remembers what it last pushed,
+/// so only real edits touch the voices; the plugin keeps one for its engine, the
+/// standalone app starts a fresh one to load a whole preset.
+#[derive(Default)]
+pub struct PatchSync {
+    last_oscillators: Option<[OscillatorConfig; 3]>,
+    last_glide: Option<GlideConfig>,
+    last_vibrato: Option<VibratoConfig>,
+    last_drift: Option<DriftConfig>,
+    last_sends: Option<SendConfig>,
+    last_delay: Option<DelayConfig>,
+    last_reverb: Option<ReverbConfig>,
+    last_eq: Option<EqConfig>,
+    last_compressor: Option<CompressorConfig>,
+    last_modulation_fx: Option<ModulationFxConfig>,
+    last_fx_chain: Option<FxChainConfig>,
+    last_waveshaper: Option<WaveshaperConfig>,
+    last_voice_waveshaper: Option<WaveshaperConfig>,
+    last_filter: Option<FilterParameters>,
+    last_filter2: Option<FilterParameters>,
+    last_filter_routing: Option<FilterRoutingConfig>,
+    last_stereo_spread: Option<f32>,
+    last_freeze_modulation: Option<bool>,
+    last_release_velocity: Option<ReleaseVelocityConfig>,
+    last_voice_dc_blocking: Option<bool>,
+    last_voice_insert: Option<VoiceInsertConfig>,
+    last_gain_compensation: Option<bool>,
+    last_detune_spread: Option<f32>,
+    last_filter_key_tracking: Option<f32>,
+    last_voice_mode: Option<VoiceMode>,
+    last_key_zones: Option<KeyZoneConfig>,
+    last_chord_memory: Option<ChordMemoryConfig>,
+    last_master_tuning: Option<(f32, i32)>,
+    last_master_gain: Option<f32>,
+    last_normalization: Option<OutputNormalization>,
+    last_quality: Option<QualityMode>,
+    last_oversampling: Option<VoiceOversampling>,
+    last_envelope: Option<EnvelopeConfig>,
+    last_filter_envelope: Option<EnvelopeConfig>,
+    last_mod_envelopes: Option<[EnvelopeConfig; MOD_ENVELOPE_COUNT]>,
+    last_routes: Option<Vec<ModulationRoute>>,
+}
+
+impl PatchSync {
+    /// Pushes every parameter into `synth` that changed since the last call; a new
+    /// `PatchSync` pushes them all. `offline` forces the highest quality.
+    pub fn sync(&mut self, params: &MyParams, synth: &mut Synthesizer, offline: bool) {
+        let oscillators = params.oscillator_configs();
+        if self.last_oscillators != Some(oscillators) {
+            synth.set_oscillator_configs(&oscillators);
+            self.last_oscillators = Some(oscillators);
+        }
+
+        let glide = params.glide_config();
+        if self.last_glide != Some(glide) {
+            synth.set_glide_config(glide);
+            self.last_glide = Some(glide);
+        }
+
+        let vibrato = params.vibrato_config();
+        if self.last_vibrato != Some(vibrato) {
+            synth.set_vibrato_config(vibrato);
+            self.last_vibrato = Some(vibrato);
+        }
+
+        let drift = params.drift_config();
+        if self.last_drift != Some(drift) {
+            synth.set_drift_config(drift);
+            self.last_drift = Some(drift);
+        }
+
+        let sends = params.send_config();
+        if self.last_sends != Some(sends) {
+            synth.set_send_config(sends);
+            self.last_sends = Some(sends);
+        }
+        let delay = params.delay_config();
+        if self.last_delay != Some(delay) {
+            synth.set_delay_config(delay);
+            self.last_delay = Some(delay);
+        }
+        let waveshaper = params.waveshaper.config(params.overrides());
+        if self.last_waveshaper != Some(waveshaper) {
+            synth.set_waveshaper_config(waveshaper);
+            self.last_waveshaper = Some(waveshaper);
+        }
+        let voice_waveshaper = params.voice_waveshaper.config(params.overrides());
+        if self.last_voice_waveshaper != Some(voice_waveshaper) {
+            synth.set_voice_waveshaper_config(voice_waveshaper);
+            self.last_voice_waveshaper = Some(voice_waveshaper);
+        }
+        let eq = params.eq_config();
+        if self.last_eq != Some(eq) {
+            synth.set_eq_config(eq);
+            self.last_eq = Some(eq);
+        }
+
+        let compressor = params.compressor_config();
+        if self.last_compressor != Some(compressor) {
+            synth.set_compressor_config(compressor);
+            self.last_compressor = Some(compressor);
+        }
+        let modulation_fx = params.modulation_fx_config();
+        if self.last_modulation_fx != Some(modulation_fx) {
+            synth.set_modulation_fx_config(modulation_fx);
+            self.last_modulation_fx = Some(modulation_fx);
+        }
+        // While two slots hold the same effect the chain keeps its last valid order; the
+        // editor points out the repeat
+        if let Ok(fx_chain) = params.fx_chain_config()
+            && self.last_fx_chain != Some(fx_chain)
+        {
+            synth.set_fx_chain(fx_chain);
+            self.last_fx_chain = Some(fx_chain);
+        }
+        let reverb = params.reverb_config();
+        if self.last_reverb != Some(reverb) {
+            synth.set_reverb_config(reverb);
+            self.last_reverb = Some(reverb);
+        }
+
+        let filter = params.filter_parameters();
+        let filter2 = params.filter2_parameters();
+        let envelope = params.amp_envelope.config(params.overrides());
+        let filter_envelope = params.filter_envelope.config(params.overrides());
+        let filter_changed = self.last_filter.as_ref() != Some(&filter);
+        let filter2_changed = self.last_filter2.as_ref() != Some(&filter2);
+        let envelope_changed = self.last_envelope.as_ref() != Some(&envelope);
+        let filter_envelope_changed = self.last_filter_envelope.as_ref() != Some(&filter_envelope);
+
+        // Only audition real edits, not the first block after loading
+        let edited = (filter_changed && self.last_filter.is_some())
+            || (filter2_changed && self.last_filter2.is_some())
+            || (envelope_changed && self.last_envelope.is_some())
+            || (filter_envelope_changed && self.last_filter_envelope.is_some());
+
+        if filter_changed {
+            synth.set_filter_parameters(filter.clone());
+            self.last_filter = Some(filter);
+        }
+        if filter2_changed {
+            synth.set_filter2_parameters(filter2.clone());
+            self.last_filter2 = Some(filter2);
+        }
+        if envelope_changed {
+            synth.set_envelope_config(envelope.clone());
+            self.last_envelope = Some(envelope);
+        }
+        if filter_envelope_changed {
+            synth.set_filter_envelope_config(filter_envelope.clone());
+            self.last_filter_envelope = Some(filter_envelope);
+        }
+        let mod_envelopes = params.mod_envelope_configs();
+        if self.last_mod_envelopes.as_ref() != Some(&mod_envelopes) {
+            for (index, config) in mod_envelopes.iter().enumerate() {
+                synth.set_mod_envelope_config(index, config.clone());
+            }
+            self.last_mod_envelopes = Some(mod_envelopes);
+        }
+        if edited && params.value(&params.audition) {
+            synth.audition(AUDITION_NOTE_HZ, AUDITION_SECS);
+        }
+
+        let filter_routing = params.filter_routing_config();
+        if self.last_filter_routing != Some(filter_routing) {
+            synth.set_filter_routing(filter_routing);
+            self.last_filter_routing = Some(filter_routing);
+        }
+
+        let stereo_spread = params.value(&params.filter_stereo_spread);
+        if self.last_stereo_spread != Some(stereo_spread) {
+            synth.set_stereo_filter_spread(stereo_spread);
+            self.last_stereo_spread = Some(stereo_spread);
+        }
+
+        let quality = if offline { QualityMode::High } else { params.quality() };
+        if self.last_quality != Some(quality) {
+            synth.set_quality(quality);
+            self.last_quality = Some(quality);
+        }
+        let oversampling = params.voice_oversampling();
+        if self.last_oversampling != Some(oversampling) {
+            synth.set_voice_oversampling(oversampling);
+            self.last_oversampling = Some(oversampling);
+        }
+
+        let voice_dc_blocking = params.value(&params.voice_dc_blocking);
+        if self.last_voice_dc_blocking != Some(voice_dc_blocking) {
+            synth.set_voice_dc_blocking(voice_dc_blocking);
+            self.last_voice_dc_blocking = Some(voice_dc_blocking);
+        }
+
+        let gain_compensation = params.value(&params.oscillator_gain_compensation);
+        if self.last_gain_compensation != Some(gain_compensation) {
+            synth.set_oscillator_gain_compensation(gain_compensation);
+            self.last_gain_compensation = Some(gain_compensation);
+        }
+
+        let detune_spread = params.value(&params.detune_spread);
+        if self.last_detune_spread != Some(detune_spread) {
+            synth.set_detune_spread(detune_spread);
+            self.last_detune_spread = Some(detune_spread);
+        }
+
+        let filter_key_tracking = params.value(&params.filter_key_tracking);
+        if self.last_filter_key_tracking != Some(filter_key_tracking) {
+            synth.set_filter_key_tracking(filter_key_tracking);
+            self.last_filter_key_tracking = Some(filter_key_tracking);
+        }
+
+        let voice_mode = params.voice_mode();
+        if self.last_voice_mode != Some(voice_mode) {
+            synth.set_voice_mode(voice_mode);
+            self.last_voice_mode = Some(voice_mode);
+        }
+
+        let key_zones = params.key_zone_config();
+        if self.last_key_zones != Some(key_zones) {
+            synth.set_key_zones(key_zones);
+            self.last_key_zones = Some(key_zones);
+        }
+
+        if let Some(chord_memory) = params.chord_memory_config() {
+            if self.last_chord_memory != Some(chord_memory) {
+                synth.set_chord_memory(chord_memory);
+                self.last_chord_memory = Some(chord_memory);
+            }
+        }
+
+        let master_tuning = (params.value(&params.master_tune), params.value(&params.transpose));
+        if self.last_master_tuning != Some(master_tuning) {
+            synth.set_master_tuning(master_tuning.0, master_tuning.1);
+            self.last_master_tuning = Some(master_tuning);
+        }
+
+        let voice_insert = params.voice_insert_config();
+        if self.last_voice_insert != Some(voice_insert) {
+            synth.set_voice_insert(voice_insert);
+            self.last_voice_insert = Some(voice_insert);
+        }
+
+        let master_gain = params.value(&params.gain);
+        if self.last_master_gain != Some(master_gain) {
+            synth.set_master_gain(master_gain);
+            self.last_master_gain = Some(master_gain);
+        }
+
+        let normalization = params.normalization();
+        if self.last_normalization != Some(normalization) {
+            synth.set_output_normalization(normalization);
+            self.last_normalization = Some(normalization);
+        }
+
+        let freeze_modulation = params.value(&params.freeze_modulation);
+        if self.last_freeze_modulation != Some(freeze_modulation) {
+            synth.set_freeze_modulation_on_release(freeze_modulation);
+            self.last_freeze_modulation = Some(freeze_modulation);
+        }
+
+        let release_velocity = params.release_velocity_config();
+        if self.last_release_velocity != Some(release_velocity) {
+            synth.set_release_velocity_config(release_velocity);
+            self.last_release_velocity = Some(release_velocity);
+        }
+
+        synth.set_sequencer(params.sequencer_config());
+        for (index, config) in params.lfo_configs().into_iter().enumerate() {
+            synth.set_lfo_config(index, config);
+        }
+
+        // The GUI may hold the lock while editing; pick the change up next block instead of waiting
+        if let Ok(routes) = params.modulation_routes.try_read() {
+            if self.last_routes.as_ref() != Some(&*routes) {
+                synth.set_modulation_routes(&routes);
+                // Reuses the copy from the last change rather than allocating a new one
+                self.last_routes.get_or_insert_with(Vec::new).clone_from(&routes);
+            }
+        }
+    }
+}
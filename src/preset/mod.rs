@@ -0,0 +1,485 @@
+use nih_plug::prelude::{ParamPtr, Params};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, OnceLock};
+
+use crate::midi_mapping::CcMapping;
+use crate::modulation::{default_routes, ModulationRoute};
+use crate::params::{MyParams, PatchSync};
+use crate::sample::{SampleData, SampleLayer};
+use crate::synthesizer::{Synthesizer, VoiceMode};
+
+const EXTENSION: &str = "json";
+const MATCH_TOLERANCE: f32 = 1e-4;     // normalized; loading through the host rounds a little
+
+const INIT_PRESET: &str = include_str!("factory/init.json");
+const FACTORY_PRESETS: [&str; 4] = [
+    INIT_PRESET,
+    include_str!("factory/warm_pad.json"),
+    include_str!("factory/sub_bass.json"),
+    include_str!("factory/pluck_sequence.json"),
+];
+
+/// A stored patch. Parameter values are plain (unnormalized) and keyed by parameter ID,
+/// so presets stay readable and survive range changes; missing IDs load as defaults.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Preset {
+    pub name: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub values: BTreeMap<String, f32>,
+    #[serde(default = "default_routes")]
+    pub modulation_routes: Vec<ModulationRoute>,
+    /// Random oscillator seed; presets without one keep the instance's current seed.
+    #[serde(default)]
+    pub noise_seed: Option<u64>,
+    /// Controller mappings that travel with this patch; see `midi_mapping::merge`.
+    #[serde(default)]
+    pub cc_mappings: Vec<CcMapping>,
+    /// WAV file of the sample layer; presets without one play no sample.
+    #[serde(default)]
+    pub sample_path: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum PresetSource {
+    Factory,
+    User(PathBuf),
+}
+
+#[derive(Clone, Debug)]
+pub struct PresetEntry {
+    pub preset: Preset,
+    pub source: PresetSource,
+}
+
+impl Preset {
+    /// Captures the current state of `params`.
+    pub fn capture(name: &str, tags: Vec<String>, params: &MyParams) -> Self {
+        let values = params
+            .param_map()
+            .into_iter()
+            .map(|(id, ptr, _)| (id, unsafe { ptr.unmodulated_plain_value() }))
+            .collect();
+        let modulation_routes = params.modulation_routes.read().unwrap_or_else(|e| e.into_inner()).clone();
+        let noise_seed = *params.noise_seed.read().unwrap_or_else(|e| e.into_inner());
+        let cc_mappings = params.preset_cc_mappings.read().unwrap_or_else(|e| e.into_inner()).clone();
+        let sample_path = params.sample_path.read().unwrap_or_else(|e| e.into_inner()).clone();
+
+        Self {
+            name: name.to_string(),
+            tags,
+            values,
+            modulation_routes,
+            noise_seed: Some(noise_seed),
+            cc_mappings,
+            sample_path,
+        }
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, Box<dyn Error>> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    pub fn to_json(&self) -> Result<String, Box<dyn Error>> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// Loads the whole patch into a bare engine, as in the standalone app, through a
+    /// parameter set of its own. Reads the sample and builds tables, so it is too slow for
+    /// the audio thread.
+    pub fn apply_to_engine(&self, synth: &mut Synthesizer) {
+        let params = Arc::new(MyParams::default());
+        self.load_into_overrides(&params);
+        PatchSync::default().sync(&params, synth, false);
+
+        synth.set_harmonics(params.harmonics());
+        if let Some(seed) = self.noise_seed {
+            synth.set_noise_seed(seed);
+        }
+        let data = self.sample_path.as_deref().and_then(|path| SampleData::load(Path::new(path)).ok());
+        let config = params.sample.config(params.overrides());
+        synth.set_sample_layer(data.map(|data| SampleLayer { data: Arc::new(data), config }));
+    }
+
+    /// Loads the patch without the editor. Only the editor can set parameters, so their
+    /// values go into `params.overrides()`; the rest of the patch is written as usual.
+    pub fn load_into_overrides(&self, params: &MyParams) {
+        let overrides = params.overrides();
+        for (id, ptr, _) in params.param_map() {
+            if let Some(index) = overrides.index_of(&id) {
+                overrides.set(index, self.normalized_value(&id, ptr));
+            }
+        }
+        *params.modulation_routes.write().unwrap_or_else(|e| e.into_inner()) = self.modulation_routes.clone();
+        if let Some(seed) = self.noise_seed {
+            *params.noise_seed.write().unwrap_or_else(|e| e.into_inner()) = seed;
+        }
+        *params.preset_cc_mappings.write().unwrap_or_else(|e| e.into_inner()) = self.cc_mappings.clone();
+        *params.sample_path.write().unwrap_or_else(|e| e.into_inner()) = self.sample_path.clone();
+    }
+
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|t| t.eq_ignore_ascii_case(tag))
+    }
+
+    /// Whether `params` still hold this patch. Values are compared normalized, so the
+    /// rounding from loading a preset doesn't count as an edit.
+    pub fn matches(&self, params: &MyParams) -> bool {
+        if *params.modulation_routes.read().unwrap_or_else(|e| e.into_inner()) != self.modulation_routes {
+            return false;
+        }
+        if *params.preset_cc_mappings.read().unwrap_or_else(|e| e.into_inner()) != self.cc_mappings {
+            return false;
+        }
+        if *params.sample_path.read().unwrap_or_else(|e| e.into_inner()) != self.sample_path {
+            return false;
+        }
+        if self.noise_seed.is_some_and(|seed| seed != *params.noise_seed.read().unwrap_or_else(|e| e.into_inner())) {
+            return false;
+        }
+        params.param_map().into_iter().all(|(id, ptr, _)| unsafe {
+            (ptr.unmodulated_normalized_value() - self.normalized_value(&id, ptr)).abs() < MATCH_TOLERANCE
+        })
+    }
+
+    /// The normalized value this patch gives the parameter `id` behind `ptr`; parameters it
+    /// doesn't list get their default.
+    pub fn normalized_value(&self, id: &str, ptr: ParamPtr) -> f32 {
+        unsafe {
+            match self.values.get(id) {
+                Some(&value) => ptr.preview_normalized(value),
+                None => ptr.default_normalized_value(),
+            }
+        }
+    }
+}
+
+/// The factory "Init" patch, which the compare-to-init view measures edits against.
+pub fn init_preset() -> &'static Preset {
+    static INIT: OnceLock<Preset> = OnceLock::new();
+    INIT.get_or_init(|| Preset::from_json(INIT_PRESET).expect("the factory init patch parses"))
+}
+
+/// Remembers `preset` as the patch the current edits are measured against.
+pub fn mark_patch_clean(params: &MyParams, preset: Preset) {
+    *params.patch_baseline.write().unwrap_or_else(|e| e.into_inner()) = Some(preset);
+}
+
+/// Whether the patch was edited since it was last loaded or saved.
+pub fn is_patch_dirty(params: &MyParams) -> bool {
+    params
+        .patch_baseline
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .as_ref()
+        .is_some_and(|baseline| !baseline.matches(params))
+}
+
+/// Name of the loaded patch for the editor header, with an asterisk once it has been edited.
+pub fn patch_title(params: &MyParams) -> String {
+    let baseline = params.patch_baseline.read().unwrap_or_else(|e| e.into_inner());
+    match baseline.as_ref() {
+        Some(preset) if preset.matches(params) => preset.name.clone(),
+        Some(preset) => format!("{}*", preset.name),
+        None => String::new(),
+    }
+}
+
+/// Which of the two compare slots the patch being edited belongs to.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+pub enum CompareSlot {
+    #[default]
+    A,
+    B,
+}
+
+impl CompareSlot {
+    pub fn other(self) -> Self {
+        match self {
+            CompareSlot::A => CompareSlot::B,
+            CompareSlot::B => CompareSlot::A,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            CompareSlot::A => "A",
+            CompareSlot::B => "B",
+        }
+    }
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+/// A/B compare: two complete patch snapshots to flip between while sound designing. The
+/// parameters always hold the active slot; the other slot's snapshot is taken when leaving it.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct CompareSlots {
+    active: CompareSlot,
+    slots: [Option<Preset>; 2],
+}
+
+impl CompareSlots {
+    pub fn active(&self) -> CompareSlot {
+        self.active
+    }
+
+    /// Makes `slot` active without taking a snapshot, e.g. when undoing a switch, whose undo
+    /// also puts the parameters back.
+    pub fn set_active(&mut self, slot: CompareSlot) {
+        self.active = slot;
+    }
+
+    /// Snapshots `params` into the active slot and makes the other one active. Returns the
+    /// patch to load for it; a slot never used yet starts as a copy of the one just left.
+    pub fn switch(&mut self, params: &MyParams) -> Preset {
+        let current = Preset::capture(self.active.label(), Vec::new(), params);
+        self.slots[self.active.index()] = Some(current.clone());
+        self.active = self.active.other();
+        self.slots[self.active.index()].clone().unwrap_or(current)
+    }
+
+    /// Overwrites the inactive slot with the patch in `params`, e.g. A to B.
+    pub fn copy_to_other(&mut self, params: &MyParams) {
+        let other = self.active.other();
+        self.slots[other.index()] = Some(Preset::capture(other.label(), Vec::new(), params));
+    }
+}
+
+/// One parameter that differs from the init patch, with both values as displayed.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParamDiff {
+    pub id: String,
+    pub name: String,
+    pub section: String,        // the parameter's group, e.g. "LFO 1", or "Main"
+    pub value: String,
+    pub init_value: String,
+}
+
+/// Section name for parameters outside any group.
+pub const MAIN_SECTION: &str = "Main";
+
+// Parameters that set up the instrument rather than make the sound: tuning, gain, output
+// quality, the key range and zones, chord memory and the sequencer
+const SETUP_PARAMS: [&str; 14] = [
+    "gain", "normalize", "quality", "oversample", "master_tune", "transpose", "zone_mode", "split_key",
+    "key_low", "key_high", "chord_mem", "audition", "dc_voice", "osc_gain_comp",
+];
+const SETUP_PREFIXES: [&str; 3] = ["zone_a_", "zone_b_", "seq_"];
+
+/// Whether the parameter with ID `id` belongs to the patch, which initializing resets; the
+/// setup stays, as with `Synthesizer::init_patch`.
+pub fn is_patch_param(id: &str) -> bool {
+    !SETUP_PARAMS.contains(&id) && !SETUP_PREFIXES.iter().any(|prefix| id.starts_with(prefix))
+}
+
+/// Every parameter of `params` that differs from the init patch, in parameter order.
+pub fn diff_from_init(params: &MyParams) -> Vec<ParamDiff> {
+    let init_preset = init_preset();
+    params
+        .param_map()
+        .into_iter()
+        .filter_map(|(id, ptr, group)| unsafe {
+            let init = init_preset.normalized_value(&id, ptr);
+            let current = ptr.unmodulated_normalized_value();
+            if (current - init).abs() < MATCH_TOLERANCE {
+                return None;
+            }
+            Some(ParamDiff {
+                id,
+                name: ptr.name().to_string(),
+                section: if group.is_empty() { MAIN_SECTION.to_string() } else { group },
+                value: ptr.normalized_value_to_string(current, true),
+                init_value: ptr.normalized_value_to_string(init, true),
+            })
+        })
+        .collect()
+}
+
+/// Whether the mod matrix was changed from the init patch's routes.
+pub fn routes_differ_from_init(params: &MyParams) -> bool {
+    *params.modulation_routes.read().unwrap_or_else(|e| e.into_inner()) != init_preset().modulation_routes
+}
+
+/// Plain-text listing of `diffs`, one "Section / Name: value (init value)" line each, for notes.
+pub fn describe_diff(diffs: &[ParamDiff]) -> String {
+    diffs
+        .iter()
+        .map(|d| format!("{} / {}: {} (init {})\n", d.section, d.name, d.value, d.init_value))
+        .collect()
+}
+
+/// A partial patch laid over the current one: only the listed parameters (plain values by
+/// ID) change, so one switch sets up a playing style without touching the sound's basics.
+#[derive(Clone, Copy, Debug)]
+pub struct PatchTemplate {
+    pub name: &'static str,
+    values: fn() -> Vec<(&'static str, f32)>,
+}
+
+/// Legato mono line with glide, a sub an octave down and the filter following the keys.
+pub const MONO_BASS: PatchTemplate = PatchTemplate {
+    name: "Mono Bass",
+    values: || {
+        vec![
+            ("voice_mode", choice_index(&VoiceMode::ALL, VoiceMode::Legato)),
+            ("legato", 1.0),
+            ("glide", 0.06),
+            ("sub_level", 0.7),
+            ("flt_keytrack", 0.5),
+        ]
+    },
+};
+
+pub static PATCH_TEMPLATES: [PatchTemplate; 1] = [MONO_BASS];
+
+impl PatchTemplate {
+    /// The plain value of every parameter the template sets, by ID.
+    pub fn values(&self) -> Vec<(&'static str, f32)> {
+        (self.values)()
+    }
+
+    /// Whether every value of the template is already set on `params`.
+    pub fn is_applied(&self, params: &MyParams) -> bool {
+        let param_map = params.param_map();
+        self.values().into_iter().all(|(id, value)| {
+            param_map.iter().find(|(param_id, _, _)| param_id == id).is_some_and(|(_, ptr, _)| unsafe {
+                (ptr.unmodulated_normalized_value() - ptr.preview_normalized(value)).abs() < MATCH_TOLERANCE
+            })
+        })
+    }
+}
+
+/// Plain value of a choice parameter set to `option`.
+fn choice_index<T: PartialEq>(options: &[T], option: T) -> f32 {
+    options.iter().position(|o| *o == option).unwrap_or(0) as f32
+}
+
+pub fn factory_presets() -> Vec<Preset> {
+    FACTORY_PRESETS
+        .iter()
+        .filter_map(|json| Preset::from_json(json).ok())
+        .collect()
+}
+
+/// Per-platform directory for user presets, or `None` if no home directory can be found.
+pub fn user_preset_dir() -> Option<PathBuf> {
+    user_data_dir().map(|dir| dir.join("presets"))
+}
+
+/// Per-platform directory for everything the synth keeps per user, e.g. presets and GUI
+/// translations, or `None` if no home directory can be found.
+pub fn user_data_dir() -> Option<PathBuf> {
+    let base = if cfg!(target_os = "windows") {
+        std::env::var_os("APPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        std::env::var_os("HOME").map(|home| PathBuf::from(home).join("Library/Application Support"))
+    } else {
+        std::env::var_os("XDG_DATA_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share")))
+    };
+    base.map(|dir| dir.join("RustVstSynth"))
+}
+
+/// Every user preset in `dir`; unreadable or malformed files are skipped.
+pub fn load_user_presets(dir: &Path) -> Vec<PresetEntry> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+
+    let mut presets: Vec<PresetEntry> = entries
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == EXTENSION))
+        .filter_map(|path| {
+            let preset = Preset::from_json(&fs::read_to_string(&path).ok()?).ok()?;
+            Some(PresetEntry { preset, source: PresetSource::User(path) })
+        })
+        .collect();
+    presets.sort_by(|a, b| a.preset.name.to_lowercase().cmp(&b.preset.name.to_lowercase()));
+    presets
+}
+
+/// Factory presets first, then the user's own.
+pub fn all_presets() -> Vec<PresetEntry> {
+    let mut presets: Vec<PresetEntry> = factory_presets()
+        .into_iter()
+        .map(|preset| PresetEntry { preset, source: PresetSource::Factory })
+        .collect();
+    if let Some(dir) = user_preset_dir() {
+        presets.extend(load_user_presets(&dir));
+    }
+    presets
+}
+
+/// Program change `program` picks this entry of a bank: 0 is the first preset.
+pub fn program_preset(bank: &[PresetEntry], program: u8) -> Option<&PresetEntry> {
+    bank.get(program as usize)
+}
+
+const NO_PROGRAM: u32 = u32::MAX;
+
+/// The latest program change the background thread has loaded, for the editor to catch up
+/// with: it loads the same preset through the parameters, so the host sees it. Only the
+/// most recent one is kept.
+pub struct ProgramChangeInbox {
+    pending: AtomicU32,
+}
+
+impl Default for ProgramChangeInbox {
+    fn default() -> Self {
+        Self { pending: AtomicU32::new(NO_PROGRAM) }
+    }
+}
+
+impl ProgramChangeInbox {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&self, program: u8) {
+        self.pending.store(program as u32, Ordering::Release);
+    }
+
+    pub fn take(&self) -> Option<u8> {
+        let program = self.pending.swap(NO_PROGRAM, Ordering::AcqRel);
+        (program != NO_PROGRAM).then_some(program as u8)
+    }
+}
+
+/// Writes `preset` into `dir` under a file name derived from its name, returning the path.
+pub fn save_user_preset(dir: &Path, preset: &Preset) -> Result<PathBuf, Box<dyn Error>> {
+    fs::create_dir_all(dir)?;
+    let path = dir.join(format!("{}.{}", file_stem(&preset.name), EXTENSION));
+    fs::write(&path, preset.to_json()?)?;
+    Ok(path)
+}
+
+/// Renames a saved user preset, moving it to the file matching its new name.
+pub fn rename_user_preset(path: &Path, new_name: &str) -> Result<PathBuf, Box<dyn Error>> {
+    let mut preset = Preset::from_json(&fs::read_to_string(path)?)?;
+    preset.name = new_name.to_string();
+    let dir = path.parent().ok_or("preset has no parent directory")?;
+    let new_path = save_user_preset(dir, &preset)?;
+    if new_path != path {
+        fs::remove_file(path)?;
+    }
+    Ok(new_path)
+}
+
+fn file_stem(name: &str) -> String {
+    let stem: String = name
+        .trim()
+        .chars()
+        .map(|c| if c.is_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect();
+    if stem.is_empty() { "Untitled".to_string() } else { stem }
+}
@@ -0,0 +1,45 @@
+/// One switch trading fidelity for CPU: eco while tracking, high for the final render.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum QualityMode {
+    Eco,
+    Normal,
+    High,
+}
+
+impl QualityMode {
+    pub const ALL: [QualityMode; 3] = [QualityMode::Eco, QualityMode::Normal, QualityMode::High];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            QualityMode::Eco => "Eco",
+            QualityMode::Normal => "Normal",
+            QualityMode::High => "High",
+        }
+    }
+
+    /// Samples between updates of the modulation routes and filter cutoff.
+    pub fn control_rate_period(self) -> usize {
+        match self {
+            QualityMode::Eco => 32,
+            QualityMode::Normal => 8,
+            QualityMode::High => 1,
+        }
+    }
+
+    /// Least internal oversampling of the voice signal path; the oversampling setting can raise it.
+    pub fn oversampling_factor(self) -> usize {
+        match self {
+            QualityMode::Eco | QualityMode::Normal => 1,
+            QualityMode::High => 2,
+        }
+    }
+
+    /// Most stacked unison voices a single note may use.
+    pub fn max_unison_voices(self) -> usize {
+        match self {
+            QualityMode::Eco => 2,
+            QualityMode::Normal => 4,
+            QualityMode::High => 8,
+        }
+    }
+}
@@ -0,0 +1,131 @@
+use crate::envelope::EnvelopeConfig;
+
+/// Share of the way towards a random patch the "Vary" command moves.
+pub const VARIATION_AMOUNT: f32 = 0.25;
+
+/// How a random value is spread over its range.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Spread {
+    Linear,
+    Exponential,    // even in octaves or decades, for frequencies and times
+    Steps,          // whole numbers, for choices; min and max are indices
+}
+
+/// The part of a parameter's range the randomizer keeps to, so a random patch stays playable.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct RandomRange {
+    pub min: f32,
+    pub max: f32,
+    pub spread: Spread,
+}
+
+impl RandomRange {
+    const fn linear(min: f32, max: f32) -> Self {
+        Self { min, max, spread: Spread::Linear }
+    }
+
+    const fn exponential(min: f32, max: f32) -> Self {
+        Self { min, max, spread: Spread::Exponential }
+    }
+
+    const fn steps(min: f32, max: f32) -> Self {
+        Self { min, max, spread: Spread::Steps }
+    }
+
+    /// A value `amount` (0.0 to 1.0) of the way from `current` to the point `random` (0.0
+    /// to 1.0) of the range. A choice either stays or jumps, with `amount` as the odds of jumping.
+    pub fn pick(&self, current: f32, random: f32, amount: f32) -> f32 {
+        let amount = amount.clamp(0.0, 1.0);
+        let random = random.clamp(0.0, 1.0);
+        match self.spread {
+            Spread::Linear => {
+                let target = self.min + random * (self.max - self.min);
+                current + amount * (target - current)
+            }
+            Spread::Exponential => {
+                let (min, max) = (self.min.max(1e-6).ln(), self.max.max(1e-6).ln());
+                let current = current.max(1e-6).ln();
+                let target = min + random * (max - min);
+                (current + amount * (target - current)).exp()
+            }
+            Spread::Steps => {
+                // The draw decides both whether to jump and where to, so one draw does for both
+                if random >= amount {
+                    return current;
+                }
+                let where_to = if amount > 0.0 { random / amount } else { 0.0 };
+                (self.min + where_to * (self.max - self.min + 1.0)).floor().min(self.max)
+            }
+        }
+    }
+}
+
+/// Sine, saw or square; the noise waveforms stay out of random patches.
+pub const WAVEFORM: RandomRange = RandomRange::steps(0.0, 2.0);
+pub const DETUNE_SEMITONES: RandomRange = RandomRange::linear(-0.2, 0.2);
+pub const OSC_VOLUME: RandomRange = RandomRange::linear(0.4, 1.0);
+pub const SUB_LEVEL: RandomRange = RandomRange::linear(0.0, 0.7);
+pub const CUTOFF_HZ: RandomRange = RandomRange::exponential(250.0, 10000.0);
+pub const RESONANCE: RandomRange = RandomRange::exponential(0.5, 4.0);
+pub const FILTER_ENV_AMOUNT: RandomRange = RandomRange::linear(0.0, 0.8);
+pub const ATTACK_SECS: RandomRange = RandomRange::exponential(0.002, 1.5);
+pub const DECAY_SECS: RandomRange = RandomRange::exponential(0.05, 2.0);
+pub const SUSTAIN_LEVEL: RandomRange = RandomRange::linear(0.2, 1.0);
+pub const RELEASE_SECS: RandomRange = RandomRange::exponential(0.05, 3.0);
+
+/// The plugin parameters a random patch moves, by ID, with the range each keeps to.
+/// Everything else, the effects and performance settings included, is left alone.
+pub const PARAM_RANGES: [(&str, RandomRange); 17] = [
+    ("osc1_wave", WAVEFORM),
+    ("osc1_volume", OSC_VOLUME),
+    ("osc2_wave", WAVEFORM),
+    ("osc2_detune", DETUNE_SEMITONES),
+    ("osc2_volume", OSC_VOLUME),
+    ("sub_level", SUB_LEVEL),
+    ("cutoff", CUTOFF_HZ),
+    ("res", RESONANCE),
+    ("flt_env", FILTER_ENV_AMOUNT),
+    ("attack", ATTACK_SECS),
+    ("decay", DECAY_SECS),
+    ("sustain", SUSTAIN_LEVEL),
+    ("release", RELEASE_SECS),
+    ("fenv_attack", ATTACK_SECS),
+    ("fenv_decay", DECAY_SECS),
+    ("fenv_sustain", SUSTAIN_LEVEL),
+    ("fenv_release", RELEASE_SECS),
+];
+
+/// Seeded draws for a random patch; the same seed always gives the same patch.
+pub struct PatchDice {
+    state: u64,
+}
+
+impl PatchDice {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    /// Next draw, 0.0 to just under 1.0.
+    pub fn roll(&mut self) -> f32 {
+        self.state = self.state
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        ((self.state >> 32) as f32) / ((u32::MAX as f32) + 1.0)
+    }
+
+    /// `current` moved `amount` of the way towards a random value of `range`.
+    pub fn pick(&mut self, range: RandomRange, current: f32, amount: f32) -> f32 {
+        range.pick(current, self.roll(), amount)
+    }
+
+    /// `current` with every stage time and the sustain level moved towards random values.
+    pub fn envelope(&mut self, current: &EnvelopeConfig, amount: f32) -> EnvelopeConfig {
+        EnvelopeConfig {
+            attack_time: self.pick(ATTACK_SECS, current.attack_time, amount),
+            decay_time: self.pick(DECAY_SECS, current.decay_time, amount),
+            sustain_level: self.pick(SUSTAIN_LEVEL, current.sustain_level, amount),
+            release_time: self.pick(RELEASE_SECS, current.release_time, amount),
+            ..current.clone()
+        }
+    }
+}
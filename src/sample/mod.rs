@@ -0,0 +1,157 @@
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+/// Whether a sample stops at its end or repeats its loop for as long as the note sounds.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum SampleLoop {
+    OneShot,
+    Loop,
+}
+
+impl SampleLoop {
+    pub const ALL: [SampleLoop; 2] = [SampleLoop::OneShot, SampleLoop::Loop];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            SampleLoop::OneShot => "One-Shot",
+            SampleLoop::Loop => "Loop",
+        }
+    }
+}
+
+/// How the sample layer plays its file. Positions are shares of the file's length, so
+/// they keep their meaning when a different file is loaded.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct SampleConfig {
+    pub level: f32,
+    pub root_note: u8,          // MIDI note the file plays back unpitched at
+    pub start: f32,             // 0.0 to 1.0, where every note starts
+    pub loop_mode: SampleLoop,
+    pub loop_start: f32,        // 0.0 to 1.0
+    pub loop_end: f32,          // 0.0 to 1.0, past loop_start
+}
+
+impl Default for SampleConfig {
+    fn default() -> Self {
+        Self {
+            level: 1.0,
+            root_note: 60,
+            start: 0.0,
+            loop_mode: SampleLoop::OneShot,
+            loop_start: 0.0,
+            loop_end: 1.0,
+        }
+    }
+}
+
+impl SampleConfig {
+    pub fn root_frequency(&self) -> f32 {
+        440.0 * 2.0f32.powf((self.root_note as f32 - 69.0) / 12.0)
+    }
+}
+
+/// A decoded audio file, mixed down to mono.
+#[derive(Clone, Debug, Default)]
+pub struct SampleData {
+    pub frames: Vec<f32>,
+    pub sample_rate: f32,
+}
+
+/// A file and how to play it, as one extra sound source per voice.
+#[derive(Clone)]
+pub struct SampleLayer {
+    pub data: Arc<SampleData>,
+    pub config: SampleConfig,
+}
+
+struct WavFormat {
+    encoding: u16,              // 1 for integer PCM, 3 for float
+    channels: usize,
+    sample_rate: u32,
+    bits: u16,
+}
+
+impl SampleData {
+    pub fn load(path: &Path) -> Result<Self, Box<dyn Error>> {
+        Self::parse_wav(&fs::read(path)?)
+    }
+
+    /// Reads 8, 16, 24 or 32-bit integer and 32-bit float WAV data, any channel count.
+    pub fn parse_wav(bytes: &[u8]) -> Result<Self, Box<dyn Error>> {
+        if bytes.len() < 12 || &bytes[0..4] != b"RIFF" || &bytes[8..12] != b"WAVE" {
+            return Err("not a WAV file".into());
+        }
+
+        let mut format = None;
+        let mut data = None;
+        let mut rest = &bytes[12..];
+        while rest.len() >= 8 {
+            let length = u32::from_le_bytes([rest[4], rest[5], rest[6], rest[7]]) as usize;
+            // Some writers leave the length of a final data chunk wrong; take what is there
+            let body = rest.get(8..8 + length).unwrap_or(&rest[8..]);
+            match &rest[0..4] {
+                b"fmt " => format = Some(WavFormat::parse(body)?),
+                b"data" => data = Some(body),
+                _ => {}
+            }
+            // Chunks are padded to an even length
+            rest = rest.get(8 + length + (length & 1)..).unwrap_or(&[]);
+        }
+
+        let format = format.ok_or("WAV file has no format chunk")?;
+        let frames = format.decode_mono(data.ok_or("WAV file has no data chunk")?)?;
+        if frames.is_empty() {
+            return Err("WAV file has no audio".into());
+        }
+        Ok(Self { frames, sample_rate: format.sample_rate as f32 })
+    }
+
+    pub fn duration_secs(&self) -> f32 {
+        self.frames.len() as f32 / self.sample_rate.max(1.0)
+    }
+}
+
+impl WavFormat {
+    fn parse(body: &[u8]) -> Result<Self, Box<dyn Error>> {
+        if body.len() < 16 {
+            return Err("WAV format chunk is too short".into());
+        }
+        let u16_at = |at: usize| u16::from_le_bytes([body[at], body[at + 1]]);
+        let mut encoding = u16_at(0);
+        // WAVE_FORMAT_EXTENSIBLE keeps the real encoding at the start of its sub-format GUID
+        if encoding == 0xFFFE && body.len() >= 26 {
+            encoding = u16_at(24);
+        }
+        Ok(Self {
+            encoding,
+            channels: u16_at(2) as usize,
+            sample_rate: u32::from_le_bytes([body[4], body[5], body[6], body[7]]),
+            bits: u16_at(14),
+        })
+    }
+
+    fn decode_mono(&self, data: &[u8]) -> Result<Vec<f32>, Box<dyn Error>> {
+        let read: fn(&[u8]) -> f32 = match (self.encoding, self.bits) {
+            (1, 8) => |b| (b[0] as f32 - 128.0) / 128.0,
+            (1, 16) => |b| i16::from_le_bytes([b[0], b[1]]) as f32 / 32768.0,
+            (1, 24) => |b| i32::from_le_bytes([0, b[0], b[1], b[2]]) as f32 / 2_147_483_648.0,
+            (1, 32) => |b| i32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f32 / 2_147_483_648.0,
+            (3, 32) => |b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]),
+            (encoding, bits) => {
+                return Err(format!("{}-bit WAV data in encoding {} is not supported", bits, encoding).into());
+            }
+        };
+        if self.channels == 0 || self.sample_rate == 0 {
+            return Err("WAV file has no channels".into());
+        }
+
+        let bytes_per_sample = self.bits as usize / 8;
+        let scale = 1.0 / self.channels as f32;
+        Ok(data
+            .chunks_exact(bytes_per_sample * self.channels)
+            .map(|frame| frame.chunks_exact(bytes_per_sample).map(read).sum::<f32>() * scale)
+            .collect())
+    }
+}
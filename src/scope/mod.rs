@@ -0,0 +1,131 @@
+use std::f32::consts::PI;
+use std::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
+
+/// Lock-free ring buffer of the most recent output samples. The audio thread is the
+/// only writer; readers (the GUI) copy out the latest window without blocking it.
+pub struct ScopeBuffer {
+    samples: Box<[AtomicU32]>,
+    write_pos: AtomicUsize,
+    sample_rate: AtomicU32,     // f32 bits, so the spectrum view can label frequencies
+}
+
+impl ScopeBuffer {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            samples: (0..capacity.max(1)).map(|_| AtomicU32::new(0)).collect(),
+            write_pos: AtomicUsize::new(0),
+            sample_rate: AtomicU32::new(44100.0_f32.to_bits()),
+        }
+    }
+
+    pub fn set_sample_rate(&self, sample_rate: f32) {
+        self.sample_rate.store(sample_rate.to_bits(), Ordering::Relaxed);
+    }
+
+    pub fn sample_rate(&self) -> f32 {
+        f32::from_bits(self.sample_rate.load(Ordering::Relaxed))
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn push(&self, sample: f32) {
+        let pos = self.write_pos.load(Ordering::Relaxed);
+        self.samples[pos % self.samples.len()].store(sample.to_bits(), Ordering::Relaxed);
+        self.write_pos.store(pos.wrapping_add(1), Ordering::Release);
+    }
+
+    pub fn push_slice(&self, samples: &[f32]) {
+        let len = self.samples.len();
+        let mut pos = self.write_pos.load(Ordering::Relaxed);
+        for &sample in samples {
+            self.samples[pos % len].store(sample.to_bits(), Ordering::Relaxed);
+            pos = pos.wrapping_add(1);
+        }
+        self.write_pos.store(pos, Ordering::Release);
+    }
+
+    /// Total number of samples written so far; changes whenever new audio arrives.
+    pub fn write_position(&self) -> usize {
+        self.write_pos.load(Ordering::Acquire)
+    }
+
+    /// Fills `out` with the latest `out.len()` samples, oldest first.
+    pub fn read_latest(&self, out: &mut [f32]) {
+        let len = self.samples.len();
+        let end = self.write_position();
+        let count = out.len().min(len);
+        let start = end.wrapping_sub(count);
+
+        for (i, sample) in out.iter_mut().enumerate() {
+            *sample = if i < count {
+                f32::from_bits(self.samples[start.wrapping_add(i) % len].load(Ordering::Relaxed))
+            } else {
+                0.0
+            };
+        }
+    }
+}
+
+/// Hann-windowed magnitude spectrum in dBFS of a power-of-two sized block.
+/// Returns `samples.len() / 2` bins from DC up to just below Nyquist.
+pub fn magnitude_spectrum(samples: &[f32]) -> Vec<f32> {
+    let n = samples.len();
+    assert!(n.is_power_of_two(), "spectrum size must be a power of two");
+
+    let mut re: Vec<f32> = samples
+        .iter()
+        .enumerate()
+        .map(|(i, s)| s * (0.5 - 0.5 * (2.0 * PI * i as f32 / n as f32).cos()))
+        .collect();
+    let mut im = vec![0.0_f32; n];
+    fft_in_place(&mut re, &mut im);
+
+    // The Hann window halves the amplitude, and a full-scale sine spreads over two bins
+    let scale = 4.0 / n as f32;
+    (0..n / 2)
+        .map(|i| {
+            let magnitude = (re[i] * re[i] + im[i] * im[i]).sqrt() * scale;
+            20.0 * magnitude.max(1e-9).log10()
+        })
+        .collect()
+}
+
+// Iterative radix-2 Cooley-Tukey
+fn fft_in_place(re: &mut [f32], im: &mut [f32]) {
+    let n = re.len();
+
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            re.swap(i, j);
+            im.swap(i, j);
+        }
+    }
+
+    let mut size = 2;
+    while size <= n {
+        let angle = -2.0 * PI / size as f32;
+        for start in (0..n).step_by(size) {
+            for k in 0..size / 2 {
+                let (sin, cos) = (angle * k as f32).sin_cos();
+                let a = start + k;
+                let b = a + size / 2;
+                let t_re = re[b] * cos - im[b] * sin;
+                let t_im = re[b] * sin + im[b] * cos;
+                re[b] = re[a] - t_re;
+                im[b] = im[a] - t_im;
+                re[a] += t_re;
+                im[a] += t_im;
+            }
+        }
+        size <<= 1;
+    }
+}
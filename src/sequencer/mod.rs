@@ -0,0 +1,207 @@
+use crate::filter::ModulationSource;
+use crate::tempo::{SyncDivision, TransportInfo};
+
+pub const STEP_COUNT: usize = 16;
+
+#[derive(Clone, Copy)]
+pub struct StepSequencerConfig {
+    pub enabled: bool,
+    pub drive_notes: bool,              // also play `notes` through the voice engine
+    pub values: [f32; STEP_COUNT],      // 0.0 to 1.0 modulation value per step
+    pub notes: [Option<u8>; STEP_COUNT],// MIDI note per step, None is a rest
+    pub probability: [f32; STEP_COUNT], // 0.0 to 1.0 chance that a step's note plays
+    pub ratchets: [u8; STEP_COUNT],     // number of evenly spaced retriggers within each step
+    pub length: usize,                  // number of steps used, 1 to STEP_COUNT
+    pub division: SyncDivision,         // length of one step
+    pub glide: f32,                     // 0.0 to 1.0, fraction of a step spent sliding to the next value
+    pub swing: f32,                     // 0.0 to 0.5, delay of every second step as a fraction of a step
+    pub gate: f32,                      // 0.0 to 1.0, note length as a fraction of a step
+    pub humanize: f32,                  // 0.0 to 0.5, maximum random note delay as a fraction of a step
+}
+
+impl Default for StepSequencerConfig {
+    fn default() -> Self {
+        let pattern = [48, 51, 55, 58, 60, 58, 55, 51];
+        Self {
+            enabled: false,
+            drive_notes: false,
+            values: std::array::from_fn(|i| i as f32 / (STEP_COUNT - 1) as f32),
+            notes: std::array::from_fn(|i| Some(pattern[i % pattern.len()])),
+            probability: [1.0; STEP_COUNT],
+            ratchets: [1; STEP_COUNT],
+            length: STEP_COUNT,
+            division: SyncDivision::Sixteenth,
+            glide: 0.0,
+            swing: 0.0,
+            gate: 0.5,
+            humanize: 0.0,
+        }
+    }
+}
+
+/// What happened during one sample of sequencer playback.
+#[derive(Clone, Copy, Default)]
+pub struct SequencerTick {
+    pub value: f32,
+    pub note_off: Option<u8>,
+    pub note_on: Option<u8>,
+}
+
+#[derive(Clone)]
+pub struct StepSequencer {
+    config: StepSequencerConfig,
+    sample_rate: f32,
+    tempo_bpm: f32,
+    position: f64,          // in steps, wraps at an even step count so swing pairs stay aligned
+    current_step: Option<usize>,
+    current_ratchet: Option<usize>,
+    step_plays: bool,           // result of the current step's probability roll
+    sounding_note: Option<u8>,
+    pending_note: Option<u8>,   // note of the current step waiting out its humanize delay
+    note_delay: f32,            // humanize delay of the current step, as a fraction of a step
+    value: f32,
+    rng: u64,
+}
+
+impl StepSequencer {
+    pub fn new(config: StepSequencerConfig, sample_rate: f32) -> Self {
+        Self {
+            config,
+            sample_rate,
+            tempo_bpm: 120.0,
+            position: 0.0,
+            current_step: None,
+            current_ratchet: None,
+            step_plays: false,
+            sounding_note: None,
+            pending_note: None,
+            note_delay: 0.0,
+            value: 0.0,
+            rng: 12345,
+        }
+    }
+
+    /// Replaces the pattern and settings while keeping the playhead where it is.
+    pub fn set_config(&mut self, config: StepSequencerConfig) {
+        self.config = config;
+    }
+
+    pub fn config(&self) -> &StepSequencerConfig {
+        &self.config
+    }
+
+    pub fn update_sample_rate(&mut self, new_sample_rate: f32) {
+        self.sample_rate = new_sample_rate;
+    }
+
+    /// Follows the transport tempo and, while it is playing, locks the playhead to its position.
+    pub fn sync_to_transport(&mut self, transport: &TransportInfo) {
+        self.tempo_bpm = transport.tempo_bpm;
+        if transport.playing {
+            self.position = (transport.position_beats / self.config.division.beats()) % self.wrap_length();
+        }
+    }
+
+    fn wrap_length(&self) -> f64 {
+        let length = self.config.length.clamp(1, STEP_COUNT);
+        (if length % 2 == 0 { length } else { length * 2 }) as f64
+    }
+
+    pub fn tick(&mut self) -> SequencerTick {
+        let mut tick = SequencerTick::default();
+
+        if !self.config.enabled {
+            tick.note_off = self.sounding_note.take();
+            self.pending_note = None;
+            self.current_step = None;
+            self.value = 0.0;
+            return tick;
+        }
+
+        let length = self.config.length.clamp(1, STEP_COUNT);
+        let (step, fraction) = self.step_at(self.position);
+        let step = step % length;
+
+        if self.current_step != Some(step) {
+            self.current_step = Some(step);
+            self.current_ratchet = None;
+            self.step_plays = self.config.drive_notes && self.next_random() < self.config.probability[step];
+            self.note_delay = self.next_random() * self.config.humanize.clamp(0.0, 0.5);
+        }
+
+        // Ratchets split the step into equal parts, each retriggering the note with its own gate
+        let ratchets = self.config.ratchets[step].max(1) as usize;
+        let scaled = fraction * ratchets as f32;
+        let ratchet = (scaled as usize).min(ratchets - 1);
+        let ratchet_fraction = scaled - ratchet as f32;
+        if self.current_ratchet != Some(ratchet) {
+            self.current_ratchet = Some(ratchet);
+            tick.note_off = self.sounding_note.take();
+            self.pending_note = if self.step_plays { self.config.notes[step] } else { None };
+        }
+
+        // Humanized notes start late and keep their gate length
+        if let Some(note) = self.pending_note {
+            if ratchet_fraction >= self.note_delay {
+                tick.note_on = Some(note);
+                self.sounding_note = Some(note);
+                self.pending_note = None;
+            }
+        } else if ratchet_fraction >= (self.config.gate + self.note_delay).min(1.0) {
+            tick.note_off = self.sounding_note.take();
+        }
+
+        let value = self.config.values[step];
+        let glide = self.config.glide.clamp(0.0, 1.0);
+        self.value = if glide > 0.0 && fraction > 1.0 - glide {
+            let next = self.config.values[(step + 1) % length];
+            let t = (fraction - (1.0 - glide)) / glide;
+            value + (next - value) * t * t * (3.0 - 2.0 * t)
+        } else {
+            value
+        };
+        tick.value = self.value;
+
+        let steps_per_second = self.config.division.frequency_hz(self.tempo_bpm);
+        self.position = (self.position + (steps_per_second / self.sample_rate) as f64) % self.wrap_length();
+
+        tick
+    }
+
+    fn next_random(&mut self) -> f32 {
+        self.rng = self.rng
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        ((self.rng >> 32) as f32) / ((u32::MAX as f32) + 1.0)
+    }
+
+    // Maps the playhead to a step index and how far through that step we are, applying swing
+    fn step_at(&self, position: f64) -> (usize, f32) {
+        let swing = self.config.swing.clamp(0.0, 0.5) as f64;
+        let pair = (position / 2.0).floor();
+        let within = position - pair * 2.0;
+        let first = pair as usize * 2;
+
+        if within < 1.0 + swing {
+            (first, (within / (1.0 + swing)) as f32)
+        } else {
+            (first + 1, ((within - 1.0 - swing) / (1.0 - swing)) as f32)
+        }
+    }
+}
+
+impl ModulationSource for StepSequencer {
+    fn next_value(&mut self) -> f32 {
+        self.tick().value
+    }
+
+    fn is_active(&self) -> bool {
+        self.config.enabled
+    }
+
+    fn reset(&mut self) {
+        self.position = 0.0;
+        self.current_step = None;
+        self.current_ratchet = None;
+    }
+}
@@ -0,0 +1,159 @@
+use super::Synthesizer;
+use crate::cpu_meter::CpuMeter;
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+
+/// A running device stream playing a `Synthesizer`. It lives apart from the synth because
+/// `cpal::Stream` may not leave the thread that built it; the synth stays free to be shared
+/// with control threads. Audio stops when this is dropped.
+pub struct AudioOutput {
+    _stream: cpal::Stream,
+    info: StreamInfo,
+}
+
+impl AudioOutput {
+    pub fn start(synth: &Synthesizer, options: StreamConfigOptions) -> Result<Self, Box<dyn std::error::Error>> {
+        let host = cpal::default_host();
+        let device = host.default_output_device()
+            .ok_or("no output device available")?;
+        Self::start_on(synth, &device, options)
+    }
+
+    /// Starts the stream on a specific device, negotiating `options` against what it supports.
+    pub fn start_on(synth: &Synthesizer, device: &cpal::Device, options: StreamConfigOptions) -> Result<Self, Box<dyn std::error::Error>> {
+        println!("Starting audio...");
+        println!("Using audio device: {}", device.name()?);
+
+        let (config, buffer_range) = negotiate_stream_config(device, &options)?;
+        println!("Sample rate: {}", config.sample_rate.0);
+
+        {
+            let mut state = synth.shared_state.lock().unwrap_or_else(|e| e.into_inner());
+            state.sample_rate = config.sample_rate.0 as f32;
+        }
+
+        let channels = config.channels as usize;
+        let callback_frames = Arc::new(AtomicUsize::new(0));
+        let callback_frames_writer = callback_frames.clone();
+        let cpu_meter = Arc::new(CpuMeter::default());
+        let cpu_meter_writer = cpu_meter.clone();
+        let sample_rate = config.sample_rate.0 as f32;
+        let shared_state = synth.shared_state.clone();
+        let stream = device.build_output_stream(
+            &config,
+            move |data: &mut [f32], _| {
+                let started = Instant::now();
+                let frames = data.len() / channels.max(1);
+                callback_frames_writer.store(frames, Ordering::Relaxed);
+                if let Ok(mut state) = shared_state.lock() {
+                    Synthesizer::process_audio(&mut state, data, channels);
+                }
+                cpu_meter_writer.record(started.elapsed(), frames, sample_rate);
+            },
+            |err| eprintln!("an error occurred on stream: {}", err),
+            None
+        )?;
+
+        let info = StreamInfo {
+            sample_rate: config.sample_rate.0,
+            channels: config.channels,
+            requested_buffer_frames: match config.buffer_size {
+                cpal::BufferSize::Fixed(frames) => Some(frames),
+                cpal::BufferSize::Default => None,
+            },
+            supported_buffer_frames: buffer_range,
+            callback_frames,
+            cpu_meter,
+        };
+
+        println!("Playing stream...");
+        stream.play()?;
+        println!("Audio started successfully");
+        Ok(Self { _stream: stream, info })
+    }
+
+    /// What the stream actually got from the device.
+    pub fn info(&self) -> &StreamInfo {
+        &self.info
+    }
+}
+
+/// Requested audio stream settings; `None` keeps the device default. Requests the device
+/// cannot meet are clamped to the nearest supported value.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct StreamConfigOptions {
+    pub buffer_size: Option<u32>,
+    pub sample_rate: Option<u32>,
+    pub channels: Option<u16>,
+}
+
+/// The negotiated stream settings and the buffer size the callback is really being handed.
+pub struct StreamInfo {
+    pub sample_rate: u32,
+    pub channels: u16,
+    pub requested_buffer_frames: Option<u32>,
+    pub supported_buffer_frames: Option<(u32, u32)>,
+    callback_frames: Arc<AtomicUsize>,
+    cpu_meter: Arc<CpuMeter>,
+}
+
+impl StreamInfo {
+    /// Frames per callback as seen by the audio thread, or `None` before the first callback.
+    pub fn callback_frames(&self) -> Option<usize> {
+        match self.callback_frames.load(Ordering::Relaxed) {
+            0 => None,
+            frames => Some(frames),
+        }
+    }
+
+    /// Load of the audio callback, shared so a status display can keep reading it.
+    pub fn cpu_meter(&self) -> Arc<CpuMeter> {
+        self.cpu_meter.clone()
+    }
+
+    /// Output latency of one buffer, from the observed callback size when available.
+    pub fn latency_secs(&self) -> Option<f32> {
+        let frames = self
+            .callback_frames()
+            .map(|f| f as u32)
+            .or(self.requested_buffer_frames)?;
+        Some(frames as f32 / self.sample_rate as f32)
+    }
+}
+
+fn negotiate_stream_config(
+    device: &cpal::Device,
+    options: &StreamConfigOptions,
+) -> Result<(cpal::StreamConfig, Option<(u32, u32)>), Box<dyn std::error::Error>> {
+    let default = device.default_output_config()?;
+    let wanted_channels = options.channels.unwrap_or(default.channels());
+    let wanted_rate = options.sample_rate.unwrap_or(default.sample_rate().0);
+
+    // Prefer an f32 config with the requested channel count, then any f32 config
+    let supported: Vec<_> = device
+        .supported_output_configs()?
+        .filter(|c| c.sample_format() == cpal::SampleFormat::F32)
+        .collect();
+    let range = supported
+        .iter()
+        .find(|c| c.channels() == wanted_channels)
+        .or_else(|| supported.first())
+        .ok_or("device has no f32 output configuration")?;
+
+    let rate = wanted_rate.clamp(range.min_sample_rate().0, range.max_sample_rate().0);
+    let supported_config = range.clone().with_sample_rate(cpal::SampleRate(rate));
+    let buffer_range = match supported_config.buffer_size() {
+        cpal::SupportedBufferSize::Range { min, max } => Some((*min, *max)),
+        cpal::SupportedBufferSize::Unknown => None,
+    };
+
+    let mut config: cpal::StreamConfig = supported_config.into();
+    config.buffer_size = match (options.buffer_size, buffer_range) {
+        (Some(frames), Some((min, max))) => cpal::BufferSize::Fixed(frames.clamp(min, max)),
+        (Some(frames), None) => cpal::BufferSize::Fixed(frames),
+        (None, _) => cpal::BufferSize::Default,
+    };
+    Ok((config, buffer_range))
+}
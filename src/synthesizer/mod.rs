@@ -1,173 +1,1228 @@
-use std::collections::HashMap;
-use crate::envelope::{Envelope, EnvelopeConfig};
-use crate::filter::Filter;
-use crate::oscillator::OscillatorConfig;
-use crate::voice::{Voice, VoiceConfig};
-use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+pub mod audio_output;
+pub mod part;
+mod workers;
+
+pub use audio_output::{AudioOutput, StreamConfigOptions, StreamInfo};
+pub use part::{Part, VoiceMode};
+
+use crate::chord_memory::{ChordMemory, ChordMemoryConfig, ChordShape};
+use crate::denormal::{scrub, DenormalGuard};
+use crate::drift::DriftConfig;
+use crate::dynamics::{AutoGain, OutputNormalization};
+use crate::effects::{CompressorConfig, DelayConfig, Effects, EqConfig, FxChainConfig, ModulationFxConfig, ReverbConfig, SendBus, SendConfig, VoiceInsertConfig, WaveshaperConfig};
+use crate::envelope::{EnvelopeConfig, EnvelopeLevel, EnvelopeState, ReleaseVelocityConfig, MOD_ENVELOPE_COUNT};
+use crate::filter::{DcBlocker, DC_BLOCKER_CUTOFF_HZ, Filter, FilterParameters, FilterRoutingConfig, FilterSlope, FilterType, SaturationCurve};
+use crate::glide::GlideConfig;
+use crate::keyzone::KeyZoneConfig;
+use crate::lfo::{Lfo, LfoConfig, LFO_COUNT};
+use crate::modulation::{controller_source, default_routes, ModulationRoute};
+use crate::oscillator::{AdditiveTables, Harmonics, NoiseTables, OscillatorConfig, DEFAULT_NOISE_SEED};
+use crate::oversampling::{Decimator, VoiceOversampling};
+use crate::quality::QualityMode;
+use crate::randomize::{self, PatchDice};
+use crate::sample::{SampleData, SampleLayer};
+use crate::scope::ScopeBuffer;
+use crate::sequencer::{StepSequencer, StepSequencerConfig};
+use crate::tempo::{InternalClock, TransportInfo};
+use crate::vibrato::VibratoConfig;
+use crate::voice_configuration::Waveform;
+use std::ops::Range;
 use std::sync::{Arc, Mutex};
+use workers::VoiceWorkers;
+
+/// Most parts a multi-timbral setup can hold, one per MIDI channel.
+pub const MAX_PARTS: usize = 16;
+/// Channel mode messages, handled on every part listening to the channel.
+pub const ALL_SOUND_OFF_CC: u8 = 120;
+pub const ALL_NOTES_OFF_CC: u8 = 123;
+/// Voices render this many frames into their own scratch blocks before the block is mixed.
+const RENDER_BLOCK: usize = 64;
 
 pub struct Synthesizer {
-    active_notes: HashMap<u32, Vec<usize>>,
-    next_voice: usize,
     config: SynthesizerConfig,
     shared_state: Arc<Mutex<SharedState>>,
-    stream: Option<cpal::Stream>,
 }
 
 struct SharedState {
-    voices: Vec<Voice>,
+    parts: Vec<Part>,           // never empty; part 0 is the main part
+    chord_memory: ChordMemory,
+    sequencer: StepSequencer,
+    lfos: [Lfo; LFO_COUNT],
+    clock: InternalClock,
+    host_transport: Option<TransportInfo>,
+    audition_samples_left: usize,
+    output_tap: Option<Arc<ScopeBuffer>>,
+    normalization: OutputNormalization,
+    voice_oversampling: usize,  // factor every part's voices render at
+    auto_gain: AutoGain,
+    effects: Effects,
+    dc_blockers: [DcBlocker; 2],  // left and right of the master output
+    silent_frames: usize,       // frames since a voice last played or the output was audible
+    voice_headroom: f32,        // fixed gain per voice, from the size of the whole voice pool
+    master_gain: f32,
     sample_rate: f32,
-    next_voice: usize,
+    mix: MixBlock,
+    voice_solo: Option<usize>,  // index across all parts' voices
+    workers: Option<VoiceWorkers>,  // None renders every voice on the audio thread
+}
+
+/// The parts' mixed voices for one block, frame by frame, before sends and effects.
+struct MixBlock {
+    left: [f32; RENDER_BLOCK],
+    right: [f32; RENDER_BLOCK],
+    sends: [SendBus; RENDER_BLOCK],
+    voices: [usize; RENDER_BLOCK],  // voices sounding in each frame
+}
+
+/// What the voices read in one frame, worked out before the frame renders.
+#[derive(Clone, Copy, Default)]
+struct FrameControls {
+    sequencer: f32,
+    lfos: [f32; 2],
+}
+
+/// Notes the controls start or stop in one frame; rendering stops short of that frame so
+/// they land on it.
+struct NoteChanges {
+    note_off: Option<u8>,
+    note_on: Option<u8>,
+    audition_end: bool,
+}
+
+impl NoteChanges {
+    fn any(&self) -> bool {
+        self.note_off.is_some() || self.note_on.is_some() || self.audition_end
+    }
+}
+
+impl Default for MixBlock {
+    fn default() -> Self {
+        Self {
+            left: [0.0; RENDER_BLOCK],
+            right: [0.0; RENDER_BLOCK],
+            sends: [SendBus::default(); RENDER_BLOCK],
+            voices: [0; RENDER_BLOCK],
+        }
+    }
 }
 
 impl SharedState {
-    fn find_free_voice(&mut self) -> Option<usize> {
-        if self.voices.is_empty() { return None; }
-        if let Some(i) = self.voices.iter().position(|v| !v.is_active) {
-            Some(i)
-        } else {
-            let i = self.next_voice;
-            let len = self.voices.len();
-            self.next_voice = (self.next_voice + 1) % len;
-            Some(i)
+    fn main_part(&mut self) -> &mut Part {
+        &mut self.parts[0]
+    }
+
+    fn parts_on_channel(&mut self, channel: u8) -> impl Iterator<Item = &mut Part> {
+        self.parts.iter_mut().filter(move |p| p.responds_to(channel))
+    }
+
+    fn update_voice_headroom(&mut self) {
+        self.voice_headroom = voice_headroom(self.parts.iter().map(|p| p.voice_count()).sum());
+    }
+
+    fn apply_voice_solo(&mut self) {
+        let solo = self.voice_solo;
+        let mut first = 0;
+        for part in &mut self.parts {
+            let offset = first;
+            part.set_audible(|idx| solo.is_none_or(|s| s == offset + idx));
+            first += part.voice_count();
         }
     }
 }
 
-// Add Send marker for the Synthesizer
-unsafe impl Send for Synthesizer {}
+// Control threads share the synth behind a mutex; keep everything it holds thread-safe
+const _: () = {
+    const fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<Synthesizer>();
+};
 
 impl Synthesizer {
     pub fn new(config: SynthesizerConfig) -> Self {
-        let voice_cfg = VoiceConfig {
-            oscillator_configs: config.oscillator_configs.clone(),
-            filter: config.filter.clone(),
-        };
-
-        let voice_count = config.max_voices.max(1);
-        let voices = (0..voice_count)
-            .map(|_| Voice::new(&voice_cfg, &config.envelope_config, config.sample_rate))
-            .collect::<Vec<_>>();
-
         let shared_state = Arc::new(Mutex::new(SharedState {
-            voices,
+            parts: vec![Part::new(&config, None)],
+            chord_memory: ChordMemory::with_config(config.chord_memory),
+            sequencer: StepSequencer::new(config.sequencer, config.sample_rate),
+            lfos: config.lfos.map(|lfo| Lfo::new(lfo, config.sample_rate)),
+            clock: InternalClock::new(config.tempo_bpm, config.sample_rate),
+            host_transport: None,
+            audition_samples_left: 0,
+            output_tap: None,
+            normalization: config.normalization,
+            voice_oversampling: config.voice_oversampling_factor(),
+            auto_gain: AutoGain::new(config.sample_rate),
+            effects: Effects::new(
+                config.delay,
+                config.reverb,
+                config.waveshaper,
+                config.eq,
+                config.compressor,
+                config.modulation_fx,
+                config.sample_rate,
+            )
+            .with_chain(config.fx_chain),
+            dc_blockers: std::array::from_fn(|_| DcBlocker::new(DC_BLOCKER_CUTOFF_HZ, config.sample_rate)),
+            silent_frames: 0,
+            voice_headroom: voice_headroom(config.max_voices.max(1)),
+            master_gain: config.master_gain,
             sample_rate: config.sample_rate,
-            next_voice: 0,
+            mix: MixBlock::default(),
+            voice_solo: None,
+            workers: VoiceWorkers::new(config.render_threads),
         }));
 
         Self {
-            active_notes: HashMap::new(),
-            next_voice: 0,
             config,
             shared_state,
-            stream: None,
         }
     }
-    pub fn note_on(&mut self, frequency: f32) {
-        let note_id = self.frequency_to_note_id(frequency);
 
-        let mut state = self.shared_state.lock()
-            .unwrap_or_else(|e| e.into_inner());
-
-        let existing_env_value = state.voices.iter()
-            .find(|v| v.is_active && v.note_id == note_id)
-            .map(|v| v.get_envelope_value());
+    /// Adds a part playing `config`'s patch on `midi_channel` (0-based, `None` for all channels).
+    /// Returns its index, or `None` once [`MAX_PARTS`] are in use.
+    pub fn add_part(&mut self, config: &SynthesizerConfig, midi_channel: Option<u8>) -> Option<usize> {
+        let mut state = self.shared_state.lock().unwrap_or_else(|e| e.into_inner());
+        if state.parts.len() >= MAX_PARTS {
+            return None;
+        }
+        let mut part = Part::new(config, midi_channel);
+        part.update_sample_rate(state.sample_rate);
+        part.set_control_period(self.config.quality.control_rate_period());
+        part.set_oversampling(self.config.voice_oversampling_factor());
+        part.set_master_tuning(self.config.master_tuning_semitones());
+        state.parts.push(part);
+        state.update_voice_headroom();
+        state.apply_voice_solo();
+        Some(state.parts.len() - 1)
+    }
 
-        let other_env_value = if !self.config.envelope_config.retrigger { existing_env_value } else { None };
+    pub fn part_count(&self) -> usize {
+        self.shared_state.lock().unwrap_or_else(|e| e.into_inner()).parts.len()
+    }
 
-        let Some(voice_idx) = state.find_free_voice() else {
-            eprintln!("No voices configured; ignoring note_on for {}", note_id);
-            return;
-        };
+    /// Runs `edit` on one part, e.g. to change its patch or channel. Returns false for an unknown index.
+    pub fn edit_part(&mut self, index: usize, edit: impl FnOnce(&mut Part)) -> bool {
+        let mut state = self.shared_state.lock().unwrap_or_else(|e| e.into_inner());
+        match state.parts.get_mut(index) {
+            Some(part) => {
+                edit(part);
+                true
+            }
+            None => false,
+        }
+    }
 
-        state.voices[voice_idx].trigger(frequency, note_id, other_env_value);
-        self.active_notes.entry(note_id).or_default().push(voice_idx);
+    /// Plays a note on the main part; with chord memory on, the remembered chord on top of it.
+    pub fn note_on(&mut self, frequency: f32, velocity: f32) {
+        let mut state = self.shared_state.lock().unwrap_or_else(|e| e.into_inner());
+        let notes = state.chord_memory.note_on(frequency);
+        for note in notes.iter() {
+            state.main_part().start_note(note, frequency_to_note_id(note), velocity);
+        }
     }
 
     pub fn note_off(&mut self, frequency: f32) {
-        let note_id = self.frequency_to_note_id(frequency);
+        let mut state = self.shared_state.lock().unwrap_or_else(|e| e.into_inner());
+        let notes = state.chord_memory.note_off(frequency);
+        for note in notes.iter() {
+            state.main_part().stop_note(frequency_to_note_id(note));
+        }
+    }
+
+    pub fn poly_pressure(&mut self, frequency: f32, pressure: f32) {
+        let mut state = self.shared_state.lock().unwrap_or_else(|e| e.into_inner());
+        state.main_part().poly_pressure(frequency_to_note_id(frequency), pressure);
+    }
+
+    pub fn channel_pressure(&mut self, pressure: f32) {
+        let mut state = self.shared_state.lock().unwrap_or_else(|e| e.into_inner());
+        state.main_part().channel_pressure(pressure);
+    }
+
+    /// Plays a note on every part listening to MIDI `channel` (0-based).
+    pub fn note_on_channel(&mut self, channel: u8, frequency: f32, velocity: f32) {
+        let mut state = self.shared_state.lock().unwrap_or_else(|e| e.into_inner());
+        let notes = state.chord_memory.note_on(frequency);
+        for part in state.parts_on_channel(channel) {
+            for note in notes.iter() {
+                part.start_note(note, frequency_to_note_id(note), velocity);
+            }
+        }
+    }
 
-        let mut state = self.shared_state.lock()
-            .unwrap_or_else(|e| e.into_inner());
+    /// Releases a note on every part listening to MIDI `channel`; `velocity` is the
+    /// note-off velocity that can scale the release time.
+    pub fn note_off_channel(&mut self, channel: u8, frequency: f32, velocity: f32) {
+        let mut state = self.shared_state.lock().unwrap_or_else(|e| e.into_inner());
+        let notes = state.chord_memory.note_off(frequency);
+        for part in state.parts_on_channel(channel) {
+            for note in notes.iter() {
+                part.stop_note_with_velocity(frequency_to_note_id(note), velocity);
+            }
+        }
+    }
+
+    pub fn poly_pressure_channel(&mut self, channel: u8, frequency: f32, pressure: f32) {
+        let mut state = self.shared_state.lock().unwrap_or_else(|e| e.into_inner());
+        for part in state.parts_on_channel(channel) {
+            part.poly_pressure(frequency_to_note_id(frequency), pressure);
+        }
+    }
 
-        if let Some(indices) = self.active_notes.remove(&note_id) {
-            for idx in indices {
-                if let Some(v) = state.voices.get_mut(idx) {
-                    v.release(note_id);
+    pub fn channel_pressure_channel(&mut self, channel: u8, pressure: f32) {
+        let mut state = self.shared_state.lock().unwrap_or_else(|e| e.into_inner());
+        for part in state.parts_on_channel(channel) {
+            part.channel_pressure(pressure);
+        }
+    }
+
+    /// Feeds a controller (`value` 0.0 to 1.0) to the parts on `channel`; expression and
+    /// breath become modulation sources, All Sound Off and All Notes Off silence the parts,
+    /// other controllers are ignored.
+    pub fn control_change_channel(&mut self, channel: u8, cc: u8, value: f32) {
+        let mut state = self.shared_state.lock().unwrap_or_else(|e| e.into_inner());
+        match cc {
+            ALL_SOUND_OFF_CC => {
+                state.chord_memory.clear();
+                state.parts_on_channel(channel).for_each(|part| part.all_sound_off());
+            }
+            ALL_NOTES_OFF_CC => {
+                state.chord_memory.clear();
+                state.parts_on_channel(channel).for_each(|part| part.all_notes_off());
+            }
+            _ => {
+                if let Some(source) = controller_source(cc) {
+                    for part in state.parts_on_channel(channel) {
+                        part.set_modulation_value(source, value);
+                    }
                 }
             }
         }
     }
 
-    fn frequency_to_note_id(&self, frequency: f32) -> u32 {
-        // Convert frequency to a unique identifier
-        // This could be as simple as rounding the frequency to the nearest integer
-        frequency.round() as u32
+    /// Stuck-note recovery: every voice of every part fades out within milliseconds, held
+    /// notes are forgotten and the effect tails are cleared.
+    pub fn panic(&mut self) {
+        let mut state = self.shared_state.lock().unwrap_or_else(|e| e.into_inner());
+        for part in &mut state.parts {
+            part.all_sound_off();
+        }
+        state.chord_memory.clear();
+        state.audition_samples_left = 0;
+        state.effects.reset();
+    }
+
+    // The patch setters below edit the main part; use `edit_part` for the others
+
+    /// Moves the main part's oscillators, filter and envelopes `amount` (0.0 to 1.0) of the
+    /// way towards a random patch drawn from `seed`, keeping each value within a musically
+    /// useful range; see [`randomize::PARAM_RANGES`]. Effects and performance settings stay.
+    pub fn randomize_patch(&mut self, seed: u64, amount: f32) {
+        let mut dice = PatchDice::new(seed);
+
+        let mut oscillators = self.config.oscillator_configs.clone();
+        for (i, oscillator) in oscillators.iter_mut().enumerate() {
+            let current = Waveform::ALL.iter().position(|&w| w == oscillator.waveform).unwrap_or(0);
+            let waveform = dice.pick(randomize::WAVEFORM, current as f32, amount) as usize;
+            oscillator.waveform = Waveform::ALL[waveform.min(Waveform::ALL.len() - 1)];
+            oscillator.volume = dice.pick(randomize::OSC_VOLUME, oscillator.volume, amount);
+            // The first oscillator stays the tuning reference the others detune against
+            if i > 0 {
+                oscillator.detune_semitones = dice.pick(randomize::DETUNE_SEMITONES, oscillator.detune_semitones, amount);
+            }
+        }
+        self.set_oscillator_configs(&oscillators);
+
+        let mut filter = self.config.filter.parameters().clone();
+        filter.cutoff_frequency = dice.pick(randomize::CUTOFF_HZ, filter.cutoff_frequency, amount);
+        filter.resonance_amount = dice.pick(randomize::RESONANCE, filter.resonance_amount, amount);
+        filter.modulation_amount = dice.pick(randomize::FILTER_ENV_AMOUNT, filter.modulation_amount, amount);
+        self.set_filter_parameters(filter);
+
+        let envelope = dice.envelope(&self.config.envelope_config, amount);
+        self.set_envelope_config(envelope);
+        let filter_envelope = dice.envelope(&self.config.filter_envelope_config, amount);
+        self.set_filter_envelope_config(filter_envelope);
+    }
+
+    /// Puts the main part's sound back to the default patch: oscillators, filters, envelopes,
+    /// modulation, glide and the effects. Tuning, gain, quality, the voice pool, key zones,
+    /// chord memory and the sequencer stay.
+    pub fn init_patch(&mut self) {
+        let init = SynthesizerConfig::default();
+        self.set_oscillator_configs(&init.oscillator_configs);
+        self.set_harmonics(init.harmonics);
+        self.set_sample_layer(init.sample_layer);
+        self.set_envelope_config(init.envelope_config);
+        self.set_filter_envelope_config(init.filter_envelope_config);
+        for (index, config) in init.mod_envelope_configs.into_iter().enumerate() {
+            self.set_mod_envelope_config(index, config);
+        }
+        self.set_filter_parameters(init.filter.parameters().clone());
+        self.set_filter2_parameters(init.filter2.parameters().clone());
+        self.set_filter_routing(init.filter_routing);
+        self.set_filter_key_tracking(init.filter_key_tracking);
+        self.set_stereo_filter_spread(init.stereo_filter_spread);
+        self.set_modulation_routes(&init.modulation_routes);
+        for (index, config) in init.lfos.into_iter().enumerate() {
+            self.set_lfo_config(index, config);
+        }
+        self.set_freeze_modulation_on_release(init.freeze_modulation_on_release);
+        self.set_release_velocity_config(init.release_velocity);
+        self.set_glide_config(init.glide);
+        self.set_vibrato_config(init.vibrato);
+        self.set_drift_config(init.drift);
+        self.set_detune_spread(init.detune_spread);
+        self.set_voice_mode(init.voice_mode);
+        self.set_voice_insert(init.voice_insert);
+        self.set_voice_waveshaper_config(init.voice_waveshaper);
+        self.set_send_config(init.sends);
+        self.set_delay_config(init.delay);
+        self.set_reverb_config(init.reverb);
+        self.set_waveshaper_config(init.waveshaper);
+        self.set_eq_config(init.eq);
+        self.set_compressor_config(init.compressor);
+        self.set_modulation_fx_config(init.modulation_fx);
+        self.set_fx_chain(init.fx_chain);
+    }
+
+    pub fn set_oscillator_configs(&mut self, oscillator_configs: &[OscillatorConfig]) {
+        let mut state = self.shared_state.lock().unwrap_or_else(|e| e.into_inner());
+        state.main_part().set_oscillator_configs(oscillator_configs);
+        self.config.oscillator_configs.clear();
+        self.config.oscillator_configs.extend_from_slice(oscillator_configs);
+    }
+
+    /// Sets the harmonic levels of additive oscillators. Builds their tables here, so it is
+    /// too slow for the audio thread; there, hand over tables built elsewhere with
+    /// `set_additive_tables`.
+    pub fn set_harmonics(&mut self, harmonics: Harmonics) {
+        self.set_additive_tables(Arc::new(AdditiveTables::generate(&harmonics)));
+    }
+
+    /// Switches additive oscillators to `tables` in place, so sounding notes carry on.
+    /// Returns the tables it replaces, which the audio thread hands back rather than freeing.
+    pub fn set_additive_tables(&mut self, tables: Arc<AdditiveTables>) -> Arc<AdditiveTables> {
+        let mut state = self.shared_state.lock().unwrap_or_else(|e| e.into_inner());
+        self.config.harmonics = tables.harmonics();
+        state.main_part().set_additive_tables(tables)
+    }
+
+    /// Plays `layer` next to the oscillators of every voice, or no sample with `None`. A new
+    /// file or new settings reach sounding notes in place; only adding or removing the layer
+    /// rebuilds the main part's oscillators. Returns the file it replaces, if it was another
+    /// one, which the audio thread hands back rather than freeing.
+    pub fn set_sample_layer(&mut self, layer: Option<SampleLayer>) -> Option<Arc<SampleData>> {
+        let mut state = self.shared_state.lock().unwrap_or_else(|e| e.into_inner());
+        let rebuild = self.config.sample_layer.is_some() != layer.is_some();
+        let previous = state.main_part().set_sample_layer(layer.clone());
+        if rebuild {
+            state.main_part().set_oscillator_configs(&self.config.oscillator_configs);
+        }
+        self.config.sample_layer = layer;
+        previous.filter(|data| !self.config.sample_layer.as_ref().is_some_and(|layer| Arc::ptr_eq(&layer.data, data)))
+    }
+
+    /// The file the sample layer plays, if there is one.
+    pub fn sample_data(&self) -> Option<Arc<SampleData>> {
+        self.config.sample_layer.as_ref().map(|layer| layer.data.clone())
+    }
+
+    /// Seeds the tables of random oscillators.
+    /// Builds the tables here, so it is too slow for the audio thread; there, hand over
+    /// tables built elsewhere with `set_noise_tables`.
+    pub fn set_noise_seed(&mut self, seed: u64) {
+        self.set_noise_tables(Arc::new(NoiseTables::generate(seed)));
+    }
+
+    /// Switches random oscillators to `tables` in place, so sounding notes carry on.
+    /// Returns the tables it replaces, which the audio thread hands back rather than freeing.
+    pub fn set_noise_tables(&mut self, tables: Arc<NoiseTables>) -> Arc<NoiseTables> {
+        let mut state = self.shared_state.lock().unwrap_or_else(|e| e.into_inner());
+        self.config.noise_seed = tables.seed();
+        state.main_part().set_noise_tables(tables)
+    }
+
+    pub fn set_envelope_config(&mut self, envelope_config: EnvelopeConfig) {
+        let mut state = self.shared_state.lock().unwrap_or_else(|e| e.into_inner());
+        state.main_part().set_envelope_config(envelope_config.clone());
+        self.config.envelope_config = envelope_config;
+    }
+
+    pub fn set_filter_envelope_config(&mut self, envelope_config: EnvelopeConfig) {
+        let mut state = self.shared_state.lock().unwrap_or_else(|e| e.into_inner());
+        state.main_part().set_filter_envelope_config(envelope_config.clone());
+        self.config.filter_envelope_config = envelope_config;
+    }
+
+    /// Configures mod envelope `index` (0 for Mod Env 3, 1 for Mod Env 4) of the main part.
+    pub fn set_mod_envelope_config(&mut self, index: usize, envelope_config: EnvelopeConfig) {
+        if index >= MOD_ENVELOPE_COUNT {
+            return;
+        }
+        let mut state = self.shared_state.lock().unwrap_or_else(|e| e.into_inner());
+        state.main_part().set_mod_envelope_config(index, envelope_config.clone());
+        self.config.mod_envelope_configs[index] = envelope_config;
+    }
+
+    pub fn set_filter_parameters(&mut self, parameters: FilterParameters) {
+        let mut state = self.shared_state.lock().unwrap_or_else(|e| e.into_inner());
+        state.main_part().set_filter_parameters(parameters.clone());
+        self.config.filter.set_parameters(parameters);
+    }
+
+    pub fn set_filter2_parameters(&mut self, parameters: FilterParameters) {
+        let mut state = self.shared_state.lock().unwrap_or_else(|e| e.into_inner());
+        state.main_part().set_filter2_parameters(parameters.clone());
+        self.config.filter2.set_parameters(parameters);
+    }
+
+    /// Adds a DC blocker to every voice of the main part, on top of the one on the master output.
+    pub fn set_voice_dc_blocking(&mut self, enabled: bool) {
+        let mut state = self.shared_state.lock().unwrap_or_else(|e| e.into_inner());
+        state.main_part().set_dc_blocking(enabled);
+        self.config.voice_dc_blocking = enabled;
+    }
+
+    /// Turns the oscillator stack's automatic make-up attenuation on or off.
+    pub fn set_oscillator_gain_compensation(&mut self, enabled: bool) {
+        let mut state = self.shared_state.lock().unwrap_or_else(|e| e.into_inner());
+        state.main_part().set_gain_compensation(enabled);
+        self.config.oscillator_gain_compensation = enabled;
+    }
+
+    /// Scales every oscillator detune and the per-note detune of the main part at once;
+    /// cheap enough to follow host automation block by block.
+    pub fn set_detune_spread(&mut self, spread: f32) {
+        let mut state = self.shared_state.lock().unwrap_or_else(|e| e.into_inner());
+        state.main_part().set_detune_spread(spread);
+        self.config.detune_spread = spread;
+    }
+
+    /// Moves the main part's filter cutoffs with the played note; 1.0 tracks the keyboard
+    /// one octave per octave, centred on middle C.
+    pub fn set_filter_key_tracking(&mut self, amount: f32) {
+        let mut state = self.shared_state.lock().unwrap_or_else(|e| e.into_inner());
+        state.main_part().set_key_tracking(amount);
+        self.config.filter_key_tracking = amount;
+    }
+
+    /// Retunes every part at once; the voices slide to the new pitch instead of jumping.
+    pub fn set_master_tuning(&mut self, fine_tune_cents: f32, transpose_semitones: i32) {
+        self.config.master_tune_cents = fine_tune_cents;
+        self.config.transpose_semitones = transpose_semitones;
+        let semitones = self.config.master_tuning_semitones();
+        let mut state = self.shared_state.lock().unwrap_or_else(|e| e.into_inner());
+        for part in &mut state.parts {
+            part.set_master_tuning(semitones);
+        }
+    }
+
+    pub fn set_voice_mode(&mut self, mode: VoiceMode) {
+        let mut state = self.shared_state.lock().unwrap_or_else(|e| e.into_inner());
+        state.main_part().set_voice_mode(mode);
+        self.config.voice_mode = mode;
+    }
+
+    /// Turns chord memory on or off and sets the remembered chord. Chords already sounding
+    /// are still released as they were started.
+    pub fn set_chord_memory(&mut self, config: ChordMemoryConfig) {
+        let mut state = self.shared_state.lock().unwrap_or_else(|e| e.into_inner());
+        state.chord_memory.set_config(config);
+        self.config.chord_memory = config;
+    }
+
+    /// Remembers the keys played right now as the chord, or returns `None` when none are held.
+    pub fn capture_chord(&mut self) -> Option<ChordShape> {
+        let mut state = self.shared_state.lock().unwrap_or_else(|e| e.into_inner());
+        let shape = state.chord_memory.capture()?;
+        self.config.chord_memory = *state.chord_memory.config();
+        Some(shape)
+    }
+
+    /// Key split or layer of the main part's oscillators, and the keys the part answers.
+    pub fn set_key_zones(&mut self, key_zones: KeyZoneConfig) {
+        let mut state = self.shared_state.lock().unwrap_or_else(|e| e.into_inner());
+        state.main_part().set_key_zones(key_zones);
+        self.config.key_zones = key_zones;
+    }
+
+    /// Picks the drive or chorus that runs inside every voice of the main part.
+    pub fn set_voice_insert(&mut self, config: VoiceInsertConfig) {
+        let mut state = self.shared_state.lock().unwrap_or_else(|e| e.into_inner());
+        state.main_part().set_insert_config(config);
+        self.config.voice_insert = config;
+    }
+
+    /// Chooses how filter 2 is wired to filter 1 (bypassed, serial, parallel or split).
+    pub fn set_filter_routing(&mut self, routing: FilterRoutingConfig) {
+        let mut state = self.shared_state.lock().unwrap_or_else(|e| e.into_inner());
+        state.main_part().set_filter_routing(routing);
+        self.config.filter_routing = routing;
+    }
+
+    /// Plays a short preview note while nothing else is held, so edits can be heard without a keyboard.
+    /// Calling it again while the preview sounds just extends it.
+    pub fn audition(&mut self, frequency: f32, duration_secs: f32) {
+        let mut state = self.shared_state.lock().unwrap_or_else(|e| e.into_inner());
+        let samples = (duration_secs * state.sample_rate) as usize;
+
+        if state.audition_samples_left > 0 {
+            state.audition_samples_left = samples;
+        } else if !state.main_part().has_active_notes() {
+            state.main_part().start_note(frequency, AUDITION_NOTE_ID, 0.8);
+            state.audition_samples_left = samples;
+        }
+    }
+
+    /// Copies `routes` into the voices in place; nothing is allocated unless the list grows.
+    pub fn set_modulation_routes(&mut self, routes: &[ModulationRoute]) {
+        let mut state = self.shared_state.lock().unwrap_or_else(|e| e.into_inner());
+        state.main_part().set_modulation_routes(routes);
+        self.config.modulation_routes.clear();
+        self.config.modulation_routes.extend_from_slice(routes);
+    }
+
+    /// Gives every voice the custom modulation sources registered since the synth was
+    /// created. Allocates, so call it from a control thread rather than the audio thread.
+    pub fn refresh_modulation_sources(&mut self) {
+        let mut state = self.shared_state.lock().unwrap_or_else(|e| e.into_inner());
+        for part in &mut state.parts {
+            part.refresh_custom_sources();
+        }
+    }
+
+    pub fn set_sequencer(&mut self, config: StepSequencerConfig) {
+        let mut state = self.shared_state.lock().unwrap_or_else(|e| e.into_inner());
+        state.sequencer.set_config(config);
+    }
+
+    /// Configures LFO `index` (0-based, below `LFO_COUNT`); other indices are ignored.
+    pub fn set_lfo_config(&mut self, index: usize, config: LfoConfig) {
+        let mut state = self.shared_state.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(lfo) = state.lfos.get_mut(index) {
+            lfo.set_config(config);
+            self.config.lfos[index] = config;
+        }
+    }
+
+
+    /// Hands the host's transport to the engine; from then on the internal clock is no longer used.
+    pub fn set_transport(&mut self, transport: TransportInfo) {
+        let mut state = self.shared_state.lock().unwrap_or_else(|e| e.into_inner());
+        state.host_transport = Some(transport);
+    }
+
+    /// Sets the tempo of the internal clock used when no host transport is available.
+    pub fn set_tempo(&mut self, tempo_bpm: f32) {
+        let mut state = self.shared_state.lock().unwrap_or_else(|e| e.into_inner());
+        state.clock.set_tempo(tempo_bpm);
+    }
+
+    pub fn transport(&self) -> TransportInfo {
+        let state = self.shared_state.lock().unwrap_or_else(|e| e.into_inner());
+        state.host_transport.unwrap_or_else(|| state.clock.info())
+    }
+
+    /// Copies everything the engine renders into `tap`, e.g. for an oscilloscope.
+    pub fn set_output_tap(&mut self, tap: Option<Arc<ScopeBuffer>>) {
+        let mut state = self.shared_state.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(tap) = &tap {
+            tap.set_sample_rate(state.sample_rate);
+        }
+        state.output_tap = tap;
+    }
+
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        let mut state = self.shared_state.lock().unwrap_or_else(|e| e.into_inner());
+        state.sample_rate = sample_rate;
+        state.auto_gain.update_sample_rate(sample_rate);
     }
 
+    /// The patch as last set, e.g. to change one field of a config struct.
+    pub fn config(&self) -> &SynthesizerConfig {
+        &self.config
+    }
 
-    pub fn start_audio(&mut self) -> Result<(), Box<dyn std::error::Error>> {
-        println!("Starting audio...");
-        let host = cpal::default_host();
-        let device = host.default_output_device()
-            .ok_or("no output device available")?;
-        println!("Using audio device: {}", device.name()?);
+    /// Renders into a mono buffer, for hosts that drive processing themselves.
+    pub fn render(&mut self, buffer: &mut [f32]) {
+        let mut state = self.shared_state.lock().unwrap_or_else(|e| e.into_inner());
+        Self::process_audio(&mut state, buffer, 1);
+    }
 
-        let config = device.default_output_config()?;
-        println!("Sample rate: {}", config.sample_rate().0);
+    /// Renders into separate left and right buffers of equal length.
+    pub fn render_stereo(&mut self, left: &mut [f32], right: &mut [f32]) {
+        let mut state = self.shared_state.lock().unwrap_or_else(|e| e.into_inner());
+        Self::process_stereo(&mut state, left, right);
+    }
 
-        {
+    /// Spreads the voices over `threads` threads, the audio thread included, or keeps them all
+    /// on the audio thread with 1, or when no worker thread could be started. Either way
+    /// renders the same samples.
+    pub fn set_render_threads(&mut self, threads: usize) {
+        let workers = VoiceWorkers::new(threads);
+        let old_workers = {
             let mut state = self.shared_state.lock().unwrap_or_else(|e| e.into_inner());
-            state.sample_rate = config.sample_rate().0 as f32;
+            std::mem::replace(&mut state.workers, workers)
+        };
+        // Joining the old threads takes a moment; keep it out of the audio thread's way
+        drop(old_workers);
+        self.config.render_threads = threads.max(1);
+    }
+
+    /// Plays only one voice, counted across all parts in order, or every voice again with `None`.
+    pub fn set_voice_solo(&mut self, voice: Option<usize>) {
+        let mut state = self.shared_state.lock().unwrap_or_else(|e| e.into_inner());
+        state.voice_solo = voice;
+        state.apply_voice_solo();
+    }
+
+    pub fn set_stereo_filter_spread(&mut self, octaves: f32) {
+        let mut state = self.shared_state.lock().unwrap_or_else(|e| e.into_inner());
+        state.main_part().set_stereo_filter_spread(octaves);
+        self.config.stereo_filter_spread = octaves;
+    }
+
+    /// How the main part's note-off velocity, or a fixed value, scales release times.
+    pub fn set_release_velocity_config(&mut self, config: ReleaseVelocityConfig) {
+        let mut state = self.shared_state.lock().unwrap_or_else(|e| e.into_inner());
+        state.main_part().set_release_velocity_config(config);
+        self.config.release_velocity = config;
+    }
+
+    pub fn set_freeze_modulation_on_release(&mut self, freeze: bool) {
+        let mut state = self.shared_state.lock().unwrap_or_else(|e| e.into_inner());
+        state.main_part().set_freeze_modulation_on_release(freeze);
+        self.config.freeze_modulation_on_release = freeze;
+    }
+
+    pub fn set_glide_config(&mut self, config: GlideConfig) {
+        let mut state = self.shared_state.lock().unwrap_or_else(|e| e.into_inner());
+        state.main_part().set_glide_config(config);
+        self.config.glide = config;
+    }
+
+    pub fn set_vibrato_config(&mut self, config: VibratoConfig) {
+        let mut state = self.shared_state.lock().unwrap_or_else(|e| e.into_inner());
+        state.main_part().set_vibrato_config(config);
+        self.config.vibrato = config;
+    }
+
+    pub fn set_drift_config(&mut self, config: DriftConfig) {
+        let mut state = self.shared_state.lock().unwrap_or_else(|e| e.into_inner());
+        state.main_part().set_drift_config(config);
+        self.config.drift = config;
+    }
+
+    /// Delay and reverb send levels of the main part's voices.
+    pub fn set_send_config(&mut self, config: SendConfig) {
+        let mut state = self.shared_state.lock().unwrap_or_else(|e| e.into_inner());
+        state.main_part().set_send_config(config);
+        self.config.sends = config;
+    }
+
+    pub fn set_delay_config(&mut self, config: DelayConfig) {
+        let mut state = self.shared_state.lock().unwrap_or_else(|e| e.into_inner());
+        state.effects.delay.set_config(config);
+        self.config.delay = config;
+    }
+
+    /// Oversampled waveshaper on the master bus, ahead of the compressor.
+    pub fn set_waveshaper_config(&mut self, config: WaveshaperConfig) {
+        let mut state = self.shared_state.lock().unwrap_or_else(|e| e.into_inner());
+        state.effects.waveshaper.set_config(config);
+        self.config.waveshaper = config;
+    }
+
+    /// Waveshaper inside every voice of the main part, between the oscillators and the filters.
+    pub fn set_voice_waveshaper_config(&mut self, config: WaveshaperConfig) {
+        let mut state = self.shared_state.lock().unwrap_or_else(|e| e.into_inner());
+        state.main_part().set_waveshaper_config(config);
+        self.config.voice_waveshaper = config;
+    }
+
+    /// Three-band EQ on the master bus, between the waveshaper and the compressor.
+    pub fn set_eq_config(&mut self, config: EqConfig) {
+        let mut state = self.shared_state.lock().unwrap_or_else(|e| e.into_inner());
+        state.effects.eq.set_config(config);
+        self.config.eq = config;
+    }
+
+    pub fn set_compressor_config(&mut self, config: CompressorConfig) {
+        let mut state = self.shared_state.lock().unwrap_or_else(|e| e.into_inner());
+        state.effects.compressor.set_config(config);
+        self.config.compressor = config;
+    }
+
+    /// Order of the master bus inserts and of the delay and reverb sends.
+    pub fn set_fx_chain(&mut self, chain: FxChainConfig) {
+        let mut state = self.shared_state.lock().unwrap_or_else(|e| e.into_inner());
+        state.effects.set_chain(chain);
+        self.config.fx_chain = chain;
+    }
+
+    /// Phaser and flanger on the master bus.
+    pub fn set_modulation_fx_config(&mut self, config: ModulationFxConfig) {
+        let mut state = self.shared_state.lock().unwrap_or_else(|e| e.into_inner());
+        state.effects.set_modulation_config(config);
+        self.config.modulation_fx = config;
+    }
+
+    pub fn set_reverb_config(&mut self, config: ReverbConfig) {
+        let mut state = self.shared_state.lock().unwrap_or_else(|e| e.into_inner());
+        state.effects.reverb.set_config(config);
+        self.config.reverb = config;
+    }
+
+    /// Applies a quality mode to every part.
+    pub fn set_quality(&mut self, quality: QualityMode) {
+        self.config.quality = quality;
+        let oversampling = self.config.voice_oversampling_factor();
+        let mut state = self.shared_state.lock().unwrap_or_else(|e| e.into_inner());
+        state.voice_oversampling = oversampling;
+        for part in &mut state.parts {
+            part.set_control_period(quality.control_rate_period());
+            part.set_oversampling(oversampling);
         }
+    }
 
-        let shared_state = self.shared_state.clone();
-        let stream = device.build_output_stream(
-            &config.into(),
-            move |data: &mut [f32], _| {
-                if let Ok(mut state) = shared_state.lock() {
-                    Self::process_audio(&mut state, data);
-                }
-            },
-            |err| eprintln!("an error occurred on stream: {}", err),
-            None
-        )?;
+    /// Renders every voice at a multiple of the output rate. The quality mode may ask for
+    /// more, in which case that wins.
+    pub fn set_voice_oversampling(&mut self, oversampling: VoiceOversampling) {
+        self.config.oversampling = oversampling;
+        let factor = self.config.voice_oversampling_factor();
+        let mut state = self.shared_state.lock().unwrap_or_else(|e| e.into_inner());
+        state.voice_oversampling = factor;
+        for part in &mut state.parts {
+            part.set_oversampling(factor);
+        }
+    }
+
+    pub fn quality(&self) -> QualityMode {
+        self.config.quality
+    }
+
+    /// Output gain applied after normalization, 1.0 for unity.
+    pub fn set_master_gain(&mut self, gain: f32) {
+        let mut state = self.shared_state.lock().unwrap_or_else(|e| e.into_inner());
+        state.master_gain = gain.max(0.0);
+        self.config.master_gain = gain.max(0.0);
+    }
+
+    pub fn set_output_normalization(&mut self, normalization: OutputNormalization) {
+        let mut state = self.shared_state.lock().unwrap_or_else(|e| e.into_inner());
+        if state.normalization != normalization {
+            state.auto_gain.reset();
+        }
+        state.normalization = normalization;
+        self.config.normalization = normalization;
+    }
+
+    /// Whether anything is still playing or about to: held or releasing voices, a sequencer
+    /// that plays notes by itself, or effect and lookahead tails. Hosts may suspend the
+    /// plugin once this is false.
+    pub fn is_sounding(&self) -> bool {
+        let state = self.shared_state.lock().unwrap_or_else(|e| e.into_inner());
+        let sequencer = state.sequencer.config();
+        if sequencer.enabled && sequencer.drive_notes {
+            return true;
+        }
+        state.silent_frames < state.effects.silent_gap_samples(state.sample_rate) + Self::latency(&state)
+    }
+
+    /// Voice usage across all parts, for spotting when the polyphony limit is reached.
+    pub fn voice_stats(&self) -> VoiceStats {
+        let mut stats = VoiceStats::default();
+        self.read_voice_stats(&mut stats);
+        stats
+    }
+
+    /// Handles to the amp envelope level of every voice, counted across all parts in order.
+    /// Reading them never locks the synth; they go stale once the voice pools are rebuilt.
+    pub fn envelope_levels(&self) -> Vec<Arc<EnvelopeLevel>> {
+        let state = self.shared_state.lock().unwrap_or_else(|e| e.into_inner());
+        state.parts.iter().flat_map(|p| p.envelope_levels()).collect()
+    }
+
+    /// Like `voice_stats`, but refills an existing value so the audio thread doesn't allocate.
+    pub fn read_voice_stats(&self, stats: &mut VoiceStats) {
+        stats.clear();
+        let state = self.shared_state.lock().unwrap_or_else(|e| e.into_inner());
+        for part in &state.parts {
+            part.accumulate_voice_stats(stats);
+        }
+    }
+
+    /// Output delay introduced by the voices' and the master waveshaper's oversampling and
+    /// the current normalization mode.
+    pub fn latency_samples(&self) -> usize {
+        let state = self.shared_state.lock().unwrap_or_else(|e| e.into_inner());
+        Self::latency(&state)
+    }
+
+    fn latency(state: &SharedState) -> usize {
+        let normalization = match state.normalization {
+            OutputNormalization::FixedHeadroom => 0,
+            OutputNormalization::AutoGain => state.auto_gain.latency_samples(),
+        };
+        let voices = Decimator::latency_samples(state.voice_oversampling).round() as usize;
+        voices + state.effects.waveshaper.latency_samples() + normalization
+    }
+
+    fn begin_block(state: &mut SharedState) {
+        let sample_rate = state.sample_rate;
+        for part in &mut state.parts {
+            part.update_sample_rate(sample_rate);
+        }
+        state.sequencer.update_sample_rate(sample_rate);
+        for lfo in &mut state.lfos {
+            lfo.update_sample_rate(sample_rate);
+        }
+        state.clock.update_sample_rate(sample_rate);
+        state.auto_gain.update_sample_rate(sample_rate);
+        state.effects.update_sample_rate(sample_rate);
+        for blocker in &mut state.dc_blockers {
+            blocker.update_sample_rate(sample_rate);
+        }
+
+        let transport = state.host_transport.unwrap_or_else(|| state.clock.info());
+        state.sequencer.sync_to_transport(&transport);
+        for lfo in &mut state.lfos {
+            lfo.sync_to_transport(&transport);
+        }
+        state.effects.sync_to_transport(&transport);
+    }
+
+    /// Steps the sequencer, LFOs and audition timer by one frame.
+    fn advance_controls(state: &mut SharedState) -> (FrameControls, NoteChanges) {
+        let tick = state.sequencer.tick();
+        let lfos = [state.lfos[0].next_value(), state.lfos[1].next_value()];
+        let mut audition_end = false;
+        if state.audition_samples_left > 0 {
+            state.audition_samples_left -= 1;
+            audition_end = state.audition_samples_left == 0;
+        }
+        let changes = NoteChanges { note_off: tick.note_off, note_on: tick.note_on, audition_end };
+        (FrameControls { sequencer: tick.value, lfos }, changes)
+    }
 
-        println!("Playing stream...");
-        stream.play()?;
-        self.stream = Some(stream);
-        println!("Audio started successfully");
-        Ok(())
+    fn apply_note_changes(state: &mut SharedState, changes: &NoteChanges) {
+        if let Some(note) = changes.note_off {
+            state.main_part().stop_note(frequency_to_note_id(midi_note_to_freq(note)));
+        }
+        if let Some(note) = changes.note_on {
+            let frequency = midi_note_to_freq(note);
+            state.main_part().start_note(frequency, frequency_to_note_id(frequency), 1.0);
+        }
+        if changes.audition_end {
+            state.main_part().stop_note(AUDITION_NOTE_ID);
+        }
     }
 
-    fn process_audio(state: &mut SharedState, buffer: &mut [f32]) {
-        // Keep the pool intact
-        for voice in &mut state.voices {
-            voice.update_sample_rate(state.sample_rate);
+    /// Renders up to `RENDER_BLOCK` frames of every voice into its scratch block, then mixes
+    /// them. Voices render a segment at a time, each segment ending where a note starts or
+    /// stops or a watchdog is due, so those land on the right frame wherever the voices run.
+    fn render_block(state: &mut SharedState, frames: usize) {
+        let mut controls = [FrameControls::default(); RENDER_BLOCK];
+        let mut start = 0;
+        let mut end = 0;    // the frame the current segment has to stop at, at the latest
+        for index in 0..frames {
+            let (frame, changes) = Self::advance_controls(state);
+            controls[index] = frame;
+            if index == 0 || index == end || changes.any() {
+                Self::render_segment(state, start..index, &controls);
+                Self::apply_note_changes(state, &changes);
+                start = index;
+                end = state.parts.iter_mut().map(|p| index + p.begin_segment()).min().unwrap_or(frames);
+            }
         }
+        Self::render_segment(state, start..frames, &controls);
 
-        for sample in buffer.iter_mut() {
-            let mut sum = 0.0;
+        // Summed in voice order, so the sends don't depend on which thread finished first
+        for index in 0..frames {
+            let mut sends = SendBus::default();
             let mut count = 0;
+            for part in &state.parts {
+                count += part.add_frame_sends(index, &mut sends);
+            }
+            state.mix.sends[index] = sends;
+            state.mix.voices[index] = count;
+        }
+
+        let (left, right) = (&mut state.mix.left[..frames], &mut state.mix.right[..frames]);
+        left.fill(0.0);
+        right.fill(0.0);
+        for part in &mut state.parts {
+            part.mix_block(left, right);
+        }
+    }
+
+    fn render_segment(state: &mut SharedState, range: Range<usize>, controls: &[FrameControls]) {
+        if range.is_empty() {
+            return;
+        }
+        let workers = state.workers.as_ref();
+        for part in &mut state.parts {
+            part.render_segment(range.clone(), controls, workers);
+        }
+    }
+
+    /// Runs frame `index` of the mixed block through the sends, effects and master stage.
+    fn finish_frame(state: &mut SharedState, index: usize) -> (f32, f32) {
+        let (left, right) = (state.mix.left[index], state.mix.right[index]);
+        let sends = state.mix.sends[index];
+        let count = state.mix.voices[index];
+
+        // Sends are scaled like the dry mix, and the effects keep running so their tails ring out
+        let scale = match state.normalization {
+            OutputNormalization::FixedHeadroom => state.voice_headroom,
+            OutputNormalization::AutoGain => 1.0,
+        };
+        let (wet_left, wet_right) = state.effects.process(sends.scaled(scale));
+        let mut mixed = (left * scale + wet_left, right * scale + wet_right);
+
+        // Keep a bad sample from circulating in the effect feedback paths
+        if !(mixed.0.is_finite() && mixed.1.is_finite()) {
+            state.effects.reset();
+            for blocker in &mut state.dc_blockers {
+                blocker.reset();
+            }
+            mixed = (0.0, 0.0);
+        }
+        let mixed = state.effects.process_inserts((
+            state.dc_blockers[0].process_sample(mixed.0),
+            state.dc_blockers[1].process_sample(mixed.1),
+        ));
+
+        let frame = match state.normalization {
+            OutputNormalization::FixedHeadroom => mixed,
+            OutputNormalization::AutoGain => state.auto_gain.process(mixed.0, mixed.1),
+        };
+        let frame = (scrub(frame.0 * state.master_gain), scrub(frame.1 * state.master_gain));
+        if count > 0 || frame.0.abs() > SILENCE_THRESHOLD || frame.1.abs() > SILENCE_THRESHOLD {
+            state.silent_frames = 0;
+        } else {
+            state.silent_frames = state.silent_frames.saturating_add(1);
+        }
+        if let Some(tap) = &state.output_tap {
+            tap.push(0.5 * (frame.0 + frame.1));
+        }
+        frame
+    }
+
+    /// Renders interleaved frames; the first two channels get left and right, any others the left.
+    fn process_audio(state: &mut SharedState, buffer: &mut [f32], channels: usize) {
+        let _denormals = DenormalGuard::enable();
+        Self::begin_block(state);
 
-            for v in &mut state.voices {
-                if v.is_active() {
-                    sum += v.next_sample();
-                    count += 1;
+        let channels = channels.max(1);
+        for block in buffer.chunks_mut(channels * RENDER_BLOCK) {
+            Self::render_block(state, block.len().div_ceil(channels));
+            for (index, frame) in block.chunks_mut(channels).enumerate() {
+                let (left, right) = Self::finish_frame(state, index);
+                if channels == 1 {
+                    frame[0] = 0.5 * (left + right);
+                    continue;
+                }
+                for (i, sample) in frame.iter_mut().enumerate() {
+                    *sample = if i == 1 { right } else { left };
                 }
             }
+        }
 
-            *sample = if count > 0 { sum / count as f32 } else { 0.0 };
+        state.clock.advance(buffer.len() / channels);
+    }
+
+    fn process_stereo(state: &mut SharedState, left: &mut [f32], right: &mut [f32]) {
+        let _denormals = DenormalGuard::enable();
+        Self::begin_block(state);
+
+        for (left, right) in left.chunks_mut(RENDER_BLOCK).zip(right.chunks_mut(RENDER_BLOCK)) {
+            Self::render_block(state, left.len().min(right.len()));
+            for (index, (l, r)) in left.iter_mut().zip(right.iter_mut()).enumerate() {
+                (*l, *r) = Self::finish_frame(state, index);
+            }
         }
+
+        state.clock.advance(left.len());
+    }
+}
+
+// Outside the range of rounded note frequencies, so a preview never collides with a played note
+const AUDITION_NOTE_ID: u32 = u32::MAX;
+const SILENCE_THRESHOLD: f32 = 1e-5;    // about -100 dBFS
+
+// Enough headroom for every voice at full level with uncorrelated phases
+fn voice_headroom(voices: usize) -> f32 {
+    1.0 / (voices.max(1) as f32).sqrt()
+}
+
+fn frequency_to_note_id(frequency: f32) -> u32 {
+    // Convert frequency to a unique identifier
+    // This could be as simple as rounding the frequency to the nearest integer
+    frequency.round() as u32
+}
+
+pub fn midi_note_to_freq(note: u8) -> f32 {
+    440.0 * 2.0_f32.powf((note as f32 - 69.0) / 12.0)
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct VoiceStats {
+    pub active_voices: usize,
+    pub max_voices: usize,
+    pub stolen_voices: u64,             // total since the synth was created
+    pub stuck_voices: u64,              // released by the watchdog, total since the synth was created
+    pub stages: Vec<EnvelopeState>,     // amp envelope stage of every voice, part by part
+    pub levels: Vec<f32>,               // peak output of every voice over the last block
+}
+
+impl VoiceStats {
+    fn clear(&mut self) {
+        self.active_voices = 0;
+        self.max_voices = 0;
+        self.stolen_voices = 0;
+        self.stuck_voices = 0;
+        self.stages.clear();
+        self.levels.clear();
     }
 }
 
 #[derive(Clone)]
 pub struct SynthesizerConfig {
     pub oscillator_configs: Vec<OscillatorConfig>,
+    pub noise_seed: u64,            // table seed of random oscillators
+    pub harmonics: Harmonics,       // levels of additive oscillators
+    pub sample_layer: Option<SampleLayer>,
     pub envelope_config: EnvelopeConfig,
     pub filter: Filter,
+    pub filter2: Filter,
+    pub filter_routing: FilterRoutingConfig,
     pub filter_envelope_config: EnvelopeConfig,
+    pub mod_envelope_configs: [EnvelopeConfig; MOD_ENVELOPE_COUNT],
+    pub modulation_routes: Vec<ModulationRoute>,
+    pub freeze_modulation_on_release: bool,
+    pub release_velocity: ReleaseVelocityConfig,
+    pub glide: GlideConfig,
+    pub vibrato: VibratoConfig,
+    pub drift: DriftConfig,
+    pub sends: SendConfig,
+    pub delay: DelayConfig,
+    pub reverb: ReverbConfig,
+    pub waveshaper: WaveshaperConfig,
+    pub eq: EqConfig,
+    pub compressor: CompressorConfig,
+    pub modulation_fx: ModulationFxConfig,
+    pub fx_chain: FxChainConfig,
+    pub stereo_filter_spread: f32,  // octaves between left and right cutoff, 0.0 for a mono filter
+    pub voice_dc_blocking: bool,
+    pub voice_insert: VoiceInsertConfig,
+    pub voice_waveshaper: WaveshaperConfig,
+    pub oscillator_gain_compensation: bool,
+    pub detune_spread: f32,         // 1.0 plays the oscillator detunes as configured
+    pub filter_key_tracking: f32,   // 0.0 to 1.0
+    pub voice_mode: VoiceMode,
+    pub key_zones: KeyZoneConfig,
+    pub chord_memory: ChordMemoryConfig,
+    pub master_tune_cents: f32,     // -100 to 100, applies to every part
+    pub transpose_semitones: i32,   // -24 to 24, applies to every part
+    pub sequencer: StepSequencerConfig,
+    pub lfos: [LfoConfig; LFO_COUNT],
+    pub tempo_bpm: f32,
+    pub normalization: OutputNormalization,
+    pub master_gain: f32,
+    pub quality: QualityMode,
+    pub oversampling: VoiceOversampling,
     pub max_voices: usize,
+    pub render_threads: usize,      // voices are spread over this many threads; 1 keeps them on the audio thread
     pub sample_rate: f32,
 }
+
+impl SynthesizerConfig {
+    pub fn master_tuning_semitones(&self) -> f32 {
+        self.transpose_semitones as f32 + self.master_tune_cents / 100.0
+    }
+
+    /// The rate voices render at: the oversampling setting or the quality mode's, whichever is higher.
+    pub fn voice_oversampling_factor(&self) -> usize {
+        self.oversampling.factor().max(self.quality.oversampling_factor())
+    }
+}
+
+impl Default for SynthesizerConfig {
+    fn default() -> Self {
+        let sample_rate = 44100.0;
+
+        let filter = Filter::new(FilterParameters {
+            filter_type: FilterType::LowPass,
+            slope: FilterSlope::Slope24dB,
+            cutoff_frequency: 2000.0,
+            resonance_amount: 0.8,
+            modulation_amount: 0.2,
+            drive: 0.0,
+            saturation: SaturationCurve::Tanh,
+        }, sample_rate);
+        let filter2 = Filter::new(FilterParameters {
+            filter_type: FilterType::HighPass,
+            slope: FilterSlope::Slope12dB,
+            cutoff_frequency: 200.0,
+            resonance_amount: 0.7,
+            modulation_amount: 0.0,
+            drive: 0.0,
+            saturation: SaturationCurve::Tanh,
+        }, sample_rate);
+
+        Self {
+            oscillator_configs: vec![OscillatorConfig::default()],
+            noise_seed: DEFAULT_NOISE_SEED,
+            harmonics: Harmonics::default(),
+            sample_layer: None,
+            envelope_config: EnvelopeConfig::default(),
+            filter,
+            filter2,
+            filter_routing: FilterRoutingConfig::default(),
+            filter_envelope_config: EnvelopeConfig::default(),
+            mod_envelope_configs: std::array::from_fn(|_| EnvelopeConfig {
+                attack_time: 0.2,
+                decay_time: 0.2,
+                sustain_level: 0.0,
+                release_time: 0.3,
+                ..EnvelopeConfig::default()
+            }),
+            modulation_routes: default_routes(),
+            freeze_modulation_on_release: false,
+            release_velocity: ReleaseVelocityConfig::default(),
+            glide: GlideConfig::default(),
+            vibrato: VibratoConfig::default(),
+            drift: DriftConfig::default(),
+            sends: SendConfig::default(),
+            delay: DelayConfig::default(),
+            reverb: ReverbConfig::default(),
+            waveshaper: WaveshaperConfig::default(),
+            eq: EqConfig::default(),
+            compressor: CompressorConfig::default(),
+            modulation_fx: ModulationFxConfig::default(),
+            fx_chain: FxChainConfig::default(),
+            stereo_filter_spread: 0.0,
+            voice_dc_blocking: false,
+            voice_insert: VoiceInsertConfig::default(),
+            voice_waveshaper: WaveshaperConfig::default(),
+            oscillator_gain_compensation: true,
+            detune_spread: 1.0,
+            filter_key_tracking: 0.0,
+            voice_mode: VoiceMode::Poly,
+            key_zones: KeyZoneConfig::default(),
+            chord_memory: ChordMemoryConfig::default(),
+            master_tune_cents: 0.0,
+            transpose_semitones: 0,
+            sequencer: StepSequencerConfig::default(),
+            lfos: [LfoConfig::default(); LFO_COUNT],
+            tempo_bpm: 120.0,
+            normalization: OutputNormalization::FixedHeadroom,
+            master_gain: 1.0,
+            quality: QualityMode::Normal,
+            oversampling: VoiceOversampling::Off,
+            max_voices: 16,
+            render_threads: 1,
+            sample_rate,
+        }
+    }
+}
@@ -0,0 +1,690 @@
+use std::ops::Range;
+use std::sync::Arc;
+
+use super::workers::VoiceWorkers;
+use super::{FrameControls, SynthesizerConfig, VoiceStats, RENDER_BLOCK};
+use crate::drift::DriftConfig;
+use crate::effects::{SendBus, SendConfig, VoiceInsertConfig, WaveshaperConfig};
+use crate::envelope::{EnvelopeConfig, EnvelopeLevel, EnvelopeState, ReleaseVelocityConfig, NEUTRAL_RELEASE_VELOCITY};
+use crate::filter::{FilterParameters, FilterRoutingConfig};
+use crate::glide::GlideConfig;
+use crate::keyzone::{frequency_to_key, KeyZoneConfig};
+use crate::modulation::{ModulationRoute, ModulationSourceId};
+use crate::oscillator::{make_oscillator, silenced_oscillators, AdditiveTables, NoiseTables, OscillatorConfig, SampleOscillator};
+use crate::sample::{SampleData, SampleLayer};
+use crate::vibrato::VibratoConfig;
+use crate::voice::{Voice, VoiceConfig};
+
+/// How often the stuck-voice watchdog looks over the pool.
+const WATCHDOG_INTERVAL_SECS: f32 = 0.25;
+/// A voice nobody holds gets this many times its envelope's full length, plus the grace
+/// period, before it counts as stuck.
+const WATCHDOG_MARGIN: f32 = 2.0;
+const WATCHDOG_GRACE_SECS: f32 = 1.0;
+/// Fade for All Sound Off: fast enough to count as immediate, long enough not to click.
+const SOUND_OFF_FADE_SECS: f32 = 0.005;
+/// Notes a mono part remembers to fall back to; one per key.
+const MAX_HELD_NOTES: usize = 128;
+
+/// Whether a part plays chords or a single line.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum VoiceMode {
+    Poly,
+    Mono,       // one voice, every note retriggers the envelopes
+    Legato,     // one voice, overlapping notes only change the pitch
+}
+
+impl VoiceMode {
+    pub const ALL: [VoiceMode; 3] = [VoiceMode::Poly, VoiceMode::Mono, VoiceMode::Legato];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            VoiceMode::Poly => "Poly",
+            VoiceMode::Mono => "Mono",
+            VoiceMode::Legato => "Legato",
+        }
+    }
+}
+
+/// One voice's share of the block being rendered, kept apart until the part mixes it.
+#[derive(Clone)]
+struct VoiceBlock {
+    left: [f32; RENDER_BLOCK],
+    right: [f32; RENDER_BLOCK],
+    active: [bool; RENDER_BLOCK],
+    send_levels: [(f32, f32); RENDER_BLOCK],
+    peak: f32,                  // highest absolute sample of the last mixed block
+    audible: bool,              // false while another voice is soloed
+}
+
+impl Default for VoiceBlock {
+    fn default() -> Self {
+        Self {
+            left: [0.0; RENDER_BLOCK],
+            right: [0.0; RENDER_BLOCK],
+            active: [false; RENDER_BLOCK],
+            send_levels: [(0.0, 0.0); RENDER_BLOCK],
+            peak: 0.0,
+            audible: true,
+        }
+    }
+}
+
+/// One timbre of a multi-timbral setup: its own voice pool playing its own patch,
+/// answering a single MIDI channel or, with no channel set, all of them.
+pub struct Part {
+    voices: Vec<Voice>,
+    voice_notes: Vec<Option<u32>>, // per voice, the note holding it; None once released
+    retrigger: bool,
+    next_voice: usize,
+    stolen_voices: u64,         // notes that had to take over a still-sounding voice
+    stuck_voices: u64,          // voices the watchdog had to release
+    last_frequency: f32,        // pitch of the most recent note, where the next glide starts
+    voice_mode: VoiceMode,
+    held_notes: Vec<(u32, f32, f32)>,   // mono modes: note ID, frequency and velocity, oldest first
+    midi_channel: Option<u8>,   // 0-based; None listens on every channel
+    unheld_samples: Vec<usize>, // per voice, how long it has sounded without a note holding it
+    blocks: Vec<VoiceBlock>,    // per voice scratch output
+    block_len: usize,           // frames in the last mixed block
+    watchdog_countdown: usize,
+    noise_tables: Arc<NoiseTables>,
+    additive_tables: Arc<AdditiveTables>,
+    sample_layer: Option<SampleLayer>,  // played after the oscillators, grouped with oscillator 2 and the sub
+    key_zones: KeyZoneConfig,
+    sample_rate: f32,
+}
+
+impl Part {
+    pub fn new(config: &SynthesizerConfig, midi_channel: Option<u8>) -> Self {
+        let noise_tables = Arc::new(NoiseTables::generate(config.noise_seed));
+        let additive_tables = Arc::new(AdditiveTables::generate(&config.harmonics));
+        let voice_cfg = VoiceConfig {
+            oscillator_configs: config.oscillator_configs.clone(),
+            noise_tables: noise_tables.clone(),
+            additive_tables: additive_tables.clone(),
+            filter: config.filter.clone(),
+            filter2: config.filter2.clone(),
+            filter_routing: config.filter_routing,
+            filter_envelope_config: config.filter_envelope_config.clone(),
+            mod_envelope_configs: config.mod_envelope_configs.clone(),
+            modulation_routes: config.modulation_routes.clone(),
+            stereo_filter_spread: config.stereo_filter_spread,
+            dc_blocking: config.voice_dc_blocking,
+            insert: config.voice_insert,
+            waveshaper: config.voice_waveshaper,
+            gain_compensation: config.oscillator_gain_compensation,
+            detune_spread: config.detune_spread,
+            key_tracking: config.filter_key_tracking,
+            master_tuning: config.master_tuning_semitones(),
+            freeze_modulation_on_release: config.freeze_modulation_on_release,
+            release_velocity: config.release_velocity,
+            glide: config.glide,
+            vibrato: config.vibrato,
+            drift: config.drift,
+            sends: config.sends,
+        };
+
+        let voice_count = config.max_voices.max(1);
+        // Clone one prototype voice so wavetables are shared across the pool
+        let prototype = Voice::new(&voice_cfg, &config.envelope_config, config.sample_rate);
+        let voices = (0..voice_count)
+            .map(|i| {
+                let mut voice = prototype.clone();
+                voice.seed_drift(i as u64 + 1);
+                voice.set_control_period(config.quality.control_rate_period());
+                voice.set_oversampling(config.voice_oversampling_factor());
+                voice
+            })
+            .collect::<Vec<_>>();
+
+        let mut part = Self {
+            voices,
+            voice_notes: vec![None; voice_count],
+            retrigger: config.envelope_config.retrigger,
+            next_voice: 0,
+            stolen_voices: 0,
+            stuck_voices: 0,
+            last_frequency: 0.0,
+            voice_mode: config.voice_mode,
+            held_notes: Vec::with_capacity(MAX_HELD_NOTES),
+            midi_channel,
+            unheld_samples: vec![0; voice_count],
+            blocks: vec![VoiceBlock::default(); voice_count],
+            block_len: 0,
+            watchdog_countdown: 0,
+            noise_tables,
+            additive_tables,
+            sample_layer: config.sample_layer.clone(),
+            key_zones: config.key_zones,
+            sample_rate: config.sample_rate,
+        };
+        // Voices are built from the oscillator configs alone; the sample joins them here
+        if part.sample_layer.is_some() {
+            part.set_oscillator_configs(&config.oscillator_configs);
+        }
+        part
+    }
+
+    pub fn midi_channel(&self) -> Option<u8> {
+        self.midi_channel
+    }
+
+    pub fn set_midi_channel(&mut self, midi_channel: Option<u8>) {
+        self.midi_channel = midi_channel;
+    }
+
+    pub fn responds_to(&self, channel: u8) -> bool {
+        self.midi_channel.is_none_or(|c| c == channel)
+    }
+
+    /// Splits or layers the oscillators over the keyboard and sets the keys the part
+    /// answers. Takes effect from the next note.
+    pub fn set_key_zones(&mut self, key_zones: KeyZoneConfig) {
+        self.key_zones = key_zones;
+    }
+
+    pub fn key_zones(&self) -> &KeyZoneConfig {
+        &self.key_zones
+    }
+
+    pub fn voice_count(&self) -> usize {
+        self.voices.len()
+    }
+
+    /// Handles to every voice's published amp envelope level, in voice order.
+    pub fn envelope_levels(&self) -> Vec<Arc<EnvelopeLevel>> {
+        self.voices.iter().map(|v| v.envelope_level()).collect()
+    }
+
+    pub fn has_active_notes(&self) -> bool {
+        self.voice_notes.iter().any(Option::is_some)
+    }
+
+    /// Adds this part's voice usage to `stats`.
+    pub fn accumulate_voice_stats(&self, stats: &mut VoiceStats) {
+        stats.active_voices += self.voices.iter().filter(|v| v.is_active()).count();
+        stats.max_voices += self.voices.len();
+        stats.stolen_voices += self.stolen_voices;
+        stats.stuck_voices += self.stuck_voices;
+        stats.stages.extend(self.voices.iter().map(|v| v.envelope_state()));
+        stats.levels.extend(self.blocks.iter().map(|b| b.peak));
+    }
+
+    fn find_free_voice(&mut self) -> Option<usize> {
+        if self.voices.is_empty() { return None; }
+        if let Some(i) = self.voices.iter().position(|v| !v.is_active()) {
+            Some(i)
+        } else {
+            let i = self.next_voice;
+            let len = self.voices.len();
+            self.next_voice = (self.next_voice + 1) % len;
+            self.stolen_voices += 1;
+            Some(i)
+        }
+    }
+
+    fn assign_voice(&mut self, voice_idx: usize, note_id: u32) {
+        // A stolen voice must no longer be released by the note it was playing before
+        self.voice_notes[voice_idx] = Some(note_id);
+    }
+
+    /// Starts a note on one voice per key zone it falls in; keys outside the part's range
+    /// are ignored. Mono modes play only the first zone.
+    pub fn start_note(&mut self, frequency: f32, note_id: u32, velocity: f32) {
+        let key_zones = self.key_zones;
+        let zones = key_zones.zones_for(frequency_to_key(frequency));
+        let Some(first_zone) = zones.first() else {
+            return;
+        };
+        let frequency = frequency * key_zones.part.pitch_ratio();
+        if self.voice_mode != VoiceMode::Poly {
+            if let Some(voice) = self.voices.first_mut() {
+                voice.set_zone(first_zone.oscillators, first_zone.level * key_zones.part.level);
+            }
+            self.start_mono_note(frequency * first_zone.pitch_ratio(), note_id, velocity);
+            return;
+        }
+        let existing_env_value = self.voices.iter()
+            .find(|v| v.is_active() && v.note_id() == note_id)
+            .map(|v| v.get_envelope_value());
+
+        let other_env_value = if !self.retrigger { existing_env_value } else { None };
+        let glide_from = self.has_active_notes().then_some(self.last_frequency);
+
+        for zone in zones {
+            let Some(voice_idx) = self.find_free_voice() else {
+                eprintln!("No voices configured; ignoring note_on for {}", note_id);
+                return;
+            };
+
+            let voice = &mut self.voices[voice_idx];
+            voice.set_zone(zone.oscillators, zone.level * key_zones.part.level);
+            voice.trigger(frequency * zone.pitch_ratio(), note_id, velocity, other_env_value, glide_from);
+            self.assign_voice(voice_idx, note_id);
+        }
+        self.last_frequency = frequency;
+    }
+
+    pub fn stop_note(&mut self, note_id: u32) {
+        self.stop_note_with_velocity(note_id, NEUTRAL_RELEASE_VELOCITY);
+    }
+
+    pub fn stop_note_with_velocity(&mut self, note_id: u32, velocity: f32) {
+        if self.voice_mode != VoiceMode::Poly {
+            self.held_notes.retain(|&(id, _, _)| id != note_id);
+            // Releasing the sounding note falls back to the most recent one still held
+            let fallback = self.held_notes.last().copied().filter(|_| self.voice_notes.contains(&Some(note_id)));
+            if let Some((previous_id, frequency, previous_velocity)) = fallback {
+                self.play_mono_note(frequency, previous_id, previous_velocity, true);
+                return;
+            }
+        }
+        for (voice, held) in self.voices.iter_mut().zip(&mut self.voice_notes) {
+            if *held == Some(note_id) {
+                voice.release_with_velocity(note_id, velocity);
+                *held = None;
+            }
+        }
+    }
+
+    /// All Notes Off: releases every voice as if its key went up, with the patch's release.
+    pub fn all_notes_off(&mut self) {
+        for voice in &mut self.voices {
+            voice.force_release();
+        }
+        self.voice_notes.fill(None);
+        self.held_notes.clear();
+    }
+
+    /// All Sound Off: every voice fades out within a few milliseconds.
+    pub fn all_sound_off(&mut self) {
+        for voice in &mut self.voices {
+            voice.fade_out(SOUND_OFF_FADE_SECS);
+        }
+        self.voice_notes.fill(None);
+        self.held_notes.clear();
+    }
+
+    fn start_mono_note(&mut self, frequency: f32, note_id: u32, velocity: f32) {
+        let sounding = !self.held_notes.is_empty() && self.voices.first().is_some_and(|v| v.is_active());
+        self.held_notes.retain(|&(id, _, _)| id != note_id);
+        // Never grows on the audio thread; past the limit the oldest note is forgotten
+        if self.held_notes.len() == MAX_HELD_NOTES {
+            self.held_notes.remove(0);
+        }
+        self.held_notes.push((note_id, frequency, velocity));
+        self.play_mono_note(frequency, note_id, velocity, sounding);
+    }
+
+    fn play_mono_note(&mut self, frequency: f32, note_id: u32, velocity: f32, sounding: bool) {
+        let Some(voice) = self.voices.first_mut() else {
+            return;
+        };
+        if sounding && self.voice_mode == VoiceMode::Legato {
+            voice.slide_to(frequency, note_id);
+        } else {
+            voice.trigger(frequency, note_id, velocity, None, sounding.then_some(self.last_frequency));
+        }
+        self.assign_voice(0, note_id);
+        self.last_frequency = frequency;
+    }
+
+    pub fn poly_pressure(&mut self, note_id: u32, pressure: f32) {
+        for (voice, held) in self.voices.iter_mut().zip(&self.voice_notes) {
+            if *held == Some(note_id) {
+                voice.set_poly_pressure(pressure);
+            }
+        }
+    }
+
+    pub fn channel_pressure(&mut self, pressure: f32) {
+        for v in &mut self.voices {
+            v.set_channel_pressure(pressure);
+        }
+    }
+
+    /// Oscillators that take the new settings in place keep sounding, e.g. a ringing pluck
+    /// while its damping turns; the others, and all of them when the slots change, are rebuilt.
+    pub fn set_oscillator_configs(&mut self, oscillator_configs: &[OscillatorConfig]) {
+        let mut silenced = silenced_oscillators(oscillator_configs);
+        // The sample has no solo switch of its own, so soloing an oscillator leaves it out
+        if self.sample_layer.is_some() && oscillator_configs.iter().any(|c| c.solo) {
+            silenced |= 1 << oscillator_configs.len();
+        }
+        let slots = oscillator_configs.len() + usize::from(self.sample_layer.is_some());
+        let (sample_rate, noise_tables, additive_tables) = (self.sample_rate, &self.noise_tables, &self.additive_tables);
+        let build = |slot: usize| make_oscillator(oscillator_configs[slot], sample_rate, 440.0, noise_tables.table(slot), additive_tables);
+        for v in &mut self.voices {
+            if v.oscillator_count() == slots {
+                v.update_oscillators(oscillator_configs, build);
+            } else {
+                let mut oscillators: Vec<_> = (0..oscillator_configs.len()).map(build).collect();
+                if let Some(layer) = &self.sample_layer {
+                    oscillators.push(Box::new(SampleOscillator::new(sample_rate, 440.0, layer)));
+                }
+                v.set_oscillators(oscillators);
+            }
+            v.set_silenced_oscillators(silenced);
+        }
+    }
+
+    /// Switches the voices' random oscillators to `tables` in place. Returns the tables it
+    /// replaces.
+    pub fn set_noise_tables(&mut self, tables: Arc<NoiseTables>) -> Arc<NoiseTables> {
+        for v in &mut self.voices {
+            v.set_noise_tables(&tables);
+        }
+        std::mem::replace(&mut self.noise_tables, tables)
+    }
+
+    /// Switches the voices' additive oscillators to `tables` in place. Returns the tables it
+    /// replaces.
+    pub fn set_additive_tables(&mut self, tables: Arc<AdditiveTables>) -> Arc<AdditiveTables> {
+        for v in &mut self.voices {
+            v.set_additive_tables(&tables);
+        }
+        std::mem::replace(&mut self.additive_tables, tables)
+    }
+
+    /// Switches the voices' sample oscillators to `layer` in place and returns the file it
+    /// replaces. Adding or removing the layer takes effect with the next `set_oscillator_configs`.
+    pub fn set_sample_layer(&mut self, layer: Option<SampleLayer>) -> Option<Arc<SampleData>> {
+        if let Some(layer) = &layer {
+            for v in &mut self.voices {
+                v.set_sample_layer(layer);
+            }
+        }
+        // Held until the voices have let go of it, so the caller gets the last copy
+        std::mem::replace(&mut self.sample_layer, layer).map(|previous| previous.data)
+    }
+
+    pub fn set_envelope_config(&mut self, envelope_config: EnvelopeConfig) {
+        self.retrigger = envelope_config.retrigger;
+        for v in &mut self.voices {
+            v.set_envelope_config(envelope_config.clone());
+        }
+    }
+
+    pub fn set_filter_envelope_config(&mut self, envelope_config: EnvelopeConfig) {
+        for v in &mut self.voices {
+            v.set_filter_envelope_config(envelope_config.clone());
+        }
+    }
+
+    pub fn set_mod_envelope_config(&mut self, index: usize, envelope_config: EnvelopeConfig) {
+        for v in &mut self.voices {
+            v.set_mod_envelope_config(index, envelope_config.clone());
+        }
+    }
+
+    pub fn set_filter_parameters(&mut self, parameters: FilterParameters) {
+        for v in &mut self.voices {
+            v.set_filter_parameters(parameters.clone());
+        }
+    }
+
+    pub fn set_filter2_parameters(&mut self, parameters: FilterParameters) {
+        for v in &mut self.voices {
+            v.set_filter2_parameters(parameters.clone());
+        }
+    }
+
+    pub fn set_filter_routing(&mut self, routing: FilterRoutingConfig) {
+        for v in &mut self.voices {
+            v.set_filter_routing(routing);
+        }
+    }
+
+    pub fn set_modulation_routes(&mut self, routes: &[ModulationRoute]) {
+        for v in &mut self.voices {
+            v.set_modulation_routes(routes);
+        }
+    }
+
+    /// Allocates, so not for the audio thread.
+    pub fn refresh_custom_sources(&mut self) {
+        for v in &mut self.voices {
+            v.refresh_custom_sources();
+        }
+    }
+
+    pub fn set_stereo_filter_spread(&mut self, octaves: f32) {
+        for v in &mut self.voices {
+            v.set_stereo_filter_spread(octaves);
+        }
+    }
+
+    pub fn set_dc_blocking(&mut self, enabled: bool) {
+        for v in &mut self.voices {
+            v.set_dc_blocking(enabled);
+        }
+    }
+
+    pub fn set_detune_spread(&mut self, spread: f32) {
+        for v in &mut self.voices {
+            v.set_detune_spread(spread);
+        }
+    }
+
+    pub fn set_key_tracking(&mut self, amount: f32) {
+        for v in &mut self.voices {
+            v.set_key_tracking(amount);
+        }
+    }
+
+    pub fn set_master_tuning(&mut self, semitones: f32) {
+        for v in &mut self.voices {
+            v.set_master_tuning(semitones);
+        }
+    }
+
+    /// Switching modes releases whatever is playing, so no voice is left without its note.
+    pub fn set_voice_mode(&mut self, mode: VoiceMode) {
+        if mode == self.voice_mode {
+            return;
+        }
+        for (voice, held) in self.voices.iter_mut().zip(&mut self.voice_notes) {
+            if let Some(note_id) = held.take() {
+                voice.release(note_id);
+            }
+        }
+        self.held_notes.clear();
+        self.voice_mode = mode;
+    }
+
+    pub fn set_gain_compensation(&mut self, enabled: bool) {
+        for v in &mut self.voices {
+            v.set_gain_compensation(enabled);
+        }
+    }
+
+    pub fn set_waveshaper_config(&mut self, config: WaveshaperConfig) {
+        for v in &mut self.voices {
+            v.set_waveshaper_config(config);
+        }
+    }
+
+    pub fn set_insert_config(&mut self, config: VoiceInsertConfig) {
+        for v in &mut self.voices {
+            v.set_insert_config(config);
+        }
+    }
+
+    pub fn set_release_velocity_config(&mut self, config: ReleaseVelocityConfig) {
+        for v in &mut self.voices {
+            v.set_release_velocity_config(config);
+        }
+    }
+
+    pub fn set_freeze_modulation_on_release(&mut self, freeze: bool) {
+        for v in &mut self.voices {
+            v.set_freeze_modulation_on_release(freeze);
+        }
+    }
+
+    pub fn set_glide_config(&mut self, config: GlideConfig) {
+        for v in &mut self.voices {
+            v.set_glide_config(config);
+        }
+    }
+
+    pub fn set_vibrato_config(&mut self, config: VibratoConfig) {
+        for v in &mut self.voices {
+            v.set_vibrato_config(config);
+        }
+    }
+
+    pub fn set_drift_config(&mut self, config: DriftConfig) {
+        for v in &mut self.voices {
+            v.set_drift_config(config);
+        }
+    }
+
+    pub fn set_send_config(&mut self, config: SendConfig) {
+        for v in &mut self.voices {
+            v.set_send_config(config);
+        }
+    }
+
+    pub fn set_modulation_value(&mut self, source: ModulationSourceId, value: f32) {
+        for v in &mut self.voices {
+            v.set_modulation_value(source, value);
+        }
+    }
+
+    pub fn set_control_period(&mut self, period: usize) {
+        for v in &mut self.voices {
+            v.set_control_period(period);
+        }
+    }
+
+    pub fn set_oversampling(&mut self, factor: usize) {
+        for v in &mut self.voices {
+            v.set_oversampling(factor);
+        }
+    }
+
+    pub fn update_sample_rate(&mut self, sample_rate: f32) {
+        self.sample_rate = sample_rate;
+        for voice in &mut self.voices {
+            voice.update_sample_rate(sample_rate);
+        }
+    }
+
+    /// Safety net against allocator bookkeeping bugs: a voice still sounding long after it
+    /// could have finished, with no note holding it, is released, and stopped outright if
+    /// it is still stuck after another full allowance. Runs on the audio thread, so it only
+    /// counts what it does; the count goes out with the voice stats.
+    fn run_watchdog(&mut self, elapsed: usize) {
+        for (idx, voice) in self.voices.iter_mut().enumerate() {
+            if !voice.is_active() || self.voice_notes[idx].is_some() {
+                self.unheld_samples[idx] = 0;
+                continue;
+            }
+
+            self.unheld_samples[idx] += elapsed;
+            let limit = ((voice.max_unheld_secs() * WATCHDOG_MARGIN + WATCHDOG_GRACE_SECS)
+                * self.sample_rate) as usize;
+            if self.unheld_samples[idx] >= 2 * limit {
+                voice.stop();
+                self.unheld_samples[idx] = 0;
+            } else if self.unheld_samples[idx] >= limit && voice.envelope_state() != EnvelopeState::Release {
+                voice.force_release();
+                self.stuck_voices += 1;
+            }
+        }
+    }
+
+    /// Mutes every voice for which `audible` returns false, e.g. to solo one voice.
+    pub fn set_audible(&mut self, audible: impl Fn(usize) -> bool) {
+        for (idx, block) in self.blocks.iter_mut().enumerate() {
+            block.audible = audible(idx);
+        }
+    }
+
+    /// Left and right output of one voice over the last mixed block, muted or not.
+    pub fn voice_block(&self, voice: usize) -> Option<(&[f32], &[f32])> {
+        self.blocks.get(voice).map(|b| (&b.left[..self.block_len], &b.right[..self.block_len]))
+    }
+
+    /// Runs the stuck-voice watchdog if it is due, and returns how many frames can be
+    /// rendered before it is due again.
+    pub fn begin_segment(&mut self) -> usize {
+        if self.watchdog_countdown == 0 {
+            let interval = ((WATCHDOG_INTERVAL_SECS * self.sample_rate) as usize).max(1);
+            self.run_watchdog(interval);
+            self.watchdog_countdown = interval;
+        }
+        self.watchdog_countdown
+    }
+
+    /// Renders frames `range` of the current block, each voice into its own scratch block,
+    /// reading the sequencer and LFOs from `controls`. With `workers` the voices are split
+    /// across its threads; voices don't touch each other, so the samples come out the same.
+    pub(super) fn render_segment(&mut self, range: Range<usize>, controls: &[FrameControls], workers: Option<&VoiceWorkers>) {
+        self.watchdog_countdown -= range.len();
+        let render = |(voices, blocks): (&mut [Voice], &mut [VoiceBlock])| {
+            for (voice, block) in voices.iter_mut().zip(blocks) {
+                render_voice(voice, block, range.clone(), controls);
+            }
+        };
+        match workers {
+            Some(workers) if workers.threads() > 1 => {
+                let chunk = self.voices.len().div_ceil(workers.threads()).max(1);
+                workers.run(self.voices.chunks_mut(chunk).zip(self.blocks.chunks_mut(chunk)), &render);
+            }
+            _ => render((self.voices.as_mut_slice(), self.blocks.as_mut_slice())),
+        }
+    }
+
+    /// Adds the sends of frame `index` from every audible voice to `sends`, in voice order,
+    /// and returns how many voices sounded in it.
+    pub fn add_frame_sends(&self, index: usize, sends: &mut SendBus) -> usize {
+        let mut count = 0;
+        for block in &self.blocks {
+            count += block.active[index] as usize;
+            if block.audible {
+                sends.add((block.left[index], block.right[index]), block.send_levels[index]);
+            }
+        }
+        count
+    }
+
+    /// Adds the rendered block of every audible voice to `left` and `right`, whose length is
+    /// the block's, and takes each voice's peak level.
+    pub fn mix_block(&mut self, left: &mut [f32], right: &mut [f32]) {
+        let len = left.len().min(right.len()).min(RENDER_BLOCK);
+        self.block_len = len;
+        for block in &mut self.blocks {
+            block.peak = block.left[..len]
+                .iter()
+                .chain(&block.right[..len])
+                .fold(0.0, |peak: f32, s| peak.max(s.abs()));
+            if !block.audible {
+                continue;
+            }
+            for (out, s) in left.iter_mut().zip(&block.left[..len]) {
+                *out += s;
+            }
+            for (out, s) in right.iter_mut().zip(&block.right[..len]) {
+                *out += s;
+            }
+        }
+    }
+}
+
+fn render_voice(voice: &mut Voice, block: &mut VoiceBlock, range: Range<usize>, controls: &[FrameControls]) {
+    for index in range {
+        let frame = &controls[index];
+        voice.set_modulation_value(ModulationSourceId::StepSequencer, frame.sequencer);
+        voice.set_modulation_value(ModulationSourceId::Lfo1, frame.lfos[0]);
+        voice.set_modulation_value(ModulationSourceId::Lfo2, frame.lfos[1]);
+        let active = voice.is_active();
+        let (l, r) = if active { voice.next_frame() } else { (0.0, 0.0) };
+        block.left[index] = l;
+        block.right[index] = r;
+        block.active[index] = active;
+        block.send_levels[index] = voice.send_levels();
+    }
+    voice.publish_envelope_level();
+}
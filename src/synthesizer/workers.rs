@@ -0,0 +1,207 @@
+use std::cell::UnsafeCell;
+use std::hint::spin_loop;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::atomic::{AtomicU8, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread::{JoinHandle, Thread};
+
+use crate::denormal::DenormalGuard;
+
+/// Most threads a pool renders on; more can't be fed from one part's voices anyway.
+pub const MAX_RENDER_THREADS: usize = 16;
+
+// How often an idle worker checks for a job before it parks until the next one is handed over
+const IDLE_SPINS: u32 = 2_000;
+
+const IDLE: u8 = 0;
+const READY: u8 = 1;
+const SHUTDOWN: u8 = 2;
+
+// A job borrowed from `VoiceWorkers::run`'s caller, which waits for it before returning
+struct Job(*mut (dyn FnMut() + Send + 'static));
+
+// SAFETY: the closure behind the pointer is `Send`, and only the worker it was handed to
+// touches it until `run` has seen it finish
+unsafe impl Send for Job {}
+
+struct Worker {
+    state: AtomicU8,
+    job: UnsafeCell<Option<Job>>,
+}
+
+// SAFETY: `run` only writes `job` while `state` is IDLE, and the worker only takes it after
+// seeing READY, so the two never touch it at the same time
+unsafe impl Sync for Worker {}
+
+/// Threads kept around to render voices next to the audio thread. Each block hands every
+/// worker at most one job and waits for all of them, so the pool never queues work. Jobs
+/// are handed over and waited for with atomics, never a lock, and the workers ask for
+/// realtime priority so the audio thread isn't kept waiting on a lower-priority thread.
+pub struct VoiceWorkers {
+    workers: Vec<Arc<Worker>>,
+    pending: Arc<AtomicUsize>,
+    threads: Vec<Thread>,
+    handles: Vec<JoinHandle<()>>,
+}
+
+impl VoiceWorkers {
+    /// A pool that renders on `threads` threads in total, the caller's included, at most
+    /// `MAX_RENDER_THREADS`. Threads that fail to start are left out; `None` when not even
+    /// one worker started, so the caller renders on its own.
+    pub fn new(threads: usize) -> Option<Self> {
+        let pending = Arc::new(AtomicUsize::new(0));
+        let mut pool = Self { workers: Vec::new(), pending, threads: Vec::new(), handles: Vec::new() };
+        for i in 1..threads.clamp(1, MAX_RENDER_THREADS) {
+            let worker = Arc::new(Worker { state: AtomicU8::new(IDLE), job: UnsafeCell::new(None) });
+            let spawned = {
+                let worker = worker.clone();
+                let pending = pool.pending.clone();
+                std::thread::Builder::new()
+                    .name(format!("voice-worker-{}", i))
+                    .spawn(move || work(&worker, &pending))
+            };
+            let Ok(handle) = spawned else {
+                break;
+            };
+            pool.workers.push(worker);
+            pool.threads.push(handle.thread().clone());
+            pool.handles.push(handle);
+        }
+        (!pool.workers.is_empty()).then_some(pool)
+    }
+
+    /// Threads a block is spread across, the caller's included.
+    pub fn threads(&self) -> usize {
+        self.workers.len() + 1
+    }
+
+    /// Calls `job` once for every item, the first on the calling thread and the others on
+    /// the workers, and returns once all calls are done. Takes at most `threads()` items;
+    /// nothing is allocated or locked, so this is fine on the audio thread.
+    pub fn run<T: Send>(&self, items: impl Iterator<Item = T>, job: &(dyn Fn(T) + Sync)) {
+        let mut items = items.take(self.threads());
+        let mut calls: [Option<_>; MAX_RENDER_THREADS] = std::array::from_fn(|_| {
+            items.next().map(|item| {
+                let mut item = Some(item);
+                move || {
+                    if let Some(item) = item.take() {
+                        job(item);
+                    }
+                }
+            })
+        });
+        let Some((Some(first), rest)) = calls.split_first_mut() else {
+            return;
+        };
+
+        let rest = rest.iter_mut().map_while(|call| call.as_mut());
+        let mut dispatched = 0;
+        for ((worker, thread), call) in self.workers.iter().zip(&self.threads).zip(rest) {
+            let call: *mut (dyn FnMut() + Send + '_) = call;
+            // SAFETY: only the lifetime is erased; this function doesn't return before the
+            // worker has finished with the call, so the borrow outlives every use of it
+            let call = unsafe { std::mem::transmute::<*mut (dyn FnMut() + Send + '_), *mut (dyn FnMut() + Send + 'static)>(call) };
+            self.pending.fetch_add(1, Ordering::Relaxed);
+            // SAFETY: the worker is IDLE, since `pending` was zero when the last `run` returned
+            unsafe { *worker.job.get() = Some(Job(call)) };
+            worker.state.store(READY, Ordering::Release);
+            thread.unpark();
+            dispatched += 1;
+        }
+
+        // Waits even if the caller's own call panics, since the workers still hold borrows
+        let _wait = WaitForWorkers(&self.pending, dispatched);
+        first();
+    }
+}
+
+struct WaitForWorkers<'a>(&'a AtomicUsize, usize);
+
+impl Drop for WaitForWorkers<'_> {
+    fn drop(&mut self) {
+        if self.1 == 0 {
+            return;
+        }
+        // The workers render alongside the caller, so their share is about done by now
+        while self.0.load(Ordering::Acquire) > 0 {
+            spin_loop();
+        }
+    }
+}
+
+impl Drop for VoiceWorkers {
+    fn drop(&mut self) {
+        for (worker, thread) in self.workers.iter().zip(&self.threads) {
+            worker.state.store(SHUTDOWN, Ordering::Release);
+            thread.unpark();
+        }
+        for handle in self.handles.drain(..) {
+            let _ = handle.join();
+        }
+    }
+}
+
+fn work(worker: &Worker, pending: &AtomicUsize) {
+    // Voices render here as they would on the audio thread, denormals flushed alike
+    let _denormals = DenormalGuard::enable();
+    promote_to_realtime();
+    let mut spins = 0;
+    loop {
+        match worker.state.load(Ordering::Acquire) {
+            SHUTDOWN => return,
+            READY => spins = 0,
+            _ if spins < IDLE_SPINS => {
+                spins += 1;
+                spin_loop();
+                continue;
+            }
+            // An unpark that came before this still wakes it straight away
+            _ => {
+                std::thread::park();
+                continue;
+            }
+        }
+
+        // SAFETY: see `Worker`; `run` wrote the job before storing READY
+        let job = unsafe { (*worker.job.get()).take() };
+        if let Some(job) = job {
+            // A panicking voice must still count as finished, or the audio thread waits forever
+            // SAFETY: see `Job`; the caller of `run` is waiting until the count below reaches zero
+            let _ = catch_unwind(AssertUnwindSafe(|| unsafe { (*job.0)() }));
+        }
+        worker.state.store(IDLE, Ordering::Release);
+        pending.fetch_sub(1, Ordering::Release);
+    }
+}
+
+// Asks for a realtime scheduling class. Without the permission for it the worker keeps its
+// normal priority, which still renders correctly, just with less headroom.
+#[cfg(unix)]
+fn promote_to_realtime() {
+    // SAFETY: only changes the scheduling of the calling thread
+    unsafe {
+        let policy = libc::SCHED_FIFO;
+        let (min, max) = (libc::sched_get_priority_min(policy), libc::sched_get_priority_max(policy));
+        let mut param: libc::sched_param = std::mem::zeroed();
+        // The middle of the range if allowed, else the lowest realtime priority, which
+        // most systems' realtime limits still allow
+        for priority in [min + (max - min) / 2, min] {
+            param.sched_priority = priority;
+            if libc::pthread_setschedparam(libc::pthread_self(), policy, &param) == 0 {
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(windows)]
+fn promote_to_realtime() {
+    use windows_sys::Win32::System::Threading::{GetCurrentThread, SetThreadPriority, THREAD_PRIORITY_TIME_CRITICAL};
+    // SAFETY: only changes the priority of the calling thread
+    unsafe {
+        SetThreadPriority(GetCurrentThread(), THREAD_PRIORITY_TIME_CRITICAL);
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+fn promote_to_realtime() {}
@@ -0,0 +1,227 @@
+use nih_plug::prelude::SysExMessage;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+
+/// The non-commercial manufacturer ID; every message is `F0 7D <command> <payload> F7`.
+pub const MANUFACTURER_ID: u8 = 0x7D;
+/// Most parameter values carried by one `ParamBlock`, so a dump is a series of blocks.
+pub const BLOCK_PARAMS: usize = 24;
+pub const BUFFER_SIZE: usize = 3 + 1 + 6 * BLOCK_PARAMS + 1;
+
+const START: u8 = 0xF0;
+const END: u8 = 0xF7;
+const DUMP_REQUEST: u8 = 0x01;
+const PARAM_BLOCK: u8 = 0x02;
+const PARAM_REQUEST: u8 = 0x03;
+const VALUE_STEPS: f32 = ((1 << 21) - 1) as f32;   // three 7-bit bytes per value
+const ADDRESS_MASK: u32 = (1 << 21) - 1;            // three 7-bit bytes per address
+
+/// The synth's sysex protocol. Parameters are addressed by `param_address`, a hash of their
+/// ID, and carry normalized values, so every parameter is reachable without a per-parameter
+/// table and addresses stay the same when parameters are added or reordered.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum SysExCommand {
+    /// Asks for the whole patch; answered with `ParamBlock`s covering every parameter.
+    DumpRequest,
+    /// The first `count` of `values`, each an address and a value. Sent as a dump or as
+    /// the answer to a request; received, it sets those parameters, which loads a patch
+    /// block by block or edits a single parameter remotely.
+    ParamBlock { count: u8, values: [(u32, f32); BLOCK_PARAMS] },
+    /// Asks for one parameter; answered with a one-value `ParamBlock`.
+    ParamRequest { address: u32 },
+}
+
+impl SysExCommand {
+    pub fn single_value(address: u32, value: f32) -> Self {
+        let mut values = [(0, 0.0); BLOCK_PARAMS];
+        values[0] = (address, value);
+        SysExCommand::ParamBlock { count: 1, values }
+    }
+
+    /// The address and value pairs a `ParamBlock` carries; empty for the other commands.
+    pub fn block_values(&self) -> &[(u32, f32)] {
+        match self {
+            SysExCommand::ParamBlock { count, values } => &values[..(*count as usize).min(BLOCK_PARAMS)],
+            _ => &[],
+        }
+    }
+
+    /// Parses a complete message, including the `F0` and `F7` framing.
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let [START, MANUFACTURER_ID, command, payload @ .., END] = bytes else {
+            return None;
+        };
+        match (*command, payload) {
+            (DUMP_REQUEST, []) => Some(SysExCommand::DumpRequest),
+            (PARAM_REQUEST, address @ [_, _, _]) => Some(SysExCommand::ParamRequest { address: decode_bytes(address) }),
+            (PARAM_BLOCK, [count, values @ ..]) => {
+                let count = *count as usize;
+                if count == 0 || count > BLOCK_PARAMS || values.len() != 6 * count {
+                    return None;
+                }
+                let mut decoded = [(0, 0.0); BLOCK_PARAMS];
+                for (value, bytes) in decoded.iter_mut().zip(values.chunks_exact(6)) {
+                    *value = (decode_bytes(&bytes[..3]), decode_value(&bytes[3..]));
+                }
+                Some(SysExCommand::ParamBlock { count: count as u8, values: decoded })
+            }
+            _ => None,
+        }
+    }
+
+    /// Writes the message into `buffer`, returning its length.
+    pub fn write_bytes(&self, buffer: &mut [u8; BUFFER_SIZE]) -> usize {
+        buffer[..2].copy_from_slice(&[START, MANUFACTURER_ID]);
+        let mut len = 2;
+        let mut push = |byte: u8| {
+            buffer[len] = byte;
+            len += 1;
+        };
+        match *self {
+            SysExCommand::DumpRequest => push(DUMP_REQUEST),
+            SysExCommand::ParamRequest { address } => {
+                push(PARAM_REQUEST);
+                encode_bytes(address).into_iter().for_each(&mut push);
+            }
+            SysExCommand::ParamBlock { count, values } => {
+                let count = (count as usize).clamp(1, BLOCK_PARAMS);
+                push(PARAM_BLOCK);
+                push(count as u8);
+                for &(address, value) in &values[..count] {
+                    encode_bytes(address).into_iter().for_each(&mut push);
+                    encode_value(value).into_iter().for_each(&mut push);
+                }
+            }
+        }
+        push(END);
+        len
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut buffer = [0; BUFFER_SIZE];
+        let len = self.write_bytes(&mut buffer);
+        buffer[..len].to_vec()
+    }
+}
+
+impl SysExMessage for SysExCommand {
+    type Buffer = [u8; BUFFER_SIZE];
+
+    fn from_buffer(buffer: &[u8]) -> Option<Self> {
+        Self::from_bytes(buffer)
+    }
+
+    fn to_buffer(self) -> (Self::Buffer, usize) {
+        let mut buffer = [0; BUFFER_SIZE];
+        let len = self.write_bytes(&mut buffer);
+        (buffer, len)
+    }
+}
+
+/// Calls `send` with the blocks of a dump of `values`, each an address and a normalized value.
+pub fn dump(values: impl IntoIterator<Item = (u32, f32)>, mut send: impl FnMut(SysExCommand)) {
+    let mut block = [(0, 0.0); BLOCK_PARAMS];
+    let mut count = 0;
+    for value in values {
+        block[count] = value;
+        count += 1;
+        if count == BLOCK_PARAMS {
+            send(SysExCommand::ParamBlock { count: count as u8, values: block });
+            count = 0;
+        }
+    }
+    if count > 0 {
+        send(SysExCommand::ParamBlock { count: count as u8, values: block });
+    }
+}
+
+/// The sysex address of the parameter with ID `id`: its 32-bit FNV-1a hash folded to the
+/// 21 bits a message carries. It only depends on the ID, so saved dumps and controller
+/// templates keep working across versions.
+pub fn param_address(id: &str) -> u32 {
+    let hash = id.bytes().fold(0x811C_9DC5u32, |hash, byte| (hash ^ byte as u32).wrapping_mul(0x0100_0193));
+    ((hash >> 21) ^ hash) & ADDRESS_MASK
+}
+
+/// Looks parameters up by sysex address; built once, so lookups on the audio thread don't allocate.
+pub struct ParamAddresses {
+    addresses: Vec<u32>,            // by parameter index
+    sorted: Vec<(u32, usize)>,      // address and index, sorted by address
+}
+
+impl ParamAddresses {
+    /// Addresses the parameters with the IDs `ids`, indexed in that order.
+    pub fn new<'a>(ids: impl IntoIterator<Item = &'a str>) -> Self {
+        let addresses: Vec<u32> = ids.into_iter().map(param_address).collect();
+        let mut sorted: Vec<(u32, usize)> = addresses.iter().enumerate().map(|(index, &address)| (address, index)).collect();
+        sorted.sort_unstable();
+        Self { addresses, sorted }
+    }
+
+    pub fn len(&self) -> usize {
+        self.addresses.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.addresses.is_empty()
+    }
+
+    pub fn address(&self, index: usize) -> u32 {
+        self.addresses[index]
+    }
+
+    /// The index of the parameter at `address`, if there is one.
+    pub fn index_of(&self, address: u32) -> Option<usize> {
+        let position = self.sorted.binary_search_by_key(&address, |&(address, _)| address).ok()?;
+        Some(self.sorted[position].1)
+    }
+}
+
+fn encode_bytes(bits: u32) -> [u8; 3] {
+    [((bits >> 14) & 0x7F) as u8, ((bits >> 7) & 0x7F) as u8, (bits & 0x7F) as u8]
+}
+
+fn decode_bytes(bytes: &[u8]) -> u32 {
+    bytes.iter().fold(0u32, |acc, &b| (acc << 7) | (b as u32 & 0x7F))
+}
+
+fn encode_value(value: f32) -> [u8; 3] {
+    encode_bytes((value.clamp(0.0, 1.0) * VALUE_STEPS).round() as u32)
+}
+
+fn decode_value(bytes: &[u8]) -> f32 {
+    decode_bytes(bytes) as f32 / VALUE_STEPS
+}
+
+/// Parameter values received over sysex, written by the audio thread after it has set them
+/// as overrides (see `ParamOverrides`), so the editor can write them to the parameters and
+/// the host sees them.
+pub struct ParamInbox {
+    values: Vec<AtomicU32>,
+    pending: Vec<AtomicBool>,
+}
+
+impl ParamInbox {
+    pub fn new(param_count: usize) -> Self {
+        Self {
+            values: (0..param_count).map(|_| AtomicU32::new(0)).collect(),
+            pending: (0..param_count).map(|_| AtomicBool::new(false)).collect(),
+        }
+    }
+
+    /// Stores a received value for the parameter at `index`; indices past the parameter list are dropped.
+    pub fn record(&self, index: usize, value: f32) {
+        if let (Some(slot), Some(pending)) = (self.values.get(index), self.pending.get(index)) {
+            slot.store(value.to_bits(), Ordering::Relaxed);
+            pending.store(true, Ordering::Release);
+        }
+    }
+
+    /// Calls `f(index, value)` for every parameter received since the last drain.
+    pub fn drain(&self, mut f: impl FnMut(usize, f32)) {
+        for (index, pending) in self.pending.iter().enumerate() {
+            if pending.swap(false, Ordering::Acquire) {
+                f(index, f32::from_bits(self.values[index].load(Ordering::Relaxed)));
+            }
+        }
+    }
+}
@@ -0,0 +1,144 @@
+/// Musical time as reported by the host, or by `InternalClock` when there is no host.
+#[derive(Clone, Copy)]
+pub struct TransportInfo {
+    pub tempo_bpm: f32,
+    pub time_signature_numerator: u32,
+    pub time_signature_denominator: u32,
+    pub position_beats: f64,    // in quarter notes since the start of the song
+    pub playing: bool,
+}
+
+impl Default for TransportInfo {
+    fn default() -> Self {
+        Self {
+            tempo_bpm: 120.0,
+            time_signature_numerator: 4,
+            time_signature_denominator: 4,
+            position_beats: 0.0,
+            playing: false,
+        }
+    }
+}
+
+impl TransportInfo {
+    pub fn beats_per_bar(&self) -> f64 {
+        self.time_signature_numerator as f64 * 4.0 / self.time_signature_denominator.max(1) as f64
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum SyncDivision {
+    Whole,
+    Half,
+    HalfDotted,
+    HalfTriplet,
+    Quarter,
+    QuarterDotted,
+    QuarterTriplet,
+    Eighth,
+    EighthDotted,
+    EighthTriplet,
+    Sixteenth,
+    SixteenthTriplet,
+    ThirtySecond,
+}
+
+impl SyncDivision {
+    pub const ALL: [SyncDivision; 13] = [
+        SyncDivision::Whole,
+        SyncDivision::Half,
+        SyncDivision::HalfDotted,
+        SyncDivision::HalfTriplet,
+        SyncDivision::Quarter,
+        SyncDivision::QuarterDotted,
+        SyncDivision::QuarterTriplet,
+        SyncDivision::Eighth,
+        SyncDivision::EighthDotted,
+        SyncDivision::EighthTriplet,
+        SyncDivision::Sixteenth,
+        SyncDivision::SixteenthTriplet,
+        SyncDivision::ThirtySecond,
+    ];
+
+    /// Length of one period in quarter-note beats.
+    pub fn beats(self) -> f64 {
+        match self {
+            SyncDivision::Whole => 4.0,
+            SyncDivision::Half => 2.0,
+            SyncDivision::HalfDotted => 3.0,
+            SyncDivision::HalfTriplet => 4.0 / 3.0,
+            SyncDivision::Quarter => 1.0,
+            SyncDivision::QuarterDotted => 1.5,
+            SyncDivision::QuarterTriplet => 2.0 / 3.0,
+            SyncDivision::Eighth => 0.5,
+            SyncDivision::EighthDotted => 0.75,
+            SyncDivision::EighthTriplet => 1.0 / 3.0,
+            SyncDivision::Sixteenth => 0.25,
+            SyncDivision::SixteenthTriplet => 1.0 / 6.0,
+            SyncDivision::ThirtySecond => 0.125,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            SyncDivision::Whole => "1/1",
+            SyncDivision::Half => "1/2",
+            SyncDivision::HalfDotted => "1/2D",
+            SyncDivision::HalfTriplet => "1/2T",
+            SyncDivision::Quarter => "1/4",
+            SyncDivision::QuarterDotted => "1/4D",
+            SyncDivision::QuarterTriplet => "1/4T",
+            SyncDivision::Eighth => "1/8",
+            SyncDivision::EighthDotted => "1/8D",
+            SyncDivision::EighthTriplet => "1/8T",
+            SyncDivision::Sixteenth => "1/16",
+            SyncDivision::SixteenthTriplet => "1/16T",
+            SyncDivision::ThirtySecond => "1/32",
+        }
+    }
+
+    pub fn frequency_hz(self, tempo_bpm: f32) -> f32 {
+        tempo_bpm / 60.0 / self.beats() as f32
+    }
+
+    pub fn seconds(self, tempo_bpm: f32) -> f32 {
+        1.0 / self.frequency_hz(tempo_bpm)
+    }
+}
+
+/// Free-running transport used by the standalone build, where no host supplies musical time.
+#[derive(Clone)]
+pub struct InternalClock {
+    info: TransportInfo,
+    sample_rate: f32,
+}
+
+impl InternalClock {
+    pub fn new(tempo_bpm: f32, sample_rate: f32) -> Self {
+        Self {
+            info: TransportInfo {
+                tempo_bpm,
+                playing: true,
+                ..TransportInfo::default()
+            },
+            sample_rate,
+        }
+    }
+
+    pub fn set_tempo(&mut self, tempo_bpm: f32) {
+        self.info.tempo_bpm = tempo_bpm;
+    }
+
+    pub fn update_sample_rate(&mut self, new_sample_rate: f32) {
+        self.sample_rate = new_sample_rate;
+    }
+
+    pub fn info(&self) -> TransportInfo {
+        self.info
+    }
+
+    pub fn advance(&mut self, samples: usize) {
+        let beats_per_sample = self.info.tempo_bpm as f64 / 60.0 / self.sample_rate as f64;
+        self.info.position_beats += beats_per_sample * samples as f64;
+    }
+}
@@ -0,0 +1,80 @@
+use std::f32::consts::PI;
+
+/// The voice's own pitch vibrato, independent of the mod matrix. Depth 0.0 turns it off.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct VibratoConfig {
+    pub rate_hz: f32,
+    pub depth_semitones: f32,
+    pub delay_secs: f32,                // held without vibrato after note-on
+    pub fade_in_secs: f32,              // then ramped up to full depth over this time
+}
+
+impl Default for VibratoConfig {
+    fn default() -> Self {
+        Self {
+            rate_hz: 5.5,
+            depth_semitones: 0.0,
+            delay_secs: 0.0,
+            fade_in_secs: 0.0,
+        }
+    }
+}
+
+impl VibratoConfig {
+    pub fn is_enabled(&self) -> bool {
+        self.depth_semitones > 0.0
+    }
+}
+
+/// Per-voice vibrato state; restarted by every retriggered note, not by legato slides.
+#[derive(Clone)]
+pub struct Vibrato {
+    config: VibratoConfig,
+    sample_rate: f32,
+    phase: f32,
+    elapsed: usize,                     // samples since the note started
+}
+
+impl Vibrato {
+    pub fn new(config: VibratoConfig, sample_rate: f32) -> Self {
+        Self {
+            config,
+            sample_rate,
+            phase: 0.0,
+            elapsed: 0,
+        }
+    }
+
+    pub fn set_config(&mut self, config: VibratoConfig) {
+        self.config = config;
+    }
+
+    pub fn update_sample_rate(&mut self, new_sample_rate: f32) {
+        self.sample_rate = new_sample_rate;
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.config.is_enabled()
+    }
+
+    pub fn retrigger(&mut self) {
+        self.phase = 0.0;
+        self.elapsed = 0;
+    }
+
+    /// Pitch offset in semitones for the next sample.
+    pub fn next_semitones(&mut self) -> f32 {
+        let delay = (self.config.delay_secs * self.sample_rate) as usize;
+        let fade = (self.config.fade_in_secs * self.sample_rate) as usize;
+        let since_onset = self.elapsed.saturating_sub(delay);
+        self.elapsed = self.elapsed.saturating_add(1);
+        if self.elapsed <= delay {
+            return 0.0;
+        }
+
+        let ramp = if fade == 0 { 1.0 } else { (since_onset as f32 / fade as f32).min(1.0) };
+        let value = (self.phase * 2.0 * PI).sin() * self.config.depth_semitones * ramp;
+        self.phase = (self.phase + self.config.rate_hz / self.sample_rate) % 1.0;
+        value
+    }
+}
@@ -1,67 +1,322 @@
-use crate::envelope::{Envelope, EnvelopeConfig};
-use crate::filter::Filter;
-use crate::oscillator::{make_oscillator, OscillatorConfig, WaveformGenerator};
+use crate::drift::{Drift, DriftConfig};
+use crate::effects::{SendConfig, VoiceInsert, VoiceInsertConfig, Waveshaper, WaveshaperConfig};
+use crate::envelope::{
+    Envelope, EnvelopeConfig, EnvelopeLevel, EnvelopeState, ReleaseVelocityConfig, MOD_ENVELOPE_COUNT, NEUTRAL_RELEASE_VELOCITY,
+};
+use crate::filter::{DcBlocker, Filter, FilterParameters, FilterRouting, FilterRoutingConfig, ModulationSource, DC_BLOCKER_CUTOFF_HZ};
+use crate::glide::{Glide, GlideConfig};
+use crate::keyzone::OscillatorGroup;
+use crate::modulation::registry::{create_custom_source, registered_sources};
+use crate::modulation::{apply_routes, ModulationOutputs, ModulationRoute, ModulationSourceId, ModulationValues};
+use crate::oscillator::{make_oscillator, silenced_oscillators, AdditiveTables, NoiseTables, OscillatorConfig, WaveformGenerator};
+use crate::oversampling::{Decimator, MAX_FACTOR};
+use crate::sample::SampleLayer;
+use crate::vibrato::{Vibrato, VibratoConfig};
+use std::sync::Arc;
+
+const VIBRATO_RATE_HZ: f32 = 5.5;
+const MOD_ENVELOPE_SOURCES: [ModulationSourceId; MOD_ENVELOPE_COUNT] =
+    [ModulationSourceId::ModEnv3, ModulationSourceId::ModEnv4];
+const OSC_BLOCK: usize = 4;          // samples rendered ahead while the pitch holds still
+const TUNING_GLIDE_SECS: f32 = 0.05;  // master tune and transpose changes slide over this time
+const KEY_TRACKING_CENTER_HZ: f32 = 261.63;  // middle C, where key tracking leaves the cutoff alone
 
 pub struct VoiceConfig {
     pub oscillator_configs: Vec<OscillatorConfig>,
+    pub noise_tables: Arc<NoiseTables>,
+    pub additive_tables: Arc<AdditiveTables>,
     pub filter: Filter,
+    pub filter2: Filter,
+    pub filter_routing: FilterRoutingConfig,
+    pub filter_envelope_config: EnvelopeConfig,
+    pub mod_envelope_configs: [EnvelopeConfig; MOD_ENVELOPE_COUNT],
+    pub modulation_routes: Vec<ModulationRoute>,
+    pub stereo_filter_spread: f32,
+    pub dc_blocking: bool,
+    pub insert: VoiceInsertConfig,
+    pub waveshaper: WaveshaperConfig,
+    pub gain_compensation: bool,
+    pub detune_spread: f32,
+    pub key_tracking: f32,
+    pub master_tuning: f32,             // semitones, transpose and fine tune together
+    pub freeze_modulation_on_release: bool,
+    pub release_velocity: ReleaseVelocityConfig,
+    pub glide: GlideConfig,
+    pub vibrato: VibratoConfig,
+    pub drift: DriftConfig,
+    pub sends: SendConfig,
 }
 
 pub struct Voice {
     frequency: f32,
     oscillators: Vec<Box<dyn WaveformGenerator>>, // polymorphic oscillators
     envelope: Envelope,
+    envelope_level: Arc<EnvelopeLevel>,    // the amp envelope as of the last rendered block
     filter: Filter,
-    pub(crate) is_active: bool,
-    pub(crate) note_id: u32,
+    filter_right: Option<Filter>,   // separate right channel state when the stereo spread is non-zero
+    filter2: Filter,
+    filter2_right: Option<Filter>,
+    filter_routing: FilterRoutingConfig,
+    stereo_filter_spread: f32,      // octaves between left and right cutoff
+    dc_blockers: Option<[DcBlocker; 2]>,    // per-voice DC removal, left and right
+    insert: VoiceInsert,
+    waveshaper: Option<Waveshaper>, // pre-filter stage, only allocated while enabled
+    filter_envelope: Envelope,
+    mod_envelopes: [Envelope; MOD_ENVELOPE_COUNT],
+    modulation_routes: Vec<ModulationRoute>,
+    custom_sources: Vec<(u8, Box<dyn ModulationSource>)>,  // every registered source, by slot
+    used_custom_sources: u32,   // bit per slot the routes read from
+    modulation_values: ModulationValues,
+    freeze_modulation_on_release: bool,
+    frozen_modulation: Option<ModulationValues>,    // values held since note-off
+    release_velocity: ReleaseVelocityConfig,
+    vibrato_phase: f32,
+    glide: Glide,
+    vibrato: Vibrato,
+    drift: Drift,
+    sends: SendConfig,
+    send_levels: (f32, f32),        // delay and reverb send gains of the current note
+    pitch_modulated: bool,      // oscillators are off the note's pitch and need resetting
+    osc_mix_gain: f32,              // make-up attenuation for the oscillator stack, 1.0 when off
+    zone_oscillators: OscillatorGroup,  // oscillators the current note's key zone plays
+    silenced_oscillators: u64,      // bit per oscillator left out by its mute or solo switch
+    zone_level: f32,
+    gain_compensation: bool,
+    detune_spread: f32,             // scales every oscillator detune and the per-note detune
+    key_tracking: f32,              // 0.0 to 1.0, octaves of cutoff per octave of pitch
+    tuning: f32,                    // semitones of master tuning applied right now
+    tuning_target: f32,
+    tuning_step: f32,               // per-sample slide towards tuning_target
+    osc_block: [[f32; OSC_BLOCK]; 2],   // oscillator 1 and the others, rendered ahead through fill_block
+    osc_block_pos: usize,           // next unread sample of osc_block, OSC_BLOCK when empty
+    pending_frequency: Option<f32>, // new note's start pitch, held back while the old note fades out
+    control_period: usize,          // samples between modulation and cutoff updates
+    control_countdown: usize,
+    modulation: ModulationOutputs,  // routes as of the last control update
+    cutoff_offset: f32,             // octaves, as of the last control update
+    cutoff_offset2: f32,            // the same for filter 2
+    oversampling: usize,            // frames rendered per output frame
+    decimators: [Decimator; 2],     // left and right, back down to the output rate
+    base_sample_rate: f32,          // the output rate; sample_rate is the rendering rate
+    sample_rate: f32,
+    note_id: u32,
+    velocity: f32,
 }
 
 impl Voice {
     pub fn new(config: &VoiceConfig, envelope_config: &EnvelopeConfig, sample_rate: f32) -> Self {
         // Create polymorphic oscillators with a harmless initial frequency (will be set on trigger)
         let init_freq = 440.0;
-        let oscillators = config
+        let mut oscillators = config
             .oscillator_configs
             .iter()
             .cloned()
-            .map(|cfg| make_oscillator(cfg, sample_rate, init_freq))
+            .enumerate()
+            .map(|(i, cfg)| make_oscillator(cfg, sample_rate, init_freq, config.noise_tables.table(i), &config.additive_tables))
             .collect::<Vec<_>>();
+        for osc in &mut oscillators {
+            osc.set_detune_spread(config.detune_spread);
+        }
+        let mut drift = Drift::new(config.drift, sample_rate);
+        drift.set_detune_spread(config.detune_spread);
 
         Self {
             frequency: 0.0,
+            osc_mix_gain: oscillator_mix_gain(&oscillators, config.gain_compensation),
+            zone_oscillators: OscillatorGroup::All,
+            silenced_oscillators: silenced_oscillators(&config.oscillator_configs),
+            zone_level: 1.0,
+            gain_compensation: config.gain_compensation,
+            detune_spread: config.detune_spread,
+            key_tracking: config.key_tracking,
+            tuning: config.master_tuning,
+            tuning_target: config.master_tuning,
+            tuning_step: 0.0,
             oscillators,
             envelope: Envelope::new(envelope_config.clone(), sample_rate),
+            envelope_level: Arc::new(EnvelopeLevel::default()),
             filter: config.filter.clone(),
-            is_active: false,
+            filter_right: (config.stereo_filter_spread != 0.0).then(|| config.filter.clone()),
+            filter2: config.filter2.clone(),
+            filter2_right: (config.stereo_filter_spread != 0.0).then(|| config.filter2.clone()),
+            filter_routing: config.filter_routing,
+            stereo_filter_spread: config.stereo_filter_spread,
+            dc_blockers: config.dc_blocking.then(|| new_dc_blockers(sample_rate)),
+            insert: VoiceInsert::new(config.insert, sample_rate),
+            waveshaper: config.waveshaper.enabled.then(|| Waveshaper::new(config.waveshaper, sample_rate)),
+            filter_envelope: Envelope::new(config.filter_envelope_config.clone(), sample_rate),
+            mod_envelopes: std::array::from_fn(|i| Envelope::new(config.mod_envelope_configs[i].clone(), sample_rate)),
+            modulation_routes: config.modulation_routes.clone(),
+            custom_sources: custom_sources(sample_rate),
+            used_custom_sources: used_custom_sources(&config.modulation_routes),
+            modulation_values: ModulationValues::default(),
+            freeze_modulation_on_release: config.freeze_modulation_on_release,
+            frozen_modulation: None,
+            release_velocity: config.release_velocity,
+            vibrato_phase: 0.0,
+            glide: Glide::new(config.glide, sample_rate),
+            vibrato: Vibrato::new(config.vibrato, sample_rate),
+            drift,
+            sends: config.sends,
+            send_levels: (0.0, 0.0),
+            pitch_modulated: false,
+            osc_block: [[0.0; OSC_BLOCK]; 2],
+            osc_block_pos: OSC_BLOCK,
+            pending_frequency: None,
+            control_period: 1,
+            control_countdown: 0,
+            modulation: ModulationOutputs::default(),
+            cutoff_offset: 0.0,
+            cutoff_offset2: 0.0,
+            oversampling: 1,
+            decimators: Default::default(),
+            base_sample_rate: sample_rate,
+            sample_rate,
             note_id: 0,
+            velocity: 0.0,
         }
     }
 
+    /// Retunes everything rate-dependent; cheap when the rate is unchanged, so it can be
+    /// called every block.
     pub fn update_sample_rate(&mut self, new_sample_rate: f32) {
+        if new_sample_rate != self.base_sample_rate {
+            self.base_sample_rate = new_sample_rate;
+            self.apply_sample_rate();
+        }
+    }
+
+    // Brings everything to the base rate times the oversampling factor
+    fn apply_sample_rate(&mut self) {
+        let new_sample_rate = self.base_sample_rate * self.oversampling as f32;
+        self.sample_rate = new_sample_rate;
         self.envelope.update_sample_rate(new_sample_rate);
+        self.filter_envelope.update_sample_rate(new_sample_rate);
+        for envelope in &mut self.mod_envelopes {
+            envelope.update_sample_rate(new_sample_rate);
+        }
+        self.glide.update_sample_rate(new_sample_rate);
+        self.vibrato.update_sample_rate(new_sample_rate);
+        self.drift.update_sample_rate(new_sample_rate);
+        for (_, source) in &mut self.custom_sources {
+            source.set_sample_rate(new_sample_rate);
+        }
+        self.insert.update_sample_rate(new_sample_rate);
+        if let Some(shaper) = &mut self.waveshaper {
+            shaper.update_sample_rate(new_sample_rate);
+        }
         for osc in &mut self.oscillators {
             osc.update_sample_rate(new_sample_rate);
         }
         self.filter.update_sample_rate(new_sample_rate);
+        self.filter2.update_sample_rate(new_sample_rate);
+        for filter in self.filter_right.iter_mut().chain(self.filter2_right.iter_mut()) {
+            filter.update_sample_rate(new_sample_rate);
+        }
+        for blocker in self.dc_blockers.iter_mut().flatten() {
+            blocker.update_sample_rate(new_sample_rate);
+        }
+    }
+
+    /// Plays only `oscillators` at `level` from the next trigger on, for key splits and layers.
+    pub fn set_zone(&mut self, oscillators: OscillatorGroup, level: f32) {
+        self.zone_oscillators = oscillators;
+        self.zone_level = level.max(0.0);
     }
 
-    pub fn trigger(&mut self, frequency: f32, note_id: u32, other_env_value: Option<f32>) {
+    /// Starts the voice for a note. `note_id` identifies the note for a later targeted release,
+    /// `other_env_value` lets a non-retriggering envelope continue from another voice's level and
+    /// `glide_from` is the pitch of a note still held elsewhere, for portamento across voices.
+    pub fn trigger(
+        &mut self,
+        frequency: f32,
+        note_id: u32,
+        velocity: f32,
+        other_env_value: Option<f32>,
+        glide_from: Option<f32>,
+    ) {
+        // Outside legato mode a voice retriggered while still sounding also glides from its own pitch
+        let previous = if self.glide.config().legato {
+            glide_from
+        } else {
+            glide_from.or(self.is_active().then_some(self.frequency))
+        };
+        let start_frequency = self.glide.start(previous, frequency);
+        let reused = self.is_active() && self.note_id != note_id;
+        if !self.is_active() {
+            // Golden-ratio spacing keeps neighbouring notes' chorus LFOs apart
+            self.insert.reset(note_id as f32 * 0.618_034);
+        }
         self.frequency = frequency;
+        self.drift.retrigger();
+        self.vibrato.retrigger();
         self.note_id = note_id;
-        self.is_active = true;
+        self.velocity = velocity.clamp(0.0, 1.0);
+        self.send_levels = self.sends.voice_levels(frequency, self.velocity);
+        self.modulation_values.poly_pressure = 0.0;
+        self.frozen_modulation = None;
+        self.control_countdown = 0;
 
-        // Retrigger or continue from current env value depending on config
-        self.envelope.trigger(other_env_value);
+        // Retrigger or continue from current env value depending on config. A voice taken over
+        // by another note fades out first; the same note retriggering only crossfades
+        if reused {
+            self.envelope.trigger_with_fade_out(other_env_value);
+        } else {
+            self.envelope.trigger(other_env_value);
+        }
+        self.publish_envelope_level();
+        self.filter_envelope.trigger(None);
+        for envelope in &mut self.mod_envelopes {
+            envelope.trigger(None);
+        }
+        for (_, source) in &mut self.custom_sources {
+            source.reset();
+        }
 
-        // Retune all oscillators for this note
-        for osc in &mut self.oscillators {
-            osc.set_frequency(frequency);
+        // A voice reused while sounding keeps its old pitch until the fade-out ends
+        if self.envelope.is_fading_out() {
+            self.pending_frequency = Some(start_frequency);
+        } else {
+            self.pending_frequency = None;
+            self.retune(start_frequency);
+            self.retrigger_oscillators();
         }
     }
 
-    pub fn release(&mut self, note_id: u32) {
-        if self.note_id == note_id {
-            self.envelope.release();
+    /// Moves a sounding voice to another note without retriggering its envelopes or
+    /// oscillators, gliding if glide is on; used by legato mono play.
+    pub fn slide_to(&mut self, frequency: f32, note_id: u32) {
+        let start_frequency = self.glide.start(Some(self.frequency), frequency);
+        self.frequency = frequency;
+        self.note_id = note_id;
+        self.send_levels = self.sends.voice_levels(frequency, self.velocity);
+        if self.envelope.is_fading_out() {
+            self.pending_frequency = Some(start_frequency);
+        } else {
+            self.retune(start_frequency);
+        }
+    }
+
+    /// Releases the voice only if it is still playing `note_id`; returns whether it did.
+    pub fn release(&mut self, note_id: u32) -> bool {
+        self.release_with_velocity(note_id, NEUTRAL_RELEASE_VELOCITY)
+    }
+
+    /// Like `release`, with the release times scaled by the note-off `velocity`.
+    pub fn release_with_velocity(&mut self, note_id: u32, velocity: f32) -> bool {
+        if self.note_id == note_id && self.is_active() {
+            let time_scale = self.release_velocity.time_scale(velocity);
+            self.envelope.release_scaled(time_scale);
+            self.filter_envelope.release_scaled(time_scale);
+            for envelope in &mut self.mod_envelopes {
+                envelope.release_scaled(time_scale);
+            }
+            if self.freeze_modulation_on_release {
+                self.frozen_modulation = Some(self.modulation_values);
+            }
+            true
+        } else {
+            false
         }
     }
 
@@ -69,22 +324,556 @@ impl Voice {
         self.envelope.is_active()
     }
 
+    /// Releases whatever note the voice is playing, regardless of who holds it.
+    pub fn force_release(&mut self) {
+        self.release(self.note_id);
+    }
+
+    /// Fades the voice out over `secs` regardless of its release time, e.g. for a panic.
+    pub fn fade_out(&mut self, secs: f32) {
+        self.envelope.fade_to_silence(secs);
+        self.pending_frequency = None;
+    }
+
+    /// Silences the voice at once, for voices that stay stuck even after a release.
+    pub fn stop(&mut self) {
+        self.envelope.stop();
+        self.filter_envelope.stop();
+        for envelope in &mut self.mod_envelopes {
+            envelope.stop();
+        }
+        self.pending_frequency = None;
+        self.publish_envelope_level();
+    }
+
+    /// Longest an unheld note can legitimately keep sounding: a full attack, decay and release.
+    pub fn max_unheld_secs(&self) -> f32 {
+        let config = self.envelope.config();
+        config.attack_time + config.decay_time + config.release_time + config.retrigger_fade_time
+    }
+
+    pub fn note_id(&self) -> u32 {
+        self.note_id
+    }
+
+    /// Stage of the amp envelope, which decides whether the voice is still sounding.
+    pub fn envelope_state(&self) -> EnvelopeState {
+        self.envelope.state()
+    }
+
+    pub fn velocity(&self) -> f32 {
+        self.velocity
+    }
+
+    /// Swaps in new oscillators (e.g. after a waveform change) and retunes them to the current note.
+    pub fn set_oscillators(&mut self, oscillators: Vec<Box<dyn WaveformGenerator>>) {
+        self.osc_mix_gain = oscillator_mix_gain(&oscillators, self.gain_compensation);
+        self.oscillators = oscillators;
+        for osc in &mut self.oscillators {
+            osc.update_sample_rate(self.sample_rate);
+            osc.set_detune_spread(self.detune_spread);
+            osc.set_frequency(self.frequency * 2.0f32.powf(self.tuning / 12.0));
+        }
+        self.osc_block_pos = OSC_BLOCK;
+    }
+
+    /// Passes new settings to the oscillators. Those that take them in place carry on
+    /// sounding; the rest, e.g. after a waveform change, are swapped for `build(slot)`.
+    /// Oscillators past `configs`, like the sample, stay as they are.
+    pub fn update_oscillators(&mut self, configs: &[OscillatorConfig], build: impl Fn(usize) -> Box<dyn WaveformGenerator>) {
+        let frequency = self.frequency * 2.0f32.powf(self.tuning / 12.0);
+        for (slot, (osc, config)) in self.oscillators.iter_mut().zip(configs).enumerate() {
+            if !osc.set_config(*config) {
+                *osc = build(slot);
+                osc.update_sample_rate(self.sample_rate);
+            }
+            osc.set_detune_spread(self.detune_spread);
+            osc.set_frequency(frequency);
+        }
+        self.osc_mix_gain = oscillator_mix_gain(&self.oscillators, self.gain_compensation);
+    }
+
+    /// Switches the voice's random oscillators to `tables` without restarting them.
+    pub fn set_noise_tables(&mut self, tables: &NoiseTables) {
+        for (i, osc) in self.oscillators.iter_mut().enumerate() {
+            osc.set_noise_table(tables.table(i));
+        }
+    }
+
+    /// Switches the voice's additive oscillators to `tables` without restarting them.
+    pub fn set_additive_tables(&mut self, tables: &Arc<AdditiveTables>) {
+        for osc in &mut self.oscillators {
+            osc.set_additive_tables(tables);
+        }
+    }
+
+    pub fn oscillator_count(&self) -> usize {
+        self.oscillators.len()
+    }
+
+    /// Switches the voice's sample oscillator, if it has one, to `layer` without restarting it.
+    pub fn set_sample_layer(&mut self, layer: &SampleLayer) {
+        for osc in &mut self.oscillators {
+            osc.set_sample_layer(layer);
+        }
+        self.osc_mix_gain = oscillator_mix_gain(&self.oscillators, self.gain_compensation);
+    }
+
+    /// Oscillators to leave out of the mix, as from `silenced_oscillators`.
+    pub fn set_silenced_oscillators(&mut self, mask: u64) {
+        self.silenced_oscillators = mask;
+    }
+
+    pub fn set_envelope_config(&mut self, config: EnvelopeConfig) {
+        self.envelope.set_config(config);
+    }
+
+    pub fn set_filter_envelope_config(&mut self, config: EnvelopeConfig) {
+        self.filter_envelope.set_config(config);
+    }
+
+    pub fn set_mod_envelope_config(&mut self, index: usize, config: EnvelopeConfig) {
+        if let Some(envelope) = self.mod_envelopes.get_mut(index) {
+            envelope.set_config(config);
+        }
+    }
+
+    pub fn set_filter_parameters(&mut self, parameters: FilterParameters) {
+        if let Some(filter) = &mut self.filter_right {
+            filter.set_parameters(parameters.clone());
+        }
+        self.filter.set_parameters(parameters);
+    }
+
+    pub fn set_filter2_parameters(&mut self, parameters: FilterParameters) {
+        if let Some(filter) = &mut self.filter2_right {
+            filter.set_parameters(parameters.clone());
+        }
+        self.filter2.set_parameters(parameters);
+    }
+
+    pub fn set_filter_routing(&mut self, routing: FilterRoutingConfig) {
+        self.filter_routing = routing;
+    }
+
+    /// Spreads the left and right cutoff of both filters apart by `octaves`; 0.0 runs mono filters.
+    pub fn set_stereo_filter_spread(&mut self, octaves: f32) {
+        self.stereo_filter_spread = octaves;
+        if octaves == 0.0 {
+            self.filter_right = None;
+            self.filter2_right = None;
+        } else if self.filter_right.is_none() {
+            self.filter_right = Some(self.filter.clone());
+            self.filter2_right = Some(self.filter2.clone());
+        }
+    }
+
+    pub fn set_dc_blocking(&mut self, enabled: bool) {
+        if !enabled {
+            self.dc_blockers = None;
+        } else if self.dc_blockers.is_none() {
+            self.dc_blockers = Some(new_dc_blockers(self.sample_rate));
+        }
+    }
+
+    pub fn set_gain_compensation(&mut self, enabled: bool) {
+        self.gain_compensation = enabled;
+        self.osc_mix_gain = oscillator_mix_gain(&self.oscillators, enabled);
+    }
+
+    pub fn set_detune_spread(&mut self, spread: f32) {
+        self.detune_spread = spread;
+        for osc in &mut self.oscillators {
+            osc.set_detune_spread(spread);
+        }
+        self.drift.set_detune_spread(spread);
+    }
+
+    pub fn set_key_tracking(&mut self, amount: f32) {
+        self.key_tracking = amount;
+    }
+
+    /// Slides the pitch of this voice to `semitones` off the played note; a silent voice
+    /// takes the new tuning at once.
+    pub fn set_master_tuning(&mut self, semitones: f32) {
+        if !self.is_active() {
+            self.tuning = semitones;
+        }
+        self.tuning_target = semitones;
+        self.tuning_step = (semitones - self.tuning).abs() / (TUNING_GLIDE_SECS * self.sample_rate);
+    }
+
+    fn advance_tuning(&mut self) {
+        let delta = self.tuning_target - self.tuning;
+        self.tuning += delta.clamp(-self.tuning_step, self.tuning_step);
+    }
+
+    pub fn set_waveshaper_config(&mut self, config: WaveshaperConfig) {
+        if !config.enabled {
+            self.waveshaper = None;
+        } else if let Some(shaper) = &mut self.waveshaper {
+            shaper.set_config(config);
+        } else {
+            self.waveshaper = Some(Waveshaper::new(config, self.sample_rate));
+        }
+    }
+
+    pub fn set_insert_config(&mut self, config: VoiceInsertConfig) {
+        self.insert.set_config(config);
+    }
+
+    /// Renders `factor` frames for every output frame, at that multiple of the output rate,
+    /// and decimates them. Factors other than 1, 2 and 4 round up to the next of those.
+    pub fn set_oversampling(&mut self, factor: usize) {
+        let factor = factor.clamp(1, MAX_FACTOR).next_power_of_two();
+        if factor == self.oversampling {
+            return;
+        }
+        self.oversampling = factor;
+        for decimator in &mut self.decimators {
+            decimator.reset();
+        }
+        self.apply_sample_rate();
+    }
+
+    /// Re-evaluates modulation routes and the filter cutoff only every `period` samples.
+    pub fn set_control_period(&mut self, period: usize) {
+        self.control_period = period.max(1);
+        self.control_countdown = self.control_countdown.min(self.control_period);
+    }
+
+    pub fn set_glide_config(&mut self, config: GlideConfig) {
+        self.glide.set_config(config);
+    }
+
+    pub fn set_vibrato_config(&mut self, config: VibratoConfig) {
+        self.vibrato.set_config(config);
+    }
+
+    pub fn set_drift_config(&mut self, config: DriftConfig) {
+        self.drift.set_config(config);
+    }
+
+    pub fn set_send_config(&mut self, config: SendConfig) {
+        self.sends = config;
+        self.send_levels = config.voice_levels(self.frequency, self.velocity);
+    }
+
+    pub fn send_levels(&self) -> (f32, f32) {
+        self.send_levels
+    }
+
+    /// Gives this voice its own drift pattern; voices cloned from one prototype start identical.
+    pub fn seed_drift(&mut self, seed: u64) {
+        self.drift.seed(seed);
+    }
+
+    pub fn set_poly_pressure(&mut self, pressure: f32) {
+        self.modulation_values.poly_pressure = pressure;
+    }
+
+    pub fn set_channel_pressure(&mut self, pressure: f32) {
+        self.modulation_values.channel_pressure = pressure;
+    }
+
+    /// Copies `routes` into the voice's own list, which only reallocates if it grows.
+    pub fn set_modulation_routes(&mut self, routes: &[ModulationRoute]) {
+        self.used_custom_sources = used_custom_sources(routes);
+        self.modulation_routes.clear();
+        self.modulation_routes.extend_from_slice(routes);
+    }
+
+    /// Creates fresh instances of every registered custom source, e.g. after one was
+    /// registered. Allocates, so not for the audio thread.
+    pub fn refresh_custom_sources(&mut self) {
+        self.custom_sources = custom_sources(self.sample_rate);
+    }
+
+    pub fn set_release_velocity_config(&mut self, config: ReleaseVelocityConfig) {
+        self.release_velocity = config;
+    }
+
+    /// Holds modulation source values and the vibrato phase from note-off onwards, so the
+    /// release always decays the same way.
+    pub fn set_freeze_modulation_on_release(&mut self, freeze: bool) {
+        self.freeze_modulation_on_release = freeze;
+        if !freeze {
+            self.frozen_modulation = None;
+        }
+    }
+
+    pub fn set_modulation_value(&mut self, source: ModulationSourceId, value: f32) {
+        self.modulation_values.set(source, value);
+    }
+
     pub fn next_sample(&mut self) -> f32 {
-        let env = self.envelope.next_value();
+        let (left, right) = self.next_frame();
+        0.5 * (left + right)
+    }
+
+    pub fn next_frame(&mut self) -> (f32, f32) {
+        if self.oversampling == 1 {
+            return self.render_frame();
+        }
+        let mut left = [0.0; MAX_FACTOR];
+        let mut right = [0.0; MAX_FACTOR];
+        for (l, r) in left.iter_mut().zip(right.iter_mut()).take(self.oversampling) {
+            (*l, *r) = self.render_frame();
+        }
+        let [decimate_left, decimate_right] = &mut self.decimators;
+        (
+            decimate_left.process(&left[..self.oversampling]),
+            decimate_right.process(&right[..self.oversampling]),
+        )
+    }
+
+    // One frame at the rendering rate
+    fn render_frame(&mut self) -> (f32, f32) {
+        // Mod envelopes run every sample; the routes read them at the next control update
+        for (envelope, source) in self.mod_envelopes.iter_mut().zip(MOD_ENVELOPE_SOURCES) {
+            self.modulation_values.set(source, envelope.next_value());
+        }
+        for (slot, source) in &mut self.custom_sources {
+            if self.used_custom_sources & (1 << *slot) != 0 {
+                self.modulation_values.set(ModulationSourceId::Custom(*slot), source.next_value());
+            }
+        }
+
+        let control_update = self.control_countdown == 0;
+        if control_update {
+            let values = self.frozen_modulation.as_ref().unwrap_or(&self.modulation_values);
+            self.modulation = apply_routes(&self.modulation_routes, values);
+            for filter in [&mut self.filter, &mut self.filter2]
+                .into_iter()
+                .chain(self.filter_right.iter_mut())
+                .chain(self.filter2_right.iter_mut())
+            {
+                filter.update_modulation(values);
+            }
+            self.control_countdown = self.control_period * self.oversampling;   // same period in time
+        }
+        self.control_countdown -= 1;
+        let modulation = self.modulation;
+
+        let fading_out = self.envelope.is_fading_out();
+        if !fading_out {
+            if let Some(frequency) = self.pending_frequency.take() {
+                self.retune(frequency);
+                self.retrigger_oscillators();
+            }
+        }
+
+        let gliding = self.glide.is_active();
+        let drifting = self.drift.is_active();
+        if drifting {
+            self.drift.advance();
+        }
+        let vibrating = self.vibrato.is_active();
+        let vibrato = if vibrating { self.vibrato.next_semitones() } else { 0.0 };
+        let retuning = self.tuning != self.tuning_target;
+        if retuning {
+            self.advance_tuning();
+        }
+        if !fading_out && (modulation.vibrato != 0.0 || vibrating || retuning || gliding || drifting || self.pitch_modulated) {
+            let base_frequency = if gliding { self.glide.next_frequency() } else { self.frequency };
+            let lfo = (self.vibrato_phase * 2.0 * std::f32::consts::PI).sin();
+            let frequency = base_frequency * 2.0f32.powf((modulation.vibrato * lfo + vibrato + self.tuning) / 12.0);
+            for (i, osc) in self.oscillators.iter_mut().enumerate() {
+                osc.set_frequency(frequency * self.drift.pitch_ratio(i));
+            }
+            if self.frozen_modulation.is_none() {
+                self.vibrato_phase = (self.vibrato_phase + VIBRATO_RATE_HZ / self.sample_rate) % 1.0;
+            }
+            self.pitch_modulated = modulation.vibrato != 0.0 || vibrating || retuning || gliding || drifting;
+        }
+        // The filter envelope sweeps up to 10 octaves at full modulation amount
+        let filter_env = self.filter_envelope.next_value() * 10.0;
+        if control_update {
+            // Tracks the pitch the glide has reached, not the note it is heading for
+            let pitch = if gliding { self.glide.current_frequency() } else { self.frequency };
+            let key = self.key_tracking * (pitch.max(1.0) / KEY_TRACKING_CENTER_HZ).log2();
+            let common = modulation.cutoff + self.drift.cutoff_offset() + key;
+            self.cutoff_offset = common + filter_env * self.filter.parameters().modulation_amount;
+            self.cutoff_offset2 = common + filter_env * self.filter2.parameters().modulation_amount;
+        }
+        let (cutoff_offset, cutoff_offset2) = (self.cutoff_offset, self.cutoff_offset2);
+
+        let env = self.envelope.next_value() * modulation.amplitude * self.velocity * self.osc_mix_gain * self.zone_level;
+
+        let (group1, group2) = self.next_oscillator_groups();
+        let (group1, group2) = (group1 * env, group2 * env);
+        // The split routing filters the two groups apart, so they are shaped apart too
+        let (group1, group2, mixed) = match &mut self.waveshaper {
+            Some(shaper) if self.filter_routing.routing == FilterRouting::Split => {
+                let (a, b) = shaper.process(group1, group2);
+                (a, b, a + b)
+            }
+            Some(shaper) => (group1, group2, shaper.process_mono(group1 + group2)),
+            None => (group1, group2, group1 + group2),
+        };
 
-        let osc_sum = self
-            .oscillators
-            .iter_mut()
-            .map(|osc| osc.next_sample())
-            .sum::<f32>();
+        let spread = self.stereo_filter_spread;
+        let filter1 = (&mut self.filter, self.filter_right.as_mut());
+        let filter2 = (&mut self.filter2, self.filter2_right.as_mut());
+        let (left, right) = match self.filter_routing.routing {
+            FilterRouting::Single => filter_frame(filter1, cutoff_offset, spread, (mixed, mixed)),
+            FilterRouting::Serial => {
+                let first = filter_frame(filter1, cutoff_offset, spread, (mixed, mixed));
+                filter_frame(filter2, cutoff_offset2, spread, first)
+            }
+            FilterRouting::Parallel => {
+                let a = filter_frame(filter1, cutoff_offset, spread, (mixed, mixed));
+                let b = filter_frame(filter2, cutoff_offset2, spread, (mixed, mixed));
+                let mix = self.filter_routing.parallel_mix;
+                (a.0 + (b.0 - a.0) * mix, a.1 + (b.1 - a.1) * mix)
+            }
+            FilterRouting::Split => {
+                let a = filter_frame(filter1, cutoff_offset, spread, (group1, group1));
+                let b = filter_frame(filter2, cutoff_offset2, spread, (group2, group2));
+                (a.0 + b.0, a.1 + b.1)
+            }
+        };
 
-        let enveloped = osc_sum * env;
-        self.filter.process_sample(enveloped)
+        // A NaN or infinity would otherwise live on in the filter state for good
+        if !(left.is_finite() && right.is_finite()) {
+            self.filter.reset();
+            self.filter2.reset();
+            for filter in self.filter_right.iter_mut().chain(self.filter2_right.iter_mut()) {
+                filter.reset();
+            }
+            for blocker in self.dc_blockers.iter_mut().flatten() {
+                blocker.reset();
+            }
+            return (0.0, 0.0);
+        }
+        let (left, right) = self.insert.process(left, right);
+        match &mut self.dc_blockers {
+            Some([blocker_left, blocker_right]) => (blocker_left.process_sample(left), blocker_right.process_sample(right)),
+            None => (left, right),
+        }
+    }
+
+    // Sets every oscillator to `frequency`, dropping samples rendered ahead at the old pitch
+    fn retune(&mut self, frequency: f32) {
+        let frequency = frequency * 2.0f32.powf(self.tuning / 12.0);
+        for osc in &mut self.oscillators {
+            osc.set_frequency(frequency);
+        }
+        self.osc_block_pos = OSC_BLOCK;
+    }
+
+    fn retrigger_oscillators(&mut self) {
+        for osc in &mut self.oscillators {
+            osc.retrigger();
+        }
+        self.osc_block_pos = OSC_BLOCK;
     }
 
+    // Returns oscillator 1 and the sum of the others, kept apart for the split filter routing.
+    // A steady pitch renders the oscillators a block ahead through their vectorised path;
+    // a moving pitch needs a new frequency every sample and takes the scalar path
+    fn next_oscillator_groups(&mut self) -> (f32, f32) {
+        if self.osc_block_pos < OSC_BLOCK {
+            let pos = self.osc_block_pos;
+            self.osc_block_pos += 1;
+            return (self.osc_block[0][pos], self.osc_block[1][pos]);
+        }
+        if self.pitch_modulated {
+            let mut groups = (0.0, 0.0);
+            for (i, osc) in self.oscillators.iter_mut().enumerate() {
+                if !self.zone_oscillators.includes(i) || self.silenced_oscillators & (1 << i) != 0 {
+                    continue;
+                }
+                let sample = osc.next_sample();
+                if i == 0 { groups.0 += sample } else { groups.1 += sample }
+            }
+            return groups;
+        }
+
+        let mut scratch = [0.0; OSC_BLOCK];
+        self.osc_block = [[0.0; OSC_BLOCK]; 2];
+        for (i, osc) in self.oscillators.iter_mut().enumerate() {
+            if !self.zone_oscillators.includes(i) || self.silenced_oscillators & (1 << i) != 0 {
+                continue;
+            }
+            osc.fill_block(&mut scratch);
+            let group = &mut self.osc_block[usize::from(i > 0)];
+            for (sum, sample) in group.iter_mut().zip(scratch) {
+                *sum += sample;
+            }
+        }
+        self.osc_block_pos = 1;
+        (self.osc_block[0][0], self.osc_block[1][0])
+    }
+
+    /// The amp envelope's current level, for the thread that renders the voice; other
+    /// threads read the published one through `envelope_level`.
     pub fn get_envelope_value(&self) -> f32 {
         self.envelope.current_value()
     }
+
+    /// Makes the amp envelope's current level visible to every holder of `envelope_level`;
+    /// the audio thread calls this once per rendered block.
+    pub fn publish_envelope_level(&self) {
+        self.envelope_level.publish(self.envelope.current_value());
+    }
+
+    /// A handle other threads can read this voice's envelope level through.
+    pub fn envelope_level(&self) -> Arc<EnvelopeLevel> {
+        self.envelope_level.clone()
+    }
+}
+
+/// One instance of every registered custom source. Voices keep them all, so changing the
+/// routes never has to build one on the audio thread.
+fn custom_sources(sample_rate: f32) -> Vec<(u8, Box<dyn ModulationSource>)> {
+    registered_sources()
+        .into_iter()
+        .filter_map(|id| match id {
+            ModulationSourceId::Custom(slot) => Some((slot, create_custom_source(slot, sample_rate)?)),
+            _ => None,
+        })
+        .collect()
+}
+
+// Bit per custom source slot that `routes` read from
+fn used_custom_sources(routes: &[ModulationRoute]) -> u32 {
+    routes.iter().fold(0, |used, route| match route.source {
+        ModulationSourceId::Custom(slot) => used | 1 << slot,
+        _ => used,
+    })
+}
+
+/// Make-up attenuation that keeps the level roughly constant as oscillators are added.
+/// Detuned oscillators add up in power rather than amplitude, so the stack is scaled by
+/// the root of the summed squared volumes; a single full-volume oscillator is left alone.
+fn oscillator_mix_gain(oscillators: &[Box<dyn WaveformGenerator>], compensate: bool) -> f32 {
+    if !compensate {
+        return 1.0;
+    }
+    let power: f32 = oscillators.iter().map(|osc| osc.volume() * osc.volume()).sum();
+    1.0 / power.sqrt().max(1.0)
+}
+
+fn new_dc_blockers(sample_rate: f32) -> [DcBlocker; 2] {
+    std::array::from_fn(|_| DcBlocker::new(DC_BLOCKER_CUTOFF_HZ, sample_rate))
+}
+
+// Runs one filter, plus its right channel copy while the stereo spread is on, over a frame
+fn filter_frame(filter: (&mut Filter, Option<&mut Filter>), cutoff_offset: f32, spread: f32, input: (f32, f32)) -> (f32, f32) {
+    match filter {
+        (left, Some(right)) => {
+            let half_spread = spread * 0.5;
+            left.set_cutoff_offset(cutoff_offset - half_spread);
+            right.set_cutoff_offset(cutoff_offset + half_spread);
+            (left.process_sample(input.0), right.process_sample(input.1))
+        }
+        (mono, None) => {
+            mono.set_cutoff_offset(cutoff_offset);
+            let output = mono.process_sample(input.0);
+            (output, output)
+        }
+    }
 }
 
 // Clone via box_clone() for the oscillators
@@ -94,9 +883,58 @@ impl Clone for Voice {
             frequency: self.frequency,
             oscillators: self.oscillators.iter().map(|o| o.box_clone()).collect(),
             envelope: self.envelope.clone(),
+            // Every copy publishes its own level
+            envelope_level: Arc::new(EnvelopeLevel::new(self.envelope_level.get())),
             filter: self.filter.clone(),
-            is_active: self.is_active,
+            filter_right: self.filter_right.clone(),
+            filter2: self.filter2.clone(),
+            filter2_right: self.filter2_right.clone(),
+            filter_routing: self.filter_routing,
+            stereo_filter_spread: self.stereo_filter_spread,
+            dc_blockers: self.dc_blockers.clone(),
+            insert: self.insert.clone(),
+            waveshaper: self.waveshaper.clone(),
+            filter_envelope: self.filter_envelope.clone(),
+            mod_envelopes: self.mod_envelopes.clone(),
+            modulation_routes: self.modulation_routes.clone(),
+            // Sources can't be cloned, so every copy gets fresh instances
+            custom_sources: custom_sources(self.sample_rate),
+            used_custom_sources: self.used_custom_sources,
+            modulation_values: self.modulation_values,
+            freeze_modulation_on_release: self.freeze_modulation_on_release,
+            frozen_modulation: self.frozen_modulation,
+            release_velocity: self.release_velocity,
+            vibrato_phase: self.vibrato_phase,
+            glide: self.glide.clone(),
+            vibrato: self.vibrato.clone(),
+            drift: self.drift.clone(),
+            sends: self.sends,
+            send_levels: self.send_levels,
+            pitch_modulated: self.pitch_modulated,
+            osc_mix_gain: self.osc_mix_gain,
+            zone_oscillators: self.zone_oscillators,
+            silenced_oscillators: self.silenced_oscillators,
+            zone_level: self.zone_level,
+            gain_compensation: self.gain_compensation,
+            detune_spread: self.detune_spread,
+            key_tracking: self.key_tracking,
+            tuning: self.tuning,
+            tuning_target: self.tuning_target,
+            tuning_step: self.tuning_step,
+            osc_block: self.osc_block,
+            osc_block_pos: self.osc_block_pos,
+            pending_frequency: self.pending_frequency,
+            control_period: self.control_period,
+            control_countdown: self.control_countdown,
+            modulation: self.modulation,
+            cutoff_offset: self.cutoff_offset,
+            cutoff_offset2: self.cutoff_offset2,
+            oversampling: self.oversampling,
+            decimators: self.decimators.clone(),
+            base_sample_rate: self.base_sample_rate,
+            sample_rate: self.sample_rate,
             note_id: self.note_id,
+            velocity: self.velocity,
         }
     }
 }
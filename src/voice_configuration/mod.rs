@@ -2,13 +2,42 @@ use std::sync::{Arc, Mutex};
 
 use crate::envelope::Envelope;
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, PartialEq, Debug)]
 pub enum Waveform {
   SINE,
   SAW,
   SQUARE,
   RANDOM,
   WHITE_NOISE,
+  PLUCK,
+  ADDITIVE,
+  SUPERSAW,
+}
+
+impl Waveform {
+  pub const ALL: [Waveform; 8] = [
+    Waveform::SINE,
+    Waveform::SAW,
+    Waveform::SQUARE,
+    Waveform::RANDOM,
+    Waveform::WHITE_NOISE,
+    Waveform::PLUCK,
+    Waveform::ADDITIVE,
+    Waveform::SUPERSAW,
+  ];
+
+  pub fn label(self) -> &'static str {
+    match self {
+      Waveform::SINE => "Sine",
+      Waveform::SAW => "Saw",
+      Waveform::SQUARE => "Square",
+      Waveform::RANDOM => "Random",
+      Waveform::WHITE_NOISE => "Noise",
+      Waveform::PLUCK => "Pluck",
+      Waveform::ADDITIVE => "Additive",
+      Waveform::SUPERSAW => "Supersaw",
+    }
+  }
 }
 
 pub struct VoiceConfiguration {
@@ -0,0 +1,116 @@
+use std::sync::atomic::{AtomicU32, AtomicU64, AtomicU8, AtomicUsize, Ordering};
+
+use crate::envelope::EnvelopeState;
+use crate::synthesizer::VoiceStats;
+
+/// Voices whose envelope stage the meter can show; larger pools only report their counts.
+pub const METER_VOICES: usize = 32;
+/// Share of a voice's displayed level kept per published block, so peaks fall back smoothly.
+const LEVEL_DECAY: f32 = 0.85;
+const NO_SOLO: usize = usize::MAX;
+
+/// Voice usage published by the audio thread for the GUI meter, without locking the synth.
+pub struct VoiceMeter {
+    active_voices: AtomicUsize,
+    max_voices: AtomicUsize,
+    stolen_voices: AtomicU64,
+    stuck_voices: AtomicU64,
+    stages: [AtomicU8; METER_VOICES],   // index into `EnvelopeState::ALL`
+    levels: [AtomicU32; METER_VOICES],  // f32 bits, decaying peak
+    solo: AtomicUsize,                  // voice the GUI asked to solo, or NO_SOLO
+    generation: AtomicU64,              // bumped by every publish, so views know to redraw
+}
+
+impl Default for VoiceMeter {
+    fn default() -> Self {
+        Self {
+            active_voices: AtomicUsize::new(0),
+            max_voices: AtomicUsize::new(0),
+            stolen_voices: AtomicU64::new(0),
+            stuck_voices: AtomicU64::new(0),
+            stages: std::array::from_fn(|_| AtomicU8::new(0)),
+            levels: std::array::from_fn(|_| AtomicU32::new(0)),
+            solo: AtomicUsize::new(NO_SOLO),
+            generation: AtomicU64::new(0),
+        }
+    }
+}
+
+impl VoiceMeter {
+    pub fn publish(&self, stats: &VoiceStats) {
+        self.active_voices.store(stats.active_voices, Ordering::Relaxed);
+        self.max_voices.store(stats.max_voices, Ordering::Relaxed);
+        self.stolen_voices.store(stats.stolen_voices, Ordering::Relaxed);
+        self.stuck_voices.store(stats.stuck_voices, Ordering::Relaxed);
+        for (slot, stage) in self.stages.iter().zip(stats.stages.iter()) {
+            let index = EnvelopeState::ALL.iter().position(|s| s == stage).unwrap_or(0);
+            slot.store(index as u8, Ordering::Relaxed);
+        }
+        for (slot, &peak) in self.levels.iter().zip(stats.levels.iter()) {
+            let held = f32::from_bits(slot.load(Ordering::Relaxed)) * LEVEL_DECAY;
+            slot.store(peak.max(held).to_bits(), Ordering::Relaxed);
+        }
+        self.generation.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn generation(&self) -> u64 {
+        self.generation.load(Ordering::Relaxed)
+    }
+
+    /// Decaying peak level of one voice, linear.
+    pub fn level(&self, voice: usize) -> f32 {
+        self.levels.get(voice).map_or(0.0, |l| f32::from_bits(l.load(Ordering::Relaxed)))
+    }
+
+    /// Soloes `voice`, or unsoloes it if it already is; the audio thread picks this up next block.
+    pub fn toggle_solo(&self, voice: usize) {
+        let current = self.solo.load(Ordering::Relaxed);
+        self.solo.store(if current == voice { NO_SOLO } else { voice }, Ordering::Relaxed);
+    }
+
+    pub fn solo(&self) -> Option<usize> {
+        let voice = self.solo.load(Ordering::Relaxed);
+        (voice != NO_SOLO).then_some(voice)
+    }
+
+    pub fn active_voices(&self) -> usize {
+        self.active_voices.load(Ordering::Relaxed)
+    }
+
+    pub fn max_voices(&self) -> usize {
+        self.max_voices.load(Ordering::Relaxed)
+    }
+
+    pub fn stolen_voices(&self) -> u64 {
+        self.stolen_voices.load(Ordering::Relaxed)
+    }
+
+    /// Voices the stuck-voice watchdog had to release, which points at a lost note-off.
+    pub fn stuck_voices(&self) -> u64 {
+        self.stuck_voices.load(Ordering::Relaxed)
+    }
+
+    /// Share of the voice pool in use, 0.0 to 1.0.
+    pub fn usage(&self) -> f32 {
+        let max = self.max_voices();
+        if max == 0 { 0.0 } else { self.active_voices() as f32 / max as f32 }
+    }
+
+    pub fn stage(&self, voice: usize) -> EnvelopeState {
+        let index = self.stages.get(voice).map_or(0, |s| s.load(Ordering::Relaxed));
+        EnvelopeState::ALL[index as usize % EnvelopeState::ALL.len()]
+    }
+
+    /// One letter per voice, e.g. "ASSR----", for a compact stage readout.
+    pub fn stage_summary(&self) -> String {
+        (0..self.max_voices().min(METER_VOICES)).map(|i| self.stage(i).short_label()).collect()
+    }
+
+    pub fn describe(&self) -> String {
+        let summary = format!("{}/{} voices, {} stolen", self.active_voices(), self.max_voices(), self.stolen_voices());
+        match self.stuck_voices() {
+            0 => summary,
+            stuck => format!("{}, {} stuck", summary, stuck),
+        }
+    }
+}
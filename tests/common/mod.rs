@@ -0,0 +1,236 @@
+//! Offline rendering and analysis shared by the DSP regression tests.
+//!
+//! Golden renders live in `tests/golden/` as 32-bit float stereo WAVs and are committed;
+//! a missing one fails the test. To record a new one, or re-record them after an intended
+//! change to the sound, run
+//!
+//!     UPDATE_GOLDEN=1 cargo test --test dsp_render
+
+#![allow(dead_code)]
+
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use rust_vst_synth::dynamics::OutputNormalization;
+use rust_vst_synth::effects::{DelayConfig, ReverbConfig};
+use rust_vst_synth::envelope::EnvelopeConfig;
+use rust_vst_synth::filter::{Filter, FilterParameters, FilterSlope, FilterType, SaturationCurve};
+use rust_vst_synth::oscillator::{OscillatorConfig, PhaseMode};
+use rust_vst_synth::synthesizer::{midi_note_to_freq, Synthesizer, SynthesizerConfig};
+use rust_vst_synth::voice_configuration::Waveform;
+
+pub const SAMPLE_RATE: f32 = 48000.0;
+const BLOCK_SIZE: usize = 256;
+
+#[derive(Clone, Copy, Debug)]
+pub enum Event {
+    NoteOn { at: f32, note: u8, velocity: f32 },
+    NoteOff { at: f32, note: u8 },
+}
+
+impl Event {
+    fn frame(&self) -> usize {
+        let at = match *self {
+            Event::NoteOn { at, .. } | Event::NoteOff { at, .. } => at,
+        };
+        (at * SAMPLE_RATE).round() as usize
+    }
+}
+
+pub struct Render {
+    pub left: Vec<f32>,
+    pub right: Vec<f32>,
+}
+
+impl Render {
+    /// Frames `start..start + len`, both in seconds, of the left channel.
+    pub fn window(&self, start: f32, len: f32) -> &[f32] {
+        let from = ((start * SAMPLE_RATE) as usize).min(self.left.len());
+        let to = (from + (len * SAMPLE_RATE) as usize).min(self.left.len());
+        &self.left[from..to]
+    }
+}
+
+// Retriggered, so every note starts at the same phase and renders repeat exactly
+pub fn oscillator(waveform: Waveform) -> OscillatorConfig {
+    OscillatorConfig { phase_mode: PhaseMode::Retrigger, ..OscillatorConfig::with_waveform(waveform) }
+}
+
+pub fn lowpass(cutoff_frequency: f32, slope: FilterSlope) -> Filter {
+    Filter::new(FilterParameters {
+        filter_type: FilterType::LowPass,
+        slope,
+        cutoff_frequency,
+        resonance_amount: std::f32::consts::FRAC_1_SQRT_2,
+        modulation_amount: 0.0,
+        drive: 0.0,
+        saturation: SaturationCurve::Tanh,
+    }, SAMPLE_RATE)
+}
+
+/// One retriggered oscillator through an open filter, with no modulation, drift or sends,
+/// so what comes out depends only on the notes and the settings a test changes.
+pub fn clean_patch(waveform: Waveform) -> SynthesizerConfig {
+    SynthesizerConfig {
+        oscillator_configs: vec![oscillator(waveform)],
+        envelope_config: EnvelopeConfig {
+            attack_time: 0.001,
+            decay_time: 0.001,
+            sustain_level: 1.0,
+            release_time: 0.001,
+            ..EnvelopeConfig::default()
+        },
+        filter: lowpass(20000.0, FilterSlope::Slope12dB),
+        modulation_routes: Vec::new(),
+        delay: DelayConfig { enabled: false, ..DelayConfig::default() },
+        reverb: ReverbConfig { enabled: false, ..ReverbConfig::default() },
+        normalization: OutputNormalization::FixedHeadroom,
+        max_voices: 8,
+        sample_rate: SAMPLE_RATE,
+        ..SynthesizerConfig::default()
+    }
+}
+
+/// Renders `seconds` of `config` in host-sized blocks, playing `events` on the frame they fall on.
+pub fn render(config: SynthesizerConfig, events: &[Event], seconds: f32) -> Render {
+    let frames = (seconds * SAMPLE_RATE) as usize;
+    let mut synth = Synthesizer::new(config);
+    synth.set_sample_rate(SAMPLE_RATE);
+
+    let mut events = events.to_vec();
+    events.sort_by_key(Event::frame);
+    let mut pending = events.into_iter().peekable();
+
+    let mut left = vec![0.0; frames];
+    let mut right = vec![0.0; frames];
+    let mut frame = 0;
+    while frame < frames {
+        while let Some(event) = pending.next_if(|e| e.frame() <= frame) {
+            match event {
+                Event::NoteOn { note, velocity, .. } => synth.note_on(midi_note_to_freq(note), velocity),
+                Event::NoteOff { note, .. } => synth.note_off(midi_note_to_freq(note)),
+            }
+        }
+        let next_event = pending.peek().map_or(frames, Event::frame);
+        let end = (frame + BLOCK_SIZE).min(next_event).min(frames);
+        synth.render_stereo(&mut left[frame..end], &mut right[frame..end]);
+        frame = end;
+    }
+    Render { left, right }
+}
+
+pub fn rms(samples: &[f32]) -> f32 {
+    if samples.is_empty() {
+        return 0.0;
+    }
+    (samples.iter().map(|s| s * s).sum::<f32>() / samples.len() as f32).sqrt()
+}
+
+/// Amplitude of the `frequency` component of `samples`, so a full-scale sine reads 1.0.
+pub fn magnitude_at(samples: &[f32], frequency: f32) -> f32 {
+    let coeff = 2.0 * (2.0 * std::f32::consts::PI * frequency / SAMPLE_RATE).cos();
+    let (mut prev, mut prev2) = (0.0_f64, 0.0_f64);
+    for &sample in samples {
+        let s = sample as f64 + coeff as f64 * prev - prev2;
+        prev2 = prev;
+        prev = s;
+    }
+    let power = prev * prev + prev2 * prev2 - coeff as f64 * prev * prev2;
+    (2.0 * power.max(0.0).sqrt() / samples.len() as f64) as f32
+}
+
+pub fn db(ratio: f32) -> f32 {
+    20.0 * ratio.max(1e-9).log10()
+}
+
+fn golden_path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/golden").join(format!("{}.wav", name))
+}
+
+/// Compares `render` sample by sample with the stored golden render `name`, recording it
+/// instead when `UPDATE_GOLDEN` is set.
+pub fn assert_matches_golden(name: &str, render: &Render, tolerance: f32) {
+    let path = golden_path(name);
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        write_wav(&path, render).unwrap();
+        eprintln!("recorded golden render {}", path.display());
+        return;
+    }
+
+    assert!(path.exists(), "no golden render at {}; record it with UPDATE_GOLDEN=1", path.display());
+    let (left, right) = read_wav(&path).unwrap_or_else(|e| panic!("can't read {}: {}", path.display(), e));
+    assert_eq!(left.len(), render.left.len(), "{}: length differs from the golden render", name);
+    for (channel, golden, actual) in [("left", &left, &render.left), ("right", &right, &render.right)] {
+        let (frame, diff) = golden
+            .iter()
+            .zip(actual.iter())
+            .map(|(g, a)| (g - a).abs())
+            .enumerate()
+            .fold((0, 0.0_f32), |worst, (i, d)| if d > worst.1 { (i, d) } else { worst });
+        assert!(
+            diff <= tolerance,
+            "{}: {} channel is {} off the golden render at frame {} (tolerance {})",
+            name, channel, diff, frame, tolerance
+        );
+    }
+}
+
+fn write_wav(path: &Path, render: &Render) -> std::io::Result<()> {
+    let data_len = (render.left.len() * 8) as u32;
+    let sample_rate = SAMPLE_RATE as u32;
+    let mut out = BufWriter::new(File::create(path)?);
+    out.write_all(b"RIFF")?;
+    out.write_all(&(36 + data_len).to_le_bytes())?;
+    out.write_all(b"WAVEfmt ")?;
+    out.write_all(&16u32.to_le_bytes())?;
+    out.write_all(&3u16.to_le_bytes())?;               // IEEE float
+    out.write_all(&2u16.to_le_bytes())?;               // channels
+    out.write_all(&sample_rate.to_le_bytes())?;
+    out.write_all(&(sample_rate * 8).to_le_bytes())?;  // bytes per second
+    out.write_all(&8u16.to_le_bytes())?;               // bytes per frame
+    out.write_all(&32u16.to_le_bytes())?;              // bits per sample
+    out.write_all(b"data")?;
+    out.write_all(&data_len.to_le_bytes())?;
+    for (l, r) in render.left.iter().zip(&render.right) {
+        out.write_all(&l.to_le_bytes())?;
+        out.write_all(&r.to_le_bytes())?;
+    }
+    out.flush()
+}
+
+// Reads back what `write_wav` wrote; skips any chunks an editor may have added
+fn read_wav(path: &Path) -> Result<(Vec<f32>, Vec<f32>), String> {
+    let bytes = fs::read(path).map_err(|e| e.to_string())?;
+    if bytes.len() < 12 || &bytes[0..4] != b"RIFF" || &bytes[8..12] != b"WAVE" {
+        return Err("not a WAV file".into());
+    }
+    let mut pos = 12;
+    while pos + 8 <= bytes.len() {
+        let id = &bytes[pos..pos + 4];
+        let len = u32::from_le_bytes(bytes[pos + 4..pos + 8].try_into().unwrap()) as usize;
+        let body = bytes.get(pos + 8..pos + 8 + len).ok_or("truncated chunk")?;
+        match id {
+            b"fmt " => {
+                let format = u16::from_le_bytes([body[0], body[1]]);
+                let channels = u16::from_le_bytes([body[2], body[3]]);
+                if format != 3 || channels != 2 {
+                    return Err("expected 32-bit float stereo".into());
+                }
+            }
+            b"data" => {
+                let samples: Vec<f32> = body
+                    .chunks_exact(4)
+                    .map(|b| f32::from_le_bytes(b.try_into().unwrap()))
+                    .collect();
+                let left = samples.iter().step_by(2).copied().collect();
+                let right = samples.iter().skip(1).step_by(2).copied().collect();
+                return Ok((left, right));
+            }
+            _ => {}
+        }
+        pos += 8 + len + (len & 1);
+    }
+    Err("no data chunk".into())
+}
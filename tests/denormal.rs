@@ -0,0 +1,107 @@
+//! NaN scrubbing and flush-to-zero: a bad sample anywhere ends as silence rather than
+//! living on in the filters, and denormals never reach the DSP while the guard is held.
+
+mod common;
+
+use std::hint::black_box;
+
+use common::*;
+use rust_vst_synth::denormal::{scrub, DenormalGuard};
+use rust_vst_synth::filter::{FilterParameters, FilterSlope, FilterType, SaturationCurve};
+use rust_vst_synth::synthesizer::{midi_note_to_freq, Synthesizer};
+use rust_vst_synth::voice_configuration::Waveform;
+
+fn lowpass_parameters(cutoff_frequency: f32) -> FilterParameters {
+    FilterParameters {
+        filter_type: FilterType::LowPass,
+        slope: FilterSlope::Slope12dB,
+        cutoff_frequency,
+        resonance_amount: std::f32::consts::FRAC_1_SQRT_2,
+        modulation_amount: 0.0,
+        drive: 0.0,
+        saturation: SaturationCurve::Tanh,
+    }
+}
+
+fn render_block(synth: &mut Synthesizer, seconds: f32) -> Render {
+    let mut left = vec![0.0; (seconds * SAMPLE_RATE) as usize];
+    let mut right = vec![0.0; left.len()];
+    synth.render_stereo(&mut left, &mut right);
+    Render { left, right }
+}
+
+fn all_finite(render: &Render) -> bool {
+    render.left.iter().chain(&render.right).all(|s| s.is_finite())
+}
+
+#[test]
+fn scrub_silences_only_non_finite_samples() {
+    assert_eq!(scrub(f32::NAN), 0.0);
+    assert_eq!(scrub(f32::INFINITY), 0.0);
+    assert_eq!(scrub(f32::NEG_INFINITY), 0.0);
+    assert_eq!(scrub(0.25), 0.25);
+    assert_eq!(scrub(-1.5), -1.5);
+}
+
+#[test]
+fn a_nan_in_a_voice_does_not_outlive_its_cause() {
+    let mut synth = Synthesizer::new(clean_patch(Waveform::SAW));
+    synth.set_sample_rate(SAMPLE_RATE);
+    synth.note_on(midi_note_to_freq(57), 1.0);
+    render_block(&mut synth, 0.05);
+
+    synth.set_filter_parameters(lowpass_parameters(f32::NAN));
+    let broken = render_block(&mut synth, 0.05);
+    assert!(all_finite(&broken), "a NaN reached the output");
+
+    synth.set_filter_parameters(lowpass_parameters(20000.0));
+    render_block(&mut synth, 0.05);
+    let recovered = render_block(&mut synth, 0.05);
+    assert!(all_finite(&recovered));
+    assert!(rms(&recovered.left) > 0.1, "the voice stayed silent after the filter recovered");
+}
+
+#[test]
+fn master_output_is_scrubbed() {
+    let mut synth = Synthesizer::new(clean_patch(Waveform::SINE));
+    synth.set_sample_rate(SAMPLE_RATE);
+    synth.set_master_gain(f32::INFINITY);
+    synth.note_on(midi_note_to_freq(69), 1.0);
+    assert!(all_finite(&render_block(&mut synth, 0.05)));
+}
+
+#[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+#[test]
+fn guard_flushes_denormals_until_dropped() {
+    let halve = |x: f32| black_box(x) * black_box(0.5);
+    {
+        let _denormals = DenormalGuard::enable();
+        assert_eq!(halve(f32::MIN_POSITIVE), 0.0, "a denormal result survived the guard");
+    }
+    assert!(halve(f32::MIN_POSITIVE) > 0.0, "the guard left flush-to-zero on after it was dropped");
+}
+
+// The workers flush denormals like the audio thread does, or a tail decaying into them
+// would come out different, and slower, when the voices are spread over threads
+#[test]
+fn threaded_voices_render_the_same_decaying_tail() {
+    let tail = |threads: usize| {
+        let mut config = clean_patch(Waveform::SAW);
+        config.filter = lowpass(800.0, FilterSlope::Slope24dB);
+        config.envelope_config.release_time = 1.0;
+        config.voice_dc_blocking = true;    // its output decays into the denormal range
+        let mut synth = Synthesizer::new(config);
+        synth.set_sample_rate(SAMPLE_RATE);
+        synth.set_render_threads(threads);
+        for note in 48..56 {
+            synth.note_on(midi_note_to_freq(note), 1.0);
+        }
+        render_block(&mut synth, 0.1);
+        for note in 48..56 {
+            synth.note_off(midi_note_to_freq(note));
+        }
+        render_block(&mut synth, 4.0)
+    };
+    let (single, threaded) = (tail(1), tail(4));
+    assert!(single.left == threaded.left && single.right == threaded.right, "threaded voices rendered a different tail");
+}
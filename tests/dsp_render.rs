@@ -0,0 +1,177 @@
+mod common;
+
+use common::*;
+use rust_vst_synth::envelope::EnvelopeConfig;
+use rust_vst_synth::filter::{FilterParameters, FilterSlope, FilterType};
+use rust_vst_synth::synthesizer::midi_note_to_freq;
+use rust_vst_synth::voice_configuration::Waveform;
+
+const GOLDEN_TOLERANCE: f32 = 1e-4;
+const LEVEL_WINDOW: f32 = 0.004;    // about four cycles of the test tone
+const TEST_TONE: u8 = 84;           // C6, 1047 Hz
+
+fn note(note: u8, on: f32, off: f32) -> [Event; 2] {
+    [
+        Event::NoteOn { at: on, note, velocity: 1.0 },
+        Event::NoteOff { at: off, note },
+    ]
+}
+
+// Level of a short window centred on `at`, relative to `full`
+fn level(render: &Render, at: f32, full: f32) -> f32 {
+    rms(render.window(at - LEVEL_WINDOW / 2.0, LEVEL_WINDOW)) / full
+}
+
+#[test]
+fn envelope_follows_its_stage_times() {
+    let events = note(TEST_TONE, 0.0, 0.5);
+    let reference = render(clean_patch(Waveform::SINE), &events, 0.45);
+    let full = rms(reference.window(0.1, 0.3));
+    assert!(full > 0.01, "the reference note should be audible");
+
+    let config = EnvelopeConfig {
+        attack_time: 0.05,
+        decay_time: 0.1,
+        sustain_level: 0.5,
+        release_time: 0.1,
+        ..EnvelopeConfig::default()
+    };
+    let mut patch = clean_patch(Waveform::SINE);
+    patch.envelope_config = config;
+    let shaped = render(patch, &events, 0.8);
+
+    let expect = |at: f32, wanted: f32, tolerance: f32| {
+        let actual = level(&shaped, at, full);
+        assert!(
+            (actual - wanted).abs() <= tolerance,
+            "level at {:.3}s is {:.3}, expected {:.3}",
+            at, actual, wanted
+        );
+    };
+    expect(0.025, 0.5, 0.05);       // halfway through the attack
+    expect(0.05, 1.0, 0.05);        // top of the attack
+    expect(0.1, 0.75, 0.05);        // halfway through the decay
+    expect(0.3, 0.5, 0.03);         // sustain
+    expect(0.55, 0.25, 0.05);       // halfway through the release
+    assert!(level(&shaped, 0.65, full) < 0.01, "the note should be silent after its release");
+}
+
+#[test]
+fn envelope_render_matches_golden() {
+    let mut patch = clean_patch(Waveform::SAW);
+    patch.envelope_config = EnvelopeConfig {
+        attack_time: 0.02,
+        decay_time: 0.08,
+        sustain_level: 0.6,
+        release_time: 0.1,
+        ..EnvelopeConfig::default()
+    };
+    let rendered = render(patch, &note(57, 0.0, 0.25), 0.4);
+    assert_matches_golden("envelope_saw", &rendered, GOLDEN_TOLERANCE);
+}
+
+#[test]
+fn lowpass_passes_the_fundamental_and_cuts_above_the_cutoff() {
+    let events = note(55, 0.0, 1.0);
+    let fundamental = midi_note_to_freq(55);
+
+    let open = render(clean_patch(Waveform::SAW), &events, 0.8);
+    let mut patch = clean_patch(Waveform::SAW);
+    patch.filter = lowpass(1000.0, FilterSlope::Slope24dB);
+    let filtered = render(patch, &events, 0.8);
+
+    let attenuation = |harmonic: usize| {
+        let frequency = fundamental * harmonic as f32;
+        db(magnitude_at(open.window(0.2, 0.5), frequency)) - db(magnitude_at(filtered.window(0.2, 0.5), frequency))
+    };
+    assert!(attenuation(1).abs() < 1.0, "the fundamental moved by {:.1} dB", attenuation(1));
+    assert!(attenuation(20) > 24.0, "only {:.1} dB cut two octaves above the cutoff", attenuation(20));
+    assert!(attenuation(10) < attenuation(20), "the slope should keep falling above the cutoff");
+}
+
+#[test]
+fn highpass_cuts_the_fundamental() {
+    let events = note(45, 0.0, 1.0);
+    let open = render(clean_patch(Waveform::SAW), &events, 0.6);
+    let mut patch = clean_patch(Waveform::SAW);
+    patch.filter.set_parameters(FilterParameters {
+        filter_type: FilterType::HighPass,
+        cutoff_frequency: 1000.0,
+        ..patch.filter.parameters().clone()
+    });
+    let filtered = render(patch, &events, 0.6);
+
+    let fundamental = midi_note_to_freq(45);
+    let cut = db(magnitude_at(open.window(0.2, 0.4), fundamental)) - db(magnitude_at(filtered.window(0.2, 0.4), fundamental));
+    let kept = db(magnitude_at(open.window(0.2, 0.4), fundamental * 30.0)) - db(magnitude_at(filtered.window(0.2, 0.4), fundamental * 30.0));
+    assert!(cut > 24.0, "only {:.1} dB cut at the fundamental", cut);
+    assert!(kept.abs() < 1.5, "the 30th harmonic moved by {:.1} dB", kept);
+}
+
+#[test]
+fn filter_envelope_render_matches_golden() {
+    let mut patch = clean_patch(Waveform::SAW);
+    let mut filter_params = patch.filter.parameters().clone();
+    filter_params.cutoff_frequency = 300.0;
+    filter_params.slope = FilterSlope::Slope24dB;
+    filter_params.modulation_amount = 0.3;
+    patch.filter.set_parameters(filter_params);
+    patch.filter_envelope_config = EnvelopeConfig {
+        attack_time: 0.01,
+        decay_time: 0.2,
+        sustain_level: 0.2,
+        release_time: 0.1,
+        ..EnvelopeConfig::default()
+    };
+    let rendered = render(patch, &note(45, 0.0, 0.3), 0.45);
+    assert_matches_golden("filter_envelope_saw", &rendered, GOLDEN_TOLERANCE);
+}
+
+#[test]
+fn chord_is_the_sum_of_its_notes() {
+    let notes = [60, 64, 67];
+    let singles: Vec<Render> = notes
+        .iter()
+        .map(|&n| render(clean_patch(Waveform::SAW), &note(n, 0.0, 0.2), 0.3))
+        .collect();
+    let events: Vec<Event> = notes.iter().flat_map(|&n| note(n, 0.0, 0.2)).collect();
+    let chord = render(clean_patch(Waveform::SAW), &events, 0.3);
+
+    for (frame, &actual) in chord.left.iter().enumerate() {
+        let summed: f32 = singles.iter().map(|r| r.left[frame]).sum();
+        assert!(
+            (actual - summed).abs() < 1e-4,
+            "frame {}: chord {} but its notes sum to {}",
+            frame, actual, summed
+        );
+    }
+}
+
+#[test]
+fn chord_render_matches_golden() {
+    let events: Vec<Event> = [48, 55, 60, 64]
+        .iter()
+        .enumerate()
+        .flat_map(|(i, &n)| note(n, i as f32 * 0.05, 0.3))
+        .collect();
+    let rendered = render(clean_patch(Waveform::SAW), &events, 0.4);
+    assert_matches_golden("chord_saw", &rendered, GOLDEN_TOLERANCE);
+}
+
+#[test]
+fn seeded_noise_renders_the_same_every_time() {
+    let patch = |seed| {
+        let mut patch = clean_patch(Waveform::RANDOM);
+        patch.noise_seed = seed;
+        patch
+    };
+    let events = note(57, 0.0, 0.2);
+    let first = render(patch(7), &events, 0.25);
+    let second = render(patch(7), &events, 0.25);
+    let other = render(patch(8), &events, 0.25);
+
+    assert!(rms(&first.left) > 0.01, "the random oscillator should be audible");
+    assert_eq!(first.left, second.left);
+    assert_eq!(first.right, second.right);
+    assert_ne!(first.left, other.left, "a different seed should change the tables");
+}
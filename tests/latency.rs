@@ -0,0 +1,65 @@
+//! Reported latencies against the delay measured through the processors, so the host
+//! lines the plugin up with the other tracks.
+
+mod common;
+
+use common::*;
+use rust_vst_synth::effects::{Oversampling, Waveshaper, WaveshaperConfig};
+use rust_vst_synth::oversampling::{Decimator, VoiceOversampling};
+use rust_vst_synth::synthesizer::Synthesizer;
+use rust_vst_synth::voice_configuration::Waveform;
+
+// A sine far below the halfband cutoff passes with nothing but the filters' delay
+const PROBE_CYCLES_PER_FRAME: f64 = 0.001;
+
+// The delay, to a hundredth of a frame, that best lines a slow sine brought down from
+// `factor` times the rate up with the same sine rendered at the output rate
+fn measured_decimator_delay(factor: usize) -> f32 {
+    let omega = 2.0 * std::f64::consts::PI * PROBE_CYCLES_PER_FRAME;
+    let mut decimator = Decimator::default();
+    let output: Vec<f64> = (0..4000)
+        .map(|frame| {
+            let samples: Vec<f32> = (0..factor)
+                .map(|i| ((frame as f64 + i as f64 / factor as f64) * omega).sin() as f32)
+                .collect();
+            decimator.process(&samples) as f64
+        })
+        .collect();
+    let error = |delay: f64| -> f64 {
+        (2000..4000).map(|frame| (output[frame] - ((frame as f64 - delay) * omega).sin()).powi(2)).sum()
+    };
+    let best = (0..2000).min_by(|&a, &b| error(a as f64 / 100.0).total_cmp(&error(b as f64 / 100.0)));
+    best.unwrap() as f32 / 100.0
+}
+
+#[test]
+fn decimator_reports_the_delay_it_adds() {
+    for factor in [1, 2, 4] {
+        let measured = measured_decimator_delay(factor);
+        let reported = Decimator::latency_samples(factor);
+        assert!((measured - reported).abs() <= 0.02, "{}x: measured {}, reported {}", factor, measured, reported);
+    }
+}
+
+#[test]
+fn voice_oversampling_adds_to_the_synth_latency() {
+    let mut synth = Synthesizer::new(clean_patch(Waveform::SINE));
+    let base = synth.latency_samples();
+    for oversampling in [VoiceOversampling::X2, VoiceOversampling::X4] {
+        synth.set_voice_oversampling(oversampling);
+        let expected = base + Decimator::latency_samples(oversampling.factor()).round() as usize;
+        assert_eq!(synth.latency_samples(), expected, "{}", oversampling.label());
+    }
+}
+
+#[test]
+fn waveshaper_reports_where_an_impulse_peaks() {
+    for oversampling in Oversampling::ALL {
+        // Undriven and at its brightest, so the shaper is all but transparent to a small click
+        let config = WaveshaperConfig { enabled: true, drive: 0.0, tone: 1.0, oversampling, ..WaveshaperConfig::default() };
+        let mut shaper = Waveshaper::new(config, SAMPLE_RATE);
+        let output: Vec<f32> = (0..64).map(|i| shaper.process_mono(if i == 0 { 0.01 } else { 0.0 })).collect();
+        let peak = (0..output.len()).max_by(|&a, &b| output[a].abs().total_cmp(&output[b].abs())).unwrap();
+        assert_eq!(shaper.latency_samples(), peak, "{}", oversampling.label());
+    }
+}
@@ -0,0 +1,50 @@
+//! Standard MIDI File parsing, on files built byte by byte.
+
+use rust_vst_synth::midi_file::MidiFile;
+
+// A format 0 file with one track holding `track`, which should end with an end-of-track event
+fn file(division: u16, track: &[u8]) -> Vec<u8> {
+    let mut bytes = b"MThd".to_vec();
+    bytes.extend(6u32.to_be_bytes());
+    bytes.extend(0u16.to_be_bytes());
+    bytes.extend(1u16.to_be_bytes());
+    bytes.extend(division.to_be_bytes());
+    bytes.extend(b"MTrk");
+    bytes.extend((track.len() as u32).to_be_bytes());
+    bytes.extend(track);
+    bytes
+}
+
+const END_OF_TRACK: [u8; 4] = [0x00, 0xFF, 0x2F, 0x00];
+
+#[test]
+fn smpte_division_times_events_in_frames() {
+    // 25 frames per second at 40 ticks each: 1000 ticks is one second
+    let track = [&[0x87, 0x68, 0x90, 60, 100][..], &END_OF_TRACK].concat();
+    let parsed = MidiFile::parse(&file(0xE728, &track)).unwrap();
+    assert_eq!(parsed.events.len(), 1);
+    assert!((parsed.events[0].time_secs - 1.0).abs() < 1e-9);
+}
+
+#[test]
+fn invalid_smpte_divisions_are_rejected() {
+    // -128 frames per second has no positive counterpart, and no ticks per frame never advances
+    assert!(MidiFile::parse(&file(0x8028, &END_OF_TRACK)).is_err());
+    assert!(MidiFile::parse(&file(0xE700, &END_OF_TRACK)).is_err());
+}
+
+#[test]
+fn running_status_continues_channel_messages() {
+    let track = [&[0x00, 0x90, 60, 100, 0x10, 64, 100][..], &END_OF_TRACK].concat();
+    let parsed = MidiFile::parse(&file(96, &track)).unwrap();
+    let messages: Vec<_> = parsed.events.iter().map(|e| e.message.clone()).collect();
+    assert_eq!(messages, [vec![0x90, 60, 100], vec![0x90, 64, 100]]);
+}
+
+#[test]
+fn meta_and_sysex_events_cancel_running_status() {
+    let after_meta = [&[0x00, 0x90, 60, 100, 0x00, 0xFF, 0x01, 0x00, 0x00, 64, 100][..], &END_OF_TRACK].concat();
+    assert!(MidiFile::parse(&file(96, &after_meta)).is_err());
+    let after_sysex = [&[0x00, 0x90, 60, 100, 0x00, 0xF0, 0x01, 0xF7, 0x00, 64, 100][..], &END_OF_TRACK].concat();
+    assert!(MidiFile::parse(&file(96, &after_sysex)).is_err());
+}
@@ -0,0 +1,66 @@
+//! Output statistics of the random wavetable oscillator, which no golden render can pin
+//! down for every seed.
+
+mod common;
+
+use common::*;
+use rust_vst_synth::oscillator::{NoiseTableOscillator, WaveformGenerator};
+use rust_vst_synth::voice_configuration::Waveform;
+
+const TABLE_SIZE: usize = 2048;
+const SEEDS: [u64; 4] = [1, 42, 0xdead_beef, u64::MAX];
+
+// `cycles` periods at a pitch that steps through the table one entry per sample
+fn samples(seed: u64, cycles: usize) -> Vec<f32> {
+    let frequency = SAMPLE_RATE / TABLE_SIZE as f32;
+    let mut oscillator = NoiseTableOscillator::with_seed(SAMPLE_RATE, frequency, oscillator(Waveform::RANDOM), seed);
+    (0..cycles * TABLE_SIZE).map(|_| oscillator.next_sample()).collect()
+}
+
+fn mean(samples: &[f32]) -> f32 {
+    samples.iter().sum::<f32>() / samples.len() as f32
+}
+
+fn variance(samples: &[f32]) -> f32 {
+    let mean = mean(samples);
+    samples.iter().map(|s| (s - mean) * (s - mean)).sum::<f32>() / samples.len() as f32
+}
+
+#[test]
+fn tables_have_no_dc_and_peak_at_full_scale() {
+    for seed in SEEDS {
+        let cycle = samples(seed, 1);
+        assert!(mean(&cycle).abs() < 1e-3, "seed {}: mean {}", seed, mean(&cycle));
+        let peak = cycle.iter().fold(0.0_f32, |peak, s| peak.max(s.abs()));
+        assert!((peak - 1.0).abs() < 1e-3, "seed {}: peak {}", seed, peak);
+    }
+}
+
+#[test]
+fn tables_have_a_useful_level() {
+    // Between a spiky table that is mostly near zero and a square wave
+    for seed in SEEDS {
+        let variance = variance(&samples(seed, 1));
+        assert!((0.02..0.5).contains(&variance), "seed {}: variance {}", seed, variance);
+    }
+}
+
+#[test]
+fn tables_are_band_limited() {
+    let frequency = 100.0;
+    let mut oscillator = NoiseTableOscillator::with_seed(SAMPLE_RATE, frequency, oscillator(Waveform::RANDOM), 42);
+    let output: Vec<f32> = (0..SAMPLE_RATE as usize).map(|_| oscillator.next_sample()).collect();
+    // The table stops at the 32nd harmonic
+    for harmonic in [40.0, 64.0, 100.0] {
+        let magnitude = magnitude_at(&output, frequency * harmonic);
+        assert!(magnitude < 1e-3, "harmonic {} at {}", harmonic, magnitude);
+    }
+}
+
+#[test]
+fn the_same_seed_gives_the_same_table() {
+    for seed in SEEDS {
+        assert_eq!(samples(seed, 2), samples(seed, 2), "seed {} isn't reproducible", seed);
+    }
+    assert_ne!(samples(1, 1), samples(2, 1), "neighbouring seeds gave the same table");
+}
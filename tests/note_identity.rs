@@ -0,0 +1,97 @@
+//! Notes are told apart by their note id rather than their pitch, so a note-off releases the
+//! voices of its own note and nothing else.
+
+mod common;
+
+use common::*;
+use rust_vst_synth::synthesizer::{midi_note_to_freq, Synthesizer};
+use rust_vst_synth::voice_configuration::Waveform;
+
+// Long enough for clean_patch's attack, or its release, to finish
+const SETTLE: f32 = 0.05;
+
+fn synth() -> Synthesizer {
+    let mut synth = Synthesizer::new(clean_patch(Waveform::SINE));
+    synth.set_sample_rate(SAMPLE_RATE);
+    synth
+}
+
+fn settle(synth: &mut Synthesizer) -> Render {
+    let mut left = vec![0.0; (SETTLE * SAMPLE_RATE) as usize];
+    let mut right = vec![0.0; left.len()];
+    synth.render_stereo(&mut left, &mut right);
+    Render { left, right }
+}
+
+fn active_voices(synth: &Synthesizer) -> usize {
+    synth.voice_stats().active_voices
+}
+
+#[test]
+fn note_off_releases_only_its_own_note() {
+    let mut synth = synth();
+    synth.note_on(midi_note_to_freq(60), 1.0);
+    synth.note_on(midi_note_to_freq(67), 1.0);
+    settle(&mut synth);
+    assert_eq!(active_voices(&synth), 2);
+
+    synth.note_off(midi_note_to_freq(60));
+    settle(&mut synth);
+    let render = settle(&mut synth);
+    assert_eq!(active_voices(&synth), 1, "only the released note should stop");
+    // The held note leaks a little into the released note's bin over such a short window
+    let released = magnitude_at(&render.left, midi_note_to_freq(60));
+    let held = magnitude_at(&render.left, midi_note_to_freq(67));
+    assert!(held > 0.1, "the held note stopped");
+    assert!(released < held * 0.1, "the released note is still sounding");
+}
+
+#[test]
+fn note_off_for_an_unplayed_note_releases_nothing() {
+    let mut synth = synth();
+    synth.note_on(midi_note_to_freq(60), 1.0);
+    settle(&mut synth);
+
+    synth.note_off(midi_note_to_freq(62));
+    settle(&mut synth);
+    assert_eq!(active_voices(&synth), 1);
+}
+
+#[test]
+fn stopping_one_of_two_notes_on_the_same_pitch_keeps_the_other() {
+    let mut synth = synth();
+    let frequency = midi_note_to_freq(60);
+    synth.edit_part(0, |part| {
+        part.start_note(frequency, 1, 1.0);
+        part.start_note(frequency, 2, 1.0);
+    });
+    settle(&mut synth);
+    assert_eq!(active_voices(&synth), 2);
+
+    synth.edit_part(0, |part| part.stop_note(1));
+    settle(&mut synth);
+    let render = settle(&mut synth);
+    assert_eq!(active_voices(&synth), 1, "the second note should keep its voice");
+    assert!(magnitude_at(&render.left, frequency) > 0.1, "the second note stopped with the first");
+
+    synth.edit_part(0, |part| part.stop_note(2));
+    settle(&mut synth);
+    assert_eq!(active_voices(&synth), 0);
+}
+
+#[test]
+fn a_stolen_voice_ignores_its_old_note_off() {
+    let mut synth = synth();
+    synth.edit_part(0, |part| {
+        for note in 0..8 {
+            part.start_note(midi_note_to_freq(60 + note as u8), note, 1.0);
+        }
+        // The pool is full, so this takes over the voice of note 0
+        part.start_note(midi_note_to_freq(72), 8, 1.0);
+        part.stop_note(0);
+    });
+    settle(&mut synth);
+    let render = settle(&mut synth);
+    assert_eq!(active_voices(&synth), 8);
+    assert!(magnitude_at(&render.left, midi_note_to_freq(72)) > 0.05, "the stealing note was released");
+}
@@ -0,0 +1,14 @@
+//! Sysex addresses parameters by a hash of their ID, so no two parameters may hash alike.
+
+use nih_plug::prelude::Params;
+use rust_vst_synth::params::MyParams;
+use rust_vst_synth::sysex::{param_address, ParamAddresses};
+
+#[test]
+fn every_parameter_has_its_own_address() {
+    let ids: Vec<String> = MyParams::default().param_map().into_iter().map(|(id, _, _)| id).collect();
+    let addresses = ParamAddresses::new(ids.iter().map(String::as_str));
+    for (index, id) in ids.iter().enumerate() {
+        assert_eq!(addresses.index_of(param_address(id)), Some(index), "{} shares its sysex address", id);
+    }
+}
@@ -0,0 +1,58 @@
+//! Voice levels under the fixed per-voice headroom: a note sounds the same however many
+//! others are playing with it.
+
+use rust_vst_synth::filter::{Filter, FilterParameters, FilterSlope, FilterType, SaturationCurve};
+use rust_vst_synth::synthesizer::{midi_note_to_freq, Synthesizer, SynthesizerConfig};
+use rust_vst_synth::voice_configuration::Waveform;
+
+const SAMPLE_RATE: f32 = 48000.0;
+const BLOCK_SIZE: usize = 256;
+
+// Sines through an open filter with no modulation, held for `seconds`
+fn render_notes(notes: &[u8], seconds: f32) -> Vec<f32> {
+    let mut config = SynthesizerConfig {
+        modulation_routes: Vec::new(),
+        max_voices: 8,
+        sample_rate: SAMPLE_RATE,
+        ..SynthesizerConfig::default()
+    };
+    config.oscillator_configs.truncate(1);
+    config.oscillator_configs[0].waveform = Waveform::SINE;
+    config.filter = Filter::new(FilterParameters {
+        filter_type: FilterType::LowPass,
+        slope: FilterSlope::Slope12dB,
+        cutoff_frequency: 20000.0,
+        resonance_amount: std::f32::consts::FRAC_1_SQRT_2,
+        modulation_amount: 0.0,
+        drive: 0.0,
+        saturation: SaturationCurve::Tanh,
+    }, SAMPLE_RATE);
+
+    let mut synth = Synthesizer::new(config);
+    synth.set_sample_rate(SAMPLE_RATE);
+    for &note in notes {
+        synth.note_on(midi_note_to_freq(note), 1.0);
+    }
+    let mut left = vec![0.0; (seconds * SAMPLE_RATE) as usize];
+    let mut right = vec![0.0; left.len()];
+    for (l, r) in left.chunks_mut(BLOCK_SIZE).zip(right.chunks_mut(BLOCK_SIZE)) {
+        synth.render_stereo(l, r);
+    }
+    left
+}
+
+fn rms(samples: &[f32]) -> f32 {
+    (samples.iter().map(|s| s * s).sum::<f32>() / samples.len() as f32).sqrt()
+}
+
+#[test]
+fn voices_keep_their_level_as_notes_are_added() {
+    // Past the default envelope's decay, so every voice sits at its sustain level
+    let window = (0.4 * SAMPLE_RATE) as usize..(0.6 * SAMPLE_RATE) as usize;
+    let single = render_notes(&[60], 0.6);
+    let chord = render_notes(&[60, 67, 76], 0.6);
+
+    // Unrelated sines add in power, so three of them are sqrt(3) times as loud as one
+    let ratio = rms(&chord[window.clone()]) / rms(&single[window]);
+    assert!((ratio - 3f32.sqrt()).abs() < 0.1, "three voices are {:.2} times one", ratio);
+}